@@ -0,0 +1,11 @@
+fn main() {
+    // the target triple, which names the binary `neckcheck self-update` downloads
+    println!(
+        "cargo:rustc-env=NECKCHECK_TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
+    // the gRPC service and messages, from proto/neckcheck.proto
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/neckcheck.proto")
+        .expect("Failed to compile proto/neckcheck.proto; is protoc installed?");
+}
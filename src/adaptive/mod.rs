@@ -0,0 +1,173 @@
+use std::time::{Duration, Instant};
+
+use crate::config::AdaptiveConfig;
+use crate::events::{Feedback, PostureEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    // the user sat back after the alert
+    TruePositive,
+    // the user waved the alert away straight away
+    FalsePositive,
+}
+
+// Slowly moves the limit away from the calibration based on how the user reacts to alerts. Only
+// the first reaction to an alert counts.
+pub struct ThresholdLearner {
+    config: AdaptiveConfig,
+    // the fraction by which the limit is more lenient than calibrated (negative: stricter)
+    drift: f32,
+    // when the alert of the current episode fired, until the user reacts to it
+    alerted_at: Option<Instant>,
+    snoozed_until: Option<Instant>,
+}
+
+impl ThresholdLearner {
+    pub fn new(config: AdaptiveConfig, drift: f32) -> ThresholdLearner {
+        let drift = drift.clamp(config.min_drift, config.max_drift);
+        ThresholdLearner {
+            config,
+            drift,
+            alerted_at: None,
+            snoozed_until: None,
+        }
+    }
+
+    pub fn drift(&self) -> f32 {
+        self.drift
+    }
+
+    // The factor to scale the calibrated limit by
+    pub fn scale(&self) -> f32 {
+        1.0 + self.drift
+    }
+
+    pub fn observe(&mut self, events: &[PostureEvent], now: Instant) -> Option<Verdict> {
+        let mut verdict = None;
+        for event in events {
+            match event {
+                // while snoozed the user never saw an alert to react to
+                PostureEvent::TooClose if self.snoozed_until.is_none_or(|until| now >= until) => {
+                    self.alerted_at = Some(now);
+                }
+                PostureEvent::Recovered => {
+                    if self.reacted_within(self.config.correction_window, now) {
+                        verdict = Some(self.adjust(Verdict::TruePositive));
+                    }
+                    self.alerted_at = None;
+                }
+                PostureEvent::Away => self.alerted_at = None,
+                _ => {}
+            }
+        }
+        verdict
+    }
+
+    pub fn feedback(&mut self, feedback: &Feedback, now: Instant) -> Option<Verdict> {
+        if let Feedback::Snooze(duration) = feedback {
+            self.snoozed_until = Some(now + *duration);
+        }
+        let verdict = self
+            .reacted_within(self.config.quick_dismiss, now)
+            .then(|| self.adjust(Verdict::FalsePositive));
+        self.alerted_at = None;
+        verdict
+    }
+
+    fn reacted_within(&self, seconds: f64, now: Instant) -> bool {
+        self.alerted_at.is_some_and(|alerted_at| {
+            now.saturating_duration_since(alerted_at) <= Duration::from_secs_f64(seconds)
+        })
+    }
+
+    fn adjust(&mut self, verdict: Verdict) -> Verdict {
+        let change = match verdict {
+            Verdict::FalsePositive => self.config.step,
+            // corrections are far more common than dismissals, so each one counts for less
+            Verdict::TruePositive => -self.config.step / 2.0,
+        };
+        self.drift = (self.drift + change).clamp(self.config.min_drift, self.config.max_drift);
+        verdict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn learner() -> ThresholdLearner {
+        ThresholdLearner::new(AdaptiveConfig::default(), 0.0)
+    }
+
+    fn secs(start: Instant, secs: u64) -> Instant {
+        start + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn quick_dismissal_loosens_the_limit() {
+        let mut learner = learner();
+        let start = Instant::now();
+        learner.observe(&[PostureEvent::TooClose], start);
+        assert_eq!(
+            learner.feedback(&Feedback::Dismiss, secs(start, 3)),
+            Some(Verdict::FalsePositive)
+        );
+        assert!(learner.scale() > 1.0);
+    }
+
+    #[test]
+    fn late_dismissal_teaches_nothing() {
+        let mut learner = learner();
+        let start = Instant::now();
+        learner.observe(&[PostureEvent::TooClose], start);
+        assert_eq!(learner.feedback(&Feedback::Dismiss, secs(start, 60)), None);
+        assert_eq!(learner.drift(), 0.0);
+    }
+
+    #[test]
+    fn correction_tightens_the_limit() {
+        let mut learner = learner();
+        let start = Instant::now();
+        learner.observe(&[PostureEvent::TooClose], start);
+        assert_eq!(
+            learner.observe(&[PostureEvent::Recovered], secs(start, 5)),
+            Some(Verdict::TruePositive)
+        );
+        assert!(learner.scale() < 1.0);
+    }
+
+    #[test]
+    fn only_the_first_reaction_counts() {
+        let mut learner = learner();
+        let start = Instant::now();
+        learner.observe(&[PostureEvent::TooClose], start);
+        learner.feedback(&Feedback::Dismiss, secs(start, 1));
+        assert_eq!(
+            learner.observe(&[PostureEvent::Recovered], secs(start, 5)),
+            None
+        );
+    }
+
+    #[test]
+    fn alerts_while_snoozed_are_not_judged() {
+        let mut learner = learner();
+        let start = Instant::now();
+        learner.feedback(&Feedback::Snooze(Duration::from_secs(60)), start);
+        learner.observe(&[PostureEvent::TooClose], secs(start, 10));
+        assert_eq!(
+            learner.observe(&[PostureEvent::Recovered], secs(start, 12)),
+            None
+        );
+    }
+
+    #[test]
+    fn drift_stays_within_bounds() {
+        let mut learner = learner();
+        let start = Instant::now();
+        for i in 0..100 {
+            learner.observe(&[PostureEvent::TooClose], secs(start, i * 10));
+            learner.feedback(&Feedback::Dismiss, secs(start, i * 10 + 1));
+        }
+        assert_eq!(learner.drift(), AdaptiveConfig::default().max_drift);
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::Instant;
+
+use super::{Alert, AlertKind, AlertSink, BadPosture};
+use crate::config::BrightnessConfig;
+use crate::desktop;
+
+// How far to dim, from the brightness before the bad posture started and how long it has lasted
+struct Dimming {
+    per_minute: f32,
+    min: f32,
+    state: BadPosture,
+    // the brightness to restore, and since when the posture is bad
+    dimmed: Option<(f32, Instant)>,
+}
+
+impl Dimming {
+    // On Start the caller reads the brightness and fills in `dimmed`
+    fn update(&mut self, kind: AlertKind, now: Instant) -> Option<Change> {
+        match self.state.update(kind) {
+            Some(true) => Some(Change::Start),
+            Some(false) => self.dimmed.take().map(|(original, _)| Change::To(original)),
+            None => {
+                let (original, since) = self.dimmed?;
+                let minutes = now.duration_since(since).as_secs_f32() / 60.0;
+                let floor = self.min.min(original);
+                Some(Change::To(
+                    (original - self.per_minute * minutes).max(floor),
+                ))
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Change {
+    Start,
+    To(f32),
+}
+
+// Lowers the display brightness bit by bit for as long as the posture stays bad, and puts it back
+// on recovery. It is adjusted as alerts arrive, i.e. with every reminder while too close.
+pub struct BrightnessSink {
+    config: BrightnessConfig,
+    dimming: Dimming,
+}
+
+impl BrightnessSink {
+    pub fn new(config: &BrightnessConfig) -> BrightnessSink {
+        BrightnessSink {
+            config: config.clone(),
+            dimming: Dimming {
+                per_minute: config.per_minute,
+                min: config.min,
+                state: BadPosture::new(),
+                dimmed: None,
+            },
+        }
+    }
+}
+
+impl AlertSink for BrightnessSink {
+    fn name(&self) -> &str {
+        "brightness"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        let now = Instant::now();
+        let result = match self.dimming.update(alert.kind, now) {
+            Some(Change::Start) => desktop::brightness(self.config.control, self.config.display)
+                .map(|original| self.dimming.dimmed = Some((original, now))),
+            Some(Change::To(percent)) => {
+                desktop::set_brightness(self.config.control, self.config.display, percent)
+            }
+            None => return,
+        };
+        if let Err(e) = result {
+            println!("Failed to change the brightness: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn dims_with_time_down_to_the_minimum_and_restores() {
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let mut dimming = Dimming {
+            per_minute: 20.0,
+            min: 30.0,
+            state: BadPosture::new(),
+            dimmed: None,
+        };
+        assert_eq!(
+            dimming.update(AlertKind::TooClose, at(0)),
+            Some(Change::Start)
+        );
+        dimming.dimmed = Some((80.0, at(0)));
+        assert_eq!(
+            dimming.update(AlertKind::Reminder, at(60)),
+            Some(Change::To(60.0))
+        );
+        assert_eq!(
+            dimming.update(AlertKind::Reminder, at(600)),
+            Some(Change::To(30.0))
+        );
+        assert_eq!(
+            dimming.update(AlertKind::Recovered, at(610)),
+            Some(Change::To(80.0))
+        );
+        assert_eq!(dimming.update(AlertKind::Returned, at(620)), None);
+    }
+}
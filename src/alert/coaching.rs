@@ -0,0 +1,89 @@
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Alert, AlertKind};
+use crate::config::CoachingConfig;
+use crate::i18n::tr;
+
+// Short tips on what to do about the posture, taking turns as the text of the posture alerts so
+// that they don't all read the same
+pub struct Coaching {
+    tips: Vec<String>,
+    next: Cell<usize>,
+}
+
+impl Coaching {
+    pub fn new(config: &CoachingConfig) -> Option<Coaching> {
+        if !config.enabled {
+            return None;
+        }
+        let tips = if config.messages.is_empty() {
+            vec![
+                tr!("coaching-chin-back"),
+                tr!("coaching-eye-level"),
+                tr!("coaching-sit-back"),
+                tr!("coaching-feet-flat"),
+                tr!("coaching-arms"),
+                tr!("coaching-breathe"),
+            ]
+        } else {
+            config.messages.clone()
+        };
+        // a different first tip every run
+        let start = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as usize);
+        Some(Coaching::with_tips(tips, start))
+    }
+
+    fn with_tips(tips: Vec<String>, start: usize) -> Coaching {
+        Coaching {
+            next: Cell::new(start % tips.len().max(1)),
+            tips,
+        }
+    }
+
+    // The alert with the next tip as its text, if it is one about the posture
+    pub fn coach(&self, alert: Alert) -> Alert {
+        let posture = matches!(
+            alert.kind,
+            AlertKind::TooClose | AlertKind::Reminder | AlertKind::PostureCheck
+        );
+        if !posture || self.tips.is_empty() {
+            return alert;
+        }
+        let next = self.next.get();
+        self.next.set((next + 1) % self.tips.len());
+        Alert {
+            message: self.tips[next].clone(),
+            ..alert
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::Severity;
+
+    #[test]
+    fn posture_alerts_take_turns_with_the_tips() {
+        let coaching = Coaching::with_tips(vec!["a".to_string(), "b".to_string()], 3);
+        let alert = |kind| Alert {
+            kind,
+            severity: Severity::Warning,
+            message: "Too close!".to_string(),
+            preview: false,
+        };
+        let messages: Vec<String> = [
+            AlertKind::TooClose,
+            AlertKind::Reminder,
+            AlertKind::StandUp,
+            AlertKind::Reminder,
+        ]
+        .into_iter()
+        .map(|kind| coaching.coach(alert(kind)).message)
+        .collect();
+        assert_eq!(messages, ["b", "a", "Too close!", "b"]);
+    }
+}
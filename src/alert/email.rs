@@ -0,0 +1,90 @@
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+
+use super::{Alert, AlertKind, AlertSink};
+use crate::config::{EmailConfig, EmailSecurity};
+use crate::i18n::tr;
+
+// Emails every alert over SMTP. Routed only `sustained` alerts it tells about prolonged bad
+// posture, and routed `summary` it sends the end of day summary as a daily digest.
+pub struct EmailSink {
+    config: EmailConfig,
+    transport: Option<SmtpTransport>,
+}
+
+impl EmailSink {
+    pub fn new(config: &EmailConfig) -> EmailSink {
+        let transport = match transport(config) {
+            Ok(transport) => Some(transport),
+            Err(e) => {
+                println!("Failed to set up email to {}: {}", config.server, e);
+                None
+            }
+        };
+        EmailSink {
+            config: config.clone(),
+            transport,
+        }
+    }
+
+    fn message(&self, alert: &Alert) -> Result<Message, String> {
+        let subject = match alert.kind {
+            AlertKind::Summary => tr!("summary-email-subject"),
+            _ => format!("neckcheck: {}", alert.message),
+        };
+        let mut builder = Message::builder()
+            .from(
+                self.config
+                    .from
+                    .parse()
+                    .map_err(|e| format!("from: {}", e))?,
+            )
+            .subject(subject);
+        for to in &self.config.to {
+            builder = builder.to(to.parse().map_err(|e| format!("{}: {}", to, e))?);
+        }
+        builder
+            .header(ContentType::TEXT_PLAIN)
+            .body(format!(
+                "{}\n\n{:?} at {}\n",
+                alert.message,
+                alert.severity,
+                chrono::Local::now().format("%Y-%m-%d %H:%M")
+            ))
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn transport(config: &EmailConfig) -> Result<SmtpTransport, lettre::transport::smtp::Error> {
+    let mut builder = match config.security {
+        EmailSecurity::Starttls => SmtpTransport::starttls_relay(&config.server)?,
+        EmailSecurity::Tls => SmtpTransport::relay(&config.server)?,
+        EmailSecurity::None => SmtpTransport::builder_dangerous(&config.server),
+    };
+    if let Some(port) = config.port {
+        builder = builder.port(port);
+    }
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    Ok(builder.build())
+}
+
+impl AlertSink for EmailSink {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        let Some(transport) = &self.transport else {
+            return;
+        };
+        let result = self
+            .message(alert)
+            .and_then(|message| transport.send(&message).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            println!("Failed to send email via {}: {}", self.config.server, e);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+use super::{Alert, AlertKind, AlertSink, BadPosture};
+use crate::config::EscalationConfig;
+use crate::desktop;
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    Escalate,
+    Restore,
+}
+
+// When bad posture has gone on for long enough to escalate, and when it is over
+struct Escalation {
+    after: Duration,
+    state: BadPosture,
+    bad_since: Option<Instant>,
+    escalated: bool,
+}
+
+impl Escalation {
+    fn update(&mut self, kind: AlertKind, now: Instant) -> Option<Step> {
+        match self.state.update(kind) {
+            Some(true) => self.bad_since = Some(now),
+            Some(false) => {
+                self.bad_since = None;
+                if self.escalated {
+                    self.escalated = false;
+                    return Some(Step::Restore);
+                }
+            }
+            None => {}
+        }
+        let due = self
+            .bad_since
+            .is_some_and(|since| now.duration_since(since) >= self.after);
+        if due && !self.escalated {
+            self.escalated = true;
+            return Some(Step::Escalate);
+        }
+        None
+    }
+}
+
+// Consequences for very prolonged bad posture: a reminder as the wallpaper until the posture is
+// good again, and optionally locking the screen to force a break. Only the alerts that reach the
+// sink are counted, so the reminders while too close are what keep it checking.
+pub struct EscalationSink {
+    config: EscalationConfig,
+    escalation: Escalation,
+    saved_wallpaper: Option<String>,
+}
+
+impl EscalationSink {
+    pub fn new(config: &EscalationConfig) -> EscalationSink {
+        if config.lock_screen {
+            println!(
+                "Warning: the screen will be locked after {} minutes of bad posture",
+                config.after / 60.0
+            );
+        }
+        EscalationSink {
+            config: config.clone(),
+            escalation: Escalation {
+                after: Duration::from_secs_f64(config.after),
+                state: BadPosture::new(),
+                bad_since: None,
+                escalated: false,
+            },
+            saved_wallpaper: None,
+        }
+    }
+
+    fn escalate(&mut self) -> Result<(), String> {
+        if let Some(wallpaper) = &self.config.wallpaper {
+            self.saved_wallpaper = Some(desktop::wallpaper()?);
+            desktop::set_wallpaper_file(wallpaper)?;
+        }
+        if self.config.lock_screen {
+            desktop::lock_screen()?;
+        }
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), String> {
+        match self.saved_wallpaper.take() {
+            Some(wallpaper) => desktop::set_wallpaper(&wallpaper),
+            None => Ok(()),
+        }
+    }
+}
+
+impl AlertSink for EscalationSink {
+    fn name(&self) -> &str {
+        "escalation"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        let result = match self.escalation.update(alert.kind, Instant::now()) {
+            Some(Step::Escalate) => self.escalate(),
+            Some(Step::Restore) => self.restore(),
+            None => return,
+        };
+        if let Err(e) = result {
+            println!("Failed to escalate the alert: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escalates_once_after_prolonged_bad_posture() {
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let mut escalation = Escalation {
+            after: Duration::from_secs(600),
+            state: BadPosture::new(),
+            bad_since: None,
+            escalated: false,
+        };
+        assert_eq!(escalation.update(AlertKind::TooClose, at(0)), None);
+        assert_eq!(escalation.update(AlertKind::Reminder, at(599)), None);
+        assert_eq!(
+            escalation.update(AlertKind::Reminder, at(600)),
+            Some(Step::Escalate)
+        );
+        assert_eq!(escalation.update(AlertKind::Reminder, at(700)), None);
+        assert_eq!(
+            escalation.update(AlertKind::Recovered, at(710)),
+            Some(Step::Restore)
+        );
+        // a short episode afterwards has nothing to restore
+        assert_eq!(escalation.update(AlertKind::TooClose, at(800)), None);
+        assert_eq!(escalation.update(AlertKind::Recovered, at(810)), None);
+    }
+}
@@ -0,0 +1,380 @@
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::{Alert, AlertSink, BadPosture};
+use crate::config::{HueConfig, LifxConfig, WledConfig};
+
+// A light that can be switched to an alert colour and back to how it was
+pub trait Light: Send {
+    fn name(&self) -> &str;
+    // remembers the current state, to restore later
+    fn save(&mut self) -> Result<(), String>;
+    fn alert(&mut self, color: [u8; 3], flash: bool) -> Result<(), String>;
+    fn restore(&mut self) -> Result<(), String>;
+}
+
+// Shows bad posture on a light, out of the way in peripheral vision, and puts the light back as it
+// was on recovery
+pub struct LightSink<L: Light> {
+    light: L,
+    color: [u8; 3],
+    flash: bool,
+    state: BadPosture,
+}
+
+impl<L: Light> LightSink<L> {
+    pub fn new(light: L, color: [u8; 3], flash: bool) -> LightSink<L> {
+        LightSink {
+            light,
+            color,
+            flash,
+            state: BadPosture::new(),
+        }
+    }
+}
+
+impl<L: Light> AlertSink for LightSink<L> {
+    fn name(&self) -> &str {
+        self.light.name()
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        let result = match self.state.update(alert.kind) {
+            Some(true) => self
+                .light
+                .save()
+                .and_then(|_| self.light.alert(self.color, self.flash)),
+            Some(false) => self.light.restore(),
+            None => return,
+        };
+        if let Err(e) = result {
+            println!("Failed to update the {} light: {}", self.light.name(), e);
+        }
+    }
+}
+
+// Hue in degrees, saturation and value from 0 to 1
+fn hsv([r, g, b]: [u8; 3]) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let range = max - r.min(g).min(b);
+    let hue = if range == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / range).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / range + 2.0)
+    } else {
+        60.0 * ((r - g) / range + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { range / max };
+    (hue, saturation, max)
+}
+
+// A Philips Hue light, through the bridge's local REST API
+pub struct HueLight {
+    url: String,
+    saved: Option<Value>,
+}
+
+impl HueLight {
+    pub fn new(config: &HueConfig) -> HueLight {
+        HueLight {
+            url: format!(
+                "http://{}/api/{}/lights/{}",
+                config.bridge, config.username, config.light
+            ),
+            saved: None,
+        }
+    }
+
+    fn set(&self, state: &Value) -> Result<(), String> {
+        ureq::put(&format!("{}/state", self.url))
+            .send_json(state)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Light for HueLight {
+    fn name(&self) -> &str {
+        "hue"
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        let light: Value = ureq::get(&self.url)
+            .call()
+            .map_err(|e| e.to_string())?
+            .into_json()
+            .map_err(|e| e.to_string())?;
+        let state = &light["state"];
+        let mut saved = json!({"on": state["on"], "bri": state["bri"], "alert": "none"});
+        // only the fields of the colour mode in use describe the colour
+        match state["colormode"].as_str() {
+            Some("hs") => {
+                saved["hue"] = state["hue"].clone();
+                saved["sat"] = state["sat"].clone();
+            }
+            Some("ct") => saved["ct"] = state["ct"].clone(),
+            Some("xy") => saved["xy"] = state["xy"].clone(),
+            _ => {}
+        }
+        self.saved = Some(saved);
+        Ok(())
+    }
+
+    fn alert(&mut self, color: [u8; 3], flash: bool) -> Result<(), String> {
+        let (hue, saturation, value) = hsv(color);
+        self.set(&json!({
+            "on": true,
+            "hue": (hue / 360.0 * 65535.0) as u16,
+            "sat": (saturation * 254.0) as u8,
+            "bri": (value * 254.0).max(1.0) as u8,
+            // flashes for 15 seconds
+            "alert": if flash { "lselect" } else { "none" },
+        }))
+    }
+
+    fn restore(&mut self) -> Result<(), String> {
+        match self.saved.take() {
+            Some(saved) => self.set(&saved),
+            None => Ok(()),
+        }
+    }
+}
+
+// A WLED strip, through its JSON API
+pub struct WledLight {
+    url: String,
+    saved: Option<Value>,
+}
+
+impl WledLight {
+    pub fn new(config: &WledConfig) -> WledLight {
+        WledLight {
+            url: format!("http://{}/json/state", config.address),
+            saved: None,
+        }
+    }
+
+    fn set(&self, state: &Value) -> Result<(), String> {
+        ureq::post(&self.url)
+            .send_json(state)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+// WLED's Blink and Solid effects
+const WLED_BLINK: u8 = 1;
+const WLED_SOLID: u8 = 0;
+
+impl Light for WledLight {
+    fn name(&self) -> &str {
+        "wled"
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        self.saved = Some(
+            ureq::get(&self.url)
+                .call()
+                .map_err(|e| e.to_string())?
+                .into_json()
+                .map_err(|e| e.to_string())?,
+        );
+        Ok(())
+    }
+
+    fn alert(&mut self, [r, g, b]: [u8; 3], flash: bool) -> Result<(), String> {
+        self.set(&json!({
+            "on": true,
+            "bri": 255,
+            "seg": [{"col": [[r, g, b]], "fx": if flash { WLED_BLINK } else { WLED_SOLID }}],
+        }))
+    }
+
+    fn restore(&mut self) -> Result<(), String> {
+        match self.saved.take() {
+            // the state as read is accepted back as it is
+            Some(saved) => self.set(&saved),
+            None => Ok(()),
+        }
+    }
+}
+
+const LIFX_PORT: u16 = 56700;
+const LIFX_GET_COLOR: u16 = 101;
+const LIFX_SET_COLOR: u16 = 102;
+const LIFX_SET_WAVEFORM: u16 = 103;
+const LIFX_LIGHT_STATE: u16 = 107;
+const LIFX_SET_POWER: u16 = 117;
+const LIFX_HEADER: usize = 36;
+// identifies our messages to the bulb
+const LIFX_SOURCE: u32 = 0x6e636b21;
+const LIFX_TIMEOUT: Duration = Duration::from_secs(1);
+
+// A LIFX bulb, through the LAN protocol over UDP
+pub struct LifxLight {
+    address: SocketAddr,
+    // hue, saturation, brightness, kelvin and power
+    saved: Option<([u16; 4], u16)>,
+}
+
+impl LifxLight {
+    pub fn new(config: &LifxConfig) -> LifxLight {
+        LifxLight {
+            address: SocketAddr::new(config.address, LIFX_PORT),
+            saved: None,
+        }
+    }
+
+    fn socket(&self) -> Result<UdpSocket, String> {
+        let any: IpAddr = if self.address.is_ipv4() {
+            [0, 0, 0, 0].into()
+        } else {
+            [0u16; 8].into()
+        };
+        let socket = UdpSocket::bind((any, 0)).map_err(|e| e.to_string())?;
+        socket.connect(self.address).map_err(|e| e.to_string())?;
+        socket
+            .set_read_timeout(Some(LIFX_TIMEOUT))
+            .map_err(|e| e.to_string())?;
+        Ok(socket)
+    }
+
+    fn send(&self, message_type: u16, payload: &[u8]) -> Result<(), String> {
+        self.socket()?
+            .send(&lifx_packet(message_type, payload, false))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn set_color(&self, hsbk: [u16; 4]) -> Result<(), String> {
+        let mut payload = vec![0];
+        payload.extend(hsbk.iter().flat_map(|value| value.to_le_bytes()));
+        payload.extend(0u32.to_le_bytes());
+        self.send(LIFX_SET_COLOR, &payload)
+    }
+
+    fn set_power(&self, level: u16) -> Result<(), String> {
+        let mut payload = level.to_le_bytes().to_vec();
+        payload.extend(0u32.to_le_bytes());
+        self.send(LIFX_SET_POWER, &payload)
+    }
+}
+
+// A LIFX LAN protocol message to whichever bulb is at the address it is sent to
+fn lifx_packet(message_type: u16, payload: &[u8], res_required: bool) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(LIFX_HEADER + payload.len());
+    packet.extend(((LIFX_HEADER + payload.len()) as u16).to_le_bytes());
+    // protocol 1024, addressable, tagged (for any target)
+    packet.extend((1024u16 | 0x1000 | 0x2000).to_le_bytes());
+    packet.extend(LIFX_SOURCE.to_le_bytes());
+    // target and reserved
+    packet.extend([0; 14]);
+    packet.push(res_required as u8);
+    // sequence
+    packet.push(0);
+    packet.extend([0; 8]);
+    packet.extend(message_type.to_le_bytes());
+    packet.extend([0; 2]);
+    packet.extend(payload);
+    packet
+}
+
+fn lifx_hsbk(color: [u8; 3]) -> [u16; 4] {
+    let (hue, saturation, value) = hsv(color);
+    [
+        (hue / 360.0 * 65535.0) as u16,
+        (saturation * 65535.0) as u16,
+        (value * 65535.0) as u16,
+        3500,
+    ]
+}
+
+impl Light for LifxLight {
+    fn name(&self) -> &str {
+        "lifx"
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        let socket = self.socket()?;
+        socket
+            .send(&lifx_packet(LIFX_GET_COLOR, &[], true))
+            .map_err(|e| e.to_string())?;
+        let mut buffer = [0; 128];
+        loop {
+            let length = socket.recv(&mut buffer).map_err(|e| e.to_string())?;
+            let message_type = u16::from_le_bytes([buffer[32], buffer[33]]);
+            if length < LIFX_HEADER + 12 || message_type != LIFX_LIGHT_STATE {
+                continue;
+            }
+            let field = |i: usize| {
+                let at = LIFX_HEADER + i * 2;
+                u16::from_le_bytes([buffer[at], buffer[at + 1]])
+            };
+            // hsbk, a reserved field, then the power
+            self.saved = Some(([field(0), field(1), field(2), field(3)], field(5)));
+            return Ok(());
+        }
+    }
+
+    fn alert(&mut self, color: [u8; 3], flash: bool) -> Result<(), String> {
+        self.set_power(u16::MAX)?;
+        if !flash {
+            return self.set_color(lifx_hsbk(color));
+        }
+        // pulse between the current colour and the alert colour every second until restored
+        let mut payload = vec![0, 0];
+        payload.extend(
+            lifx_hsbk(color)
+                .iter()
+                .flat_map(|value| value.to_le_bytes()),
+        );
+        payload.extend(1000u32.to_le_bytes());
+        payload.extend(1e6f32.to_le_bytes());
+        payload.extend(0i16.to_le_bytes());
+        // pulse
+        payload.push(4);
+        self.send(LIFX_SET_WAVEFORM, &payload)
+    }
+
+    fn restore(&mut self) -> Result<(), String> {
+        match self.saved.take() {
+            Some((hsbk, power)) => {
+                self.set_color(hsbk)?;
+                self.set_power(power)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_convert_to_hue_saturation_and_value() {
+        assert_eq!(hsv([255, 0, 0]), (0.0, 1.0, 1.0));
+        assert_eq!(hsv([0, 255, 0]), (120.0, 1.0, 1.0));
+        assert_eq!(hsv([0, 0, 128]), (240.0, 1.0, 128.0 / 255.0));
+        assert_eq!(hsv([255, 255, 255]), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn lifx_packets_have_the_protocol_header() {
+        let packet = lifx_packet(LIFX_GET_COLOR, &[], true);
+        assert_eq!(packet.len(), LIFX_HEADER);
+        assert_eq!(&packet[0..4], &[36, 0, 0x00, 0x34]);
+        assert_eq!(packet[22], 1);
+        assert_eq!(&packet[32..34], &[101, 0]);
+
+        let packet = lifx_packet(LIFX_SET_POWER, &[0xff, 0xff, 0, 0, 0, 0], false);
+        assert_eq!(packet.len(), LIFX_HEADER + 6);
+        assert_eq!(packet[0], 42);
+        assert_eq!(packet[22], 0);
+    }
+}
@@ -0,0 +1,24 @@
+use super::{Alert, AlertSink};
+
+pub struct LogSink;
+
+impl LogSink {
+    pub fn new() -> LogSink {
+        LogSink
+    }
+}
+
+impl AlertSink for LogSink {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        println!(
+            "[{}] {:?}: {}",
+            chrono::Local::now().format("%H:%M:%S"),
+            alert.severity,
+            alert.message
+        );
+    }
+}
@@ -0,0 +1,576 @@
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::watch;
+use tokio::time::{interval_at, sleep_until, Instant, Interval, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    build_sink, modality, Alert, AlertKind, AlertSink, Coaching, Modality, Route, Severity,
+    SinkGroup,
+};
+use crate::breathing;
+use crate::config::{
+    AccessibilityConfig, AlertConfig, BreathingConfig, PairAlerts, SinkConfig, SinkKind,
+};
+use crate::desktop;
+use crate::eventlog::{Entry, EventLog};
+use crate::events::{Feedback, PostureEvent};
+use crate::i18n::tr;
+use crate::quiet::Quiet;
+
+// how long a preview of the posture alert stays shown
+const PREVIEW_SHOWN_FOR: Duration = Duration::from_secs(2);
+
+struct SinkHandle {
+    name: String,
+    route: Route,
+    modality: Modality,
+    // still heard when alerts are softened to notifications only
+    notification: bool,
+    // what it shows stays until the posture recovers
+    lasting: bool,
+    queue: Sender<Alert>,
+}
+
+impl SinkHandle {
+    fn spawn(
+        mut sink: Box<dyn AlertSink>,
+        route: Route,
+        modality: Modality,
+        notification: bool,
+        lasting: bool,
+    ) -> SinkHandle {
+        let name = sink.name().to_string();
+        let (queue, alerts) = channel::<Alert>();
+        thread::Builder::new()
+            .name(format!("neckcheck-sink-{}", name))
+            .spawn(move || {
+                for alert in alerts {
+                    sink.handle(&alert);
+                }
+            })
+            .expect("Failed to spawn alert sink thread");
+        SinkHandle {
+            name,
+            route,
+            modality,
+            notification,
+            lasting,
+            queue,
+        }
+    }
+
+    fn send(&self, alert: &Alert) {
+        if self.queue.send(alert.clone()).is_err() {
+            println!("Alert sink {} has stopped", self.name);
+        }
+    }
+}
+
+// Turns posture events into alerts, owns the reminder/escalation timers, and routes each alert
+// only to the sinks whose rules match it.
+pub struct AlertManager {
+    sinks: Vec<SinkHandle>,
+    // with pair_alerts, a tone for alerts that are only seen and a notification for those that
+    // are only heard
+    audio_companion: Option<SinkHandle>,
+    visual_companion: Option<SinkHandle>,
+    // how far alerts are toned down right now, and a notification for softened alerts that no
+    // notification sink would show
+    quiet: watch::Receiver<Quiet>,
+    quiet_companion: SinkHandle,
+    repeat_interval: Duration,
+    sustained_after: Duration,
+    stand_up_after: Option<Duration>,
+    min_break: Duration,
+    // with the strict profile, how long after a stand-up reminder the screen is locked, and the
+    // breathing guide shown before
+    lock_after: Option<Duration>,
+    breathing: Option<BreathingConfig>,
+    coaching: Option<Coaching>,
+    event_log: Option<EventLog>,
+}
+
+impl AlertManager {
+    // Alerts can be answered, e.g. from the buttons on a notification, which is sent to `feedback`
+    pub fn new(
+        config: &AlertConfig,
+        accessibility: &AccessibilityConfig,
+        feedback: Sender<Feedback>,
+        quiet: watch::Receiver<Quiet>,
+    ) -> AlertManager {
+        let spawn = |sink_config: &SinkConfig| {
+            let (sink, route) = build_sink(sink_config, accessibility, &feedback);
+            SinkHandle::spawn(
+                sink,
+                route,
+                modality(&sink_config.kind),
+                matches!(sink_config.kind, SinkKind::Notification),
+                SinkGroup::of(&sink_config.kind) == SinkGroup::Overlay,
+            )
+        };
+        let sinks = config.sinks.iter().map(spawn).collect();
+        let companion = |kind: SinkKind| {
+            spawn(&SinkConfig {
+                kind,
+                min_severity: Severity::Info,
+                events: None,
+            })
+        };
+        let pair = accessibility.pair_alerts;
+        AlertManager {
+            sinks,
+            audio_companion: matches!(pair, PairAlerts::Audio | PairAlerts::Both)
+                .then(|| companion(SinkKind::Tone { duration: 1.0 })),
+            visual_companion: matches!(pair, PairAlerts::Visual | PairAlerts::Both)
+                .then(|| companion(SinkKind::Notification)),
+            quiet,
+            quiet_companion: companion(SinkKind::Notification),
+            repeat_interval: Duration::from_secs_f64(config.repeat_interval),
+            sustained_after: Duration::from_secs_f64(config.sustained_after),
+            stand_up_after: (config.stand_up_after > 0.0)
+                .then(|| Duration::from_secs_f64(config.stand_up_after)),
+            min_break: Duration::from_secs_f64(config.min_break),
+            lock_after: None,
+            breathing: None,
+            coaching: None,
+            event_log: None,
+        }
+    }
+
+    // Logs every alert and the sinks it went to
+    pub fn with_event_log(self, event_log: EventLog) -> AlertManager {
+        AlertManager {
+            event_log: Some(event_log),
+            ..self
+        }
+    }
+
+    // Enforces breaks: locks the screen if the user is still there `after` a stand-up reminder,
+    // after the breathing guide if there is one
+    pub fn with_enforced_breaks(
+        self,
+        after: Duration,
+        breathing: Option<BreathingConfig>,
+    ) -> AlertManager {
+        AlertManager {
+            lock_after: Some(after),
+            breathing,
+            ..self
+        }
+    }
+
+    // Words posture alerts as tips on what to do about it, a different one each time
+    pub fn with_coaching(self, coaching: Coaching) -> AlertManager {
+        AlertManager {
+            coaching: Some(coaching),
+            ..self
+        }
+    }
+
+    pub fn dispatch(&self, alert: Alert) {
+        let alert = match &self.coaching {
+            Some(coaching) => coaching.coach(alert),
+            None => alert,
+        };
+        let quiet = *self.quiet.borrow();
+        let mut noticed = Modality::default();
+        let mut softened = false;
+        let mut sent = Vec::new();
+        for sink in self.sinks.iter().filter(|sink| sink.route.matches(&alert)) {
+            if !heard(sink, quiet) {
+                softened = true;
+                continue;
+            }
+            noticed.audio |= sink.modality.audio;
+            noticed.visual |= sink.modality.visual;
+            sink.send(&alert);
+            sent.push(sink.name.clone());
+        }
+        let mut companion = None;
+        if quiet != Quiet::Normal {
+            if quiet != Quiet::Silent && softened && !noticed.visual {
+                companion = Some(&self.quiet_companion);
+            }
+        } else if noticed.visual && !noticed.audio {
+            companion = self.audio_companion.as_ref();
+        } else if noticed.audio && !noticed.visual {
+            companion = self.visual_companion.as_ref();
+        }
+        if let Some(companion) = companion {
+            companion.send(&alert);
+            sent.push(companion.name.clone());
+        }
+        self.log(&alert, sent);
+    }
+
+    // Shows what crossing the calibrated limit does, right after calibrating: the posture alert
+    // played quietly by the sinks that are heard or seen at the desk, and taken back again by those
+    // that would otherwise show it until the posture recovers
+    fn preview(&self) {
+        let (Some(mut alert), Some(mut recovered)) = (
+            alert_for(&PostureEvent::TooClose),
+            alert_for(&PostureEvent::Recovered),
+        ) else {
+            return;
+        };
+        alert.preview = true;
+        alert.message = tr!("alert-preview");
+        recovered.preview = true;
+        let quiet = *self.quiet.borrow();
+        let mut lasting = Vec::new();
+        for sink in self.sinks.iter().filter(|sink| {
+            sink.route.matches(&alert)
+                && (sink.modality.audio || sink.modality.visual)
+                && heard(sink, quiet)
+        }) {
+            sink.send(&alert);
+            if sink.lasting {
+                lasting.push(sink.queue.clone());
+            }
+        }
+        if lasting.is_empty() {
+            return;
+        }
+        thread::spawn(move || {
+            thread::sleep(PREVIEW_SHOWN_FOR);
+            for queue in lasting {
+                let _ = queue.send(recovered.clone());
+            }
+        });
+    }
+
+    fn log(&self, alert: &Alert, sinks: Vec<String>) {
+        if let Some(event_log) = &self.event_log {
+            event_log.record(Entry::Alert {
+                kind: alert.kind,
+                severity: alert.severity,
+                sinks,
+            });
+        }
+    }
+
+    pub async fn run(self, mut events: Receiver<PostureEvent>, shutdown: CancellationToken) {
+        // set while the user is too close: when that started, and the reminder timer
+        let mut too_close: Option<(Instant, Interval)> = None;
+        let mut sustained = false;
+        // posture alerts are held back until then
+        let mut snoozed_until: Option<Instant> = None;
+        // since when the user has been sitting without a break, how many times they were told to
+        // stand up since, and since when they are away (which may turn out to be a break)
+        let mut seated_since = Instant::now();
+        let mut stand_up_reminders = 0;
+        let mut away_since: Option<Instant> = None;
+        // when the screen is locked to enforce the break, unless the user gets up before, and
+        // whether the breathing guide has been shown for it
+        let mut lock_at: Option<Instant> = None;
+        let mut guided = false;
+        // how long after the capture of its frame the latest check decided on it, which is how
+        // much earlier than they arrive its events really happened
+        let mut behind = Duration::ZERO;
+        // how serious a script found the latest check, if it said
+        let mut judged: Option<Severity> = None;
+        loop {
+            let sustained_at = too_close
+                .as_ref()
+                .map(|(since, _)| *since + self.sustained_after);
+            let stand_up_at = self
+                .stand_up_after
+                .filter(|_| away_since.is_none())
+                .map(|after| seated_since + after * (stand_up_reminders + 1));
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                event = events.recv() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return,
+                    };
+                    let happened = Instant::now().checked_sub(behind).unwrap_or_else(Instant::now);
+                    match event {
+                        PostureEvent::Metrics(metrics) => {
+                            behind = Duration::from_millis(metrics.decision_latency_ms.into());
+                            judged = metrics.severity;
+                        }
+                        PostureEvent::TooClose => {
+                            // the TooClose alert covers the time up to the first reminder
+                            let mut reminders = interval_at(
+                                Instant::now() + first_reminder_in(behind, self.repeat_interval),
+                                self.repeat_interval,
+                            );
+                            reminders.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                            too_close = Some((happened, reminders));
+                            sustained = false;
+                        }
+                        PostureEvent::Away => {
+                            too_close = None;
+                            lock_at = None;
+                            away_since = Some(happened);
+                        }
+                        // a pause is as good as a break if it is long enough
+                        PostureEvent::Paused | PostureEvent::GuestArrived => {
+                            too_close = None;
+                            lock_at = None;
+                            away_since = Some(Instant::now());
+                        }
+                        PostureEvent::Recovered
+                        | PostureEvent::CameraLost(_)
+                        | PostureEvent::MonitoringFailed(_)
+                        | PostureEvent::Feedback(Feedback::Dismiss) => too_close = None,
+                        PostureEvent::Returned | PostureEvent::Resumed | PostureEvent::GuestLeft => {
+                            let back = match event {
+                                PostureEvent::Returned => happened,
+                                _ => Instant::now(),
+                            };
+                            let had_a_break = away_since.take().is_some_and(|since| {
+                                back.saturating_duration_since(since) >= self.min_break
+                            });
+                            if had_a_break {
+                                seated_since = back;
+                                stand_up_reminders = 0;
+                            }
+                        }
+                        PostureEvent::Feedback(Feedback::Snooze(duration)) => {
+                            too_close = None;
+                            snoozed_until = Some(Instant::now() + duration);
+                        }
+                        PostureEvent::Calibrated => self.preview(),
+                        _ => {}
+                    }
+                    let snoozed = snoozed_until.is_some_and(|until| Instant::now() < until);
+                    let alert = alert_for(&event).map(|mut alert| {
+                        if let (AlertKind::TooClose, Some(severity)) = (alert.kind, judged) {
+                            alert.severity = severity;
+                        }
+                        alert
+                    });
+                    match alert {
+                        Some(alert) if snoozed && is_posture_alert(alert.kind) => {
+                            self.log(&alert, Vec::new())
+                        }
+                        Some(alert) => self.dispatch(alert),
+                        None => {}
+                    }
+                }
+                _ = async { too_close.as_mut().unwrap().1.tick().await }, if too_close.is_some() => {
+                    let severity = if sustained {
+                        Severity::Critical
+                    } else {
+                        judged.unwrap_or(Severity::Warning)
+                    };
+                    self.dispatch(Alert {
+                        kind: AlertKind::Reminder,
+                        severity,
+                        message: tr!("alert-reminder"),
+                        preview: false,
+                    });
+                }
+                _ = sleep_until(stand_up_at.unwrap_or_else(Instant::now)), if stand_up_at.is_some() => {
+                    stand_up_reminders += 1;
+                    lock_at = self.lock_after.map(|after| Instant::now() + after);
+                    guided = false;
+                    self.dispatch(Alert {
+                        kind: AlertKind::StandUp,
+                        severity: Severity::Warning,
+                        message: tr!(
+                            "alert-stand-up",
+                            minutes = seated_since.elapsed().as_secs() / 60
+                        ),
+                        preview: false,
+                    });
+                }
+                _ = sleep_until(lock_at.unwrap_or_else(Instant::now)), if lock_at.is_some() => {
+                    if let (Some(breathing), false) = (&self.breathing, guided) {
+                        guided = true;
+                        lock_at = Some(Instant::now() + Duration::from_secs_f64(breathing.duration));
+                        let breathing = breathing.clone();
+                        tokio::task::spawn_blocking(move || breathing::guide(&breathing));
+                        continue;
+                    }
+                    lock_at = None;
+                    println!("Locking the screen for a break");
+                    if let Err(e) = desktop::lock_screen() {
+                        println!("Failed to lock the screen: {}", e);
+                    }
+                }
+                _ = sleep_until(sustained_at.unwrap_or_else(Instant::now)), if sustained_at.is_some() && !sustained => {
+                    sustained = true;
+                    self.dispatch(Alert {
+                        kind: AlertKind::Sustained,
+                        severity: Severity::Critical,
+                        message: tr!(
+                            "alert-sustained",
+                            seconds = self.sustained_after.as_secs()
+                        ),
+                        preview: false,
+                    });
+                }
+            }
+        }
+    }
+}
+
+// How long after a TooClose event arrives the first reminder is due, given how long after its frame
+// the check decided on it: what is left of the interval, or nothing if that took longer
+fn first_reminder_in(behind: Duration, repeat_interval: Duration) -> Duration {
+    repeat_interval.saturating_sub(behind)
+}
+
+// Whether a sink still gets alerts while they are toned down
+fn heard(sink: &SinkHandle, quiet: Quiet) -> bool {
+    match quiet {
+        Quiet::Normal => true,
+        Quiet::Visual => !sink.modality.audio,
+        Quiet::Notification => sink.notification,
+        Quiet::Silent => false,
+    }
+}
+
+// The alerts about the posture itself, which snoozing silences and which can be reported as wrong
+pub fn is_posture_alert(kind: AlertKind) -> bool {
+    matches!(
+        kind,
+        AlertKind::TooClose
+            | AlertKind::Reminder
+            | AlertKind::Sustained
+            | AlertKind::NeckAngle
+            | AlertKind::Slouch
+            | AlertKind::Sinking
+            | AlertKind::PostureCheck
+    )
+}
+
+// The alert for an event, if it has one
+pub fn alert_for(event: &PostureEvent) -> Option<Alert> {
+    let (kind, severity, message) = match event {
+        PostureEvent::TooClose => (
+            AlertKind::TooClose,
+            Severity::Warning,
+            tr!("alert-too-close"),
+        ),
+        PostureEvent::Recovered => (AlertKind::Recovered, Severity::Info, tr!("alert-recovered")),
+        PostureEvent::Away => (AlertKind::Away, Severity::Info, tr!("alert-away")),
+        PostureEvent::Returned => (AlertKind::Returned, Severity::Info, tr!("alert-returned")),
+        PostureEvent::CameraLost(reason) => (
+            AlertKind::CameraLost,
+            Severity::Warning,
+            tr!("alert-camera-lost", reason = reason.as_str()),
+        ),
+        PostureEvent::CameraRecovered => (
+            AlertKind::CameraRecovered,
+            Severity::Info,
+            tr!("alert-camera-recovered"),
+        ),
+        PostureEvent::CameraOpened => (
+            AlertKind::CameraOpened,
+            Severity::Info,
+            tr!("alert-camera-opened"),
+        ),
+        PostureEvent::CameraClosed => (
+            AlertKind::CameraClosed,
+            Severity::Info,
+            tr!("alert-camera-closed"),
+        ),
+        PostureEvent::CameraSwitched(camera) => (
+            AlertKind::CameraSwitched,
+            Severity::Info,
+            tr!("alert-camera-switched", camera = camera.as_str()),
+        ),
+        PostureEvent::MonitoringFailed(reason) => (
+            AlertKind::MonitoringFailed,
+            Severity::Critical,
+            tr!("alert-monitoring-failed", reason = reason.as_str()),
+        ),
+        PostureEvent::NeckTooSteep => (
+            AlertKind::NeckAngle,
+            Severity::Warning,
+            tr!("alert-neck-angle"),
+        ),
+        PostureEvent::NeckRecovered => (
+            AlertKind::NeckAngleRecovered,
+            Severity::Info,
+            tr!("alert-neck-angle-recovered"),
+        ),
+        PostureEvent::Drowsy => (AlertKind::Drowsy, Severity::Warning, tr!("alert-drowsy")),
+        PostureEvent::Awake => (AlertKind::Awake, Severity::Info, tr!("alert-awake")),
+        PostureEvent::LowBlinkRate => (
+            AlertKind::LowBlinkRate,
+            Severity::Warning,
+            tr!("alert-low-blink-rate"),
+        ),
+        PostureEvent::BlinkRateRecovered => (
+            AlertKind::BlinkRateRecovered,
+            Severity::Info,
+            tr!("alert-blink-rate-recovered"),
+        ),
+        PostureEvent::Slouching => (AlertKind::Slouch, Severity::Warning, tr!("alert-slouch")),
+        PostureEvent::SlouchRecovered => (
+            AlertKind::SlouchRecovered,
+            Severity::Info,
+            tr!("alert-slouch-recovered"),
+        ),
+        PostureEvent::Sinking => (AlertKind::Sinking, Severity::Warning, tr!("alert-sinking")),
+        PostureEvent::SinkingRecovered => (
+            AlertKind::SinkingRecovered,
+            Severity::Info,
+            tr!("alert-sinking-recovered"),
+        ),
+        PostureEvent::Summary(message) => (AlertKind::Summary, Severity::Info, message.clone()),
+        PostureEvent::Exercise { name, reps } => (
+            AlertKind::Exercise,
+            Severity::Info,
+            tr!("alert-exercise", exercise = name.clone(), reps = *reps),
+        ),
+        PostureEvent::PostureCheck => (
+            AlertKind::PostureCheck,
+            Severity::Info,
+            tr!("alert-posture-check"),
+        ),
+        PostureEvent::EyeBreak => (AlertKind::EyeBreak, Severity::Info, tr!("alert-eye-break")),
+        PostureEvent::Paused => (AlertKind::Paused, Severity::Info, tr!("alert-paused")),
+        PostureEvent::Resumed => (AlertKind::Resumed, Severity::Info, tr!("alert-resumed")),
+        // measurements for the stats and status displays rather than something to alert on
+        PostureEvent::BlinkRate { .. }
+        | PostureEvent::Feedback(_)
+        | PostureEvent::Metrics(_)
+        | PostureEvent::Memory(_)
+        | PostureEvent::PowerSaving(_)
+        | PostureEvent::Calibrated => return None,
+        // nothing to tell a guest, and the user isn't there
+        PostureEvent::GuestArrived | PostureEvent::GuestLeft => return None,
+    };
+    Some(Alert {
+        kind,
+        severity,
+        message,
+        preview: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_slow_check_brings_the_first_reminder_forward() {
+        let interval = Duration::from_secs(30);
+        assert_eq!(first_reminder_in(Duration::ZERO, interval), interval);
+        assert_eq!(
+            first_reminder_in(Duration::from_millis(750), interval),
+            Duration::from_millis(29_250)
+        );
+        // a check slower than the interval makes the reminder due right away
+        assert_eq!(
+            first_reminder_in(Duration::from_secs(45), interval),
+            Duration::ZERO
+        );
+        assert_eq!(
+            first_reminder_in(Duration::from_millis(u32::MAX.into()), interval),
+            Duration::ZERO
+        );
+    }
+}
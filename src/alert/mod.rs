@@ -0,0 +1,287 @@
+mod brightness;
+mod coaching;
+#[cfg(feature = "email")]
+mod email;
+mod escalation;
+mod lights;
+mod log;
+mod manager;
+mod night_mode;
+mod notification;
+mod obs;
+mod openrgb;
+mod state;
+mod terminal;
+mod tone;
+mod webhook;
+
+pub use coaching::Coaching;
+pub use manager::{alert_for, is_posture_alert, AlertManager};
+pub use state::BadPosture;
+
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::events::{Feedback, PostureEvent};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{AccessibilityConfig, AlertConfig, SinkConfig, SinkKind};
+
+// how long `neckcheck test-alert` leaves an alert showing before taking it back
+const TEST_SHOWN_FOR: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    TooClose,
+    // still too close, repeated every `repeat_interval`
+    Reminder,
+    // too close for longer than `sustained_after`
+    Sustained,
+    Recovered,
+    Away,
+    Returned,
+    CameraLost,
+    CameraRecovered,
+    // the camera was switched on, and off again
+    CameraOpened,
+    CameraClosed,
+    // another of several cameras took over
+    CameraSwitched,
+    MonitoringFailed,
+    // the neck has been bent forward for too long
+    NeckAngle,
+    NeckAngleRecovered,
+    // eyes closed or heavy-lidded for too long
+    Drowsy,
+    Awake,
+    // blinking too little, as when staring at a screen
+    LowBlinkRate,
+    BlinkRateRecovered,
+    // shoulders raised or rolled forward for too long
+    Slouch,
+    SlouchRecovered,
+    // sunk down in the chair for too long
+    Sinking,
+    SinkingRecovered,
+    // seated for too long without a break
+    StandUp,
+    // the end of day summary
+    Summary,
+    // an exercise of the physiotherapy program is due
+    Exercise,
+    // a posture reminder and a 20-20-20 eye break, on a timer without a camera
+    PostureCheck,
+    EyeBreak,
+    // monitoring paused by the user, and resumed
+    Paused,
+    Resumed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub severity: Severity,
+    pub message: String,
+    // only showing what the alert will be like, right after calibrating: sinks keep it low-key
+    #[serde(skip)]
+    pub preview: bool,
+}
+
+// Somewhere an alert can be delivered. Each sink runs on its own thread, so `handle` is free to
+// block (e.g. while a tone plays).
+pub trait AlertSink: Send {
+    fn name(&self) -> &str;
+    fn handle(&mut self, alert: &Alert);
+}
+
+// Which alerts a sink wants
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub min_severity: Severity,
+    pub kinds: Option<Vec<AlertKind>>,
+}
+
+impl Route {
+    pub fn matches(&self, alert: &Alert) -> bool {
+        alert.severity >= self.min_severity
+            && self
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&alert.kind))
+    }
+}
+
+// How a sink's alerts are noticed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Modality {
+    pub audio: bool,
+    pub visual: bool,
+}
+
+pub fn modality(kind: &SinkKind) -> Modality {
+    let (audio, visual) = match kind {
+        SinkKind::Tone { .. } => (true, false),
+        SinkKind::Terminal(terminal) => (terminal.bell, terminal.osc9 || terminal.tmux),
+        SinkKind::Notification
+        | SinkKind::Obs(_)
+        | SinkKind::Hue(_)
+        | SinkKind::Lifx(_)
+        | SinkKind::Wled(_)
+        | SinkKind::OpenRgb(_)
+        | SinkKind::Escalation(_)
+        | SinkKind::Brightness(_)
+        | SinkKind::NightMode(_) => (false, true),
+        // somewhere else, or nowhere the user is
+        SinkKind::Webhook { .. } | SinkKind::Email(_) | SinkKind::Log => (false, false),
+    };
+    Modality { audio, visual }
+}
+
+// The sinks by how they alert, for `neckcheck test-alert`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkGroup {
+    /// What stays shown until the posture recovers: OBS sources, lights, the screen's brightness
+    /// and night mode, and escalation
+    Overlay,
+    /// Tones
+    Sound,
+    /// Desktop and terminal notifications, webhooks, email and the log
+    Notify,
+    /// Every sink
+    All,
+}
+
+impl SinkGroup {
+    fn of(kind: &SinkKind) -> SinkGroup {
+        match kind {
+            SinkKind::Tone { .. } => SinkGroup::Sound,
+            SinkKind::Notification
+            | SinkKind::Terminal(_)
+            | SinkKind::Webhook { .. }
+            | SinkKind::Email(_)
+            | SinkKind::Log => SinkGroup::Notify,
+            SinkKind::Obs(_)
+            | SinkKind::Hue(_)
+            | SinkKind::Lifx(_)
+            | SinkKind::Wled(_)
+            | SinkKind::OpenRgb(_)
+            | SinkKind::Escalation(_)
+            | SinkKind::Brightness(_)
+            | SinkKind::NightMode(_) => SinkGroup::Overlay,
+        }
+    }
+
+    fn includes(self, kind: &SinkKind) -> bool {
+        self == SinkGroup::All || self == SinkGroup::of(kind)
+    }
+}
+
+// Fires a posture alert once through each configured sink of `group`, whatever it is routed, so
+// that they can be tried out without leaning in. What stays shown is taken back after a moment.
+pub fn test_sinks(alerts: &AlertConfig, accessibility: &AccessibilityConfig, group: SinkGroup) {
+    let (Some(alert), Some(recovered)) = (
+        manager::alert_for(&PostureEvent::TooClose),
+        manager::alert_for(&PostureEvent::Recovered),
+    ) else {
+        return;
+    };
+    // answers to the test are of no use to anyone
+    let (feedback, _answers) = channel();
+    let mut tested = 0;
+    for config in alerts
+        .sinks
+        .iter()
+        .filter(|sink| group.includes(&sink.kind))
+    {
+        let (mut sink, route) = build_sink(config, accessibility, &feedback);
+        if route.matches(&alert) {
+            println!("Testing {}", sink.name());
+        } else {
+            println!(
+                "Testing {}, which posture alerts aren't routed to otherwise",
+                sink.name()
+            );
+        }
+        sink.handle(&alert);
+        if SinkGroup::of(&config.kind) == SinkGroup::Overlay {
+            thread::sleep(TEST_SHOWN_FOR);
+            sink.handle(&recovered);
+        }
+        tested += 1;
+    }
+    if tested == 0 {
+        println!("No such sinks are configured in [[alerts.sinks]]");
+    }
+}
+
+// The kind of alert an event would cause, if any
+pub fn alert_kind(event: &PostureEvent) -> Option<AlertKind> {
+    manager::alert_for(event).map(|alert| alert.kind)
+}
+
+pub fn build_sink(
+    config: &SinkConfig,
+    accessibility: &AccessibilityConfig,
+    feedback: &Sender<Feedback>,
+) -> (Box<dyn AlertSink>, Route) {
+    let flash = |flash: bool| flash && !accessibility.reduced_motion;
+    let sink: Box<dyn AlertSink> = match &config.kind {
+        SinkKind::Tone { duration } => Box::new(tone::ToneSink::new(*duration)),
+        SinkKind::Notification => Box::new(notification::NotificationSink::new(feedback.clone())),
+        SinkKind::Webhook { url } => Box::new(webhook::WebhookSink::new(url)),
+        #[cfg(feature = "email")]
+        SinkKind::Email(email) => Box::new(email::EmailSink::new(email)),
+        #[cfg(not(feature = "email"))]
+        SinkKind::Email(email) => {
+            println!(
+                "Emailing {} needs neckcheck built with the `email` feature, logging instead",
+                email.to.join(", ")
+            );
+            Box::new(log::LogSink::new())
+        }
+        SinkKind::Obs(obs) => Box::new(obs::ObsSink::new(obs)),
+        SinkKind::Hue(hue) => Box::new(lights::LightSink::new(
+            lights::HueLight::new(hue),
+            hue.color,
+            flash(hue.flash),
+        )),
+        SinkKind::Lifx(lifx) => Box::new(lights::LightSink::new(
+            lights::LifxLight::new(lifx),
+            lifx.color,
+            flash(lifx.flash),
+        )),
+        SinkKind::Wled(wled) => Box::new(lights::LightSink::new(
+            lights::WledLight::new(wled),
+            wled.color,
+            flash(wled.flash),
+        )),
+        SinkKind::OpenRgb(openrgb) => Box::new(lights::LightSink::new(
+            openrgb::OpenRgbLight::new(openrgb),
+            openrgb.color,
+            false,
+        )),
+        SinkKind::Terminal(terminal) => Box::new(terminal::TerminalSink::new(terminal)),
+        SinkKind::Escalation(escalation) => Box::new(escalation::EscalationSink::new(escalation)),
+        SinkKind::Brightness(brightness) => Box::new(brightness::BrightnessSink::new(brightness)),
+        SinkKind::NightMode(night_mode) => Box::new(night_mode::NightModeSink::new(night_mode)),
+        SinkKind::Log => Box::new(log::LogSink::new()),
+    };
+    let route = Route {
+        min_severity: config.min_severity,
+        kinds: config.events.clone(),
+    };
+    (sink, route)
+}
@@ -0,0 +1,42 @@
+use super::{Alert, AlertKind, AlertSink};
+use crate::config::NightModeConfig;
+use crate::desktop::{self, NightMode};
+
+// Turns on a blue light filter when it is time for a break, and restores the screen once the user
+// is back from it (or resumes monitoring), so the break feels like one
+pub struct NightModeSink {
+    config: NightModeConfig,
+    active: Option<NightMode>,
+}
+
+impl NightModeSink {
+    pub fn new(config: &NightModeConfig) -> NightModeSink {
+        NightModeSink {
+            config: config.clone(),
+            active: None,
+        }
+    }
+}
+
+impl AlertSink for NightModeSink {
+    fn name(&self) -> &str {
+        "night_mode"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        let result = match alert.kind {
+            AlertKind::StandUp if self.active.is_none() => {
+                desktop::night_mode_on(self.config.method, self.config.temperature)
+                    .map(|night_mode| self.active = Some(night_mode))
+            }
+            AlertKind::Returned | AlertKind::Resumed => match self.active.take() {
+                Some(night_mode) => desktop::night_mode_off(night_mode),
+                None => return,
+            },
+            _ => return,
+        };
+        if let Err(e) = result {
+            println!("Failed to switch night mode: {}", e);
+        }
+    }
+}
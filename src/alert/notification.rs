@@ -0,0 +1,92 @@
+use std::sync::mpsc::Sender;
+
+use notify_rust::Notification;
+
+use super::{Alert, AlertSink};
+use crate::events::Feedback;
+
+// How long the snooze button on a toast snoozes for
+#[cfg(windows)]
+const SNOOZE_MINUTES: u64 = 10;
+
+// A desktop notification per alert. On Windows, posture alerts come as toasts with buttons to
+// snooze or dismiss them, which are passed on as feedback.
+pub struct NotificationSink {
+    #[cfg_attr(not(windows), allow(dead_code))]
+    feedback: Sender<Feedback>,
+}
+
+impl NotificationSink {
+    pub fn new(feedback: Sender<Feedback>) -> NotificationSink {
+        NotificationSink { feedback }
+    }
+}
+
+impl AlertSink for NotificationSink {
+    fn name(&self) -> &str {
+        "notification"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        let mut notification = Notification::new();
+        notification.summary("neckcheck").body(&alert.message);
+        // urgency is only supported by the freedesktop notification spec
+        #[cfg(all(unix, not(target_os = "macos")))]
+        notification.urgency(match alert.severity {
+            super::Severity::Info => notify_rust::Urgency::Low,
+            super::Severity::Warning => notify_rust::Urgency::Normal,
+            super::Severity::Critical => notify_rust::Urgency::Critical,
+        });
+        #[cfg(windows)]
+        if is_posture(alert.kind) {
+            notification
+                .action(
+                    "snooze",
+                    &crate::i18n::tr!("alert-action-snooze", minutes = SNOOZE_MINUTES),
+                )
+                .action("dismiss", &crate::i18n::tr!("alert-action-fixed"));
+        }
+        match notification.show() {
+            #[cfg(windows)]
+            Ok(handle) if is_posture(alert.kind) => {
+                // waiting for a click would hold up the alerts after this one
+                let feedback = self.feedback.clone();
+                let spawned = std::thread::Builder::new()
+                    .name("neckcheck-toast".to_string())
+                    .spawn(move || {
+                        handle.wait_for_action(|action| {
+                            let chosen = match action {
+                                "snooze" => Feedback::Snooze(std::time::Duration::from_secs(
+                                    SNOOZE_MINUTES * 60,
+                                )),
+                                "dismiss" => Feedback::Dismiss,
+                                _ => return,
+                            };
+                            let _ = feedback.send(chosen);
+                        })
+                    });
+                if let Err(e) = spawned {
+                    println!("Failed to wait for the notification's buttons: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => println!("Failed to show notification: {}", e),
+        }
+    }
+}
+
+// Whether snoozing or dismissing the alert makes sense
+#[cfg(windows)]
+fn is_posture(kind: super::AlertKind) -> bool {
+    use super::AlertKind;
+
+    matches!(
+        kind,
+        AlertKind::TooClose
+            | AlertKind::Reminder
+            | AlertKind::Sustained
+            | AlertKind::NeckAngle
+            | AlertKind::Slouch
+            | AlertKind::Sinking
+    )
+}
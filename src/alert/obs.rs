@@ -0,0 +1,185 @@
+use std::net::TcpStream;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use super::{Alert, AlertSink, BadPosture};
+use crate::config::ObsConfig;
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+// obs-websocket 5 opcodes
+const HELLO: u64 = 0;
+const IDENTIFY: u64 = 1;
+const IDENTIFIED: u64 = 2;
+const REQUEST: u64 = 6;
+const REQUEST_RESPONSE: u64 = 7;
+
+// Shows a scene item (or enables a filter) in OBS while the posture is bad, and hides it again on
+// recovery, through obs-websocket 5
+pub struct ObsSink {
+    config: ObsConfig,
+    // connected on first use, and again after a failure
+    socket: Option<Socket>,
+    state: BadPosture,
+    next_request: u64,
+}
+
+impl ObsSink {
+    pub fn new(config: &ObsConfig) -> ObsSink {
+        ObsSink {
+            config: config.clone(),
+            socket: None,
+            state: BadPosture::new(),
+            next_request: 0,
+        }
+    }
+
+    fn show(&mut self, visible: bool) -> Result<(), String> {
+        let config = self.config.clone();
+        match &config.filter {
+            Some(filter) => self.request(
+                "SetSourceFilterEnabled",
+                json!({
+                    "sourceName": config.source,
+                    "filterName": filter,
+                    "filterEnabled": visible,
+                }),
+            )?,
+            None => {
+                let response = self.request(
+                    "GetSceneItemId",
+                    json!({"sceneName": config.scene, "sourceName": config.source}),
+                )?;
+                let id = response["sceneItemId"]
+                    .as_u64()
+                    .ok_or_else(|| format!("No {} in the scene {}", config.source, config.scene))?;
+                self.request(
+                    "SetSceneItemEnabled",
+                    json!({
+                        "sceneName": config.scene,
+                        "sceneItemId": id,
+                        "sceneItemEnabled": visible,
+                    }),
+                )?
+            }
+        };
+        Ok(())
+    }
+
+    // Sends a request and waits for its response data
+    fn request(&mut self, request_type: &str, data: Value) -> Result<Value, String> {
+        if self.socket.is_none() {
+            self.socket = Some(connect(&self.config)?);
+        }
+        let socket = self.socket.as_mut().unwrap();
+        self.next_request += 1;
+        let id = self.next_request.to_string();
+        send(
+            socket,
+            REQUEST,
+            json!({"requestType": request_type, "requestId": id, "requestData": data}),
+        )?;
+        loop {
+            let response = receive(socket, REQUEST_RESPONSE)?;
+            if response["requestId"] != id.as_str() {
+                continue;
+            }
+            let status = &response["requestStatus"];
+            if status["result"] != true {
+                return Err(format!(
+                    "{} failed: {}",
+                    request_type,
+                    status["comment"].as_str().unwrap_or("unknown error")
+                ));
+            }
+            return Ok(response["responseData"].clone());
+        }
+    }
+}
+
+fn connect(config: &ObsConfig) -> Result<Socket, String> {
+    let (mut socket, _) = tungstenite::connect(config.address.as_str())
+        .map_err(|e| format!("Failed to connect to {}: {}", config.address, e))?;
+    let hello = receive(&mut socket, HELLO)?;
+    let mut identify = json!({"rpcVersion": 1, "eventSubscriptions": 0});
+    if let Some(auth) = hello.get("authentication") {
+        let password = config
+            .password
+            .as_deref()
+            .ok_or("OBS asks for a password but none is configured")?;
+        identify["authentication"] = authentication(
+            password,
+            auth["salt"].as_str().unwrap_or_default(),
+            auth["challenge"].as_str().unwrap_or_default(),
+        )
+        .into();
+    }
+    send(&mut socket, IDENTIFY, identify)?;
+    receive(&mut socket, IDENTIFIED)?;
+    Ok(socket)
+}
+
+// base64(sha256(base64(sha256(password + salt)) + challenge)), as obs-websocket expects
+fn authentication(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = STANDARD.encode(Sha256::digest(format!("{}{}", password, salt)));
+    STANDARD.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+fn send(socket: &mut Socket, op: u64, data: Value) -> Result<(), String> {
+    socket
+        .send(Message::text(json!({"op": op, "d": data}).to_string()))
+        .map_err(|e| e.to_string())
+}
+
+// Waits for a message with the given opcode, skipping any others
+fn receive(socket: &mut Socket, op: u64) -> Result<Value, String> {
+    loop {
+        let message = socket.read().map_err(|e| e.to_string())?;
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let message: Value = serde_json::from_str(text.as_str()).map_err(|e| e.to_string())?;
+        if message["op"] == op {
+            return Ok(message["d"].clone());
+        }
+    }
+}
+
+impl AlertSink for ObsSink {
+    fn name(&self) -> &str {
+        "obs"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        let Some(bad) = self.state.update(alert.kind) else {
+            return;
+        };
+        if let Err(e) = self.show(bad) {
+            println!("Failed to update OBS: {}", e);
+            self.socket = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authentication_matches_the_protocol_example() {
+        // from the obs-websocket 5 protocol documentation
+        assert_eq!(
+            authentication(
+                "supersecretpassword",
+                "lM1GncleQOaCu9lT1yeUZhFYnqhsLLP1G5lAGo3ixaI=",
+                "+IxH4CnCiqpX1rM9scsNynZzbOe4KhDeYcTNS3PDaeY="
+            ),
+            "1Ct943GAT+6YQUUX47Ia/ncufilbe6+oD6lY+5kaCu4="
+        );
+    }
+}
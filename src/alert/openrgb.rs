@@ -0,0 +1,213 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use super::lights::Light;
+use crate::config::OpenRgbConfig;
+
+// OpenRGB SDK packet ids
+const SET_CLIENT_NAME: u32 = 50;
+const REQUEST_CONTROLLER_DATA: u32 = 1;
+const REQUEST_LOAD_PROFILE: u32 = 151;
+const UPDATE_LEDS: u32 = 1050;
+const UPDATE_ZONE_LEDS: u32 = 1051;
+const SET_CUSTOM_MODE: u32 = 1100;
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+// A keyboard (or any other device) lit through an OpenRGB SDK server, set back to a saved profile
+// afterwards
+pub struct OpenRgbLight {
+    config: OpenRgbConfig,
+}
+
+impl OpenRgbLight {
+    pub fn new(config: &OpenRgbConfig) -> OpenRgbLight {
+        OpenRgbLight {
+            config: config.clone(),
+        }
+    }
+
+    fn connect(&self) -> Result<TcpStream, String> {
+        let mut stream = TcpStream::connect(&self.config.address)
+            .map_err(|e| format!("Failed to connect to {}: {}", self.config.address, e))?;
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .map_err(|e| e.to_string())?;
+        send(&mut stream, 0, SET_CLIENT_NAME, b"neckcheck\0")?;
+        Ok(stream)
+    }
+}
+
+impl Light for OpenRgbLight {
+    fn name(&self) -> &str {
+        "openrgb"
+    }
+
+    // the profile is what is restored
+    fn save(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn alert(&mut self, [r, g, b]: [u8; 3], _flash: bool) -> Result<(), String> {
+        let mut stream = self.connect()?;
+        let device = self.config.device;
+        // without a protocol version the server answers in version 0 of the format
+        send(&mut stream, device, REQUEST_CONTROLLER_DATA, &[])?;
+        let data = receive(&mut stream, REQUEST_CONTROLLER_DATA)?;
+        let (zones, leds) = led_counts(&data)?;
+        send(&mut stream, device, SET_CUSTOM_MODE, &[])?;
+        let (id, count, mut payload) = match self.config.zone {
+            Some(zone) => {
+                let count = *zones
+                    .get(zone as usize)
+                    .ok_or_else(|| format!("Device {} has no zone {}", device, zone))?;
+                (UPDATE_ZONE_LEDS, count, zone.to_le_bytes().to_vec())
+            }
+            None => (UPDATE_LEDS, leds, Vec::new()),
+        };
+        payload.extend((count as u16).to_le_bytes());
+        for _ in 0..count {
+            payload.extend([r, g, b, 0]);
+        }
+        // the payload is prefixed with its own size, including that size
+        let mut data = ((payload.len() + 4) as u32).to_le_bytes().to_vec();
+        data.extend(payload);
+        send(&mut stream, device, id, &data)
+    }
+
+    fn restore(&mut self) -> Result<(), String> {
+        let mut stream = self.connect()?;
+        let mut name = self.config.profile.as_bytes().to_vec();
+        name.push(0);
+        send(&mut stream, 0, REQUEST_LOAD_PROFILE, &name)
+    }
+}
+
+fn send(stream: &mut TcpStream, device: u32, id: u32, data: &[u8]) -> Result<(), String> {
+    let mut packet = b"ORGB".to_vec();
+    packet.extend(device.to_le_bytes());
+    packet.extend(id.to_le_bytes());
+    packet.extend((data.len() as u32).to_le_bytes());
+    packet.extend(data);
+    stream.write_all(&packet).map_err(|e| e.to_string())
+}
+
+// Waits for a packet with the given id, skipping any others
+fn receive(stream: &mut TcpStream, id: u32) -> Result<Vec<u8>, String> {
+    loop {
+        let mut header = [0; 16];
+        stream.read_exact(&mut header).map_err(|e| e.to_string())?;
+        if &header[0..4] != b"ORGB" {
+            return Err("Not an OpenRGB server".to_string());
+        }
+        let field = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().unwrap());
+        let mut data = vec![0; field(12) as usize];
+        stream.read_exact(&mut data).map_err(|e| e.to_string())?;
+        if field(8) == id {
+            return Ok(data);
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, count: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .data
+            .get(self.at..self.at + count)
+            .ok_or("Truncated controller data")?;
+        self.at += count;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn skip_string(&mut self) -> Result<(), String> {
+        let length = self.u16()? as usize;
+        self.bytes(length).map(|_| ())
+    }
+}
+
+// The LED count of each zone and of the whole device, from version 0 controller data
+fn led_counts(data: &[u8]) -> Result<(Vec<u32>, u32), String> {
+    let mut reader = Reader { data, at: 0 };
+    // size and type
+    reader.bytes(8)?;
+    // name, description, version, serial and location
+    for _ in 0..5 {
+        reader.skip_string()?;
+    }
+    let modes = reader.u16()?;
+    // the active mode
+    reader.u32()?;
+    for _ in 0..modes {
+        reader.skip_string()?;
+        // value, flags, speed and colour limits, speed, direction and colour mode
+        reader.bytes(4 * 9)?;
+        let colors = reader.u16()? as usize;
+        reader.bytes(4 * colors)?;
+    }
+    let zone_count = reader.u16()?;
+    let mut zones = Vec::new();
+    for _ in 0..zone_count {
+        reader.skip_string()?;
+        // type, minimum and maximum LEDs
+        reader.bytes(4 * 3)?;
+        zones.push(reader.u32()?);
+        let matrix = reader.u16()? as usize;
+        reader.bytes(matrix)?;
+    }
+    let leds = reader.u16()? as u32;
+    Ok((zones, leds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(data: &mut Vec<u8>, value: &str) {
+        data.extend((value.len() as u16 + 1).to_le_bytes());
+        data.extend(value.as_bytes());
+        data.push(0);
+    }
+
+    #[test]
+    fn led_counts_are_read_from_the_controller_data() {
+        let mut data = vec![0; 8];
+        for value in ["Keyboard", "A keyboard", "1.0", "", "HID"] {
+            string(&mut data, value);
+        }
+        // one mode with two colours
+        data.extend(1u16.to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        string(&mut data, "Direct");
+        data.extend([0; 4 * 9]);
+        data.extend(2u16.to_le_bytes());
+        data.extend([0; 8]);
+        // a keys zone of 104 LEDs with a 1x2 matrix map, and a logo zone of 1
+        data.extend(2u16.to_le_bytes());
+        string(&mut data, "Keys");
+        data.extend([0; 12]);
+        data.extend(104u32.to_le_bytes());
+        data.extend(16u16.to_le_bytes());
+        data.extend([0; 16]);
+        string(&mut data, "Logo");
+        data.extend([0; 12]);
+        data.extend(1u32.to_le_bytes());
+        data.extend(0u16.to_le_bytes());
+        data.extend(105u16.to_le_bytes());
+
+        assert_eq!(led_counts(&data), Ok((vec![104, 1], 105)));
+        assert!(led_counts(&data[..data.len() - 1]).is_err());
+    }
+}
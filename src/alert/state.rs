@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use super::AlertKind;
+
+// Follows which posture problems are current from the alerts, for sinks that show a state (a
+// scene item, a light) rather than reacting to each alert
+#[derive(Debug, Default)]
+pub struct BadPosture {
+    // the alert that started each current problem
+    active: HashSet<AlertKind>,
+}
+
+impl BadPosture {
+    pub fn new() -> BadPosture {
+        BadPosture::default()
+    }
+
+    pub fn is_bad(&self) -> bool {
+        !self.active.is_empty()
+    }
+
+    // Some(true) when bad posture starts, Some(false) when the last problem is over, otherwise
+    // None
+    pub fn update(&mut self, kind: AlertKind) -> Option<bool> {
+        let was_bad = self.is_bad();
+        match kind {
+            AlertKind::TooClose | AlertKind::Reminder | AlertKind::Sustained => {
+                self.active.insert(AlertKind::TooClose);
+            }
+            AlertKind::NeckAngle | AlertKind::Slouch | AlertKind::Sinking => {
+                self.active.insert(kind);
+            }
+            AlertKind::Recovered => {
+                self.active.remove(&AlertKind::TooClose);
+            }
+            AlertKind::NeckAngleRecovered => {
+                self.active.remove(&AlertKind::NeckAngle);
+            }
+            AlertKind::SlouchRecovered => {
+                self.active.remove(&AlertKind::Slouch);
+            }
+            AlertKind::SinkingRecovered => {
+                self.active.remove(&AlertKind::Sinking);
+            }
+            // nobody there to have a bad posture
+            AlertKind::Away
+            | AlertKind::CameraLost
+            | AlertKind::MonitoringFailed
+            | AlertKind::Paused => self.active.clear(),
+            _ => {}
+        }
+        (self.is_bad() != was_bad).then_some(self.is_bad())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_posture_lasts_until_every_problem_is_over() {
+        let mut state = BadPosture::new();
+        assert_eq!(state.update(AlertKind::TooClose), Some(true));
+        assert_eq!(state.update(AlertKind::Reminder), None);
+        assert_eq!(state.update(AlertKind::Slouch), None);
+        assert_eq!(state.update(AlertKind::Recovered), None);
+        assert_eq!(state.update(AlertKind::SlouchRecovered), Some(false));
+        assert_eq!(state.update(AlertKind::Recovered), None);
+        assert_eq!(state.update(AlertKind::Sinking), Some(true));
+        assert_eq!(state.update(AlertKind::Away), Some(false));
+    }
+}
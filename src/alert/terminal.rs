@@ -0,0 +1,91 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::Command;
+
+use super::{Alert, AlertSink};
+use crate::config::TerminalConfig;
+
+// Reaches a terminal over SSH or in a multiplexer: a bell and an OSC 9 notification (shown by
+// e.g. wezterm, iTerm2, kitty, Windows Terminal) written to a TTY, and/or a tmux status message
+pub struct TerminalSink {
+    config: TerminalConfig,
+}
+
+impl TerminalSink {
+    pub fn new(config: &TerminalConfig) -> TerminalSink {
+        TerminalSink {
+            config: config.clone(),
+        }
+    }
+
+    fn write(&self, sequence: &str) -> std::io::Result<()> {
+        match &self.config.tty {
+            Some(tty) => OpenOptions::new()
+                .write(true)
+                .open(tty)?
+                .write_all(sequence.as_bytes()),
+            None => {
+                let mut stdout = std::io::stdout();
+                stdout.write_all(sequence.as_bytes())?;
+                stdout.flush()
+            }
+        }
+    }
+}
+
+// The bell and OSC 9 notification for a message, without any control characters of its own that
+// would end the sequence early
+fn escape_sequence(message: &str, bell: bool, osc9: bool) -> String {
+    let mut sequence = String::new();
+    if bell {
+        sequence.push('\x07');
+    }
+    if osc9 {
+        let message: String = message.chars().filter(|c| !c.is_control()).collect();
+        sequence.push_str(&format!("\x1b]9;{}\x07", message));
+    }
+    sequence
+}
+
+impl AlertSink for TerminalSink {
+    fn name(&self) -> &str {
+        "terminal"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        let sequence = escape_sequence(&alert.message, self.config.bell, self.config.osc9);
+        if !sequence.is_empty() {
+            if let Err(e) = self.write(&sequence) {
+                let tty = self.config.tty.as_deref().unwrap_or("stdout".as_ref());
+                println!("Failed to write the alert to {}: {}", tty.display(), e);
+            }
+        }
+        if self.config.tmux {
+            let mut command = Command::new("tmux");
+            command.arg("display-message");
+            if let Some(client) = &self.config.tmux_client {
+                command.args(["-c", client]);
+            }
+            // -l: the message is not a tmux format
+            match command.args(["-l", &alert.message]).status() {
+                Ok(status) if status.success() => {}
+                Ok(status) => println!("tmux display-message failed: {}", status),
+                Err(e) => println!("Failed to run tmux: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_wrapped_in_osc_9() {
+        assert_eq!(
+            escape_sequence("Sit back\x1b]0;x", true, true),
+            "\x07\x1b]9;Sit back]0;x\x07"
+        );
+        assert_eq!(escape_sequence("Sit back", false, false), "");
+    }
+}
@@ -0,0 +1,26 @@
+use super::{Alert, AlertSink};
+use crate::tone::play_tone;
+
+// of the full volume, for previews
+const PREVIEW_VOLUME: f32 = 0.3;
+
+pub struct ToneSink {
+    duration: f64,
+}
+
+impl ToneSink {
+    pub fn new(duration: f64) -> ToneSink {
+        ToneSink { duration }
+    }
+}
+
+impl AlertSink for ToneSink {
+    fn name(&self) -> &str {
+        "tone"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        let volume = if alert.preview { PREVIEW_VOLUME } else { 1.0 };
+        play_tone(self.duration, volume);
+    }
+}
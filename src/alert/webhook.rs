@@ -0,0 +1,26 @@
+use super::{Alert, AlertSink};
+
+// POSTs every alert as JSON, e.g. `{"kind":"too_close","severity":"warning","message":"..."}`
+pub struct WebhookSink {
+    url: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> WebhookSink {
+        WebhookSink {
+            url: url.to_string(),
+        }
+    }
+}
+
+impl AlertSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn handle(&mut self, alert: &Alert) {
+        if let Err(e) = ureq::post(&self.url).send_json(alert) {
+            println!("Failed to call webhook {}: {}", self.url, e);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::path::Path;
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{Duration, Local, NaiveDate};
+
+use crate::eventlog::{self, Entry, Session};
+use crate::events::PostureEvent;
+use crate::posture::{PostureStateMachine, PostureThresholds, Proximity};
+use crate::stats::format_duration;
+
+// What the decision logic made of a stretch of the log
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Outcome {
+    pub alerts: usize,
+    pub bad_posture: Duration,
+}
+
+impl std::ops::AddAssign for Outcome {
+    fn add_assign(&mut self, other: Outcome) {
+        self.alerts += other.alerts;
+        self.bad_posture += other.bad_posture;
+    }
+}
+
+// How much more lenient `limit` is than `base`, as the state machine scales limits
+fn scale_between(base: Proximity, limit: Proximity) -> Option<f32> {
+    match (base, limit) {
+        (Proximity::FaceSize { width, .. }, Proximity::FaceSize { width: limit, .. }) => {
+            (width > 0).then(|| limit as f32 / width as f32)
+        }
+        (Proximity::Distance(base), Proximity::Distance(limit)) => {
+            (limit > 0.0).then(|| base / limit)
+        }
+        _ => None,
+    }
+}
+
+// Feeds the measurements of the sessions through the state machine, with every limit they were
+// held against scaled by `scale`: above 1 more lenient, below 1 stricter
+pub fn replay(sessions: &[Session], scale: f32) -> Outcome {
+    let mut outcome = Outcome::default();
+    // only the differences between timestamps matter to the state machine
+    let origin = Instant::now();
+    for session in sessions {
+        let mut machine: Option<(PostureStateMachine, Proximity)> = None;
+        let mut since = None;
+        let mut last = StdDuration::ZERO;
+        for record in &session.records {
+            let at = StdDuration::from_millis(record.t);
+            match &record.entry {
+                Entry::Limit(limit) => {
+                    let limit = Proximity::from(*limit);
+                    match &mut machine {
+                        Some((machine, base)) => {
+                            if let Some(relative) = scale_between(*base, limit) {
+                                machine.set_limit_scale(relative * scale);
+                            }
+                        }
+                        None => {
+                            let mut new = PostureStateMachine::new(PostureThresholds::new(limit));
+                            new.set_limit_scale(scale);
+                            machine = Some((new, limit));
+                        }
+                    }
+                }
+                Entry::Sample(sample) => {
+                    let Some((machine, _)) = &mut machine else {
+                        continue;
+                    };
+                    last = at;
+                    let current = sample.map(Proximity::from);
+                    for event in machine.update(current, origin + at) {
+                        match event {
+                            PostureEvent::TooClose => {
+                                outcome.alerts += 1;
+                                since = Some(at);
+                            }
+                            PostureEvent::Recovered | PostureEvent::Away => {
+                                if let Some(since) = since.take() {
+                                    outcome.bad_posture +=
+                                        Duration::from_std(at - since).unwrap_or_default();
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(since) = since {
+            outcome.bad_posture += Duration::from_std(last - since).unwrap_or_default();
+        }
+    }
+    outcome
+}
+
+fn change(now: f64, then: f64) -> String {
+    if now == 0.0 {
+        return String::new();
+    }
+    format!(" ({:+.0}%)", (then - now) / now * 100.0)
+}
+
+// Replays the last `days` days of the event log in `dir` as logged and with the limit scaled by
+// `scale`, and prints how alerts and bad posture would have changed
+pub fn run(dir: &Path, days: u32, scale: f32) -> Result<(), String> {
+    let today = Local::now().date_naive();
+    let first = today - Duration::days(days.max(1) as i64 - 1);
+    let logged: Vec<(NaiveDate, Vec<Session>)> = eventlog::days(dir)
+        .into_iter()
+        .filter(|(day, _)| *day >= first)
+        .map(|(day, path)| eventlog::read(&path).map(|sessions| (day, sessions)))
+        .collect::<Result<_, _>>()?;
+    if logged.is_empty() {
+        return Err(format!(
+            "Nothing was logged in {} since {}; is the event log enabled?",
+            dir.display(),
+            first
+        ));
+    }
+    println!("With the limit at {:.0}% of what it was:\n", scale * 100.0);
+    println!(
+        "{:<12} {:>16} {:>24}",
+        "day", "posture alerts", "bad posture"
+    );
+    let mut totals = (Outcome::default(), Outcome::default());
+    for (day, sessions) in &logged {
+        let before = replay(sessions, 1.0);
+        let after = replay(sessions, scale);
+        totals.0 += before;
+        totals.1 += after;
+        println!(
+            "{:<12} {:>16} {:>24}",
+            day.to_string(),
+            format!("{} -> {}", before.alerts, after.alerts),
+            format!(
+                "{} -> {}",
+                format_duration(before.bad_posture),
+                format_duration(after.bad_posture)
+            ),
+        );
+    }
+    let (before, after) = totals;
+    println!(
+        "\nPosture alerts: {} -> {}{}",
+        before.alerts,
+        after.alerts,
+        change(before.alerts as f64, after.alerts as f64)
+    );
+    println!(
+        "Bad posture: {} -> {}{}",
+        format_duration(before.bad_posture),
+        format_duration(after.bad_posture),
+        change(
+            before.bad_posture.num_seconds() as f64,
+            after.bad_posture.num_seconds() as f64
+        )
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eventlog::{Header, Record, Sample, FORMAT_VERSION};
+
+    #[test]
+    fn a_more_lenient_limit_means_fewer_alerts() {
+        let mut records = vec![Record {
+            t: 0,
+            entry: Entry::Limit(Sample::FaceSize(100, 100)),
+        }];
+        // settled, then leaning in a little for two minutes, then back, every half a second
+        let sizes = [(30, 90), (120, 110), (60, 90)];
+        let mut t = 0;
+        for (seconds, size) in sizes {
+            for _ in 0..seconds * 2 {
+                t += 500;
+                records.push(Record {
+                    t,
+                    entry: Entry::Sample(Some(Sample::FaceSize(size, size))),
+                });
+            }
+        }
+        let sessions = vec![Session {
+            header: Header {
+                version: FORMAT_VERSION,
+                neckcheck: "1.0.0".to_string(),
+                start: Local::now(),
+                interval: 0.5,
+                config: None,
+            },
+            records,
+        }];
+        let logged = replay(&sessions, 1.0);
+        assert_eq!(logged.alerts, 1);
+        // from when leaning in held for long enough until back for long enough
+        let bad = logged.bad_posture.num_seconds();
+        assert!((115..=125).contains(&bad), "{}", bad);
+        assert_eq!(replay(&sessions, 1.2), Outcome::default());
+        assert_eq!(replay(&sessions, 0.8).alerts, 1);
+    }
+}
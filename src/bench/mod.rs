@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use image::GrayImage;
+
+use crate::detector::{create_detector, Detector, DetectorBackend, DetectorConfig, Face};
+
+// Runs every backend that can be created over the given images and prints how long a detection
+// takes and how many faces each backend finds. Backends other than the configured one are tried
+// with their default model, so those that need an explicit model are skipped.
+pub fn run(config: &DetectorConfig, images: &[PathBuf], iterations: usize) {
+    let mut frames: Vec<(String, GrayImage)> = Vec::new();
+    for path in images {
+        match image::open(path) {
+            Ok(image) => frames.push((path.display().to_string(), image.to_luma8())),
+            Err(e) => println!("Skipping {}: {}", path.display(), e),
+        }
+    }
+    if frames.is_empty() {
+        println!("No images to run the detectors on");
+        return;
+    }
+    let iterations = iterations.max(1);
+
+    for backend in DetectorBackend::value_variants() {
+        let backend_config = if *backend == config.backend {
+            config.clone()
+        } else {
+            DetectorConfig {
+                backend: *backend,
+                ..DetectorConfig::default()
+            }
+        };
+        let mut detector = match create_detector(&backend_config) {
+            Ok(detector) => detector,
+            Err(e) => {
+                println!("{}: skipped ({})", backend, e);
+                continue;
+            }
+        };
+        println!("{}:", detector.name());
+        // the same detector on a single thread, to show what the `parallel` feature gains
+        #[cfg(feature = "parallel")]
+        let mut sequential = (*backend == DetectorBackend::Rustface)
+            .then(|| crate::detector::RustfaceDetector::new(backend_config.model.as_deref()).ok())
+            .flatten()
+            .map(|mut detector| {
+                detector.set_parallel(false);
+                detector
+            });
+        for (name, frame) in &frames {
+            let (faces, mean, p95) = time(&mut *detector, frame, iterations);
+            let best = faces.iter().map(|face| face.score).fold(f32::NAN, f32::max);
+            println!(
+                "  {} ({}x{}): {} face(s), best score {:.2}, mean {:.1?}, p95 {:.1?}",
+                name,
+                frame.width(),
+                frame.height(),
+                faces.len(),
+                best,
+                mean,
+                p95
+            );
+            #[cfg(feature = "parallel")]
+            if let Some(sequential) = &mut sequential {
+                let (_, single, _) = time(sequential, frame, iterations);
+                println!(
+                    "    on 1 thread: mean {:.1?}, {:.1}x faster on {} threads",
+                    single,
+                    single.as_secs_f64() / mean.as_secs_f64(),
+                    rayon::current_num_threads()
+                );
+            }
+        }
+    }
+}
+
+// The faces found, and the mean and 95th percentile time to find them
+fn time(
+    detector: &mut dyn Detector,
+    frame: &GrayImage,
+    iterations: usize,
+) -> (Vec<Face>, Duration, Duration) {
+    let mut timings = Vec::with_capacity(iterations);
+    let mut faces = Vec::new();
+    for _ in 0..iterations {
+        let start = Instant::now();
+        faces = detector.detect(frame);
+        timings.push(start.elapsed());
+    }
+    timings.sort();
+    let mean = timings.iter().sum::<Duration>() / iterations as u32;
+    let p95 = timings[(iterations * 95 / 100).min(iterations - 1)];
+    (faces, mean, p95)
+}
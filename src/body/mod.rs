@@ -0,0 +1,68 @@
+#[cfg(feature = "onnx")]
+mod onnx;
+mod slouch;
+
+pub use slouch::{is_slouching, SlouchBaseline, SlouchMetrics};
+
+use std::path::PathBuf;
+
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+use crate::detector::{DetectorError, ExecutionProvider};
+
+// The 17 COCO keypoints, in frame coordinates with the model's confidence in each
+pub const LEFT_EAR: usize = 3;
+pub const RIGHT_EAR: usize = 4;
+pub const LEFT_SHOULDER: usize = 5;
+pub const RIGHT_SHOULDER: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keypoint {
+    pub x: f32,
+    pub y: f32,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodyKeypoints {
+    pub points: Vec<Keypoint>,
+}
+
+// Finds the upper body of the (single) person in the frame
+pub trait BodyPoseDetector {
+    fn keypoints(&mut self, frame: &RgbImage) -> Option<BodyKeypoints>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct BodyPoseConfig {
+    // a single person pose model with MoveNet's interface: a 192x192 (or 256x256) RGB image in,
+    // 17 (y, x, score) keypoints relative to the image out
+    pub model: Option<PathBuf>,
+    pub execution_provider: ExecutionProvider,
+}
+
+pub fn create_body_pose_detector(
+    config: &BodyPoseConfig,
+) -> Result<Box<dyn BodyPoseDetector>, DetectorError> {
+    #[cfg(feature = "onnx")]
+    {
+        let model = config
+            .model
+            .as_deref()
+            .ok_or_else(|| DetectorError::MissingModel("body pose".to_string()))?;
+        Ok(Box::new(onnx::MoveNet::new(
+            model,
+            config.execution_provider,
+        )?))
+    }
+    #[cfg(not(feature = "onnx"))]
+    {
+        let _ = config;
+        Err(DetectorError::Unavailable(
+            "body pose".to_string(),
+            "onnx".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::RgbImage;
+use ort::session::Session;
+use ort::value::Tensor;
+
+use super::{BodyKeypoints, BodyPoseDetector, Keypoint};
+use crate::detector::{onnx_session, DetectorError, ExecutionProvider};
+
+const DEFAULT_INPUT_SIZE: u32 = 192;
+const KEYPOINT_COUNT: usize = 17;
+
+// MoveNet single pose (lightning or thunder) exported to ONNX: a square int32 NHWC image in, and
+// [1, 1, 17, 3] keypoints out
+pub struct MoveNet {
+    session: Session,
+    input_size: u32,
+}
+
+impl MoveNet {
+    pub fn new(model: &Path, preference: ExecutionProvider) -> Result<MoveNet, DetectorError> {
+        let (session, _) = onnx_session(model, preference)?;
+        // lightning takes 192x192 and thunder 256x256; the model says which
+        let input_size = session.inputs[0]
+            .input_type
+            .tensor_shape()
+            .and_then(|shape| shape.get(1).copied())
+            .filter(|&size| size > 0)
+            .map_or(DEFAULT_INPUT_SIZE, |size| size as u32);
+        Ok(MoveNet {
+            session,
+            input_size,
+        })
+    }
+
+    fn try_keypoints(&mut self, frame: &RgbImage) -> ort::Result<Option<BodyKeypoints>> {
+        // padded to a square rather than stretched, so that the proportions of the body hold
+        let side = frame.width().max(frame.height());
+        let mut square = RgbImage::new(side, side);
+        imageops::replace(&mut square, frame, 0, 0);
+        let resized = imageops::resize(
+            &square,
+            self.input_size,
+            self.input_size,
+            FilterType::Triangle,
+        );
+        let input: Vec<i32> = resized.as_raw().iter().map(|&value| value as i32).collect();
+        let size = self.input_size as usize;
+        let shape = [1, size, size, 3];
+        let input_name = self.session.inputs[0].name.clone();
+        let output_name = self.session.outputs[0].name.clone();
+        let outputs = self
+            .session
+            .run(ort::inputs![input_name => Tensor::from_array((shape, input))?])?;
+        let (_, values) = outputs[output_name.as_str()].try_extract_tensor::<f32>()?;
+        if values.len() < KEYPOINT_COUNT * 3 {
+            return Ok(None);
+        }
+        let points = values[..KEYPOINT_COUNT * 3]
+            .chunks_exact(3)
+            .map(|point| Keypoint {
+                x: point[1] * side as f32,
+                y: point[0] * side as f32,
+                score: point[2],
+            })
+            .collect();
+        Ok(Some(BodyKeypoints { points }))
+    }
+}
+
+impl BodyPoseDetector for MoveNet {
+    fn keypoints(&mut self, frame: &RgbImage) -> Option<BodyKeypoints> {
+        self.try_keypoints(frame).unwrap_or_else(|e| {
+            println!("ONNX body pose estimation failed: {}", e);
+            None
+        })
+    }
+}
@@ -0,0 +1,146 @@
+use super::{BodyKeypoints, Keypoint, LEFT_EAR, LEFT_SHOULDER, RIGHT_EAR, RIGHT_SHOULDER};
+
+// keypoints the model is less sure of than this are ignored
+const MIN_SCORE: f32 = 0.3;
+
+// The shape of the upper body seen from the front, free of the distance to the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlouchMetrics {
+    // how far the shoulders are below the ears, over the shoulder width: shrugged or raised
+    // shoulders bring them closer
+    pub neck: f32,
+    // the shoulder width over the width between the ears: rolling the shoulders forward makes
+    // them look narrower
+    pub breadth: f32,
+}
+
+impl SlouchMetrics {
+    pub fn from_keypoints(keypoints: &BodyKeypoints) -> Option<SlouchMetrics> {
+        let point = |index: usize| {
+            keypoints
+                .points
+                .get(index)
+                .filter(|point| point.score >= MIN_SCORE)
+                .copied()
+        };
+        let (left_ear, right_ear) = (point(LEFT_EAR)?, point(RIGHT_EAR)?);
+        let (left_shoulder, right_shoulder) = (point(LEFT_SHOULDER)?, point(RIGHT_SHOULDER)?);
+        let shoulder_width = distance(left_shoulder, right_shoulder);
+        let head_width = distance(left_ear, right_ear);
+        if shoulder_width <= 0.0 || head_width <= 0.0 {
+            return None;
+        }
+        let ears = (left_ear.y + right_ear.y) / 2.0;
+        let shoulders = (left_shoulder.y + right_shoulder.y) / 2.0;
+        Some(SlouchMetrics {
+            neck: (shoulders - ears) / shoulder_width,
+            breadth: shoulder_width / head_width,
+        })
+    }
+}
+
+fn distance(a: Keypoint, b: Keypoint) -> f32 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+// The user's upright posture, learned from the first measurements of a session: everyone's
+// proportions differ, so slouching is judged relative to them
+pub struct SlouchBaseline {
+    needed: usize,
+    samples: Vec<SlouchMetrics>,
+    baseline: Option<SlouchMetrics>,
+}
+
+impl SlouchBaseline {
+    pub fn new(needed: usize) -> SlouchBaseline {
+        SlouchBaseline {
+            needed: needed.max(1),
+            samples: Vec::new(),
+            baseline: None,
+        }
+    }
+
+    // Learns from the measurement until the baseline is known, then returns it
+    pub fn update(&mut self, metrics: SlouchMetrics) -> Option<SlouchMetrics> {
+        if self.baseline.is_none() {
+            self.samples.push(metrics);
+            if self.samples.len() >= self.needed {
+                let median = |value: fn(&SlouchMetrics) -> f32| {
+                    let mut values: Vec<f32> = self.samples.iter().map(value).collect();
+                    values.sort_unstable_by(f32::total_cmp);
+                    values[values.len() / 2]
+                };
+                self.baseline = Some(SlouchMetrics {
+                    neck: median(|m| m.neck),
+                    breadth: median(|m| m.breadth),
+                });
+                self.samples.clear();
+            }
+            return None;
+        }
+        self.baseline
+    }
+}
+
+// Whether the metrics have shrunk by more than `tolerance` (a fraction) from the baseline in
+// either respect
+pub fn is_slouching(metrics: SlouchMetrics, baseline: SlouchMetrics, tolerance: f32) -> bool {
+    metrics.neck < baseline.neck * (1.0 - tolerance)
+        || metrics.breadth < baseline.breadth * (1.0 - tolerance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keypoints(ear_y: f32, shoulder_half_width: f32) -> BodyKeypoints {
+        let mut points = vec![
+            Keypoint {
+                x: 0.0,
+                y: 0.0,
+                score: 0.0,
+            };
+            17
+        ];
+        let at = |x, y| Keypoint { x, y, score: 0.9 };
+        points[LEFT_EAR] = at(280.0, ear_y);
+        points[RIGHT_EAR] = at(360.0, ear_y);
+        points[LEFT_SHOULDER] = at(320.0 - shoulder_half_width, 300.0);
+        points[RIGHT_SHOULDER] = at(320.0 + shoulder_half_width, 300.0);
+        BodyKeypoints { points }
+    }
+
+    #[test]
+    fn measures_neck_and_breadth() {
+        let metrics = SlouchMetrics::from_keypoints(&keypoints(200.0, 100.0)).unwrap();
+        assert_eq!(metrics.neck, 0.5);
+        assert_eq!(metrics.breadth, 2.5);
+    }
+
+    #[test]
+    fn needs_confident_keypoints() {
+        let mut body = keypoints(200.0, 100.0);
+        body.points[LEFT_SHOULDER].score = 0.1;
+        assert_eq!(SlouchMetrics::from_keypoints(&body), None);
+    }
+
+    #[test]
+    fn raised_or_rolled_shoulders_are_slouching() {
+        let upright = SlouchMetrics::from_keypoints(&keypoints(200.0, 100.0)).unwrap();
+        let raised = SlouchMetrics::from_keypoints(&keypoints(240.0, 100.0)).unwrap();
+        let rolled = SlouchMetrics::from_keypoints(&keypoints(200.0, 80.0)).unwrap();
+        assert!(!is_slouching(upright, upright, 0.15));
+        assert!(is_slouching(raised, upright, 0.15));
+        assert!(is_slouching(rolled, upright, 0.15));
+    }
+
+    #[test]
+    fn baseline_is_the_median_of_the_first_samples() {
+        let mut baseline = SlouchBaseline::new(3);
+        let metrics = |neck| SlouchMetrics { neck, breadth: 2.0 };
+        assert_eq!(baseline.update(metrics(0.5)), None);
+        assert_eq!(baseline.update(metrics(0.9)), None);
+        assert_eq!(baseline.update(metrics(0.6)), None);
+        assert_eq!(baseline.update(metrics(0.1)), Some(metrics(0.6)));
+    }
+}
@@ -0,0 +1,119 @@
+// A 4-7-8 breathing guide drawn in the terminal during an enforced break: a circle that grows while
+// breathing in, stays while holding the breath and shrinks while breathing out
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use console::Term;
+
+use crate::config::BreathingConfig;
+use crate::i18n::tr;
+
+const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+// the circle at its largest, in rows; terminal cells are about twice as tall as they are wide, so
+// it is twice as many columns across
+const RADIUS: usize = 6;
+// the circle never shrinks below this fraction of its size, so that there is always something
+const MIN_SIZE: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    In,
+    Hold,
+    Out,
+}
+
+// Where in the cycle the breath is after `elapsed` seconds: the phase, the seconds left in it, and
+// the size of the circle from 0 to 1
+fn breath_at(config: &BreathingConfig, elapsed: f64) -> (Phase, f64, f32) {
+    let cycle = config.inhale + config.hold + config.exhale;
+    let t = elapsed % cycle;
+    if t < config.inhale {
+        (Phase::In, config.inhale - t, (t / config.inhale) as f32)
+    } else if t < config.inhale + config.hold {
+        (Phase::Hold, config.inhale + config.hold - t, 1.0)
+    } else {
+        let out = t - config.inhale - config.hold;
+        (
+            Phase::Out,
+            config.exhale - out,
+            1.0 - (out / config.exhale) as f32,
+        )
+    }
+}
+
+// The circle at `size` from 0 to 1, as lines of text of the same width whatever the size
+fn circle(size: f32) -> Vec<String> {
+    let radius = (MIN_SIZE + (1.0 - MIN_SIZE) * size.clamp(0.0, 1.0)) * RADIUS as f32;
+    let rows = 2 * RADIUS + 1;
+    (0..rows)
+        .map(|row| {
+            let y = row as f32 - RADIUS as f32;
+            (0..rows * 2)
+                .map(|column| {
+                    let x = (column as f32 - (rows * 2) as f32 / 2.0) / 2.0;
+                    if x.hypot(y) <= radius {
+                        'o'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+fn label(phase: Phase, left: f64) -> String {
+    let seconds = left.ceil() as u32;
+    match phase {
+        Phase::In => tr!("breathing-in", seconds = seconds),
+        Phase::Hold => tr!("breathing-hold", seconds = seconds),
+        Phase::Out => tr!("breathing-out", seconds = seconds),
+    }
+}
+
+// Draws the guide in neckcheck's terminal for `config.duration` seconds, if there is one to draw
+// in. Blocks until it is over.
+pub fn guide(config: &BreathingConfig) {
+    let term = Term::stdout();
+    if !term.is_term() {
+        return;
+    }
+    let _ = term.write_line(&tr!("breathing-start"));
+    let start = Instant::now();
+    let mut drawn = 0;
+    while start.elapsed().as_secs_f64() < config.duration {
+        let (phase, left, size) = breath_at(config, start.elapsed().as_secs_f64());
+        let mut lines = circle(size);
+        lines.push(label(phase, left));
+        let _ = term.clear_last_lines(drawn);
+        let _ = term.write_line(&lines.join("\n"));
+        drawn = lines.len();
+        thread::sleep(FRAME_INTERVAL);
+    }
+    let _ = term.clear_last_lines(drawn);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_circle_follows_the_breath() {
+        let config = BreathingConfig::default();
+        assert_eq!(breath_at(&config, 0.0), (Phase::In, 4.0, 0.0));
+        assert_eq!(breath_at(&config, 2.0), (Phase::In, 2.0, 0.5));
+        assert_eq!(breath_at(&config, 5.0), (Phase::Hold, 6.0, 1.0));
+        assert_eq!(breath_at(&config, 15.0), (Phase::Out, 4.0, 0.5));
+        // the next cycle
+        assert_eq!(breath_at(&config, 21.0).0, Phase::In);
+
+        let filled = |size| circle(size).concat().matches('o').count();
+        assert!(filled(0.0) > 0);
+        assert!(filled(0.5) > filled(0.0));
+        assert!(filled(1.0) > filled(0.5));
+        assert_eq!(circle(1.0).len(), 2 * RADIUS + 1);
+    }
+}
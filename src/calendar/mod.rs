@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, Duration, Local, Utc, Weekday};
+
+use crate::stats::{bad_posture, heatmap, StatsLog};
+
+// Writes an iCalendar file with a weekly, tentative stretch break at the start of each of the
+// `count` hours of the week in which posture has been worst
+pub fn write_breaks(log: &StatsLog, out: &Path, count: usize, minutes: u32) -> Result<(), String> {
+    let records = log
+        .read()
+        .map_err(|e| format!("Failed to read {}: {}", log.path().display(), e))?;
+    let slots = heatmap(&bad_posture(&records)).worst(count);
+    if slots.is_empty() {
+        return Err(format!(
+            "No bad posture recorded in {} to suggest breaks from",
+            log.path().display()
+        ));
+    }
+    fs::write(out, calendar(&slots, minutes, Local::now()))
+        .map_err(|e| format!("Failed to write {}: {}", out.display(), e))
+}
+
+fn calendar(slots: &[(Weekday, u32)], minutes: u32, now: DateTime<Local>) -> String {
+    let mut ics = String::new();
+    let mut line = |text: String| {
+        // iCalendar lines end in CRLF
+        ics.push_str(&text);
+        ics.push_str("\r\n");
+    };
+    line("BEGIN:VCALENDAR".to_string());
+    line("VERSION:2.0".to_string());
+    line("PRODID:-//neckcheck//suggested breaks//EN".to_string());
+    let stamp = now.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ");
+    for (weekday, hour) in slots {
+        // the first one is this week's, or today's, so that the series starts straight away
+        let days_ahead =
+            (weekday.num_days_from_monday() + 7 - now.weekday().num_days_from_monday()) % 7;
+        let date = now.date_naive() + Duration::days(days_ahead as i64);
+        line("BEGIN:VEVENT".to_string());
+        line(format!(
+            "UID:neckcheck-break-{}-{:02}@neckcheck",
+            weekday.to_string().to_lowercase(),
+            hour
+        ));
+        line(format!("DTSTAMP:{}", stamp));
+        // a floating time, i.e. this hour wherever the calendar is
+        line(format!("DTSTART:{}T{:02}0000", date.format("%Y%m%d"), hour));
+        line(format!("DURATION:PT{}M", minutes));
+        line("RRULE:FREQ=WEEKLY".to_string());
+        line("SUMMARY:Stretch break".to_string());
+        line(
+            "DESCRIPTION:Suggested by neckcheck: your posture is often at its worst around \
+             this time."
+                .to_string(),
+        );
+        line("STATUS:TENTATIVE".to_string());
+        line("TRANSP:TRANSPARENT".to_string());
+        line("END:VEVENT".to_string());
+    }
+    line("END:VCALENDAR".to_string());
+    ics
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn breaks_recur_weekly_from_their_next_day() {
+        // a Wednesday
+        let now = Local.with_ymd_and_hms(2024, 3, 6, 12, 0, 0).unwrap();
+        let ics = calendar(&[(Weekday::Mon, 10), (Weekday::Wed, 15)], 10, now);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ics.contains("DTSTART:20240311T100000\r\n"));
+        assert!(ics.contains("DTSTART:20240306T150000\r\n"));
+        assert!(ics.contains("DURATION:PT10M\r\n"));
+        assert!(ics.contains("STATUS:TENTATIVE\r\n"));
+    }
+}
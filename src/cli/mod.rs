@@ -0,0 +1,375 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+
+use crate::alert::SinkGroup;
+use crate::config::Config;
+use crate::detector::DetectorBackend;
+use crate::snapshot::SnapshotPrivacy;
+use crate::stats::StatsFilter;
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    version,
+    about = "Warns you when you are sitting too close to your screen"
+)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Config file to use instead of the default one in the user config directory
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Seconds between posture checks
+    #[arg(long, default_value_t = 0.5, value_parser = parse_interval)]
+    pub interval: f64,
+
+    /// Seconds after launch (or resume from suspend) before any alert can fire
+    #[arg(long, default_value_t = 10.0, value_parser = parse_duration)]
+    pub grace_period: f64,
+
+    /// Face detection backend, overriding detector.backend from the config file
+    #[arg(long, value_enum)]
+    pub detector: Option<DetectorBackend>,
+
+    /// Instead of the interactive calibration, watch you work as usual for this many seconds
+    /// (e.g. 3600) and calibrate from what was seen
+    #[arg(long, value_name = "SECONDS", value_parser = parse_interval)]
+    pub observe: Option<f64>,
+
+    /// With --observe, the percentile of your observed closeness to calibrate at: at 95, the
+    /// closest 5% of your usual posture would have counted as too close
+    #[arg(long, default_value_t = 95.0, value_parser = parse_percentile)]
+    pub observe_percentile: f32,
+
+    /// Only open the camera for a moment every this many seconds, instead of every --interval,
+    /// so that its light is off most of the time
+    #[arg(long, value_name = "SECONDS", value_parser = parse_interval, conflicts_with_all = ["url", "realsense", "phone", "serial"])]
+    pub discrete: Option<f64>,
+
+    /// With --discrete, the number of frames grabbed each time the camera is opened; the last one
+    /// is checked, once the exposure has settled
+    #[arg(long, default_value_t = 3, value_name = "FRAMES", requires = "discrete", value_parser = clap::value_parser!(u32).range(1..))]
+    pub burst: u32,
+
+    /// Calibrate again rather than use the calibration saved for this camera
+    #[arg(long, conflicts_with = "observe")]
+    pub recalibrate: bool,
+
+    /// Use the saved calibration even if it was taken with another camera, or at another
+    /// resolution
+    #[arg(long)]
+    pub ignore_camera_mismatch: bool,
+
+    /// What kind of session this is, e.g. "standing-desk", noted in the stats so that reports can
+    /// be narrowed down to it. Overrides stats.profile in the config
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Index of the local camera to use. Given more than once (e.g. --camera 0 --camera 2), every
+    /// camera watches and each check goes by whichever sees your face best. Defaults to camera in
+    /// the config, or else 0
+    #[arg(long, conflicts_with = "url")]
+    pub camera: Vec<u32>,
+
+    /// With several --camera, only use the first one that works, opening the next when it fails
+    /// or is busy, rather than all of them at once
+    #[arg(long)]
+    pub camera_failover: bool,
+
+    /// Use an Intel RealSense depth camera, measuring the actual distance to your face. Requires
+    /// the `realsense` feature
+    #[arg(long, conflicts_with_all = ["camera", "url"])]
+    pub realsense: bool,
+
+    /// Instead of a camera, take distance readings that a phone (or anything else) POSTs as JSON,
+    /// e.g. {"distance": 48.5} or {"present": false}, to http://ADDRESS/reading
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["camera", "url", "realsense"])]
+    pub phone: Option<SocketAddr>,
+
+    /// Instead of a camera, read distances in centimetres, one per line, from a sensor on this
+    /// serial port, e.g. an Arduino with an ultrasonic sensor on /dev/ttyUSB0 or COM3
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["camera", "url", "realsense", "phone"])]
+    pub serial: Option<String>,
+
+    /// Baud rate of the --serial port
+    #[arg(long, default_value_t = 9600, value_name = "BAUD")]
+    pub serial_baud: u32,
+
+    /// Don't use a camera at all: remind you to check your posture, rest your eyes and stand up on
+    /// a timer instead, as set under [reminders]
+    #[arg(long, conflicts_with_all = ["camera", "url", "realsense", "phone", "serial", "discrete", "observe", "recalibrate"])]
+    pub no_camera: bool,
+
+    /// Use a network camera instead of a local one: an MJPEG/JPEG http(s) url, or an rtsp url when
+    /// built with the `ffmpeg` feature
+    #[arg(long)]
+    pub url: Option<String>,
+
+    /// Write annotated frames (detection box, posture banner) to a v4l2loopback device such as
+    /// /dev/video10, e.g. to watch them in OBS. Requires ffmpeg
+    #[arg(long, value_name = "DEVICE")]
+    pub virtual_camera: Option<String>,
+
+    /// Save a snapshot into this directory whenever a bad posture episode starts
+    #[arg(long, value_name = "DIR")]
+    pub snapshot_dir: Option<PathBuf>,
+
+    /// How snapshots are anonymised: blur every face, or keep only the grayscale face crop
+    #[arg(long, value_enum, default_value_t = SnapshotPrivacy::Blur)]
+    pub snapshot_privacy: SnapshotPrivacy,
+
+    /// Delete snapshots older than this many days
+    #[arg(long, default_value_t = 7, value_name = "DAYS")]
+    pub snapshot_retention_days: u64,
+
+    /// Record one small, face-blurred, annotated frame per minute into this directory, and
+    /// assemble each finished day into a GIF timelapse
+    #[arg(long, value_name = "DIR")]
+    pub timelapse_dir: Option<PathBuf>,
+
+    /// On every change of posture, save the frame that caused it into this directory, in grayscale
+    /// with every face the detector found, the chosen face and the calibrated limit drawn on it,
+    /// and the detector's scores in a JSON file next to it
+    #[arg(long, value_name = "DIR")]
+    pub debug_dump: Option<PathBuf>,
+
+    /// Guarantee that camera frames never leave the process: every output that wants image data
+    /// is refused and image buffers are zeroed after each check
+    #[arg(long)]
+    pub privacy_strict: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Assemble the frames recorded with --timelapse-dir on a given day into a timelapse
+    Timelapse {
+        /// The directory passed to --timelapse-dir
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+
+        /// The day to assemble (YYYY-MM-DD), today if omitted
+        #[arg(long)]
+        date: Option<NaiveDate>,
+
+        /// Output file; .gif is encoded directly, anything else (e.g. .mp4) requires ffmpeg
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Replay the event log with another limit and show how posture alerts and time in bad
+    /// posture would have changed
+    Analyze {
+        /// The limit to try, relative to the one in use, e.g. 110% to tolerate a 10% larger face
+        /// (or a 10% shorter distance) before alerting
+        #[arg(long, value_parser = parse_threshold)]
+        threshold: f32,
+
+        /// How many days, up to today, to replay
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+    /// Show the events and alerts of a day from the event log
+    Events {
+        /// The day to show (YYYY-MM-DD), today if omitted
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Switch the face detector of the running neckcheck, using the models from the config
+    SetDetector {
+        #[arg(value_enum)]
+        backend: DetectorBackend,
+    },
+    /// Silence posture alerts of the running neckcheck for a while
+    Snooze {
+        #[arg(default_value_t = 15.0, value_parser = parse_minutes, allow_negative_numbers = true)]
+        minutes: f64,
+    },
+    /// Stop the running neckcheck reminding you about the current alert
+    Dismiss,
+    /// Record the latest posture alert of the running neckcheck as a false positive, for the stats
+    ReportFp,
+    /// Pause monitoring in the running neckcheck until it is resumed
+    Pause,
+    /// Resume monitoring in the running neckcheck
+    Resume,
+    /// Show the status of the running neckcheck
+    Status {
+        /// Print a machine-readable JSON snapshot instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Follow the status of the running neckcheck as JSON lines for a waybar custom module
+    Waybar,
+    /// Summarise the recorded stats
+    Stats {
+        #[command(flatten)]
+        filter: StatsFilter,
+
+        #[command(subcommand)]
+        command: Option<StatsCommand>,
+    },
+    /// Write a self-contained HTML report of the recorded stats, with charts
+    Report {
+        /// The HTML file to write
+        #[arg(long)]
+        out: PathBuf,
+
+        /// How many days, up to today, to include
+        #[arg(long, default_value_t = 14)]
+        days: u32,
+
+        #[command(flatten)]
+        filter: StatsFilter,
+    },
+    /// Mark an exercise of the physiotherapy program as done
+    ExerciseDone {
+        /// The exercise, as named in the config; the one prompted most recently today if omitted
+        exercise: Option<String>,
+    },
+    /// Write an iCalendar file of weekly stretch breaks at the hours your posture is usually worst
+    Breaks {
+        /// The .ics file to write
+        #[arg(long)]
+        out: PathBuf,
+
+        /// How many breaks a week to suggest
+        #[arg(long, default_value_t = 5)]
+        count: usize,
+
+        /// How long each break is, in minutes
+        #[arg(long, default_value_t = 10)]
+        minutes: u32,
+
+        #[command(flatten)]
+        filter: StatsFilter,
+    },
+    /// Manage the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Check the config, the face detector (and whether it runs on a GPU) and the cameras
+    Doctor,
+    /// Set neckcheck up step by step: pick a camera, calibrate, choose and try out the alerts, and
+    /// write the config. Runs by itself the first time neckcheck starts
+    Setup,
+    /// Print a completion script for a shell, completing the profiles and hosts in the stats and
+    /// the cameras plugged in when it is written, e.g.
+    /// `neckcheck completions bash > ~/.local/share/bash-completion/completions/neckcheck`
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Replace this binary with the latest release from GitHub, once its checksum matches.
+    /// Requires the `self-update` feature
+    SelfUpdate {
+        /// Only say whether there is a newer release
+        #[arg(long)]
+        check: bool,
+    },
+    /// Fire each configured alert sink once, to check the volume, the overlays and where
+    /// notifications go without leaning in
+    TestAlert {
+        /// Only the sinks of this kind
+        #[arg(long, value_enum, default_value_t = SinkGroup::All)]
+        sink: SinkGroup,
+    },
+    /// Teach neckcheck your face, so that guest mode can tell when someone else is at the
+    /// computer
+    Enroll,
+    /// Compare the latency and detections of the available face detection backends
+    Bench {
+        /// Images to run the detectors on
+        #[arg(default_value = "example_of_perfect_posture.jpeg")]
+        images: Vec<PathBuf>,
+
+        /// How many times each detector is run on each image
+        #[arg(long, default_value_t = 20)]
+        iterations: usize,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// Write a config file with every setting at its default, explained
+    Init {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check the config file for invalid values and unknown keys
+    Validate,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum StatsCommand {
+    /// Add another machine's stats log (a copy of its stats.jsonl) to this one's
+    Merge {
+        /// The stats log to add
+        file: PathBuf,
+
+        /// The machine it comes from, for records that don't say [default: the file's name]
+        #[arg(long)]
+        host: Option<String>,
+    },
+}
+
+// The local cameras to use: those given on the command line, else those in the config, else the
+// first one
+pub fn cameras(args: &Args, config: &Config) -> Vec<u32> {
+    if !args.camera.is_empty() {
+        args.camera.clone()
+    } else if !config.camera.is_empty() {
+        config.camera.clone()
+    } else {
+        vec![0]
+    }
+}
+
+fn parse_interval(value: &str) -> Result<f64, String> {
+    let seconds = parse_duration(value)?;
+    if seconds == 0.0 {
+        return Err("must be a positive number of seconds".to_string());
+    }
+    Ok(seconds)
+}
+
+fn parse_minutes(value: &str) -> Result<f64, String> {
+    let minutes: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if !(minutes > 0.0 && minutes.is_finite()) {
+        return Err("must be a positive number of minutes".to_string());
+    }
+    Ok(minutes)
+}
+
+fn parse_percentile(value: &str) -> Result<f32, String> {
+    let percentile: f32 = value.parse().map_err(|e| format!("{}", e))?;
+    if !(1.0..=99.0).contains(&percentile) {
+        return Err("must be between 1 and 99".to_string());
+    }
+    Ok(percentile)
+}
+
+// A percentage such as 110%, or the same as a factor such as 1.1
+fn parse_threshold(value: &str) -> Result<f32, String> {
+    let factor = match value.trim().strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().map(|percent| percent / 100.0),
+        None => value.trim().parse::<f32>(),
+    }
+    .map_err(|e| format!("{}", e))?;
+    if !(factor > 0.0 && factor.is_finite()) {
+        return Err("must be a positive percentage, e.g. 110%".to_string());
+    }
+    Ok(factor)
+}
+
+fn parse_duration(value: &str) -> Result<f64, String> {
+    let seconds: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if !(seconds >= 0.0 && seconds.is_finite()) {
+        return Err("must be a non-negative number of seconds".to_string());
+    }
+    Ok(seconds)
+}
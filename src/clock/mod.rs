@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, TimeDelta};
+
+// Wall clocks drift and get nudged back by NTP; anything smaller than this isn't a jump
+const JUMP_TOLERANCE: Duration = Duration::from_secs(60);
+
+// How the wall clock moved since the last look, other than by the time that passed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockChange {
+    // how far it moved beyond the time that passed, e.g. set by hand, or across a suspend the
+    // monotonic clock slept through; zero when only the offset changed
+    pub jump: TimeDelta,
+    // the UTC offset changed, by a time zone change or daylight saving time
+    pub offset_changed: bool,
+}
+
+// Tells changes of the wall clock apart from time passing by holding it against the monotonic clock,
+// so that schedules going by the wall clock know when to work out their times again
+#[derive(Default)]
+pub struct ClockWatch {
+    last: Option<(Instant, DateTime<Local>)>,
+}
+
+impl ClockWatch {
+    pub fn new() -> ClockWatch {
+        ClockWatch::default()
+    }
+
+    pub fn check(&mut self, now: Instant, wall: DateTime<Local>) -> Option<ClockChange> {
+        let (last, last_wall) = self.last.replace((now, wall))?;
+        let passed = TimeDelta::from_std(now.saturating_duration_since(last)).unwrap_or_default();
+        let mut jump = (wall - last_wall) - passed;
+        if jump.abs() <= TimeDelta::from_std(JUMP_TOLERANCE).unwrap_or_default() {
+            jump = TimeDelta::zero();
+        }
+        let offset_changed = wall.offset() != last_wall.offset();
+        (!jump.is_zero() || offset_changed).then_some(ClockChange {
+            jump,
+            offset_changed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn jumps_are_told_apart_from_time_passing() {
+        let start = Instant::now();
+        let wall = Local.with_ymd_and_hms(2026, 3, 10, 9, 0, 0).unwrap();
+        let mut clock = ClockWatch::new();
+        assert_eq!(clock.check(start, wall), None);
+        // a little drift is put right
+        let at = start + Duration::from_secs(300);
+        assert_eq!(clock.check(at, wall + TimeDelta::seconds(290)), None);
+
+        // set back by an hour
+        let at = at + Duration::from_secs(10);
+        let change = clock.check(at, wall - TimeDelta::seconds(3300)).unwrap();
+        assert_eq!(change.jump, TimeDelta::hours(-1));
+        assert!(!change.offset_changed);
+
+        // two hours asleep while the monotonic clock stood still
+        let change = clock.check(at, wall + TimeDelta::seconds(3900)).unwrap();
+        assert_eq!(change.jump, TimeDelta::hours(2));
+        assert_eq!(clock.check(at, wall + TimeDelta::seconds(3900)), None);
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::Write;
+
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::{Arg, Command, CommandFactory};
+use clap_complete::Shell;
+use nokhwa::utils::ApiBackend;
+
+use crate::cli::Args;
+use crate::stats::{this_host, StatsRecord};
+
+// the subcommands that take a StatsFilter, and so --host and --profile
+const FILTERED: [&str; 3] = ["stats", "report", "breaks"];
+
+// What the arguments naming things can be completed with, as known when the script is written
+#[derive(Debug, Default)]
+pub struct Names {
+    pub profiles: Vec<String>,
+    pub hosts: Vec<String>,
+    // camera indices, with the camera's name as their help
+    pub cameras: Vec<(u32, String)>,
+}
+
+impl Names {
+    // The profiles and machines in the stats log, and the cameras that can be seen right now
+    pub fn find(records: &[StatsRecord]) -> Names {
+        let mut profiles: Vec<String> = records
+            .iter()
+            .filter_map(|record| record.profile.clone())
+            .collect();
+        profiles.sort();
+        profiles.dedup();
+        let mut hosts: Vec<String> = records
+            .iter()
+            .map(|record| record.host.as_deref().unwrap_or(this_host()).to_string())
+            .collect();
+        hosts.sort();
+        hosts.dedup();
+        let cameras = nokhwa::query(ApiBackend::Auto)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|camera| Some((camera.index().as_index().ok()?, camera.human_name())))
+            .collect();
+        Names {
+            profiles,
+            hosts,
+            cameras,
+        }
+    }
+}
+
+fn one_of(values: impl IntoIterator<Item = PossibleValue>) -> impl FnOnce(Arg) -> Arg {
+    let values: Vec<PossibleValue> = values.into_iter().collect();
+    move |arg| {
+        if values.is_empty() {
+            arg
+        } else {
+            arg.value_parser(PossibleValuesParser::new(values))
+        }
+    }
+}
+
+// The CLI with the names filled in as the values of the arguments that take them. Only for writing
+// completions: the values aren't meant to be enforced.
+fn with_names(names: &Names) -> Command {
+    let profiles = || names.profiles.iter().map(PossibleValue::new);
+    let hosts = || names.hosts.iter().map(PossibleValue::new);
+    let cameras = names
+        .cameras
+        .iter()
+        .map(|(index, name)| PossibleValue::new(index.to_string()).help(name.clone()));
+    let mut command = Args::command()
+        .mut_arg("profile", one_of(profiles()))
+        .mut_arg("camera", one_of(cameras));
+    for name in FILTERED {
+        command = command.mut_subcommand(name, |subcommand| {
+            subcommand
+                .mut_arg("profile", one_of(profiles()))
+                .mut_arg("host", one_of(hosts()))
+        });
+    }
+    command
+}
+
+// Writes the completion script for `shell`
+pub fn write(shell: Shell, names: &Names, out: &mut impl Write) {
+    let mut command = with_names(names);
+    clap_complete::generate(shell, &mut command, "neckcheck", out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_hosts_and_cameras_are_completed() {
+        let names = Names {
+            profiles: vec!["standing-desk".to_string()],
+            hosts: vec!["laptop".to_string()],
+            cameras: vec![(2, "Logitech BRIO".to_string())],
+        };
+        let mut script = Vec::new();
+        write(Shell::Fish, &names, &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("standing-desk"));
+        assert!(script.contains("laptop"));
+        assert!(script.contains("Logitech BRIO"));
+
+        // nothing to offer is no reason to fail
+        let mut script = Vec::new();
+        write(Shell::Bash, &Names::default(), &mut script);
+        assert!(!script.is_empty());
+    }
+}
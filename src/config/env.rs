@@ -0,0 +1,94 @@
+use std::ffi::OsString;
+
+use toml_edit::{DocumentMut, Item, Value};
+
+const PREFIX: &str = "NECKCHECK_";
+
+// Layers NECKCHECK_* environment variables over a config file's contents, e.g.
+// NECKCHECK_ALERTS__REPEAT_INTERVAL=5 sets repeat_interval in [alerts]: a double underscore
+// separates the tables. Values are read as TOML, or as a string if they aren't valid TOML, so
+// NECKCHECK_ALERTS__SINKS='[{ type = "log" }]' replaces the sinks.
+pub fn apply(
+    contents: &str,
+    vars: impl IntoIterator<Item = (OsString, OsString)>,
+) -> Result<String, String> {
+    let mut overrides: Vec<(String, String)> = vars
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| name.starts_with(PREFIX) && name.len() > PREFIX.len())
+        .collect();
+    if overrides.is_empty() {
+        return Ok(contents.to_string());
+    }
+    // a broken file is left for the parser to report
+    let Ok(mut document) = contents.parse::<DocumentMut>() else {
+        return Ok(contents.to_string());
+    };
+    overrides.sort();
+    for (name, raw) in overrides {
+        let path: Vec<String> = name[PREFIX.len()..]
+            .to_lowercase()
+            .split("__")
+            .map(str::to_string)
+            .collect();
+        if path.iter().any(String::is_empty) {
+            return Err(format!("{}: empty key", name));
+        }
+        let value = raw
+            .parse::<Value>()
+            .unwrap_or_else(|_| Value::from(raw.as_str()));
+        set(&mut document, &path, value).map_err(|e| format!("{}: {}", name, e))?;
+    }
+    Ok(document.to_string())
+}
+
+fn set(document: &mut DocumentMut, path: &[String], value: Value) -> Result<(), String> {
+    let (last, tables) = path.split_last().unwrap();
+    let mut table = document.as_table_mut() as &mut dyn toml_edit::TableLike;
+    for key in tables {
+        table = table
+            .entry(key)
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .ok_or_else(|| format!("{} is not a table", key))?;
+    }
+    table.insert(last, Item::Value(value));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        vars.iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect()
+    }
+
+    #[test]
+    fn variables_set_nested_keys() {
+        let contents = "[alerts]\nrepeat_interval = 2.0\n";
+        let applied = apply(
+            contents,
+            vars(&[
+                ("NECKCHECK_ALERTS__REPEAT_INTERVAL", "5"),
+                ("NECKCHECK_SUMMARY__AT", "19:30"),
+                ("NECKCHECK_IPC__ENABLED", "false"),
+                ("HOME", "/home/me"),
+            ]),
+        )
+        .unwrap();
+        let table: toml::Table = applied.parse().unwrap();
+        assert_eq!(table["alerts"]["repeat_interval"].as_integer(), Some(5));
+        assert_eq!(table["summary"]["at"].as_str(), Some("19:30"));
+        assert_eq!(table["ipc"]["enabled"].as_bool(), Some(false));
+
+        assert_eq!(apply(contents, vars(&[("HOME", "/")])).unwrap(), contents);
+        assert!(apply(
+            contents,
+            vars(&[("NECKCHECK_ALERTS__REPEAT_INTERVAL__X", "1")])
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::Path;
+
+use toml_edit::{value, DocumentMut, Item, TableLike};
+
+// A change to the config format, applied to files of an older version
+struct Migration {
+    version: u32,
+    renames: &'static [Rename],
+}
+
+// A key renamed within a table: "" for the top level, dotted otherwise, e.g. "alerts.sinks" for
+// every sink
+struct Rename {
+    table: &'static str,
+    from: &'static str,
+    to: &'static str,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    // files without a version; nothing was renamed before versions were introduced
+    Migration {
+        version: 1,
+        renames: &[],
+    },
+];
+
+pub const CURRENT_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+// The config file brought up to the current version, or None if no migration changes anything in
+// it (or it is not valid TOML, which the parser then reports). Only the version wouldn't be
+// reason enough to rewrite the user's file.
+pub fn migrate(contents: &str) -> Option<String> {
+    migrate_with(contents, MIGRATIONS)
+}
+
+fn migrate_with(contents: &str, migrations: &[Migration]) -> Option<String> {
+    let mut document: DocumentMut = contents.parse().ok()?;
+    let version = match document.get("version") {
+        Some(version) => u32::try_from(version.as_integer()?).ok()?,
+        None => 0,
+    };
+    let pending: Vec<&Migration> = migrations.iter().filter(|m| m.version > version).collect();
+    let latest = pending.last()?.version;
+    let mut changed = false;
+    for migration in pending {
+        for rename in migration.renames {
+            let path: Vec<&str> = rename.table.split('.').filter(|s| !s.is_empty()).collect();
+            for_each_table(document.as_table_mut(), &path, &mut |table| {
+                changed |= rename_key(table, rename.from, rename.to)
+            });
+        }
+    }
+    if !changed {
+        return None;
+    }
+    document["version"] = value(latest as i64);
+    Some(document.to_string())
+}
+
+// Calls `f` with the table at `path`, or every table of it if it is an array of tables
+fn for_each_table(table: &mut dyn TableLike, path: &[&str], f: &mut dyn FnMut(&mut dyn TableLike)) {
+    let Some((first, rest)) = path.split_first() else {
+        return f(table);
+    };
+    match table.get_mut(first) {
+        Some(Item::ArrayOfTables(tables)) => {
+            for table in tables.iter_mut() {
+                for_each_table(table, rest, f);
+            }
+        }
+        Some(item) => {
+            if let Some(table) = item.as_table_like_mut() {
+                for_each_table(table, rest, f);
+            }
+        }
+        None => {}
+    }
+}
+
+// Renames a key, keeping its comments. If the new key is already there, the old one is left alone
+// (and reported as unknown) rather than one of them being dropped. Whether it was renamed.
+fn rename_key(table: &mut dyn TableLike, from: &str, to: &str) -> bool {
+    if table.contains_key(to) {
+        return false;
+    }
+    let decor = table.key(from).map(|key| key.leaf_decor().clone());
+    let Some(item) = table.remove(from) else {
+        return false;
+    };
+    table.insert(to, item);
+    if let (Some(decor), Some(mut key)) = (decor, table.key_mut(to)) {
+        *key.leaf_decor_mut() = decor;
+    }
+    true
+}
+
+// Upgrades the config file in place if it is from an older version that reads differently now,
+// keeping a copy of the old one next to it
+pub fn upgrade(path: &Path) -> Result<(), String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let Some(upgraded) = migrate(&contents) else {
+        return Ok(());
+    };
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    fs::write(&backup, &contents).map_err(|e| e.to_string())?;
+    fs::write(path, upgraded).map_err(|e| e.to_string())?;
+    println!(
+        "Upgraded {} to version {}; the old one is in {}",
+        path.display(),
+        CURRENT_VERSION,
+        Path::new(&backup).display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            renames: &[],
+        },
+        Migration {
+            version: 2,
+            renames: &[
+                Rename {
+                    table: "alerts",
+                    from: "repeat",
+                    to: "repeat_interval",
+                },
+                Rename {
+                    table: "alerts.sinks",
+                    from: "length",
+                    to: "duration",
+                },
+            ],
+        },
+    ];
+
+    #[test]
+    fn renamed_keys_keep_their_values_and_comments() {
+        let old = "[alerts]\n# how often\nrepeat = 5.0\n\n[[alerts.sinks]]\ntype = \"tone\"\n\
+                   length = 2.0\n\n[[alerts.sinks]]\ntype = \"log\"\n";
+        let new = migrate_with(old, MIGRATIONS).unwrap();
+        assert_eq!(
+            new,
+            "version = 2\n[alerts]\n# how often\nrepeat_interval = 5.0\n\n[[alerts.sinks]]\n\
+             type = \"tone\"\nduration = 2.0\n\n[[alerts.sinks]]\ntype = \"log\"\n"
+        );
+        assert_eq!(migrate_with(&new, MIGRATIONS), None);
+    }
+
+    #[test]
+    fn only_newer_migrations_are_applied() {
+        let old = "version = 2\n[alerts]\nrepeat = 5.0\n";
+        assert_eq!(migrate_with(old, MIGRATIONS), None);
+        let old = "version = 1\n[alerts]\nrepeat = 5.0\nrepeat_interval = 3.0\n";
+        assert_eq!(migrate_with(old, MIGRATIONS), None);
+    }
+
+    #[test]
+    fn a_file_nothing_is_renamed_in_is_left_alone() {
+        assert_eq!(
+            migrate_with("[alerts]\nrepeat_interval = 5.0\n", MIGRATIONS),
+            None
+        );
+        assert_eq!(migrate_with("", MIGRATIONS), None);
+
+        let dir = std::env::temp_dir().join(format!("neckcheck-migrate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, "[alerts]\nrepeat_interval = 5.0\n").unwrap();
+        upgrade(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[alerts]\nrepeat_interval = 5.0\n"
+        );
+        assert!(!dir.join("config.toml.bak").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
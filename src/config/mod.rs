@@ -0,0 +1,1348 @@
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::NaiveTime;
+use serde::Deserialize;
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+use crate::alert::{AlertKind, Severity};
+use crate::body::BodyPoseConfig;
+use crate::detector::DetectorConfig;
+use crate::landmarks::LandmarksConfig;
+use crate::posture::{time_of_day, Rule, SensitivityRange};
+use crate::program::Exercise;
+use crate::recognition::FaceRecognitionConfig;
+
+mod env;
+pub mod migrate;
+mod validate;
+
+// The config written by `neckcheck config init`, and the start of the one `neckcheck setup` writes
+pub const DEFAULT_CONFIG: &str = include_str!("default.toml");
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file {0}: {1}")]
+    ReadError(PathBuf, String),
+    #[error("Invalid config file {0}: {1}")]
+    ParseError(PathBuf, String),
+    #[error("Invalid config in the environment: {0}")]
+    Environment(String),
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    // the format of the file, see migrate.rs
+    pub version: u32,
+    // the language of prompts and alerts, e.g. "de", instead of the system's
+    pub language: Option<String>,
+    // the local cameras to use when --camera isn't given, e.g. as picked by `neckcheck setup`
+    pub camera: Vec<u32>,
+    pub alerts: AlertConfig,
+    pub detector: DetectorConfig,
+    pub ipc: IpcConfig,
+    pub grpc: GrpcConfig,
+    pub remote: RemoteConfig,
+    pub dashboard: DashboardConfig,
+    pub ntfy: NtfyConfig,
+    pub health: HealthConfig,
+    pub adaptive: AdaptiveConfig,
+    pub stats: StatsConfig,
+    pub event_log: EventLogConfig,
+    // face patches labelled with the posture, for training a detector
+    pub dataset: DatasetConfig,
+    // stricter or more lenient limits at certain times of day
+    pub schedule: Vec<SensitivityRange>,
+    pub duty_cycle: DutyCycleConfig,
+    // what counts as bad posture
+    pub decision: DecisionConfig,
+    // misdetections told apart by how far the measurement jumped
+    pub outliers: OutliersConfig,
+    // faces too small to be the user's
+    pub background_faces: BackgroundFacesConfig,
+    // the facial landmark model used by the neck angle
+    pub landmarks: LandmarksConfig,
+    pub neck_angle: NeckAngleConfig,
+    pub drowsiness: DrowsinessConfig,
+    pub blink_rate: BlinkRateConfig,
+    // the body pose model used to tell slouching
+    pub body_pose: BodyPoseConfig,
+    pub slouch: SlouchConfig,
+    pub face_drift: FaceDriftConfig,
+    pub summary: SummaryConfig,
+    pub accessibility: AccessibilityConfig,
+    // softer alerts while watching or presenting
+    pub quiet: QuietConfig,
+    pub latency: LatencyConfig,
+    pub skip_frames: SkipFramesConfig,
+    pub memory: MemoryConfig,
+    // less monitoring on a low battery or a hot CPU
+    pub power: PowerConfig,
+    // several people in front of one computer
+    pub household: HouseholdConfig,
+    // tighter limits and enforced breaks, e.g. for a child's computer
+    pub strict: StrictConfig,
+    // the breathing guide during the strict profile's enforced breaks
+    pub breathing: BreathingConfig,
+    // the face embedding model used to tell the user from guests
+    pub face_recognition: FaceRecognitionConfig,
+    pub guest: GuestConfig,
+    // tips as the text of posture alerts
+    pub coaching: CoachingConfig,
+    // the exercises of a physiotherapy program
+    pub program: Vec<Exercise>,
+    // posture and eye break reminders on a timer, for --no-camera or while the camera is unavailable
+    pub reminders: RemindersConfig,
+}
+
+// Without a camera, a reminder to check the posture every `posture_every` seconds and a 20-20-20
+// eye break every `eye_break_every` seconds, either 0 for none. With `fallback`, the same while the
+// camera is busy, unplugged or not allowed.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RemindersConfig {
+    pub fallback: bool,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub posture_every: f64,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub eye_break_every: f64,
+}
+
+impl Default for RemindersConfig {
+    fn default() -> RemindersConfig {
+        RemindersConfig {
+            fallback: false,
+            posture_every: 900.0,
+            eye_break_every: 1200.0,
+        }
+    }
+}
+
+// Posture alerts worded as short tips, a different one each time: `messages`, or the built-in ones
+// if there are none
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CoachingConfig {
+    pub enabled: bool,
+    pub messages: Vec<String>,
+}
+
+impl Default for CoachingConfig {
+    fn default() -> CoachingConfig {
+        CoachingConfig {
+            enabled: true,
+            messages: Vec::new(),
+        }
+    }
+}
+
+// When someone other than the enrolled user (see `neckcheck enroll`) is alone at the computer: no
+// alerts and no stats, and only a check every `check_interval` seconds until the user is back. A
+// face at least `threshold` similar to the enrolled one is the user.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GuestConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::fraction")]
+    pub threshold: f32,
+    #[serde(deserialize_with = "validate::positive")]
+    pub check_interval: f64,
+}
+
+impl Default for GuestConfig {
+    fn default() -> GuestConfig {
+        GuestConfig {
+            enabled: false,
+            threshold: 0.5,
+            check_interval: 30.0,
+        }
+    }
+}
+
+// A stricter profile: the calibrated limit scaled by `limit_scale`, the screen locked `lock_after`
+// seconds after a stand-up reminder (0 to never), and no snoozing, dismissing or pausing without the
+// passcode, given as its SHA-256 in hex
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct StrictConfig {
+    pub enabled: bool,
+    pub passcode_sha256: Option<String>,
+    #[serde(deserialize_with = "validate::fraction")]
+    pub limit_scale: f32,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub lock_after: f64,
+}
+
+// A breathing guide drawn in the terminal for `duration` seconds when the strict profile enforces a
+// break, before the screen is locked: `inhale`, `hold` and `exhale` seconds, 4-7-8 breathing by
+// default
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BreathingConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::positive")]
+    pub inhale: f64,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub hold: f64,
+    #[serde(deserialize_with = "validate::positive")]
+    pub exhale: f64,
+    #[serde(deserialize_with = "validate::positive")]
+    pub duration: f64,
+}
+
+impl Default for BreathingConfig {
+    fn default() -> BreathingConfig {
+        BreathingConfig {
+            enabled: true,
+            inhale: 4.0,
+            hold: 7.0,
+            exhale: 8.0,
+            duration: 57.0,
+        }
+    }
+}
+
+impl Default for StrictConfig {
+    fn default() -> StrictConfig {
+        StrictConfig {
+            enabled: false,
+            passcode_sha256: None,
+            limit_scale: 0.85,
+            lock_after: 60.0,
+        }
+    }
+}
+
+// On a shared computer with several people in view, keeps measuring the largest and most central
+// face instead of whichever the detector lists first
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct HouseholdConfig {
+    pub enabled: bool,
+}
+
+// Keeps checks quick on slow machines: while they take longer than `budget` seconds, faces are
+// detected on a smaller copy of the frame, down to `min_scale` of its resolution
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LatencyConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::positive")]
+    pub budget: f64,
+    #[serde(deserialize_with = "validate::fraction")]
+    pub min_scale: f32,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> LatencyConfig {
+        LatencyConfig {
+            enabled: true,
+            budget: 0.25,
+            min_scale: 0.25,
+        }
+    }
+}
+
+// How the conditions combine into bad posture, e.g. `too_close or (tilt_down and low_position)`.
+// Any monitor a condition needs runs for the rule, without alerting of its own unless it is enabled.
+// A script, if there is one, is shown what the rule made of each check and has the last word.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct DecisionConfig {
+    pub rule: Rule,
+    pub script: Option<PathBuf>,
+}
+
+// Too close once the user has been for `too_close_for` seconds out of the last `window`, rather than
+// for a couple of seconds in a row, so that sitting back for a moment doesn't start the count over
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DutyCycleConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::positive")]
+    pub too_close_for: f64,
+    #[serde(deserialize_with = "validate::positive")]
+    pub window: f64,
+}
+
+impl Default for DutyCycleConfig {
+    fn default() -> DutyCycleConfig {
+        DutyCycleConfig {
+            enabled: false,
+            too_close_for: 20.0,
+            window: 30.0,
+        }
+    }
+}
+
+// Faces narrower than `min_size` times the calibrated one are ignored entirely, as people across the
+// room or photos on the wall
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BackgroundFacesConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::fraction")]
+    pub min_size: f32,
+}
+
+impl Default for BackgroundFacesConfig {
+    fn default() -> BackgroundFacesConfig {
+        BackgroundFacesConfig {
+            enabled: true,
+            min_size: 0.4,
+        }
+    }
+}
+
+// A measurement that differs by more than `max_jump` (0.5 being half as large again) from the one
+// before is ignored as a misdetection, unless the new size lasts `accept_after` checks
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct OutliersConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::positive")]
+    pub max_jump: f32,
+    #[serde(deserialize_with = "validate::positive")]
+    pub accept_after: u32,
+}
+
+impl Default for OutliersConfig {
+    fn default() -> OutliersConfig {
+        OutliersConfig {
+            enabled: true,
+            max_jump: 0.5,
+            accept_after: 3,
+        }
+    }
+}
+
+// Runs the face detector on only every `detect_every`th frame, predicting where the face is in
+// between: less CPU, but changes take up to that many frames longer to notice
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SkipFramesConfig {
+    #[serde(deserialize_with = "validate::at_least_one")]
+    pub detect_every: usize,
+}
+
+impl Default for SkipFramesConfig {
+    fn default() -> SkipFramesConfig {
+        SkipFramesConfig { detect_every: 1 }
+    }
+}
+
+// Watches neckcheck's own memory use every `interval` seconds. Once it has settled after
+// `warm_up` seconds, every further `warn_growth` MB is warned about, as is going over `max_mb`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct MemoryConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::positive")]
+    pub interval: f64,
+    #[serde(deserialize_with = "validate::positive")]
+    pub warm_up: f64,
+    #[serde(deserialize_with = "validate::positive")]
+    pub warn_growth: f64,
+    pub max_mb: Option<f64>,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> MemoryConfig {
+        MemoryConfig {
+            enabled: true,
+            interval: 60.0,
+            warm_up: 600.0,
+            warn_growth: 100.0,
+            max_mb: None,
+        }
+    }
+}
+
+// Reduces monitoring while on battery below `min_battery` percent, or while the CPU is throttled to
+// keep cool: only every `slow_down`th check is made, and faces are detected at no more than
+// `max_scale` of the frame's resolution
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PowerConfig {
+    pub enabled: bool,
+    // seconds between looks at the battery and the CPU
+    #[serde(deserialize_with = "validate::positive")]
+    pub interval: f64,
+    #[serde(deserialize_with = "validate::percent")]
+    pub min_battery: f32,
+    pub thermal: bool,
+    #[serde(deserialize_with = "validate::at_least_one")]
+    pub slow_down: usize,
+    #[serde(deserialize_with = "validate::fraction")]
+    pub max_scale: f32,
+}
+
+impl Default for PowerConfig {
+    fn default() -> PowerConfig {
+        PowerConfig {
+            enabled: true,
+            interval: 60.0,
+            min_battery: 30.0,
+            thermal: true,
+            slow_down: 3,
+            max_scale: 0.5,
+        }
+    }
+}
+
+// For users with visual or hearing impairments
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct AccessibilityConfig {
+    // blue, yellow and white annotations instead of green, red and orange
+    pub high_contrast: bool,
+    // lights stay on rather than flashing
+    pub reduced_motion: bool,
+    // a much taller banner and thicker outlines on the annotated frames
+    pub large_overlay: bool,
+    pub pair_alerts: PairAlerts,
+}
+
+// Making sure no alert is only seen or only heard
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PairAlerts {
+    #[default]
+    None,
+    // play a tone with every alert that would otherwise only be seen
+    Audio,
+    // show a notification with every alert that would otherwise only be heard
+    Visual,
+    Both,
+}
+
+// When alerts soften on their own, going by what the desktop says is going on
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct QuietConfig {
+    // seconds between looks at the desktop
+    #[serde(deserialize_with = "validate::positive")]
+    pub interval: f64,
+    // only notifications while something keeps the screen from going idle, e.g. a video player or
+    // a presentation
+    pub idle_inhibit: bool,
+    // no sound while the microphone is in use, as in a call
+    pub microphone: bool,
+    // no alerts at all, or only notifications, while the focused window's application or title
+    // contains one of these, ignoring case, e.g. "zoom"
+    pub silence_apps: Vec<String>,
+    pub soften_apps: Vec<String>,
+}
+
+impl Default for QuietConfig {
+    fn default() -> QuietConfig {
+        QuietConfig {
+            interval: 10.0,
+            idle_inhibit: true,
+            microphone: true,
+            silence_apps: Vec::new(),
+            soften_apps: Vec::new(),
+        }
+    }
+}
+
+impl QuietConfig {
+    // Whether there is anything to look at
+    pub fn any(&self) -> bool {
+        self.idle_inhibit
+            || self.microphone
+            || !self.silence_apps.is_empty()
+            || !self.soften_apps.is_empty()
+    }
+}
+
+// A notification summarising the day at a given time (HH:MM) every day
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SummaryConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "time_of_day")]
+    pub at: NaiveTime,
+}
+
+impl Default for SummaryConfig {
+    fn default() -> SummaryConfig {
+        SummaryConfig {
+            enabled: true,
+            at: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+        }
+    }
+}
+
+// Alerts when the head has been tilted forward by more than `max_angle` degrees for
+// `sustained_for` seconds, as estimated from the facial landmarks (so it needs a landmark model).
+// `offset` corrects for the camera looking up at (negative) or down on (positive) the face.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct NeckAngleConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::positive")]
+    pub max_angle: f32,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub sustained_for: f64,
+    pub offset: f32,
+}
+
+// Alerts when the eyes have been closed or heavy-lidded, i.e. their aspect ratio (about 0.3 wide
+// open) stayed below `closed_below`, for `sustained_for` seconds. Also needs a landmark model.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DrowsinessConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::fraction")]
+    pub closed_below: f32,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub sustained_for: f64,
+}
+
+impl Default for DrowsinessConfig {
+    fn default() -> DrowsinessConfig {
+        DrowsinessConfig {
+            enabled: false,
+            closed_below: 0.2,
+            sustained_for: 3.0,
+        }
+    }
+}
+
+// Counts blinks (the eye aspect ratio dipping below `closed_below`) over windows of `window`
+// seconds, recording the rate in the stats and alerting while it is below `min_per_minute`. Needs a
+// landmark model and a short check interval, as blinks only last a few hundred milliseconds.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BlinkRateConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::fraction")]
+    pub closed_below: f32,
+    #[serde(deserialize_with = "validate::positive")]
+    pub window: f64,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub min_per_minute: f32,
+}
+
+impl Default for BlinkRateConfig {
+    fn default() -> BlinkRateConfig {
+        BlinkRateConfig {
+            enabled: false,
+            closed_below: 0.2,
+            window: 120.0,
+            min_per_minute: 8.0,
+        }
+    }
+}
+
+// Alerts when the shoulders have been raised or rolled forward for `sustained_for` seconds, i.e.
+// the distance from ears to shoulders or the width of the shoulders shrank by more than
+// `tolerance` compared to the first `baseline_samples` measurements, taken while sitting upright
+// at launch. Needs a body pose model.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SlouchConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::fraction")]
+    pub tolerance: f32,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub sustained_for: f64,
+    #[serde(deserialize_with = "validate::at_least_one")]
+    pub baseline_samples: usize,
+}
+
+impl Default for SlouchConfig {
+    fn default() -> SlouchConfig {
+        SlouchConfig {
+            enabled: false,
+            tolerance: 0.15,
+            sustained_for: 30.0,
+            baseline_samples: 20,
+        }
+    }
+}
+
+// A cheap slouch heuristic that works with any face detector: alerts when the face has dropped by
+// more than `max_drop` face heights below its usual height in the frame (the median of the first
+// `baseline_samples` detections after launch) for `sustained_for` seconds
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct FaceDriftConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::positive")]
+    pub max_drop: f32,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub sustained_for: f64,
+    #[serde(deserialize_with = "validate::at_least_one")]
+    pub baseline_samples: usize,
+}
+
+impl Default for FaceDriftConfig {
+    fn default() -> FaceDriftConfig {
+        FaceDriftConfig {
+            enabled: false,
+            max_drop: 0.5,
+            sustained_for: 60.0,
+            baseline_samples: 20,
+        }
+    }
+}
+
+impl Default for NeckAngleConfig {
+    fn default() -> NeckAngleConfig {
+        NeckAngleConfig {
+            enabled: false,
+            max_angle: 20.0,
+            sustained_for: 120.0,
+            offset: 0.0,
+        }
+    }
+}
+
+// Learning from how the user reacts to alerts: dismissing or snoozing an alert within
+// `quick_dismiss` seconds suggests it was false and loosens the limit by `step`, sitting back
+// within `correction_window` seconds confirms it and tightens the limit by half a step. The
+// drift stays between `min_drift` and `max_drift`, as fractions of the calibrated limit.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AdaptiveConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::fraction")]
+    pub step: f32,
+    #[serde(deserialize_with = "validate::non_positive")]
+    pub min_drift: f32,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub max_drift: f32,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub quick_dismiss: f64,
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub correction_window: f64,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> AdaptiveConfig {
+        AdaptiveConfig {
+            enabled: true,
+            step: 0.02,
+            min_drift: -0.05,
+            max_drift: 0.15,
+            quick_dismiss: 10.0,
+            correction_window: 30.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct StatsConfig {
+    // defaults to stats.jsonl in the user data directory
+    pub path: Option<PathBuf>,
+    // noted with every record, e.g. "work-laptop"
+    pub profile: Option<String>,
+}
+
+// Keeps every event, measurement, alert and config change in a file per day in `dir`, for
+// `neckcheck analyze` to replay, for `keep_days` days
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EventLogConfig {
+    pub enabled: bool,
+    // defaults to events/ in the user data directory
+    pub dir: Option<PathBuf>,
+    #[serde(deserialize_with = "validate::at_least_one")]
+    pub keep_days: usize,
+}
+
+impl Default for EventLogConfig {
+    fn default() -> EventLogConfig {
+        EventLogConfig {
+            enabled: true,
+            dir: None,
+            keep_days: 14,
+        }
+    }
+}
+
+// Saves a grayscale patch of the face, `size` pixels square, at most every `interval` seconds into
+// `dir`, labelled with whether it was too close, until there are `max_samples`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DatasetConfig {
+    pub enabled: bool,
+    // defaults to dataset/ in the user data directory
+    pub dir: Option<PathBuf>,
+    #[serde(deserialize_with = "validate::positive")]
+    pub interval: f64,
+    #[serde(deserialize_with = "validate::positive")]
+    pub size: u32,
+    #[serde(deserialize_with = "validate::at_least_one")]
+    pub max_samples: usize,
+}
+
+impl Default for DatasetConfig {
+    fn default() -> DatasetConfig {
+        DatasetConfig {
+            enabled: false,
+            dir: None,
+            interval: 30.0,
+            size: 96,
+            max_samples: 5000,
+        }
+    }
+}
+
+// The control connection used by `neckcheck set-detector` and friends
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct IpcConfig {
+    pub enabled: bool,
+    pub address: SocketAddr,
+}
+
+impl Default for IpcConfig {
+    fn default() -> IpcConfig {
+        IpcConfig {
+            enabled: true,
+            address: SocketAddr::from(([127, 0, 0, 1], 47809)),
+        }
+    }
+}
+
+// The gRPC API, with the `grpc` feature
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GrpcConfig {
+    pub enabled: bool,
+    pub address: SocketAddr,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> GrpcConfig {
+        GrpcConfig {
+            enabled: false,
+            address: SocketAddr::from(([127, 0, 0, 1], 47810)),
+        }
+    }
+}
+
+// The web page served by the daemon, e.g. to check on it and pause it from a phone
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DashboardConfig {
+    pub enabled: bool,
+    pub address: SocketAddr,
+    // if set, required as `Authorization: Bearer ...` or `?token=...` on every request; needed to
+    // listen on anything but localhost
+    pub token: Option<String>,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> DashboardConfig {
+        DashboardConfig {
+            enabled: false,
+            address: SocketAddr::from(([127, 0, 0, 1], 47811)),
+            token: None,
+        }
+    }
+}
+
+// Taking commands from an ntfy topic, e.g. sent from the ntfy app on a phone
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NtfyConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::http_url")]
+    pub server: String,
+    pub topic: String,
+    // for a topic that needs logging in to
+    pub token: Option<String>,
+}
+
+impl Default for NtfyConfig {
+    fn default() -> NtfyConfig {
+        NtfyConfig {
+            enabled: false,
+            server: "https://ntfy.sh".to_string(),
+            topic: String::new(),
+            token: None,
+        }
+    }
+}
+
+// The /healthz endpoint for uptime monitors; the systemd watchdog is fed whenever it's asked for
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct HealthConfig {
+    pub enabled: bool,
+    pub address: SocketAddr,
+    // seconds past the check interval without a check before the pipeline counts as stuck
+    #[serde(deserialize_with = "validate::positive")]
+    pub stuck_after: f64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> HealthConfig {
+        HealthConfig {
+            enabled: false,
+            address: SocketAddr::from(([127, 0, 0, 1], 47812)),
+            stuck_after: 60.0,
+        }
+    }
+}
+
+// Reporting the state to a collector elsewhere, e.g. to check on it from a phone
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RemoteConfig {
+    pub enabled: bool,
+    #[serde(deserialize_with = "validate::https_url")]
+    pub url: String,
+    // sent as a bearer token
+    pub token: Option<String>,
+    // seconds between reports while the state stays the same, 0 for none
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub heartbeat: f64,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> RemoteConfig {
+        RemoteConfig {
+            enabled: false,
+            url: String::new(),
+            token: None,
+            heartbeat: 300.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AlertConfig {
+    // seconds between reminders while the user stays too close
+    #[serde(deserialize_with = "validate::positive")]
+    pub repeat_interval: f64,
+    // seconds of continuous bad posture before it counts as a sustained (critical) violation
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub sustained_after: f64,
+    // seconds seated without a break before a reminder to stand up, or 0 for none
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub stand_up_after: f64,
+    // seconds away from the desk that count as a break; shorter absences don't reset the seated
+    // time
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub min_break: f64,
+    pub sinks: Vec<SinkConfig>,
+}
+
+impl Default for AlertConfig {
+    fn default() -> AlertConfig {
+        AlertConfig {
+            repeat_interval: 2.0,
+            sustained_after: 60.0,
+            stand_up_after: 55.0 * 60.0,
+            min_break: 60.0,
+            sinks: vec![
+                SinkConfig {
+                    kind: SinkKind::Tone {
+                        duration: default_tone_duration(),
+                    },
+                    min_severity: Severity::Warning,
+                    events: Some(vec![
+                        AlertKind::TooClose,
+                        AlertKind::Reminder,
+                        AlertKind::Sustained,
+                        AlertKind::MonitoringFailed,
+                        AlertKind::StandUp,
+                    ]),
+                },
+                SinkConfig {
+                    kind: SinkKind::Notification,
+                    min_severity: Severity::Info,
+                    events: Some(vec![
+                        AlertKind::Summary,
+                        AlertKind::Exercise,
+                        AlertKind::PostureCheck,
+                        AlertKind::EyeBreak,
+                    ]),
+                },
+            ],
+        }
+    }
+}
+
+// A sink and the alerts that are routed to it: those of at least `min_severity` and, if given,
+// only the listed kinds of event.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub kind: SinkKind,
+    #[serde(default)]
+    pub min_severity: Severity,
+    #[serde(default)]
+    pub events: Option<Vec<AlertKind>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkKind {
+    Tone {
+        // seconds
+        #[serde(
+            default = "default_tone_duration",
+            deserialize_with = "validate::positive"
+        )]
+        duration: f64,
+    },
+    Notification,
+    Webhook {
+        #[serde(deserialize_with = "validate::http_url")]
+        url: String,
+    },
+    Email(EmailConfig),
+    Obs(ObsConfig),
+    Hue(HueConfig),
+    Lifx(LifxConfig),
+    Wled(WledConfig),
+    #[serde(rename = "openrgb")]
+    OpenRgb(OpenRgbConfig),
+    Terminal(TerminalConfig),
+    Escalation(EscalationConfig),
+    Brightness(BrightnessConfig),
+    NightMode(NightModeConfig),
+    Log,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct EmailConfig {
+    pub server: String,
+    // the usual port for the security is used if not given
+    pub port: Option<u16>,
+    pub security: EmailSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ObsConfig {
+    // obs-websocket, as under Tools > WebSocket Server Settings
+    pub address: String,
+    pub password: Option<String>,
+    // the scene holding the source, when toggling a scene item
+    pub scene: String,
+    // the source shown while the posture is bad, e.g. a "SIT UP" text source
+    pub source: String,
+    // enable this filter of the source instead of showing the source itself
+    pub filter: Option<String>,
+}
+
+impl Default for ObsConfig {
+    fn default() -> Self {
+        ObsConfig {
+            address: "ws://localhost:4455".to_string(),
+            password: None,
+            scene: "Scene".to_string(),
+            source: "SIT UP".to_string(),
+            filter: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TerminalConfig {
+    // the terminal to write to, e.g. /dev/pts/3 (see `tty`), or neckcheck's own output
+    pub tty: Option<PathBuf>,
+    pub bell: bool,
+    // a desktop notification from the terminal emulator, for those that support OSC 9
+    pub osc9: bool,
+    // show the message in the tmux status line
+    pub tmux: bool,
+    // only on this tmux client (see `tmux list-clients`), otherwise the most recent one
+    pub tmux_client: Option<String>,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        TerminalConfig {
+            tty: None,
+            bell: true,
+            osc9: true,
+            tmux: false,
+            tmux_client: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EscalationConfig {
+    // seconds of continuous bad posture before escalating
+    #[serde(deserialize_with = "validate::non_negative")]
+    pub after: f64,
+    // an image to show as the wallpaper until the posture is good again
+    pub wallpaper: Option<PathBuf>,
+    // lock the screen to force a break
+    pub lock_screen: bool,
+}
+
+impl Default for EscalationConfig {
+    fn default() -> Self {
+        EscalationConfig {
+            after: 900.0,
+            wallpaper: None,
+            lock_screen: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BrightnessConfig {
+    pub control: BrightnessControl,
+    // the ddcutil display number, if there is more than one monitor
+    pub display: Option<u32>,
+    // percentage points of brightness taken off per minute of bad posture
+    #[serde(deserialize_with = "validate::positive")]
+    pub per_minute: f32,
+    // never dimmer than this, in percent
+    #[serde(deserialize_with = "validate::percent")]
+    pub min: f32,
+}
+
+impl Default for BrightnessConfig {
+    fn default() -> Self {
+        BrightnessConfig {
+            control: BrightnessControl::default(),
+            display: None,
+            per_minute: 10.0,
+            min: 20.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BrightnessControl {
+    // laptop backlights
+    #[default]
+    Brightnessctl,
+    // external monitors over DDC/CI
+    Ddcutil,
+    Xbacklight,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NightModeConfig {
+    pub method: NightModeMethod,
+    // the colour temperature of white during the break, in kelvin
+    #[serde(deserialize_with = "validate::kelvin")]
+    pub temperature: u32,
+}
+
+impl Default for NightModeConfig {
+    fn default() -> Self {
+        NightModeConfig {
+            method: NightModeMethod::default(),
+            temperature: 3500,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NightModeMethod {
+    Gammastep,
+    Redshift,
+    // the display gamma through the OS (Windows and macOS)
+    Native,
+}
+
+impl Default for NightModeMethod {
+    fn default() -> Self {
+        if cfg!(any(windows, target_os = "macos")) {
+            NightModeMethod::Native
+        } else {
+            NightModeMethod::Gammastep
+        }
+    }
+}
+
+// A Philips Hue light, through its bridge
+#[derive(Deserialize, Debug, Clone)]
+pub struct HueConfig {
+    // the bridge's address
+    pub bridge: String,
+    // an application key created by pressing the bridge's link button
+    pub username: String,
+    // the light's number on the bridge
+    pub light: u32,
+    #[serde(default = "default_light_color")]
+    pub color: [u8; 3],
+    #[serde(default)]
+    pub flash: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LifxConfig {
+    pub address: IpAddr,
+    #[serde(default = "default_light_color")]
+    pub color: [u8; 3],
+    #[serde(default)]
+    pub flash: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WledConfig {
+    // host or host:port
+    pub address: String,
+    #[serde(default = "default_light_color")]
+    pub color: [u8; 3],
+    #[serde(default)]
+    pub flash: bool,
+}
+
+// A device lit through the OpenRGB SDK server
+#[derive(Deserialize, Debug, Clone)]
+pub struct OpenRgbConfig {
+    #[serde(default = "default_openrgb_address")]
+    pub address: String,
+    // the device's index in OpenRGB's device list
+    #[serde(default)]
+    pub device: u32,
+    // only this zone of the device, by index
+    #[serde(default)]
+    pub zone: Option<u32>,
+    #[serde(default = "default_light_color")]
+    pub color: [u8; 3],
+    // the saved OpenRGB profile to load after recovering
+    pub profile: String,
+}
+
+fn default_openrgb_address() -> String {
+    "127.0.0.1:6742".to_string()
+}
+
+fn default_light_color() -> [u8; 3] {
+    [255, 0, 0]
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailSecurity {
+    // upgrade a plain connection, usually on port 587
+    #[default]
+    Starttls,
+    // TLS from the start, usually on port 465
+    Tls,
+    // unencrypted, e.g. to a relay on localhost
+    None,
+}
+
+fn default_tone_duration() -> f64 {
+    1.0
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("neckcheck").join("config.toml"))
+    }
+
+    // Loads the given config file, or the default one if there is one, or falls back to the
+    // built-in defaults, with any NECKCHECK_* environment variables on top. A file from an older
+    // version is upgraded in place first.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Config::default_path() {
+                Some(path) if path.exists() => path,
+                _ => {
+                    let (config, unknown) =
+                        Config::parse_with_env("").map_err(ConfigError::Environment)?;
+                    for key in unknown {
+                        println!("Ignoring unknown key {} in the environment", key);
+                    }
+                    return Ok(config);
+                }
+            },
+        };
+        if let Err(e) = migrate::upgrade(&path) {
+            println!("Failed to upgrade {}: {}", path.display(), e);
+        }
+        Config::reload(&path)
+    }
+
+    // Loads a config file again after it changed, upgrading an older one only as it is read, so
+    // that the file being edited is left alone
+    pub fn reload(path: &Path) -> Result<Config, ConfigError> {
+        let (config, unknown) = Config::read(path)?;
+        for key in unknown {
+            println!("Ignoring unknown key {} in {}", key, path.display());
+        }
+        if config.version > migrate::CURRENT_VERSION {
+            println!(
+                "{} is from a newer neckcheck; settings this one doesn't know are ignored",
+                path.display()
+            );
+        }
+        Ok(config)
+    }
+
+    // Reads a config file, along with the keys in it that mean nothing to neckcheck (e.g. typos).
+    // An older file is read as it would be upgraded.
+    pub fn read(path: &Path) -> Result<(Config, Vec<String>), ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::ReadError(path.to_path_buf(), e.to_string()))?;
+        let contents = migrate::migrate(&contents).unwrap_or(contents);
+        Config::parse_with_env(&contents)
+            .map_err(|e| ConfigError::ParseError(path.to_path_buf(), e))
+    }
+
+    fn parse_with_env(contents: &str) -> Result<(Config, Vec<String>), String> {
+        Config::parse(&env::apply(contents, std::env::vars_os())?)
+    }
+
+    pub fn parse(contents: &str) -> Result<(Config, Vec<String>), String> {
+        let mut unknown = Vec::new();
+        let config = serde_ignored::deserialize(toml::Deserializer::new(contents), |key| {
+            unknown.push(key.to_string())
+        })
+        .map_err(|e| e.to_string())?;
+        Ok((config, unknown))
+    }
+}
+
+// Writes the default config, with comments, to the given file or the default one, unless there is
+// a file there already, for `neckcheck config init`
+pub fn init(path: Option<&Path>, force: bool) {
+    let Some(path) = path.map(Path::to_path_buf).or_else(Config::default_path) else {
+        println!("No config directory to write the config to; pass --config");
+        return;
+    };
+    if path.exists() && !force {
+        println!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        );
+        return;
+    }
+    let written = match path.parent() {
+        Some(dir) => fs::create_dir_all(dir).and_then(|_| fs::write(&path, DEFAULT_CONFIG)),
+        None => fs::write(&path, DEFAULT_CONFIG),
+    };
+    match written {
+        Ok(()) => println!("Wrote {}", path.display()),
+        Err(e) => println!("Failed to write {}: {}", path.display(), e),
+    }
+}
+
+// Checks a config file (the default one if not given) for errors and unknown keys, for
+// `neckcheck config validate`
+pub fn validate(path: Option<&Path>) {
+    let path = match path.map(Path::to_path_buf).or_else(Config::default_path) {
+        Some(path) if path.exists() => path,
+        Some(path) => {
+            println!(
+                "No config file at {}; the defaults are used",
+                path.display()
+            );
+            return;
+        }
+        None => {
+            println!("No config directory; the defaults are used");
+            return;
+        }
+    };
+    match Config::read(&path) {
+        Ok((_, unknown)) if unknown.is_empty() => println!("{} is valid", path.display()),
+        Ok((_, unknown)) => {
+            println!(
+                "{} is valid, but these keys are unknown and ignored:",
+                path.display()
+            );
+            for key in unknown {
+                println!("  {}", key);
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// Checks the config file for changes every few seconds until shutdown, calling `on_change` with
+// each new version that loads. A broken edit is reported and otherwise ignored.
+pub async fn watch(path: PathBuf, shutdown: CancellationToken, mut on_change: impl FnMut(Config)) {
+    let modified = |path: &Path| -> Option<SystemTime> { fs::metadata(path).ok()?.modified().ok() };
+    let mut last = modified(&path);
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let current = modified(&path);
+        if current == last {
+            continue;
+        }
+        last = current;
+        match Config::reload(&path) {
+            Ok(config) => {
+                println!("Reloaded {}", path.display());
+                on_change(config);
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_values_are_reported_with_their_line() {
+        let error =
+            Config::parse("[alerts]\nrepeat_interval = 2.0\nmin_break = -5.0\n").unwrap_err();
+        assert!(error.contains("line 3"), "{}", error);
+        assert!(error.contains("must not be negative"), "{}", error);
+
+        let error = Config::parse("[[alerts.sinks]]\ntype = \"beep\"\n").unwrap_err();
+        assert!(error.contains("unknown variant `beep`"), "{}", error);
+    }
+
+    #[test]
+    fn the_default_config_is_current_and_complete() {
+        let (config, unknown) = Config::parse(DEFAULT_CONFIG).unwrap();
+        assert!(unknown.is_empty(), "{:?}", unknown);
+        assert_eq!(config.version, migrate::CURRENT_VERSION);
+        assert_eq!(migrate::migrate(DEFAULT_CONFIG), None);
+        let defaults = Config::default();
+        assert_eq!(
+            config.alerts.repeat_interval,
+            defaults.alerts.repeat_interval
+        );
+        assert_eq!(config.alerts.sinks.len(), defaults.alerts.sinks.len());
+        assert_eq!(config.adaptive, defaults.adaptive);
+        assert_eq!(config.neck_angle, defaults.neck_angle);
+        assert_eq!(config.slouch, defaults.slouch);
+        assert_eq!(config.summary, defaults.summary);
+    }
+
+    #[test]
+    fn unknown_keys_are_listed() {
+        let (config, unknown) =
+            Config::parse("[alerts]\nrepeat_intervall = 5.0\nmin_break = 30.0\n").unwrap();
+        assert_eq!(config.alerts.min_break, 30.0);
+        assert_eq!(unknown, vec!["alerts.repeat_intervall".to_string()]);
+    }
+}
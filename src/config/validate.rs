@@ -0,0 +1,114 @@
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+// Checks for config values, used with `#[serde(deserialize_with = ...)]` so that a bad value is
+// reported by the TOML parser along with the line and key it is on
+
+fn check<'de, D, T>(deserializer: D, ok: impl Fn(f64) -> bool, message: &str) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Into<f64> + Copy,
+{
+    let value = T::deserialize(deserializer)?;
+    let number: f64 = value.into();
+    if !(number.is_finite() && ok(number)) {
+        return Err(D::Error::custom(format!("{}, not {}", message, number)));
+    }
+    Ok(value)
+}
+
+pub fn positive<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Into<f64> + Copy,
+{
+    check(
+        deserializer,
+        |value| value > 0.0,
+        "must be a positive number",
+    )
+}
+
+pub fn non_negative<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Into<f64> + Copy,
+{
+    check(deserializer, |value| value >= 0.0, "must not be negative")
+}
+
+pub fn non_positive<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Into<f64> + Copy,
+{
+    check(deserializer, |value| value <= 0.0, "must not be positive")
+}
+
+// strictly between 0 and 1, e.g. a tolerance or an eye aspect ratio
+pub fn fraction<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Into<f64> + Copy,
+{
+    check(
+        deserializer,
+        |value| value > 0.0 && value < 1.0,
+        "must be between 0 and 1",
+    )
+}
+
+pub fn percent<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Into<f64> + Copy,
+{
+    check(
+        deserializer,
+        |value| (0.0..=100.0).contains(&value),
+        "must be a percentage between 0 and 100",
+    )
+}
+
+// a colour temperature, within what gammastep and redshift accept
+pub fn kelvin<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Into<f64> + Copy,
+{
+    check(
+        deserializer,
+        |value| (1000.0..=25000.0).contains(&value),
+        "must be between 1000 and 25000 kelvin",
+    )
+}
+
+pub fn at_least_one<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let value = usize::deserialize(deserializer)?;
+    if value == 0 {
+        return Err(D::Error::custom("must be at least 1"));
+    }
+    Ok(value)
+}
+
+pub fn https_url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if !value.starts_with("https://") {
+        return Err(D::Error::custom(format!(
+            "{:?} is not an https:// url",
+            value
+        )));
+    }
+    Ok(value)
+}
+
+pub fn http_url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if !(value.starts_with("http://") || value.starts_with("https://")) {
+        return Err(D::Error::custom(format!(
+            "{:?} is not an http:// or https:// url",
+            value
+        )));
+    }
+    Ok(value)
+}
@@ -0,0 +1,371 @@
+use std::io::Read;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Duration as ChronoDuration, DurationRound, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tokio::runtime::Handle;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+use crate::alert::AlertKind;
+use crate::config::DashboardConfig;
+use crate::events::Feedback;
+use crate::stats::{bad_posture, StatsEntry, StatsLog, StatsRecord};
+use crate::status::Status;
+use crate::worker::WorkerHandle;
+
+const INDEX: &str = include_str!("index.html");
+const MAX_BODY_SIZE: u64 = 4 * 1024;
+const HOURS_SHOWN: i64 = 24;
+// the page hears about a new state straight away, and about new numbers at most this often
+const UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+
+// What the buttons POST to /control, e.g. `{"command": "snooze", "minutes": 10}`
+#[derive(Deserialize, Debug)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Control {
+    Snooze { minutes: f64 },
+    Dismiss,
+    Pause,
+    Resume,
+}
+
+// One bar of the chart
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Hour {
+    pub start: DateTime<Local>,
+    pub bad_minutes: f64,
+    pub alerts: u32,
+}
+
+// Bad posture and alerts in each of the last 24 hours, this one included
+pub fn history(records: &[StatsRecord], now: DateTime<Local>) -> Vec<Hour> {
+    let this_hour = now.duration_trunc(ChronoDuration::hours(1)).unwrap_or(now);
+    let first = this_hour - ChronoDuration::hours(HOURS_SHOWN - 1);
+    let bad = bad_posture(records);
+    (0..HOURS_SHOWN)
+        .map(|n| {
+            let start = first + ChronoDuration::hours(n);
+            let end = start + ChronoDuration::hours(1);
+            let bad_seconds: i64 = bad
+                .iter()
+                .map(|segment| {
+                    (segment.end.min(end) - segment.start.max(start))
+                        .num_seconds()
+                        .max(0)
+                })
+                .sum();
+            let alerts = records
+                .iter()
+                .filter(|record| record.time >= start && record.time < end)
+                .filter(|record| {
+                    matches!(
+                        record.entry,
+                        StatsEntry::Event {
+                            event: AlertKind::TooClose
+                                | AlertKind::NeckAngle
+                                | AlertKind::Slouch
+                                | AlertKind::Sinking
+                        }
+                    )
+                })
+                .count() as u32;
+            Hour {
+                start,
+                bad_minutes: bad_seconds as f64 / 60.0,
+                alerts,
+            }
+        })
+        .collect()
+}
+
+// What the requests are answered from
+struct Context {
+    token: Option<String>,
+    worker: WorkerHandle,
+    status: watch::Receiver<Status>,
+    stats: Option<StatsLog>,
+    runtime: Handle,
+    shutdown: CancellationToken,
+}
+
+// Serves the dashboard on `address` until shutdown: the page itself, its live status over a
+// WebSocket at /ws, the chart at /history and the buttons at /control
+pub fn spawn(
+    config: &DashboardConfig,
+    worker: WorkerHandle,
+    status: watch::Receiver<Status>,
+    stats: Option<StatsLog>,
+    shutdown: CancellationToken,
+) {
+    if let Err(e) = check_exposure(config) {
+        println!("{}", e);
+        return;
+    }
+    let server = match Server::http(config.address) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            println!("Failed to serve the dashboard on {}: {}", config.address, e);
+            return;
+        }
+    };
+    println!("Serving the dashboard on http://{}/", config.address);
+    let context = Arc::new(Context {
+        token: config.token.clone(),
+        worker,
+        status,
+        stats,
+        runtime: Handle::current(),
+        shutdown: shutdown.clone(),
+    });
+    tokio::spawn({
+        let server = server.clone();
+        async move {
+            shutdown.cancelled().await;
+            server.unblock();
+        }
+    });
+    thread::Builder::new()
+        .name("neckcheck-dashboard".to_string())
+        .spawn(move || {
+            for request in server.incoming_requests() {
+                handle(request, &context);
+            }
+        })
+        .expect("Failed to spawn the dashboard thread");
+}
+
+// Anyone who can reach the dashboard can pause neckcheck, so beyond this machine it needs a token
+fn check_exposure(config: &DashboardConfig) -> Result<(), String> {
+    if config.token.is_none() && !config.address.ip().is_loopback() {
+        return Err(format!(
+            "Not serving the dashboard on {} without a token; set dashboard.token",
+            config.address
+        ));
+    }
+    Ok(())
+}
+
+// The token a request came with, from an `Authorization: Bearer` header or else `?token=`
+fn given_token<'a>(query: &'a str, authorization: Option<&'a str>) -> Option<&'a str> {
+    authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("token="))
+        })
+}
+
+// Compares the digests rather than the tokens themselves, taking as long whatever was given, so that
+// the token can't be worked out a character at a time from how long the answers take
+fn token_matches(token: &str, given: &str) -> bool {
+    let (token, given) = (Sha256::digest(token), Sha256::digest(given));
+    token
+        .iter()
+        .zip(given.iter())
+        .fold(0, |difference, (a, b)| difference | (a ^ b))
+        == 0
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn json(value: &impl Serialize) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_data(serde_json::to_vec(value).unwrap())
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn handle(mut request: Request, context: &Arc<Context>) {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let path = path.to_string();
+    let authorization = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .map(|header| header.value.as_str());
+    // the page itself says nothing, and takes the token from the address to send with the rest
+    let public = path == "/" && *request.method() == Method::Get;
+    if let (Some(token), false) = (&context.token, public) {
+        let given = given_token(query, authorization);
+        if !given.is_some_and(|given| token_matches(token, given)) {
+            let _ = request.respond(Response::from_string("Wrong token").with_status_code(401));
+            return;
+        }
+    }
+    let response = match (request.method(), path.as_str()) {
+        (Method::Get, "/") => Response::from_string(INDEX)
+            .with_header(header("Content-Type", "text/html; charset=utf-8")),
+        (Method::Get, "/ws") => {
+            upgrade(request, context);
+            return;
+        }
+        (Method::Get, "/history") => {
+            let records = context
+                .stats
+                .as_ref()
+                .and_then(|log| log.read().ok())
+                .unwrap_or_default();
+            json(&history(&records, Local::now()))
+        }
+        (Method::Post, "/control") => {
+            let control = serde_json::from_reader(Read::take(request.as_reader(), MAX_BODY_SIZE));
+            let result = match control {
+                Ok(control) => apply(control, &context.worker),
+                Err(e) => Err(format!("Invalid request: {}", e)),
+            };
+            match result {
+                Ok(()) => Response::from_string("").with_status_code(204),
+                Err(e) => Response::from_string(e).with_status_code(400),
+            }
+        }
+        _ => Response::from_string("Not found").with_status_code(404),
+    };
+    let _ = request.respond(response);
+}
+
+fn apply(control: Control, worker: &WorkerHandle) -> Result<(), String> {
+    match control {
+        Control::Snooze { minutes } => match Duration::try_from_secs_f64(minutes * 60.0) {
+            Ok(duration) => worker.feedback(Feedback::Snooze(duration)),
+            Err(_) => Err(format!("Invalid snooze duration: {} minutes", minutes)),
+        },
+        Control::Dismiss => worker.feedback(Feedback::Dismiss),
+        Control::Pause => worker.pause(true),
+        Control::Resume => worker.pause(false),
+    }
+}
+
+// Hands the connection over to a thread of its own that sends the status whenever it changes
+fn upgrade(request: Request, context: &Arc<Context>) {
+    let key = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+        .map(|header| derive_accept_key(header.value.as_bytes()));
+    let Some(accept) = key else {
+        let _ =
+            request.respond(Response::from_string("Expected a WebSocket").with_status_code(400));
+        return;
+    };
+    let response =
+        Response::empty(StatusCode(101)).with_header(header("Sec-WebSocket-Accept", &accept));
+    let stream = request.upgrade("websocket", response);
+    let context = context.clone();
+    let spawned = thread::Builder::new()
+        .name("neckcheck-dashboard-ws".to_string())
+        .spawn(move || {
+            let socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+            stream_status(socket, &context);
+        });
+    if let Err(e) = spawned {
+        println!("Failed to spawn a dashboard connection thread: {}", e);
+    }
+}
+
+fn stream_status<S: std::io::Read + std::io::Write>(mut socket: WebSocket<S>, context: &Context) {
+    let mut status = context.status.clone();
+    let shutdown = &context.shutdown;
+    loop {
+        let update = status.borrow_and_update().clone();
+        let text = serde_json::to_string(&update).unwrap();
+        if socket.send(Message::text(text)).is_err() {
+            return;
+        }
+        let written = tokio::time::Instant::now();
+        let open = context.runtime.block_on(async {
+            tokio::select! {
+                _ = shutdown.cancelled() => false,
+                changed = status.changed() => changed.is_ok(),
+            }
+        });
+        if !open {
+            let _ = socket.close(None);
+            return;
+        }
+        // the metrics change with every check, so only a new state is sent on at once
+        if status.borrow().state == update.state {
+            let open = context.runtime.block_on(async {
+                tokio::select! {
+                    _ = shutdown.cancelled() => false,
+                    _ = tokio::time::sleep_until(written + UPDATE_INTERVAL) => true,
+                }
+            });
+            if !open {
+                let _ = socket.close(None);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn record(time: DateTime<Local>, event: AlertKind) -> StatsRecord {
+        StatsRecord {
+            time,
+            host: None,
+            profile: None,
+            session: None,
+            others_present: false,
+            entry: StatsEntry::Event { event },
+        }
+    }
+
+    #[test]
+    fn the_chart_covers_the_last_day_by_hour() {
+        let at = |hour, minute| {
+            Local
+                .with_ymd_and_hms(2026, 3, 10, hour, minute, 0)
+                .unwrap()
+        };
+        let records = vec![
+            record(at(13, 50), AlertKind::TooClose),
+            record(at(14, 20), AlertKind::Recovered),
+            record(at(14, 40), AlertKind::Slouch),
+        ];
+        let hours = history(&records, at(15, 5));
+        assert_eq!(hours.len(), 24);
+        assert_eq!(hours[23].start, at(15, 0));
+        assert_eq!(hours[21].start, at(13, 0));
+        assert_eq!(hours[21].bad_minutes, 10.0);
+        assert_eq!(hours[21].alerts, 1);
+        assert_eq!(hours[22].bad_minutes, 20.0);
+        assert_eq!(hours[22].alerts, 1);
+        assert_eq!(hours[23].bad_minutes, 0.0);
+    }
+
+    #[test]
+    fn beyond_localhost_only_requests_with_the_token_are_served() {
+        let config = |address: &str, token: Option<&str>| DashboardConfig {
+            enabled: true,
+            address: address.parse().unwrap(),
+            token: token.map(str::to_string),
+        };
+        assert!(check_exposure(&config("127.0.0.1:47811", None)).is_ok());
+        assert!(check_exposure(&config("0.0.0.0:47811", None)).is_err());
+        assert!(check_exposure(&config("0.0.0.0:47811", Some("s3cret"))).is_ok());
+
+        assert_eq!(
+            given_token("a=1&token=s3cret", Some("Bearer other")),
+            Some("other")
+        );
+        assert_eq!(given_token("a=1&token=s3cret", None), Some("s3cret"));
+        assert_eq!(given_token("", Some("Basic s3cret")), None);
+        assert!(token_matches("s3cret", "s3cret"));
+        assert!(!token_matches("s3cret", "s3cre"));
+        assert!(!token_matches("s3cret", ""));
+    }
+}
@@ -0,0 +1,255 @@
+// Face patches labelled with the posture they were taken in, for those who want to train a detector
+// for their own camera and lighting. The patches are small and grayscale, and neither their names
+// nor their labels say when or where they were taken.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, RgbImage};
+use imageproc::rect::Rect;
+use serde::{Deserialize, Serialize};
+
+use crate::config::DatasetConfig;
+use crate::privacy::ImageAccess;
+
+const LABELS: &str = "labels.jsonl";
+// how much of the surroundings a patch shows on each side of the face, as a fraction of its size
+const MARGIN: f32 = 0.25;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum PostureLabel {
+    TooClose,
+    Ok,
+}
+
+// Where the face is within a patch
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+struct FaceBox {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+// One line of labels.jsonl
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Sample {
+    file: String,
+    label: PostureLabel,
+    // the detector's score for the face
+    confidence: Option<f32>,
+    face: FaceBox,
+}
+
+pub struct DatasetRecorder {
+    dir: PathBuf,
+    interval: Duration,
+    size: u32,
+    max_samples: usize,
+    // how many there are, including those of earlier runs
+    samples: usize,
+    last_saved: Option<Instant>,
+}
+
+impl DatasetRecorder {
+    pub fn new(dir: PathBuf, config: &DatasetConfig, _access: ImageAccess) -> DatasetRecorder {
+        if let Err(e) = fs::create_dir_all(&dir) {
+            println!(
+                "Failed to create dataset directory {}: {}",
+                dir.display(),
+                e
+            );
+        }
+        let samples = fs::read_to_string(dir.join(LABELS))
+            .map(|labels| labels.lines().count())
+            .unwrap_or(0);
+        if samples >= config.max_samples {
+            println!(
+                "The dataset in {} already has {} samples, saving no more",
+                dir.display(),
+                samples
+            );
+        }
+        DatasetRecorder {
+            dir,
+            interval: Duration::from_secs_f64(config.interval),
+            size: config.size,
+            max_samples: config.max_samples,
+            samples,
+            last_saved: None,
+        }
+    }
+
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("neckcheck").join("dataset"))
+    }
+
+    // Saves a patch of `face` labelled with the posture, unless one was saved less than the interval
+    // ago or the dataset is full
+    pub fn record(
+        &mut self,
+        frame: &RgbImage,
+        face: Rect,
+        confidence: Option<f32>,
+        too_close: bool,
+        now: Instant,
+    ) {
+        let due = self
+            .last_saved
+            .is_none_or(|last| now.saturating_duration_since(last) >= self.interval);
+        if !due || self.samples >= self.max_samples {
+            return;
+        }
+        let Some((patch, face)) = patch(frame, face, self.size) else {
+            return;
+        };
+        self.last_saved = Some(now);
+        let file = format!("sample-{:06}.png", self.samples + 1);
+        let path = self.dir.join(&file);
+        if let Err(e) = patch.save(&path) {
+            println!(
+                "Failed to save a dataset sample to {}: {}",
+                path.display(),
+                e
+            );
+            return;
+        }
+        let sample = Sample {
+            file,
+            label: if too_close {
+                PostureLabel::TooClose
+            } else {
+                PostureLabel::Ok
+            },
+            confidence,
+            face,
+        };
+        let labels = self.dir.join(LABELS);
+        let written = serde_json::to_string(&sample)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&labels)
+                    .and_then(|mut file| writeln!(file, "{}", line))
+                    .map_err(|e| e.to_string())
+            });
+        match written {
+            Ok(_) => {
+                self.samples += 1;
+                if self.samples == self.max_samples {
+                    println!(
+                        "The dataset in {} is complete with {} samples",
+                        self.dir.display(),
+                        self.samples
+                    );
+                }
+            }
+            Err(e) => println!("Failed to write {}: {}", labels.display(), e),
+        }
+    }
+}
+
+// A square grayscale patch around the face, scaled to `size`, and where the face is in it. Whatever
+// of the square lies outside the frame is black.
+fn patch(frame: &RgbImage, face: Rect, size: u32) -> Option<(GrayImage, FaceBox)> {
+    let side = (face.width().max(face.height()) as f32 * (1.0 + 2.0 * MARGIN)).round() as u32;
+    let left = face.left() + face.width() as i32 / 2 - side as i32 / 2;
+    let top = face.top() + face.height() as i32 / 2 - side as i32 / 2;
+    let (x0, y0) = (left.max(0), top.max(0));
+    let x1 = (left + side as i32).min(frame.width() as i32);
+    let y1 = (top + side as i32).min(frame.height() as i32);
+    if x0 >= x1 || y0 >= y1 {
+        return None;
+    }
+    let visible = imageops::crop_imm(
+        frame,
+        x0 as u32,
+        y0 as u32,
+        (x1 - x0) as u32,
+        (y1 - y0) as u32,
+    )
+    .to_image();
+    let mut square = GrayImage::new(side, side);
+    imageops::replace(
+        &mut square,
+        &imageops::grayscale(&visible),
+        (x0 - left) as i64,
+        (y0 - top) as i64,
+    );
+    let scale = size as f32 / side as f32;
+    let face = FaceBox {
+        x: ((face.left() - left) as f32 * scale).round() as i32,
+        y: ((face.top() - top) as f32 * scale).round() as i32,
+        width: (face.width() as f32 * scale).round() as u32,
+        height: (face.height() as f32 * scale).round() as u32,
+    };
+    Some((
+        imageops::resize(&square, size, size, FilterType::Triangle),
+        face,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::privacy::PrivacyMode;
+
+    #[test]
+    fn samples_are_saved_with_their_labels_at_intervals_until_full() {
+        let dir = std::env::temp_dir().join(format!("neckcheck-dataset-{}", std::process::id()));
+        let config = DatasetConfig {
+            enabled: true,
+            dir: None,
+            interval: 30.0,
+            size: 48,
+            max_samples: 2,
+        };
+        let access = || PrivacyMode::Normal.image_access("test").unwrap();
+        let mut dataset = DatasetRecorder::new(dir.clone(), &config, access());
+        let frame = RgbImage::from_pixel(320, 240, image::Rgb([200, 100, 50]));
+        // partly out of the frame
+        let face = Rect::at(-20, 100).of_size(80, 80);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        dataset.record(&frame, face, Some(9.5), true, at(0));
+        dataset.record(&frame, face, Some(9.0), false, at(10));
+        dataset.record(&frame, face, Some(8.0), false, at(30));
+        // full, also after a restart
+        let mut dataset = DatasetRecorder::new(dir.clone(), &config, access());
+        dataset.record(&frame, face, Some(7.0), false, at(60));
+
+        let samples: Vec<Sample> = fs::read_to_string(dir.join(LABELS))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].file, "sample-000001.png");
+        assert_eq!(samples[0].label, PostureLabel::TooClose);
+        assert_eq!(samples[1].label, PostureLabel::Ok);
+        assert_eq!(samples[1].confidence, Some(8.0));
+        // 80 pixels with 20 either side, scaled from 120 to 48
+        assert_eq!(
+            samples[0].face,
+            FaceBox {
+                x: 8,
+                y: 8,
+                width: 32,
+                height: 32
+            }
+        );
+        let patch = image::open(dir.join(&samples[0].file)).unwrap().to_luma8();
+        assert_eq!(patch.dimensions(), (48, 48));
+        // left of the frame's edge
+        assert_eq!(patch.get_pixel(2, 24)[0], 0);
+        assert_ne!(patch.get_pixel(40, 24)[0], 0);
+        assert!(!dir.join("sample-000003.png").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,841 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::{BrightnessControl, NightModeMethod};
+
+// Desktop actions that are done differently on each platform, through the tools that come with it
+
+// Runs a command, failing with its error output if it doesn't succeed
+fn run(command: &mut Command) -> Result<String, String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// The current wallpaper, in whatever form `set_wallpaper` takes back
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn wallpaper() -> Result<String, String> {
+    // GNOME and the desktops built on it
+    let uri = run(Command::new("gsettings").args([
+        "get",
+        "org.gnome.desktop.background",
+        "picture-uri",
+    ]))?;
+    Ok(uri.trim_matches('\'').to_string())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn set_wallpaper(wallpaper: &str) -> Result<(), String> {
+    let uri = if wallpaper.contains("://") {
+        wallpaper.to_string()
+    } else {
+        format!("file://{}", wallpaper)
+    };
+    // the dark style has a wallpaper of its own
+    for key in ["picture-uri", "picture-uri-dark"] {
+        run(Command::new("gsettings").args(["set", "org.gnome.desktop.background", key, &uri]))?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn wallpaper() -> Result<String, String> {
+    run(Command::new("osascript").args([
+        "-e",
+        "tell application \"System Events\" to get picture of current desktop",
+    ]))
+}
+
+#[cfg(target_os = "macos")]
+pub fn set_wallpaper(wallpaper: &str) -> Result<(), String> {
+    run(Command::new("osascript").args([
+        "-e",
+        &format!(
+            "tell application \"System Events\" to tell every desktop to set picture to {:?}",
+            wallpaper
+        ),
+    ]))
+    .map(|_| ())
+}
+
+#[cfg(windows)]
+pub fn wallpaper() -> Result<String, String> {
+    run(Command::new("powershell").args([
+        "-NoProfile",
+        "-Command",
+        "(Get-ItemProperty 'HKCU:\\Control Panel\\Desktop').Wallpaper",
+    ]))
+}
+
+#[cfg(windows)]
+pub fn set_wallpaper(wallpaper: &str) -> Result<(), String> {
+    // SPI_SETDESKWALLPAPER, saved and broadcast
+    let script = format!(
+        "Add-Type -TypeDefinition 'using System.Runtime.InteropServices; public class Wallpaper {{ \
+         [DllImport(\"user32.dll\", CharSet = CharSet.Unicode)] \
+         public static extern int SystemParametersInfo(int action, int param, string value, int flags); }}'; \
+         [void][Wallpaper]::SystemParametersInfo(20, 0, '{}', 3)",
+        wallpaper.replace('\'', "''")
+    );
+    run(Command::new("powershell").args(["-NoProfile", "-Command", &script])).map(|_| ())
+}
+
+pub fn set_wallpaper_file(path: &Path) -> Result<(), String> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    set_wallpaper(&path.to_string_lossy())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn lock_screen() -> Result<(), String> {
+    run(Command::new("loginctl").arg("lock-session")).map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+pub fn lock_screen() -> Result<(), String> {
+    // locks as long as a password is required after the display sleeps, the default
+    run(Command::new("pmset").arg("displaysleepnow")).map(|_| ())
+}
+
+#[cfg(windows)]
+pub fn lock_screen() -> Result<(), String> {
+    run(Command::new("rundll32.exe").arg("user32.dll,LockWorkStation")).map(|_| ())
+}
+
+// Whether something, e.g. a video player or a presentation, keeps the screen from going idle
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn idle_inhibited() -> Result<bool, String> {
+    // GNOME's session manager, where 8 is the idle flag
+    let gnome = run(Command::new("gdbus").args([
+        "call",
+        "--session",
+        "--dest",
+        "org.gnome.SessionManager",
+        "--object-path",
+        "/org/gnome/SessionManager",
+        "--method",
+        "org.gnome.SessionManager.IsInhibited",
+        "8",
+    ]));
+    // and KDE's power management otherwise
+    let output = gnome.or_else(|_| {
+        run(Command::new("gdbus").args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.PowerManagement.Inhibit",
+            "--object-path",
+            "/org/freedesktop/PowerManagement/Inhibit",
+            "--method",
+            "org.freedesktop.PowerManagement.Inhibit.HasInhibit",
+        ]))
+    })?;
+    parse_gdbus_bool(&output)
+}
+
+#[cfg(target_os = "macos")]
+pub fn idle_inhibited() -> Result<bool, String> {
+    Ok(parse_pmset_assertions(&run(
+        Command::new("pmset").args(["-g", "assertions"])
+    )?))
+}
+
+#[cfg(windows)]
+pub fn idle_inhibited() -> Result<bool, String> {
+    // `powercfg /requests` would tell, but only to administrators
+    Err("Telling whether the screen is kept awake isn't supported on Windows".to_string())
+}
+
+// e.g. `(true,)`
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn parse_gdbus_bool(output: &str) -> Result<bool, String> {
+    match output.trim() {
+        "(true,)" => Ok(true),
+        "(false,)" => Ok(false),
+        _ => Err(format!("Unexpected gdbus output: {}", output)),
+    }
+}
+
+// The summary at the top of `pmset -g assertions`, e.g. `   PreventUserIdleDisplaySleep    1`
+#[cfg(any(target_os = "macos", test))]
+fn parse_pmset_assertions(output: &str) -> bool {
+    output.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        matches!(fields[..], ["PreventUserIdleDisplaySleep", count] if count != "0")
+    })
+}
+
+// Whether something is recording from a microphone, as in a call
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn microphone_in_use() -> Result<bool, String> {
+    // PulseAudio, or PipeWire through its PulseAudio server
+    let sources = run(Command::new("pactl").args(["list", "short", "sources"]))?;
+    let outputs = run(Command::new("pactl").args(["list", "short", "source-outputs"]))?;
+    Ok(parse_pactl_recording(&sources, &outputs))
+}
+
+#[cfg(target_os = "macos")]
+pub fn microphone_in_use() -> Result<bool, String> {
+    Err("Telling whether the microphone is in use isn't supported on macOS".to_string())
+}
+
+#[cfg(windows)]
+pub fn microphone_in_use() -> Result<bool, String> {
+    // Windows notes when each app started and stopped using the microphone, for the privacy
+    // indicator; a stop time of 0 means it still is. reg fails when no app has used it yet.
+    let output = run(Command::new("reg").args([
+        "query",
+        "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\CapabilityAccessManager\\ConsentStore\\microphone",
+        "/s",
+        "/v",
+        "LastUsedTimeStop",
+    ]))
+    .unwrap_or_default();
+    Ok(parse_consent_store(&output))
+}
+
+// Whether a stream records from a source other than the monitor of an output, e.g. for a
+// visualiser. The first column of both lists is the index, and the second of `source-outputs` is
+// the source's, e.g. `42\t1\t87\tprotocol-native.c\ts16le 1ch 48000Hz`.
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn parse_pactl_recording(sources: &str, outputs: &str) -> bool {
+    let monitors: Vec<&str> = sources
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let index = fields.next()?;
+            fields
+                .next()
+                .is_some_and(|name| name.ends_with(".monitor"))
+                .then_some(index)
+        })
+        .collect();
+    outputs
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .any(|source| !monitors.contains(&source))
+}
+
+// e.g. `    LastUsedTimeStop    REG_QWORD    0x0`
+#[cfg(any(windows, test))]
+fn parse_consent_store(output: &str) -> bool {
+    output.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        matches!(fields[..], ["LastUsedTimeStop", "REG_QWORD", "0x0"])
+    })
+}
+
+// The system's battery
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Battery {
+    pub discharging: bool,
+    pub percent: f32,
+}
+
+// The battery, or none on a machine without one
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn battery() -> Result<Option<Battery>, String> {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return Ok(None);
+    };
+    for supply in supplies.flatten() {
+        let read = |name: &str| {
+            std::fs::read_to_string(supply.path().join(name))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        // mice and headsets have batteries too, but not the system's
+        if read("type") != "Battery" || read("scope") == "Device" {
+            continue;
+        }
+        let Ok(percent) = read("capacity").parse() else {
+            continue;
+        };
+        return Ok(Some(Battery {
+            discharging: read("status") == "Discharging",
+            percent,
+        }));
+    }
+    Ok(None)
+}
+
+#[cfg(target_os = "macos")]
+pub fn battery() -> Result<Option<Battery>, String> {
+    Ok(parse_pmset_battery(&run(
+        Command::new("pmset").args(["-g", "batt"])
+    )?))
+}
+
+#[cfg(windows)]
+pub fn battery() -> Result<Option<Battery>, String> {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    // SAFETY: the status is plain data for Windows to fill in
+    let status = unsafe {
+        let mut status: SYSTEM_POWER_STATUS = std::mem::zeroed();
+        if GetSystemPowerStatus(&mut status) == 0 {
+            return Err("Failed to read the power status".to_string());
+        }
+        status
+    };
+    // 128 means no system battery, and 255 an unknown charge
+    if status.BatteryFlag & 128 != 0 || status.BatteryLifePercent == 255 {
+        return Ok(None);
+    }
+    Ok(Some(Battery {
+        discharging: status.ACLineStatus == 0,
+        percent: status.BatteryLifePercent as f32,
+    }))
+}
+
+// e.g. `Now drawing from 'Battery Power'` and ` -InternalBattery-0 (id=4653155)\t85%; discharging;
+// 3:12 remaining present: true`
+#[cfg(any(target_os = "macos", test))]
+fn parse_pmset_battery(output: &str) -> Option<Battery> {
+    let line = output
+        .lines()
+        .find(|line| line.contains("InternalBattery"))?;
+    let percent = line
+        .split_whitespace()
+        .find_map(|field| field.strip_suffix("%;"))?
+        .parse()
+        .ok()?;
+    Some(Battery {
+        discharging: output.contains("'Battery Power'"),
+        percent,
+    })
+}
+
+// Notices the CPU slowing itself down to keep cool
+#[derive(Default)]
+pub struct ThermalMonitor {
+    // Linux counts how often each package was throttled
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    throttle_count: Option<u64>,
+}
+
+impl ThermalMonitor {
+    pub fn new() -> ThermalMonitor {
+        ThermalMonitor::default()
+    }
+
+    // Whether the CPU was throttled since the last look
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn throttled(&mut self) -> Result<bool, String> {
+        let counts = std::fs::read_dir("/sys/devices/system/cpu")
+            .map_err(|e| e.to_string())?
+            .flatten()
+            .filter_map(|cpu| {
+                let path = cpu.path().join("thermal_throttle/package_throttle_count");
+                std::fs::read_to_string(path)
+                    .ok()?
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+        if counts.is_empty() {
+            return Err("The CPU doesn't count thermal throttling".to_string());
+        }
+        let count = counts.iter().sum();
+        let throttled = self.throttle_count.is_some_and(|last| count > last);
+        self.throttle_count = Some(count);
+        Ok(throttled)
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn throttled(&mut self) -> Result<bool, String> {
+        parse_pmset_therm(&run(Command::new("pmset").args(["-g", "therm"]))?)
+    }
+
+    #[cfg(windows)]
+    pub fn throttled(&mut self) -> Result<bool, String> {
+        Err("Telling thermal throttling isn't supported on Windows".to_string())
+    }
+}
+
+// e.g. `CPU_Speed_Limit \t= 70`, in percent of the full speed
+#[cfg(any(target_os = "macos", test))]
+fn parse_pmset_therm(output: &str) -> Result<bool, String> {
+    let limit = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("CPU_Speed_Limit"))
+        .and_then(|rest| {
+            rest.trim()
+                .trim_start_matches('=')
+                .trim()
+                .parse::<u32>()
+                .ok()
+        });
+    match limit {
+        Some(limit) => Ok(limit < 100),
+        // nothing is said while the CPU runs at full speed
+        None => Ok(false),
+    }
+}
+
+// The window with the keyboard focus
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FocusedWindow {
+    // e.g. the X11 class, the Wayland app id or the executable's name
+    pub app: String,
+    pub title: String,
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn focused_window() -> Result<FocusedWindow, String> {
+    // Wayland compositors that tell, as X11 tools can't see their windows
+    if let Ok(output) = run(Command::new("hyprctl").args(["activewindow", "-j"])) {
+        return parse_hyprctl(&output);
+    }
+    if let Ok(output) = run(Command::new("swaymsg").args(["-t", "get_tree"])) {
+        return parse_sway_tree(&output);
+    }
+    Ok(FocusedWindow {
+        app: run(Command::new("xdotool").args(["getactivewindow", "getwindowclassname"]))?,
+        title: run(Command::new("xdotool").args(["getactivewindow", "getwindowname"]))?,
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub fn focused_window() -> Result<FocusedWindow, String> {
+    // the title would need the accessibility permission
+    let app = run(Command::new("osascript").args([
+        "-e",
+        "tell application \"System Events\" to get name of first application process whose frontmost is true",
+    ]))?;
+    Ok(FocusedWindow {
+        app,
+        title: String::new(),
+    })
+}
+
+#[cfg(windows)]
+pub fn focused_window() -> Result<FocusedWindow, String> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId,
+    };
+
+    let mut title = [0u16; 512];
+    let mut path = [0u16; 1024];
+    let mut path_len = path.len() as u32;
+    // SAFETY: the buffers are as long as we say they are, and the process handle is closed again
+    let (title_len, found) = unsafe {
+        let window = GetForegroundWindow();
+        if window.is_null() {
+            return Err("No window has the focus".to_string());
+        }
+        let title_len = GetWindowTextW(window, title.as_mut_ptr(), title.len() as i32);
+        let mut process_id = 0;
+        GetWindowThreadProcessId(window, &mut process_id);
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+        let found = !process.is_null()
+            && QueryFullProcessImageNameW(
+                process,
+                PROCESS_NAME_WIN32,
+                path.as_mut_ptr(),
+                &mut path_len,
+            ) != 0;
+        if !process.is_null() {
+            CloseHandle(process);
+        }
+        (title_len.max(0) as usize, found)
+    };
+    let app = if found {
+        let path = String::from_utf16_lossy(&path[..path_len as usize]);
+        Path::new(&path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or(path)
+    } else {
+        String::new()
+    };
+    Ok(FocusedWindow {
+        app,
+        title: String::from_utf16_lossy(&title[..title_len]),
+    })
+}
+
+// e.g. `{"class": "mpv", "title": "video.mkv - mpv", ...}`
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn parse_hyprctl(output: &str) -> Result<FocusedWindow, String> {
+    let window: serde_json::Value =
+        serde_json::from_str(output).map_err(|e| format!("Unexpected hyprctl output: {}", e))?;
+    let field = |name: &str| window[name].as_str().unwrap_or_default().to_string();
+    Ok(FocusedWindow {
+        app: field("class"),
+        title: field("title"),
+    })
+}
+
+// The focused node in the tree of containers, named by its app id, or its X11 class under
+// XWayland
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn parse_sway_tree(output: &str) -> Result<FocusedWindow, String> {
+    fn focused(node: &serde_json::Value) -> Option<&serde_json::Value> {
+        if node["focused"].as_bool() == Some(true) {
+            return Some(node);
+        }
+        ["nodes", "floating_nodes"]
+            .iter()
+            .filter_map(|children| node[children].as_array())
+            .flatten()
+            .find_map(focused)
+    }
+    let tree: serde_json::Value =
+        serde_json::from_str(output).map_err(|e| format!("Unexpected swaymsg output: {}", e))?;
+    let node = focused(&tree).ok_or("No window has the focus")?;
+    let app = node["app_id"]
+        .as_str()
+        .or_else(|| node["window_properties"]["class"].as_str())
+        .unwrap_or_default();
+    Ok(FocusedWindow {
+        app: app.to_string(),
+        title: node["name"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+// The display brightness in percent
+pub fn brightness(control: BrightnessControl, display: Option<u32>) -> Result<f32, String> {
+    match control {
+        BrightnessControl::Brightnessctl => {
+            parse_brightnessctl(&run(Command::new("brightnessctl").arg("-m"))?)
+        }
+        BrightnessControl::Ddcutil => {
+            let mut command = Command::new("ddcutil");
+            command.args(ddcutil_display(display));
+            parse_ddcutil(&run(command.args(["getvcp", "10", "--brief"]))?)
+        }
+        BrightnessControl::Xbacklight => run(Command::new("xbacklight").arg("-get"))?
+            .parse()
+            .map_err(|e| format!("Unexpected xbacklight output: {}", e)),
+    }
+}
+
+pub fn set_brightness(
+    control: BrightnessControl,
+    display: Option<u32>,
+    percent: f32,
+) -> Result<(), String> {
+    let percent = percent.clamp(0.0, 100.0).round();
+    match control {
+        BrightnessControl::Brightnessctl => {
+            run(Command::new("brightnessctl").args(["-q", "set", &format!("{}%", percent)]))
+        }
+        BrightnessControl::Ddcutil => {
+            let mut command = Command::new("ddcutil");
+            command.args(ddcutil_display(display));
+            // the monitors that matter report a maximum of 100
+            run(command.args(["setvcp", "10", &percent.to_string()]))
+        }
+        BrightnessControl::Xbacklight => {
+            run(Command::new("xbacklight").args(["-set", &percent.to_string()]))
+        }
+    }
+    .map(|_| ())
+}
+
+fn ddcutil_display(display: Option<u32>) -> Vec<String> {
+    display
+        .map(|display| vec!["--display".to_string(), display.to_string()])
+        .unwrap_or_default()
+}
+
+// e.g. `intel_backlight,backlight,19200,40%,48000`
+fn parse_brightnessctl(output: &str) -> Result<f32, String> {
+    output
+        .split(',')
+        .nth(3)
+        .and_then(|percent| percent.trim_end_matches('%').parse().ok())
+        .ok_or_else(|| format!("Unexpected brightnessctl output: {}", output))
+}
+
+// e.g. `VCP 10 C 50 100`: the current and the maximum value of VCP feature 10, the brightness
+fn parse_ddcutil(output: &str) -> Result<f32, String> {
+    let fields: Vec<&str> = output.split_whitespace().collect();
+    match fields[..] {
+        ["VCP", "10", "C", current, max] => {
+            let current: f32 = current.parse().map_err(|_| output.to_string())?;
+            let max: f32 = max.parse().map_err(|_| output.to_string())?;
+            Ok(current / max.max(1.0) * 100.0)
+        }
+        _ => Err(format!("Unexpected ddcutil output: {}", output)),
+    }
+}
+
+// The red, green and blue levels (0 to 1) of white at a colour temperature, after Tanner Helland's
+// fit of the black body colours
+#[cfg(any(windows, target_os = "macos", test))]
+fn white_point(kelvin: u32) -> (f32, f32, f32) {
+    let t = kelvin.clamp(1000, 6600) as f32 / 100.0;
+    let green = (99.470_8 * t.ln() - 161.119_57) / 255.0;
+    let blue = if t <= 19.0 {
+        0.0
+    } else {
+        (138.517_73 * (t - 10.0).ln() - 305.044_8) / 255.0
+    };
+    (1.0, green.clamp(0.0, 1.0), blue.clamp(0.0, 1.0))
+}
+
+// How to undo a night mode
+pub enum NightMode {
+    // the tool resets what it set
+    Tool(NightModeMethod),
+    #[cfg(windows)]
+    GammaRamp([u16; 768]),
+    #[cfg(target_os = "macos")]
+    ColorSync,
+}
+
+// Warms the colours of the screen, as a blue light filter does
+pub fn night_mode_on(method: NightModeMethod, kelvin: u32) -> Result<NightMode, String> {
+    match method {
+        NightModeMethod::Gammastep | NightModeMethod::Redshift => {
+            let program = if method == NightModeMethod::Gammastep {
+                "gammastep"
+            } else {
+                "redshift"
+            };
+            // one shot, replacing any earlier adjustment
+            run(Command::new(program).args(["-P", "-O", &kelvin.to_string()]))?;
+            Ok(NightMode::Tool(method))
+        }
+        NightModeMethod::Native => native_night_mode(kelvin),
+    }
+}
+
+pub fn night_mode_off(night_mode: NightMode) -> Result<(), String> {
+    match night_mode {
+        NightMode::Tool(NightModeMethod::Gammastep) => {
+            run(Command::new("gammastep").arg("-x")).map(|_| ())
+        }
+        NightMode::Tool(NightModeMethod::Redshift) => {
+            run(Command::new("redshift").arg("-x")).map(|_| ())
+        }
+        NightMode::Tool(NightModeMethod::Native) => Ok(()),
+        #[cfg(windows)]
+        NightMode::GammaRamp(ramp) => windows_gamma::set(&ramp),
+        #[cfg(target_os = "macos")]
+        NightMode::ColorSync => {
+            unsafe { macos_gamma::CGDisplayRestoreColorSyncSettings() };
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn native_night_mode(_kelvin: u32) -> Result<NightMode, String> {
+    Err("There is no native night mode on Linux; use gammastep or redshift".to_string())
+}
+
+#[cfg(windows)]
+fn native_night_mode(kelvin: u32) -> Result<NightMode, String> {
+    let saved = windows_gamma::get()?;
+    let (red, green, blue) = white_point(kelvin);
+    let mut ramp = [0u16; 768];
+    for i in 0..256 {
+        let level = (i * 257) as f32;
+        ramp[i] = (level * red) as u16;
+        ramp[256 + i] = (level * green) as u16;
+        ramp[512 + i] = (level * blue) as u16;
+    }
+    windows_gamma::set(&ramp)?;
+    Ok(NightMode::GammaRamp(saved))
+}
+
+// The gamma ramp of the whole screen through GDI
+#[cfg(windows)]
+mod windows_gamma {
+    use windows_sys::Win32::Graphics::Gdi::{GetDC, ReleaseDC};
+    use windows_sys::Win32::UI::ColorSystem::{GetDeviceGammaRamp, SetDeviceGammaRamp};
+
+    pub fn get() -> Result<[u16; 768], String> {
+        let mut ramp = [0u16; 768];
+        // SAFETY: the screen DC is released again, and the ramp has the 3 x 256 entries GDI
+        // expects
+        let ok = unsafe {
+            let dc = GetDC(std::ptr::null_mut());
+            let ok = GetDeviceGammaRamp(dc, ramp.as_mut_ptr().cast());
+            ReleaseDC(std::ptr::null_mut(), dc);
+            ok
+        };
+        if ok == 0 {
+            return Err("Failed to read the gamma ramp".to_string());
+        }
+        Ok(ramp)
+    }
+
+    pub fn set(ramp: &[u16; 768]) -> Result<(), String> {
+        // SAFETY: as above
+        let ok = unsafe {
+            let dc = GetDC(std::ptr::null_mut());
+            let ok = SetDeviceGammaRamp(dc, ramp.as_ptr().cast());
+            ReleaseDC(std::ptr::null_mut(), dc);
+            ok
+        };
+        if ok == 0 {
+            // Windows refuses ramps that stray too far from the identity
+            return Err("Failed to set the gamma ramp; try a higher temperature".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn native_night_mode(kelvin: u32) -> Result<NightMode, String> {
+    let (red, green, blue) = white_point(kelvin);
+    let mut displays = [0u32; 16];
+    let mut count = 0;
+    // SAFETY: the display list is as long as we say it is
+    unsafe {
+        if macos_gamma::CGGetActiveDisplayList(16, displays.as_mut_ptr(), &mut count) != 0 {
+            return Err("Failed to list the displays".to_string());
+        }
+        for display in &displays[..count as usize] {
+            macos_gamma::CGSetDisplayTransferByFormula(
+                *display, 0.0, red, 1.0, 0.0, green, 1.0, 0.0, blue, 1.0,
+            );
+        }
+    }
+    Ok(NightMode::ColorSync)
+}
+
+// Display transfer functions through CoreGraphics; they are also reset when neckcheck exits
+#[cfg(target_os = "macos")]
+mod macos_gamma {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        pub fn CGGetActiveDisplayList(max: u32, displays: *mut u32, count: *mut u32) -> i32;
+        #[allow(clippy::too_many_arguments)]
+        pub fn CGSetDisplayTransferByFormula(
+            display: u32,
+            red_min: f32,
+            red_max: f32,
+            red_gamma: f32,
+            green_min: f32,
+            green_max: f32,
+            green_gamma: f32,
+            blue_min: f32,
+            blue_max: f32,
+            blue_gamma: f32,
+        ) -> i32;
+        pub fn CGDisplayRestoreColorSyncSettings();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warmer_white_has_less_blue() {
+        let (red, green, blue) = white_point(6500);
+        assert_eq!(red, 1.0);
+        assert!(green > 0.95 && blue > 0.95);
+        let (_, green, blue) = white_point(3500);
+        assert!((green - 0.77).abs() < 0.02, "{}", green);
+        assert!((blue - 0.56).abs() < 0.02, "{}", blue);
+        assert_eq!(white_point(1000).2, 0.0);
+    }
+
+    #[test]
+    fn brightness_is_read_from_the_tools_output() {
+        assert_eq!(
+            parse_brightnessctl("intel_backlight,backlight,19200,40%,48000"),
+            Ok(40.0)
+        );
+        assert!(parse_brightnessctl("nonsense").is_err());
+        assert_eq!(parse_ddcutil("VCP 10 C 30 60"), Ok(50.0));
+        assert!(parse_ddcutil("VCP 10 ERR").is_err());
+    }
+
+    #[test]
+    fn the_focused_window_is_read_from_the_compositor() {
+        let window = parse_hyprctl(r#"{"class": "mpv", "title": "video.mkv - mpv"}"#).unwrap();
+        assert_eq!(window.app, "mpv");
+        assert_eq!(window.title, "video.mkv - mpv");
+        let tree = r#"{"focused": false, "nodes": [
+            {"focused": false, "nodes": [], "floating_nodes": [
+                {"focused": true, "name": "Zoom Meeting", "app_id": null,
+                 "window_properties": {"class": "zoom"}}
+            ]},
+            {"focused": false, "name": "Terminal", "app_id": "foot", "nodes": []}
+        ]}"#;
+        let window = parse_sway_tree(tree).unwrap();
+        assert_eq!(window.app, "zoom");
+        assert_eq!(window.title, "Zoom Meeting");
+        assert!(parse_sway_tree(r#"{"focused": false, "nodes": []}"#).is_err());
+    }
+
+    #[test]
+    fn power_is_read_from_the_tools_output() {
+        let on_battery = "Now drawing from 'Battery Power'\n \
+                          -InternalBattery-0 (id=4653155)\t23%; discharging; 1:12 remaining present: true";
+        assert_eq!(
+            parse_pmset_battery(on_battery),
+            Some(Battery {
+                discharging: true,
+                percent: 23.0
+            })
+        );
+        let charging = "Now drawing from 'AC Power'\n \
+                        -InternalBattery-0 (id=4653155)\t64%; charging; 0:50 remaining present: true";
+        assert!(!parse_pmset_battery(charging).unwrap().discharging);
+        assert_eq!(parse_pmset_battery("Now drawing from 'AC Power'"), None);
+
+        assert_eq!(parse_pmset_therm("CPU_Speed_Limit \t= 70\n"), Ok(true));
+        assert_eq!(parse_pmset_therm("CPU_Speed_Limit \t= 100\n"), Ok(false));
+        assert_eq!(
+            parse_pmset_therm("Note: No thermal warning level has been recorded"),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn recording_is_read_from_the_tools_output() {
+        let sources =
+            "0\talsa_output.pci.analog-stereo.monitor\tPipeWire\ts32le 2ch 48000Hz\tIDLE\n\
+                       1\talsa_input.pci.analog-stereo\tPipeWire\ts32le 2ch 48000Hz\tRUNNING";
+        assert!(!parse_pactl_recording(sources, ""));
+        // a visualiser listening to the speakers
+        let visualiser = "12\t0\t40\tprotocol-native.c\tfloat32le 2ch 44100Hz";
+        assert!(!parse_pactl_recording(sources, visualiser));
+        let call = "13\t1\t41\tprotocol-native.c\ts16le 1ch 48000Hz";
+        assert!(parse_pactl_recording(sources, call));
+
+        let stopped = "HKEY_CURRENT_USER\\...\\microphone\\NonPackaged\\C:#Zoom#zoom.exe\n    \
+                       LastUsedTimeStop    REG_QWORD    0x1d9f0b1c2a3e4f5\n";
+        assert!(!parse_consent_store(stopped));
+        assert!(parse_consent_store(
+            "    LastUsedTimeStop    REG_QWORD    0x0\n"
+        ));
+    }
+
+    #[test]
+    fn idle_inhibition_is_read_from_the_tools_output() {
+        assert_eq!(parse_gdbus_bool("(true,)\n"), Ok(true));
+        assert_eq!(parse_gdbus_bool("(false,)"), Ok(false));
+        assert!(parse_gdbus_bool("Error: no such service").is_err());
+        let assertions = "Assertion status system-wide:\n   BackgroundTask                 0\n   \
+                          PreventUserIdleDisplaySleep    1\n   PreventSystemSleep             0\n";
+        assert!(parse_pmset_assertions(assertions));
+        assert!(!parse_pmset_assertions(
+            "   PreventUserIdleDisplaySleep    0\n"
+        ));
+    }
+}
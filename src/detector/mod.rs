@@ -0,0 +1,139 @@
+#[cfg(feature = "onnx")]
+mod onnx;
+#[cfg(feature = "opencv")]
+mod opencv;
+mod rustface;
+
+#[cfg(feature = "onnx")]
+pub use self::onnx::session as onnx_session;
+pub use self::rustface::RustfaceDetector;
+
+use std::fmt;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use image::GrayImage;
+use imageproc::rect::Rect;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum DetectorError {
+    #[error("Failed to load detector model {0}: {1}")]
+    ModelLoadError(String, String),
+    #[cfg(any(feature = "opencv", feature = "onnx"))]
+    #[error("The {0} detector needs a model file; set detector.model in the config")]
+    MissingModel(String),
+    #[error("The {0} detector is not available; rebuild with the `{1}` feature")]
+    Unavailable(String, String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Face {
+    pub rect: Rect,
+    // backend specific confidence, only comparable between detections of the same backend
+    pub score: f32,
+}
+
+// A face detection backend
+pub trait Detector {
+    fn name(&self) -> &'static str;
+    // the hardware the detector actually runs on, e.g. after falling back from a GPU
+    fn execution_provider(&self) -> &str {
+        "CPU"
+    }
+    fn detect(&mut self, image: &GrayImage) -> Vec<Face>;
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum DetectorBackend {
+    /// SeetaFace via rustface (built in)
+    #[default]
+    Rustface,
+    /// OpenCV Haar cascade (`opencv` feature)
+    OpencvHaar,
+    /// OpenCV ResNet-SSD DNN (`opencv` feature)
+    OpencvDnn,
+    /// Ultra-light face detector on ONNX Runtime (`onnx` feature), optionally on a GPU
+    Onnx,
+}
+
+impl fmt::Display for DetectorBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the same name as on the command line and in the config
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
+
+// Where the ONNX backend should run. GPU providers need their cargo feature (`cuda`, `directml`,
+// `coreml`); anything that is unavailable at runtime falls back to the CPU.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionProvider {
+    #[default]
+    Auto,
+    Cpu,
+    Cuda,
+    Directml,
+    Coreml,
+}
+
+impl ExecutionProvider {
+    // whether a specific GPU provider was asked for
+    pub fn is_gpu(&self) -> bool {
+        !matches!(self, ExecutionProvider::Auto | ExecutionProvider::Cpu)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct DetectorConfig {
+    pub backend: DetectorBackend,
+    // the model file: rustface's .bin, the Haar cascade .xml, the DNN's .caffemodel or the .onnx
+    pub model: Option<PathBuf>,
+    // the network definition (.prototxt) for the DNN backend
+    pub model_config: Option<PathBuf>,
+    pub execution_provider: ExecutionProvider,
+}
+
+pub fn create_detector(config: &DetectorConfig) -> Result<Box<dyn Detector>, DetectorError> {
+    match config.backend {
+        DetectorBackend::Rustface => Ok(Box::new(RustfaceDetector::new(config.model.as_deref())?)),
+        #[cfg(feature = "opencv")]
+        DetectorBackend::OpencvHaar => {
+            let model = config
+                .model
+                .as_deref()
+                .ok_or_else(|| DetectorError::MissingModel("opencv-haar".to_string()))?;
+            Ok(Box::new(opencv::HaarDetector::new(model)?))
+        }
+        #[cfg(feature = "opencv")]
+        DetectorBackend::OpencvDnn => {
+            let missing = || DetectorError::MissingModel("opencv-dnn".to_string());
+            let model = config.model.as_deref().ok_or_else(missing)?;
+            let model_config = config.model_config.as_deref().ok_or_else(missing)?;
+            Ok(Box::new(opencv::DnnDetector::new(model_config, model)?))
+        }
+        #[cfg(feature = "onnx")]
+        DetectorBackend::Onnx => {
+            let model = config
+                .model
+                .as_deref()
+                .ok_or_else(|| DetectorError::MissingModel("onnx".to_string()))?;
+            Ok(Box::new(onnx::OnnxDetector::new(
+                model,
+                config.execution_provider,
+            )?))
+        }
+        #[cfg(not(feature = "opencv"))]
+        DetectorBackend::OpencvHaar | DetectorBackend::OpencvDnn => Err(
+            DetectorError::Unavailable(config.backend.to_string(), "opencv".to_string()),
+        ),
+        #[cfg(not(feature = "onnx"))]
+        DetectorBackend::Onnx => Err(DetectorError::Unavailable(
+            config.backend.to_string(),
+            "onnx".to_string(),
+        )),
+    }
+}
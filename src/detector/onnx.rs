@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::GrayImage;
+use imageproc::rect::Rect;
+#[cfg(feature = "cuda")]
+use ort::execution_providers::CUDAExecutionProvider;
+#[cfg(feature = "coreml")]
+use ort::execution_providers::CoreMLExecutionProvider;
+#[cfg(feature = "directml")]
+use ort::execution_providers::DirectMLExecutionProvider;
+use ort::session::Session;
+use ort::value::Tensor;
+
+use super::{Detector, DetectorError, ExecutionProvider, Face};
+
+// Ultra-Light-Fast-Generic-Face-Detector (version-RFB-320): a 320x240 RGB input normalised to
+// roughly [-1, 1], and per anchor `scores` (background, face) and `boxes` (corners relative to the
+// image size) outputs
+const INPUT_WIDTH: u32 = 320;
+const INPUT_HEIGHT: u32 = 240;
+const MIN_CONFIDENCE: f32 = 0.7;
+// overlapping boxes with a larger intersection over union than this are the same face
+const NMS_THRESHOLD: f32 = 0.3;
+
+fn load_error(model: &Path, e: impl ToString) -> DetectorError {
+    DetectorError::ModelLoadError(model.display().to_string(), e.to_string())
+}
+
+pub struct OnnxDetector {
+    session: Session,
+    execution_provider: &'static str,
+}
+
+// Loads a model on the preferred execution provider, returning the session and the name of the
+// provider it actually runs on
+pub fn session(
+    model: &Path,
+    preference: ExecutionProvider,
+) -> Result<(Session, &'static str), DetectorError> {
+    // a GPU provider can be compiled in and still fail at runtime (no device, missing drivers),
+    // so each one is tried in turn with the CPU as the last resort
+    for provider in gpu_providers(preference) {
+        match gpu_session(model, provider.as_ref()) {
+            Ok(session) => return Ok((session, provider.name())),
+            Err(e) => println!("{} is unavailable, falling back: {}", provider.name(), e),
+        }
+    }
+    if preference.is_gpu() {
+        println!(
+            "Running {} on the CPU instead of {:?}",
+            model.display(),
+            preference
+        );
+    }
+    let session = Session::builder()
+        .and_then(|builder| builder.commit_from_file(model))
+        .map_err(|e| load_error(model, e))?;
+    Ok((session, "CPU"))
+}
+
+impl OnnxDetector {
+    pub fn new(model: &Path, preference: ExecutionProvider) -> Result<OnnxDetector, DetectorError> {
+        let (session, execution_provider) = session(model, preference)?;
+        Ok(OnnxDetector {
+            session,
+            execution_provider,
+        })
+    }
+
+    fn try_detect(&mut self, image: &GrayImage) -> ort::Result<Vec<Face>> {
+        let resized = imageops::resize(image, INPUT_WIDTH, INPUT_HEIGHT, FilterType::Triangle);
+        let plane: Vec<f32> = resized
+            .as_raw()
+            .iter()
+            .map(|&value| (value as f32 - 127.0) / 128.0)
+            .collect();
+        // the model wants three channels; a grayscale frame simply has the same one three times
+        let input = plane.repeat(3);
+        let shape = [1, 3, INPUT_HEIGHT as usize, INPUT_WIDTH as usize];
+        let outputs = self
+            .session
+            .run(ort::inputs!["input" => Tensor::from_array((shape, input))?])?;
+        let (_, scores) = outputs["scores"].try_extract_tensor::<f32>()?;
+        let (_, boxes) = outputs["boxes"].try_extract_tensor::<f32>()?;
+
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        let mut candidates: Vec<(f32, [f32; 4])> = scores
+            .chunks_exact(2)
+            .zip(boxes.chunks_exact(4))
+            .filter(|(score, _)| score[1] >= MIN_CONFIDENCE)
+            .map(|(score, bbox)| {
+                let corners = [
+                    bbox[0].clamp(0.0, 1.0) * width,
+                    bbox[1].clamp(0.0, 1.0) * height,
+                    bbox[2].clamp(0.0, 1.0) * width,
+                    bbox[3].clamp(0.0, 1.0) * height,
+                ];
+                (score[1], corners)
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut kept: Vec<(f32, [f32; 4])> = Vec::new();
+        for candidate in candidates {
+            if kept
+                .iter()
+                .all(|k| iou(&k.1, &candidate.1) <= NMS_THRESHOLD)
+            {
+                kept.push(candidate);
+            }
+        }
+        Ok(kept
+            .into_iter()
+            .filter(|(_, [left, top, right, bottom])| right > left && bottom > top)
+            .map(|(score, [left, top, right, bottom])| Face {
+                rect: Rect::at(left as i32, top as i32)
+                    .of_size((right - left) as u32, (bottom - top) as u32),
+                score,
+            })
+            .collect())
+    }
+}
+
+impl Detector for OnnxDetector {
+    fn name(&self) -> &'static str {
+        "onnx"
+    }
+
+    fn execution_provider(&self) -> &str {
+        self.execution_provider
+    }
+
+    fn detect(&mut self, image: &GrayImage) -> Vec<Face> {
+        self.try_detect(image).unwrap_or_else(|e| {
+            println!("ONNX detection failed: {}", e);
+            Vec::new()
+        })
+    }
+}
+
+type OrtProvider = Box<dyn ort::execution_providers::ExecutionProvider>;
+
+// The GPU providers to try for the given preference, best first. Only those compiled in with
+// their cargo feature are known.
+#[allow(unused_mut, clippy::vec_init_then_push)]
+fn gpu_providers(preference: ExecutionProvider) -> Vec<OrtProvider> {
+    let mut providers: Vec<(ExecutionProvider, OrtProvider)> = Vec::new();
+    #[cfg(feature = "cuda")]
+    providers.push((
+        ExecutionProvider::Cuda,
+        Box::new(CUDAExecutionProvider::default()),
+    ));
+    #[cfg(feature = "directml")]
+    providers.push((
+        ExecutionProvider::Directml,
+        Box::new(DirectMLExecutionProvider::default()),
+    ));
+    #[cfg(feature = "coreml")]
+    providers.push((
+        ExecutionProvider::Coreml,
+        Box::new(CoreMLExecutionProvider::default()),
+    ));
+    providers
+        .into_iter()
+        .filter(|(kind, _)| preference == ExecutionProvider::Auto || preference == *kind)
+        .map(|(_, provider)| provider)
+        .collect()
+}
+
+fn gpu_session(
+    model: &Path,
+    provider: &dyn ort::execution_providers::ExecutionProvider,
+) -> ort::Result<Session> {
+    if !provider.supported_by_platform() || !provider.is_available()? {
+        return Err(ort::Error::new("not supported by this onnxruntime build"));
+    }
+    let mut builder = Session::builder()?;
+    provider.register(&mut builder)?;
+    builder.commit_from_file(model)
+}
+
+fn iou(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    let width = (a[2].min(b[2]) - a[0].max(b[0])).max(0.0);
+    let height = (a[3].min(b[3]) - a[1].max(b[1])).max(0.0);
+    let intersection = width * height;
+    let union = (a[2] - a[0]) * (a[3] - a[1]) + (b[2] - b[0]) * (b[3] - b[1]) - intersection;
+    if union <= 0.0 {
+        return 0.0;
+    }
+    intersection / union
+}
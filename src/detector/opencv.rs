@@ -0,0 +1,141 @@
+use std::path::Path;
+
+use image::GrayImage;
+use imageproc::rect::Rect;
+use opencv::core::{Mat, Scalar, Size, Vector, CV_32F};
+use opencv::dnn::{self, Net};
+use opencv::imgproc;
+use opencv::objdetect::{CascadeClassifier, CASCADE_SCALE_IMAGE};
+use opencv::prelude::*;
+
+use super::{Detector, DetectorError, Face};
+
+// the input size and per channel mean the ResNet-SSD face model was trained with
+const DNN_INPUT_SIZE: i32 = 300;
+const DNN_MEAN: (f64, f64, f64) = (104.0, 177.0, 123.0);
+const DNN_MIN_CONFIDENCE: f32 = 0.5;
+
+fn load_error(path: &Path, e: opencv::Error) -> DetectorError {
+    DetectorError::ModelLoadError(path.display().to_string(), e.to_string())
+}
+
+fn to_mat(image: &GrayImage) -> opencv::Result<Mat> {
+    let mat = Mat::new_rows_cols_with_data(
+        image.height() as i32,
+        image.width() as i32,
+        image.as_raw().as_slice(),
+    )?;
+    Ok(mat.clone_pointee())
+}
+
+pub struct HaarDetector {
+    classifier: CascadeClassifier,
+}
+
+impl HaarDetector {
+    pub fn new(cascade: &Path) -> Result<HaarDetector, DetectorError> {
+        let classifier = CascadeClassifier::new(&cascade.to_string_lossy())
+            .map_err(|e| load_error(cascade, e))?;
+        Ok(HaarDetector { classifier })
+    }
+
+    fn try_detect(&mut self, image: &GrayImage) -> opencv::Result<Vec<Face>> {
+        let mat = to_mat(image)?;
+        let mut faces = Vector::new();
+        self.classifier.detect_multi_scale(
+            &mat,
+            &mut faces,
+            1.1,
+            3,
+            CASCADE_SCALE_IMAGE,
+            Size::new(20, 20),
+            Size::new(0, 0),
+        )?;
+        Ok(faces
+            .iter()
+            .map(|face| Face {
+                rect: Rect::at(face.x, face.y).of_size(face.width as u32, face.height as u32),
+                // the cascade only says yes or no
+                score: 1.0,
+            })
+            .collect())
+    }
+}
+
+impl Detector for HaarDetector {
+    fn name(&self) -> &'static str {
+        "opencv-haar"
+    }
+
+    fn detect(&mut self, image: &GrayImage) -> Vec<Face> {
+        self.try_detect(image).unwrap_or_else(|e| {
+            println!("Haar detection failed: {}", e);
+            Vec::new()
+        })
+    }
+}
+
+pub struct DnnDetector {
+    net: Net,
+}
+
+impl DnnDetector {
+    pub fn new(prototxt: &Path, model: &Path) -> Result<DnnDetector, DetectorError> {
+        let net = dnn::read_net_from_caffe(&prototxt.to_string_lossy(), &model.to_string_lossy())
+            .map_err(|e| load_error(model, e))?;
+        Ok(DnnDetector { net })
+    }
+
+    fn try_detect(&mut self, image: &GrayImage) -> opencv::Result<Vec<Face>> {
+        // the network expects a 3 channel BGR image
+        let mut bgr = Mat::default();
+        imgproc::cvt_color_def(&to_mat(image)?, &mut bgr, imgproc::COLOR_GRAY2BGR)?;
+        let blob = dnn::blob_from_image(
+            &bgr,
+            1.0,
+            Size::new(DNN_INPUT_SIZE, DNN_INPUT_SIZE),
+            Scalar::new(DNN_MEAN.0, DNN_MEAN.1, DNN_MEAN.2, 0.0),
+            false,
+            false,
+            CV_32F,
+        )?;
+        self.net.set_input_def(&blob)?;
+        let output = self.net.forward_single_def()?;
+
+        // the output is 1x1xNx7: [image id, class, confidence, left, top, right, bottom] with the
+        // coordinates relative to the image size
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        Ok(output
+            .data_typed::<f32>()?
+            .chunks_exact(7)
+            .filter(|detection| detection[2] >= DNN_MIN_CONFIDENCE)
+            .filter_map(|detection| {
+                let left = (detection[3] * width).max(0.0);
+                let top = (detection[4] * height).max(0.0);
+                let right = (detection[5] * width).min(width);
+                let bottom = (detection[6] * height).min(height);
+                if right <= left || bottom <= top {
+                    return None;
+                }
+                Some(Face {
+                    rect: Rect::at(left as i32, top as i32)
+                        .of_size((right - left) as u32, (bottom - top) as u32),
+                    score: detection[2],
+                })
+            })
+            .collect())
+    }
+}
+
+impl Detector for DnnDetector {
+    fn name(&self) -> &'static str {
+        "opencv-dnn"
+    }
+
+    fn detect(&mut self, image: &GrayImage) -> Vec<Face> {
+        self.try_detect(image).unwrap_or_else(|e| {
+            println!("DNN detection failed: {}", e);
+            Vec::new()
+        })
+    }
+}
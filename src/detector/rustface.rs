@@ -0,0 +1,248 @@
+#[cfg(feature = "parallel")]
+use std::cell::RefCell;
+#[cfg(feature = "parallel")]
+use std::collections::HashMap;
+use std::path::Path;
+#[cfg(feature = "parallel")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rustface::ImageData;
+
+use image::GrayImage;
+
+use imageproc::rect::Rect;
+
+use super::{Detector, DetectorError, Face};
+
+const DEFAULT_MODEL: &str = "seeta_fd_frontal_v1.0.bin";
+const MIN_FACE_SIZE: u32 = 20;
+
+// the most face size ranges detected at once; the smallest faces take most of the work, so more
+// ranges split it ever more thinly
+#[cfg(feature = "parallel")]
+const MAX_RANGES: usize = 4;
+
+#[cfg(feature = "parallel")]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "parallel")]
+thread_local! {
+    // rustface's detectors can't move between threads, so every thread of the pool keeps one of
+    // each RustfaceDetector's, by its id, and is told which face sizes to look for at each check
+    static RANGE_DETECTORS: RefCell<HashMap<u64, Box<dyn rustface::Detector>>> =
+        RefCell::new(HashMap::new());
+}
+
+pub struct RustfaceDetector {
+    detector: Box<dyn rustface::Detector>,
+    // for the detectors of the threads that join the pool later
+    #[cfg(feature = "parallel")]
+    model: rustface::Model,
+    #[cfg(feature = "parallel")]
+    id: u64,
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+}
+
+impl RustfaceDetector {
+    pub fn new(model: Option<&Path>) -> Result<RustfaceDetector, DetectorError> {
+        let model = model
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        #[cfg(feature = "parallel")]
+        {
+            let loaded = std::fs::File::open(&model)
+                .and_then(|file| rustface::read_model(std::io::BufReader::new(file)))
+                .map_err(|e| DetectorError::ModelLoadError(model.clone(), e.to_string()))?;
+            let mut detector = rustface::create_detector_with_model(loaded.clone());
+            configure(&mut *detector);
+            let detector = RustfaceDetector {
+                detector,
+                model: loaded,
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                parallel: true,
+            };
+            // every thread of the pool builds its detector now rather than at the first check
+            let (id, model) = (detector.id, &detector.model);
+            rayon::broadcast(|_| with_range_detector(id, model, |_| ()));
+            Ok(detector)
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            let mut detector = rustface::create_detector(&model)
+                .map_err(|e| DetectorError::ModelLoadError(model.clone(), e.to_string()))?;
+            configure(&mut *detector);
+            Ok(RustfaceDetector { detector })
+        }
+    }
+
+    // Whether to detect on several threads, e.g. off to compare in `neckcheck bench`
+    #[cfg(feature = "parallel")]
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    // Detects each range of face sizes, i.e. each part of the image pyramid, on its own thread
+    #[cfg(feature = "parallel")]
+    fn detect_parallel(&self, image: &GrayImage) -> Vec<Face> {
+        use rayon::prelude::*;
+
+        let largest = image.width().min(image.height());
+        let ranges = face_size_ranges(
+            MIN_FACE_SIZE,
+            largest,
+            rayon::current_num_threads().min(MAX_RANGES),
+        );
+        let (id, model) = (self.id, &self.model);
+        let faces = ranges
+            .into_par_iter()
+            .flat_map_iter(|(min, max)| {
+                with_range_detector(id, model, |detector| {
+                    detector.set_min_face_size(min);
+                    detector.set_max_face_size(max);
+                    detect_with(detector, image)
+                })
+            })
+            .collect();
+        merge_overlapping(faces)
+    }
+}
+
+// Runs `f` with this thread's detector for the RustfaceDetector `id`, creating it the first time.
+// The detector is taken out of the map while `f` runs: a thread waiting in rayon inside rustface
+// can pick up another range and come back here, and then builds a detector of its own.
+#[cfg(feature = "parallel")]
+fn with_range_detector<T>(
+    id: u64,
+    model: &rustface::Model,
+    f: impl FnOnce(&mut dyn rustface::Detector) -> T,
+) -> T {
+    let mut detector = RANGE_DETECTORS
+        .with(|detectors| detectors.borrow_mut().remove(&id))
+        .unwrap_or_else(|| {
+            let mut detector = rustface::create_detector_with_model(model.clone());
+            configure(&mut *detector);
+            detector
+        });
+    let result = f(&mut *detector);
+    RANGE_DETECTORS.with(|detectors| detectors.borrow_mut().insert(id, detector));
+    result
+}
+
+fn configure(detector: &mut dyn rustface::Detector) {
+    detector.set_min_face_size(MIN_FACE_SIZE);
+    detector.set_score_thresh(2.0);
+    detector.set_pyramid_scale_factor(0.8);
+    detector.set_slide_window_step(4, 4);
+}
+
+fn detect_with(detector: &mut dyn rustface::Detector, image: &GrayImage) -> Vec<Face> {
+    let image = ImageData::new(image.as_raw(), image.width(), image.height());
+    detector
+        .detect(&image)
+        .iter()
+        .map(|f| Face {
+            rect: Rect::at(f.bbox().x(), f.bbox().y()).of_size(f.bbox().width(), f.bbox().height()),
+            score: f.score() as f32,
+        })
+        .collect()
+}
+
+// Splits face sizes from `min` to `max` into `count` ranges that take about as long to detect. The
+// pyramid level for faces of size s is (min / s)² of the image, so the ranges get wider as the
+// faces get bigger.
+#[cfg(feature = "parallel")]
+fn face_size_ranges(min: u32, max: u32, count: usize) -> Vec<(u32, u32)> {
+    if count <= 1 || max <= min {
+        return vec![(min, max.max(min))];
+    }
+    let mut bounds: Vec<u32> = (0..count)
+        .map(|k| (min as f32 / (1.0 - k as f32 / count as f32).sqrt()).round() as u32)
+        .collect();
+    bounds.push(max);
+    bounds.dedup();
+    bounds
+        .windows(2)
+        .filter(|pair| pair[0] < max)
+        .map(|pair| (pair[0], pair[1].min(max)))
+        .collect()
+}
+
+// A face on the edge between two ranges can be found in both; the better scored one is kept
+#[cfg(feature = "parallel")]
+fn merge_overlapping(mut faces: Vec<Face>) -> Vec<Face> {
+    faces.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Face> = Vec::new();
+    for face in faces {
+        if kept
+            .iter()
+            .all(|other| overlap(face.rect, other.rect) < 0.5)
+        {
+            kept.push(face);
+        }
+    }
+    kept
+}
+
+// intersection over union
+#[cfg(feature = "parallel")]
+fn overlap(a: Rect, b: Rect) -> f32 {
+    let Some(intersection) = a.intersect(b) else {
+        return 0.0;
+    };
+    let area = |rect: Rect| rect.width() as f32 * rect.height() as f32;
+    let shared = area(intersection);
+    shared / (area(a) + area(b) - shared)
+}
+
+#[cfg(feature = "parallel")]
+impl Drop for RustfaceDetector {
+    fn drop(&mut self) {
+        let id = self.id;
+        rayon::broadcast(|_| {
+            RANGE_DETECTORS.with(|detectors| detectors.borrow_mut().remove(&id));
+        });
+    }
+}
+
+impl Detector for RustfaceDetector {
+    fn name(&self) -> &'static str {
+        "rustface"
+    }
+
+    fn detect(&mut self, image: &GrayImage) -> Vec<Face> {
+        #[cfg(feature = "parallel")]
+        if self.parallel && rayon::current_num_threads() > 1 {
+            return self.detect_parallel(image);
+        }
+        detect_with(&mut *self.detector, image)
+    }
+}
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_size_ranges_split_the_work_evenly() {
+        assert_eq!(
+            face_size_ranges(20, 480, 4),
+            vec![(20, 23), (23, 28), (28, 40), (40, 480)]
+        );
+        assert_eq!(face_size_ranges(20, 480, 1), vec![(20, 480)]);
+        // a tiny frame
+        assert_eq!(face_size_ranges(20, 25, 4), vec![(20, 23), (23, 25)]);
+    }
+
+    #[test]
+    fn faces_found_in_two_ranges_are_merged() {
+        let face = |left, score| Face {
+            rect: Rect::at(left, 0).of_size(40, 40),
+            score,
+        };
+        let merged = merge_overlapping(vec![face(0, 3.0), face(2, 5.0), face(200, 4.0)]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].score, 5.0);
+        assert_eq!(merged[1].rect.left(), 200);
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::Instant;
+
+use image::GrayImage;
+use nokhwa::utils::ApiBackend;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::detector::create_detector;
+use crate::preflight;
+
+// the optional cargo features that change what neckcheck can do
+const FEATURES: &[(&str, bool)] = &[
+    ("ffmpeg", cfg!(feature = "ffmpeg")),
+    ("opencv", cfg!(feature = "opencv")),
+    ("onnx", cfg!(feature = "onnx")),
+    ("cuda", cfg!(feature = "cuda")),
+    ("directml", cfg!(feature = "directml")),
+    ("coreml", cfg!(feature = "coreml")),
+];
+
+// Prints what neckcheck would run with: the config, the compiled in features, the detector and
+// the hardware it actually ended up on, and the cameras it can see.
+pub fn run(args: &Args) {
+    let path = args.config.clone().or_else(Config::default_path);
+    let mut config = match Config::load(args.config.as_deref()) {
+        Ok(config) => {
+            match path.filter(|path| path.exists()) {
+                Some(path) => println!("[ok] config: {}", path.display()),
+                None => println!("[ok] config: built-in defaults"),
+            }
+            config
+        }
+        Err(e) => {
+            println!("[fail] config: {}", e);
+            Config::default()
+        }
+    };
+    if let Some(backend) = args.detector {
+        config.detector.backend = backend;
+    }
+
+    let enabled: Vec<&str> = FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+    if enabled.is_empty() {
+        println!("[ok] features: none");
+    } else {
+        println!("[ok] features: {}", enabled.join(", "));
+    }
+
+    match create_detector(&config.detector) {
+        Ok(mut detector) => {
+            // a first detection also shows whether the provider works, not just that it loaded
+            let frame = GrayImage::new(320, 240);
+            let start = Instant::now();
+            detector.detect(&frame);
+            println!(
+                "[ok] detector: {} on {} (first detection took {:.1?})",
+                detector.name(),
+                detector.execution_provider(),
+                start.elapsed()
+            );
+            if config.detector.execution_provider.is_gpu() && detector.execution_provider() == "CPU"
+            {
+                println!(
+                    "[warn] detector: {:?} was requested but is not available, running on the CPU",
+                    config.detector.execution_provider
+                );
+            }
+        }
+        Err(e) => println!("[fail] detector: {}", e),
+    }
+
+    match nokhwa::query(ApiBackend::Auto) {
+        Ok(cameras) if cameras.is_empty() => println!("[warn] cameras: none found"),
+        Ok(cameras) => {
+            for camera in cameras {
+                let access = camera
+                    .index()
+                    .as_index()
+                    .map_or(Ok(()), preflight::camera_access);
+                match access {
+                    Ok(()) => println!("[ok] camera {}: {}", camera.index(), camera.human_name()),
+                    Err(e) => println!(
+                        "[fail] camera {}: {}: {}",
+                        camera.index(),
+                        camera.human_name(),
+                        e
+                    ),
+                }
+            }
+        }
+        Err(e) => println!("[fail] cameras: {}", e),
+    }
+}
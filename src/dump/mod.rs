@@ -0,0 +1,218 @@
+// Keeps the frame behind every change of the posture, in grayscale with everything the detector found
+// drawn on it, along with the numbers, for working out why an alert fired (or didn't)
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::{imageops, DynamicImage, Rgb, RgbImage};
+use imageproc::drawing::draw_hollow_rect_mut;
+use imageproc::rect::Rect;
+use serde::Serialize;
+
+use crate::detector::Face;
+use crate::events::PostureEvent;
+use crate::output::{annotate, OverlayStyle};
+use crate::posture::Proximity;
+use crate::privacy::ImageAccess;
+
+// what the detector found but neckcheck didn't measure
+const CANDIDATE: Rgb<u8> = Rgb([0, 160, 255]);
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Outline {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    score: Option<f32>,
+}
+
+impl Outline {
+    fn new(rect: Rect, score: Option<f32>) -> Outline {
+        Outline {
+            x: rect.left(),
+            y: rect.top(),
+            width: rect.width(),
+            height: rect.height(),
+            score,
+        }
+    }
+}
+
+// What is written next to each frame
+#[derive(Serialize, Debug)]
+struct Details<'a> {
+    events: &'a [PostureEvent],
+    metric: Option<Proximity>,
+    // the calibrated limit as a face size, drawn centred on the chosen face
+    threshold: Option<(u32, u32)>,
+    candidates: Vec<Outline>,
+    chosen: Option<Outline>,
+}
+
+// What a dump is made of: the frame of a check that changed the posture, and what was made of it
+pub struct Transition<'a> {
+    pub events: &'a [PostureEvent],
+    pub frame: &'a RgbImage,
+    pub candidates: &'a [Face],
+    // the face that was measured
+    pub chosen: Option<Rect>,
+    pub threshold: Option<(u32, u32)>,
+    pub metric: Option<Proximity>,
+    pub ok: bool,
+}
+
+pub struct DebugDump {
+    dir: PathBuf,
+}
+
+impl DebugDump {
+    pub fn new(dir: PathBuf, _access: ImageAccess) -> DebugDump {
+        if let Err(e) = fs::create_dir_all(&dir) {
+            println!(
+                "Failed to create debug dump directory {}: {}",
+                dir.display(),
+                e
+            );
+        }
+        DebugDump { dir }
+    }
+
+    // Writes the frame as <time>-<event>.png and the details as <time>-<event>.json, returning where
+    // the frame went
+    pub fn save(&self, transition: &Transition, style: &OverlayStyle) -> Option<PathBuf> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let name = transition
+            .events
+            .iter()
+            .map(event_name)
+            .collect::<Vec<_>>()
+            .join("-");
+        let stem = self.dir.join(format!("{}-{}", millis, name));
+        let image = draw(transition, style);
+        let png = stem.with_extension("png");
+        if let Err(e) = image.save(&png) {
+            println!("Failed to save debug frame to {}: {}", png.display(), e);
+            return None;
+        }
+        write_details(&stem.with_extension("json"), transition);
+        Some(png)
+    }
+}
+
+// The frame in grayscale, so that what is drawn on it stands out
+fn draw(transition: &Transition, style: &OverlayStyle) -> DynamicImage {
+    let gray = imageops::grayscale(transition.frame);
+    let mut image = DynamicImage::ImageLuma8(gray).to_rgb8();
+    for candidate in transition.candidates {
+        draw_hollow_rect_mut(&mut image, candidate.rect, CANDIDATE);
+    }
+    let chosen: Vec<Rect> = transition.chosen.into_iter().collect();
+    annotate(
+        &mut image,
+        &chosen,
+        transition.threshold,
+        transition.ok,
+        style,
+    );
+    DynamicImage::ImageRgb8(image)
+}
+
+fn event_name(event: &PostureEvent) -> String {
+    serde_json::to_value(event)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| "event".to_string())
+}
+
+fn write_details(path: &Path, transition: &Transition) {
+    let score_of = |rect: Rect| {
+        transition
+            .candidates
+            .iter()
+            .find(|candidate| candidate.rect == rect)
+            .map(|candidate| candidate.score)
+    };
+    let details = Details {
+        events: transition.events,
+        metric: transition.metric,
+        threshold: transition.threshold,
+        candidates: transition
+            .candidates
+            .iter()
+            .map(|candidate| Outline::new(candidate.rect, Some(candidate.score)))
+            .collect(),
+        chosen: transition
+            .chosen
+            .map(|rect| Outline::new(rect, score_of(rect))),
+    };
+    let written = serde_json::to_string_pretty(&details)
+        .map_err(|e| e.to_string())
+        .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        println!("Failed to save debug details to {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::privacy::PrivacyMode;
+
+    #[test]
+    fn transitions_are_dumped_with_their_details() {
+        let dir = std::env::temp_dir().join(format!("neckcheck-dump-{}", std::process::id()));
+        let access = PrivacyMode::Normal.image_access("test").unwrap();
+        let dump = DebugDump::new(dir.clone(), access);
+        let user = Rect::at(100, 80).of_size(120, 120);
+        let poster = Rect::at(10, 150).of_size(30, 30);
+        let candidates = [
+            Face {
+                rect: user,
+                score: 9.5,
+            },
+            Face {
+                rect: poster,
+                score: 2.0,
+            },
+        ];
+        let frame = RgbImage::from_pixel(320, 240, Rgb([200, 100, 50]));
+        let transition = Transition {
+            events: &[PostureEvent::TooClose],
+            frame: &frame,
+            candidates: &candidates,
+            chosen: Some(user),
+            threshold: Some((100, 100)),
+            metric: Some(Proximity::FaceSize {
+                width: 120,
+                height: 120,
+            }),
+            ok: false,
+        };
+        dump.save(&transition, &OverlayStyle::default());
+
+        let files: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        let json = files
+            .iter()
+            .find(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .unwrap();
+        assert!(json.to_string_lossy().ends_with("-too_close.json"));
+        let details: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(json).unwrap()).unwrap();
+        assert_eq!(details["candidates"].as_array().unwrap().len(), 2);
+        assert_eq!(details["chosen"]["score"], 9.5);
+        let png = image::open(json.with_extension("png")).unwrap().to_rgb8();
+        // grayscale, but for what is drawn on it
+        let pixel = png.get_pixel(300, 220);
+        assert!(pixel[0] == pixel[1] && pixel[1] == pixel[2]);
+        assert_eq!(*png.get_pixel(10, 160), CANDIDATE);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,415 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio_util::sync::CancellationToken;
+
+use crate::alert::{AlertKind, Severity};
+use crate::events::PostureEvent;
+use crate::posture::Proximity;
+
+// Bumped whenever a change to the format would trip up older readers
+pub const FORMAT_VERSION: u32 = 1;
+
+// Starts every run of neckcheck in a file, and every new day
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    pub neckcheck: String,
+    pub start: DateTime<Local>,
+    // seconds between checks
+    pub interval: f64,
+    // the text of the config file, if there was one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<String>,
+}
+
+// A measurement, written as [width, height] or a distance to keep the lines short
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum Sample {
+    FaceSize(u32, u32),
+    Distance(f32),
+}
+
+impl From<Proximity> for Sample {
+    fn from(proximity: Proximity) -> Sample {
+        match proximity {
+            Proximity::FaceSize { width, height } => Sample::FaceSize(width, height),
+            Proximity::Distance(distance) => Sample::Distance(distance),
+        }
+    }
+}
+
+impl From<Sample> for Proximity {
+    fn from(sample: Sample) -> Proximity {
+        match sample {
+            Sample::FaceSize(width, height) => Proximity::FaceSize { width, height },
+            Sample::Distance(distance) => Proximity::Distance(distance),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Entry {
+    Header(Header),
+    // what a check measured, none when nobody was there; written for every check, so kept short
+    #[serde(rename = "s")]
+    Sample(Option<Sample>),
+    // the limit the samples are held against, whenever it changes
+    Limit(Sample),
+    // everything else on the event bus
+    Event(PostureEvent),
+    // an alert and the sinks it went to, none when it was held back
+    Alert {
+        kind: AlertKind,
+        severity: Severity,
+        sinks: Vec<String>,
+    },
+    // the text of the config file after it changed
+    Config(String),
+}
+
+// One line of the log, e.g. `{"t":1500,"s":[182,240]}`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+    // milliseconds since the header, on the monotonic clock
+    pub t: u64,
+    #[serde(flatten)]
+    pub entry: Entry,
+}
+
+// A run of neckcheck as read back from a file: its header and what followed it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub header: Header,
+    pub records: Vec<Record>,
+}
+
+// Writes what it is sent to a file per day, named after it, e.g. 2026-03-10.jsonl
+struct Writer {
+    dir: PathBuf,
+    keep_days: usize,
+    header: Header,
+    // the day and file being written, and when its header was
+    file: Option<(NaiveDate, File, Instant)>,
+}
+
+impl Writer {
+    fn write(&mut self, at: Instant, wall: DateTime<Local>, entry: &Entry) -> std::io::Result<()> {
+        // the next day starts with the config as it is by then
+        if let Entry::Config(text) = entry {
+            self.header.config = Some(text.clone());
+        }
+        let day = wall.date_naive();
+        if self.file.as_ref().is_none_or(|(open, _, _)| *open != day) {
+            fs::create_dir_all(&self.dir)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(format!("{}.jsonl", day)))?;
+            self.file = Some((day, file, at));
+            let header = Entry::Header(Header {
+                start: wall,
+                ..self.header.clone()
+            });
+            self.write(at, wall, &header)?;
+            self.prune(day);
+        }
+        let (_, file, started) = self.file.as_mut().unwrap();
+        let record = Record {
+            t: at.saturating_duration_since(*started).as_millis() as u64,
+            entry: entry.clone(),
+        };
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+    }
+
+    // Deletes the days older than `keep_days`
+    fn prune(&self, today: NaiveDate) {
+        let oldest = today - Duration::days(self.keep_days as i64 - 1);
+        for (day, path) in days(&self.dir) {
+            if day < oldest {
+                if let Err(e) = fs::remove_file(&path) {
+                    println!("Failed to delete {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+// The file of every day in `dir`, oldest first
+pub fn days(dir: &Path) -> Vec<(NaiveDate, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut days: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let day = path
+                .file_name()?
+                .to_str()?
+                .strip_suffix(".jsonl")?
+                .parse()
+                .ok()?;
+            Some((day, path))
+        })
+        .collect();
+    days.sort();
+    days
+}
+
+// The sessions in a day's file. Lines torn by a crash are skipped, and a session written in a newer
+// format than this neckcheck reads is an error rather than misread.
+pub fn read(path: &Path) -> Result<Vec<Session>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut sessions: Vec<Session> = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let Ok(record) = serde_json::from_str::<Record>(&line) else {
+            continue;
+        };
+        match record.entry {
+            Entry::Header(header) if header.version > FORMAT_VERSION => {
+                return Err(format!(
+                    "{} was written in a newer format ({}) than this neckcheck reads ({})",
+                    path.display(),
+                    header.version,
+                    FORMAT_VERSION
+                ))
+            }
+            Entry::Header(header) => sessions.push(Session {
+                header,
+                records: Vec::new(),
+            }),
+            _ => {
+                if let Some(session) = sessions.last_mut() {
+                    session.records.push(record);
+                }
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+// Prints what happened on `day`: every event, alert and config change, but not the measurements
+pub fn print_day(dir: &Path, day: NaiveDate) -> Result<(), String> {
+    let path = dir.join(format!("{}.jsonl", day));
+    if !path.exists() {
+        return Err(format!("Nothing was logged on {}", day));
+    }
+    for session in read(&path)? {
+        let start = session.header.start;
+        println!(
+            "{} neckcheck {} started",
+            start.format("%H:%M:%S"),
+            session.header.neckcheck
+        );
+        for record in &session.records {
+            let line = match &record.entry {
+                Entry::Event(event) => serde_json::to_string(event).unwrap_or_default(),
+                Entry::Alert { kind, sinks, .. } if sinks.is_empty() => {
+                    format!("alert {} held back", name(kind))
+                }
+                Entry::Alert {
+                    kind,
+                    severity,
+                    sinks,
+                } => format!(
+                    "alert {} ({}) to {}",
+                    name(kind),
+                    name(severity),
+                    sinks.join(", ")
+                ),
+                Entry::Config(_) => "config changed".to_string(),
+                Entry::Header(_) | Entry::Sample(_) | Entry::Limit(_) => continue,
+            };
+            let time = start + Duration::milliseconds(record.t as i64);
+            println!("{} {}", time.format("%H:%M:%S"), line);
+        }
+    }
+    Ok(())
+}
+
+// Serde's name for a value, e.g. "too_close"
+fn name(value: impl Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+// Hands records over to a thread that writes them, so that the disk never holds up the pipeline or
+// the alerts
+#[derive(Clone)]
+pub struct EventLog {
+    sender: Sender<(Instant, DateTime<Local>, Entry)>,
+}
+
+impl EventLog {
+    pub fn spawn(
+        dir: PathBuf,
+        keep_days: usize,
+        interval: f64,
+        config: Option<String>,
+    ) -> EventLog {
+        let mut writer = Writer {
+            dir,
+            keep_days,
+            header: Header {
+                version: FORMAT_VERSION,
+                neckcheck: env!("CARGO_PKG_VERSION").to_string(),
+                start: Local::now(),
+                interval,
+                config,
+            },
+            file: None,
+        };
+        let (sender, records) = channel::<(Instant, DateTime<Local>, Entry)>();
+        thread::Builder::new()
+            .name("neckcheck-event-log".to_string())
+            .spawn(move || {
+                for (at, wall, entry) in records {
+                    if let Err(e) = writer.write(at, wall, &entry) {
+                        println!("Failed to log to {}: {}", writer.dir.display(), e);
+                    }
+                }
+            })
+            .expect("Failed to spawn the event log thread");
+        EventLog { sender }
+    }
+
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("neckcheck").join("events"))
+    }
+
+    pub fn record(&self, entry: Entry) {
+        // an error only means that the writer is gone, which it has already said why
+        let _ = self.sender.send((Instant::now(), Local::now(), entry));
+    }
+}
+
+// Logs everything on the event bus until shutdown, the measurements of every check as samples
+pub async fn run(log: EventLog, mut events: Receiver<PostureEvent>, shutdown: CancellationToken) {
+    let mut limit = None;
+    loop {
+        let event = tokio::select! {
+            _ = shutdown.cancelled() => return,
+            event = events.recv() => match event {
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    println!("The event log missed {} events", missed);
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            },
+        };
+        let PostureEvent::Metrics(metrics) = event else {
+            log.record(Entry::Event(event));
+            continue;
+        };
+        let current = Sample::from(metrics.limit);
+        if limit.replace(current) != Some(current) {
+            log.record(Entry::Limit(current));
+        }
+        log.record(Entry::Sample(metrics.current.map(Sample::from)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration as StdDuration;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn sessions_are_read_back_as_written() {
+        let dir = std::env::temp_dir().join(format!("neckcheck-events-{}", std::process::id()));
+        let header = Header {
+            version: FORMAT_VERSION,
+            neckcheck: "1.0.0".to_string(),
+            start: Local::now(),
+            interval: 0.5,
+            config: None,
+        };
+        let mut writer = Writer {
+            dir: dir.clone(),
+            keep_days: 2,
+            header: header.clone(),
+            file: None,
+        };
+        let start = Instant::now();
+        let wall = Local.with_ymd_and_hms(2026, 3, 10, 23, 59, 0).unwrap();
+        let entries = [
+            Entry::Limit(Sample::FaceSize(180, 240)),
+            Entry::Sample(Some(Sample::FaceSize(200, 260))),
+            Entry::Event(PostureEvent::TooClose),
+            Entry::Alert {
+                kind: AlertKind::TooClose,
+                severity: Severity::Warning,
+                sinks: vec!["notification".to_string()],
+            },
+        ];
+        for (n, entry) in entries.iter().enumerate() {
+            let at = start + StdDuration::from_millis(500 * n as u64);
+            writer.write(at, wall, entry).unwrap();
+        }
+        // past midnight, into the next day's file
+        let at = start + StdDuration::from_secs(120);
+        writer
+            .write(at, wall + Duration::minutes(2), &Entry::Sample(None))
+            .unwrap();
+        let sessions = read(&dir.join("2026-03-10.jsonl")).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].header.start, wall);
+        let records: Vec<_> = sessions[0]
+            .records
+            .iter()
+            .map(|record| (record.t, record.entry.clone()))
+            .collect();
+        let expected: Vec<_> = entries
+            .iter()
+            .enumerate()
+            .map(|(n, entry)| (500 * n as u64, entry.clone()))
+            .collect();
+        assert_eq!(records, expected);
+        let next = read(&dir.join("2026-03-11.jsonl")).unwrap();
+        assert_eq!(next[0].records[0].t, 0);
+
+        // a day that's past keeping by the time the next one starts
+        writer
+            .write(at, wall + Duration::days(2), &Entry::Sample(None))
+            .unwrap();
+        let days = days(&dir);
+        assert_eq!(
+            days.iter()
+                .map(|(day, _)| day.to_string())
+                .collect::<Vec<_>>(),
+            vec!["2026-03-11", "2026-03-12"]
+        );
+
+        let newer = serde_json::to_string(&Record {
+            t: 0,
+            entry: Entry::Header(Header {
+                version: FORMAT_VERSION + 1,
+                ..header
+            }),
+        })
+        .unwrap();
+        fs::write(dir.join("newer.jsonl"), newer).unwrap();
+        assert!(read(&dir.join("newer.jsonl")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
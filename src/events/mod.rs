@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::alert::Severity;
+use crate::posture::Proximity;
+
+// how many events a slow subscriber may fall behind by before it starts missing them
+const EVENT_CAPACITY: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostureEvent {
+    TooClose,
+    Recovered,
+    Away,
+    Returned,
+    CameraLost(String),
+    CameraRecovered,
+    // the camera was switched on, so neckcheck is looking, and off again
+    CameraOpened,
+    CameraClosed,
+    // of several cameras, this one is now the one watching
+    CameraSwitched(String),
+    // the capture/detection pipeline keeps crashing and monitoring is not currently possible
+    MonitoringFailed(String),
+    // the head has been tilted forward beyond the limit for a while, and no longer is
+    NeckTooSteep,
+    NeckRecovered,
+    // the eyes have been closed or heavy-lidded for a while, and are open again
+    Drowsy,
+    Awake,
+    // the number of blinks counted over a window of this many seconds
+    BlinkRate { blinks: u32, seconds: u32 },
+    // the blink rate dropped below the minimum, and recovered
+    LowBlinkRate,
+    BlinkRateRecovered,
+    // shoulders raised or rolled forward for a while, and back upright
+    Slouching,
+    SlouchRecovered,
+    // the face has sat well below its usual height in the frame for a while, and is back
+    Sinking,
+    SinkingRecovered,
+    // how the day went
+    Summary(String),
+    // an exercise of the physiotherapy program is due
+    Exercise { name: String, reps: u32 },
+    // without a camera, time to check the posture, and to rest the eyes
+    PostureCheck,
+    EyeBreak,
+    // the user paused monitoring, and resumed it
+    Paused,
+    Resumed,
+    // someone other than the enrolled user is alone at the computer, and has gone
+    GuestArrived,
+    GuestLeft,
+    // the measurements of the latest check, for status displays
+    Metrics(Metrics),
+    // how much memory neckcheck is using
+    Memory(MemoryUsage),
+    // monitoring was reduced to save power, and why, or back to normal
+    PowerSaving(Option<String>),
+    // the user reacted to an alert
+    Feedback(Feedback),
+    // the user calibrated, so the alerts can be shown off
+    Calibrated,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    // the latest measurement, None if nobody was there
+    pub current: Option<Proximity>,
+    // what the posture is judged on
+    pub smoothed: Option<Proximity>,
+    // the limit it is held against, after learning and the schedule
+    pub limit: Proximity,
+    // how long taking the measurement took
+    pub latency_ms: u32,
+    // from when the frame was captured to when the check had decided on it; alert timers are set
+    // back by this much
+    #[serde(default)]
+    pub decision_latency_ms: u32,
+    // below 1 while faces are detected at a lower resolution to keep up
+    pub detection_scale: f32,
+    // made by the latest check; a number that keeps going up points at a leak
+    #[serde(default)]
+    pub allocations: u32,
+    // how serious a script found the check, if it said; the alerts it causes are sent as such
+    #[serde(default)]
+    pub severity: Option<Severity>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MemoryUsage {
+    pub resident_mb: f64,
+    // since memory settled after launch
+    pub growth_mb: f64,
+    // since launch
+    pub allocations: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Feedback {
+    // silence posture alerts for a while
+    Snooze(Duration),
+    // stop reminding about the current episode
+    Dismiss,
+}
+
+// Fans posture events out to every subscriber (alert sinks, ...). Each subscriber has its own
+// queue, so a slow sink never holds up the check loop or the other sinks. Publishing never blocks
+// and works from both async tasks and the blocking pipeline thread.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<PostureEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        EventBus { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PostureEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: PostureEvent) {
+        // an error only means that nobody is subscribed right now
+        let _ = self.sender.send(event);
+    }
+}
@@ -0,0 +1,170 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response};
+
+use crate::alert::alert_for;
+use crate::events::{EventBus, Feedback};
+use crate::ipc::Snapshot;
+use crate::status::Status;
+use crate::worker::WorkerHandle;
+
+mod pb {
+    tonic::include_proto!("neckcheck");
+}
+
+use pb::control_request::Action;
+use pb::neck_check_server::{NeckCheck, NeckCheckServer};
+
+// how many events may queue up for a client that is slow to read them
+const STREAM_CAPACITY: usize = 64;
+
+// The gRPC counterpart of the JSON control connection, for clients that want typed messages and a
+// stream of events rather than polling
+struct Service {
+    worker: WorkerHandle,
+    status: watch::Receiver<Status>,
+    events: EventBus,
+    started: Instant,
+    config: Option<PathBuf>,
+    shutdown: CancellationToken,
+}
+
+// Serves the gRPC API on `address` until shutdown
+pub async fn serve(
+    address: SocketAddr,
+    worker: WorkerHandle,
+    status: watch::Receiver<Status>,
+    events: EventBus,
+    config: Option<PathBuf>,
+    shutdown: CancellationToken,
+) {
+    let service = Service {
+        worker,
+        status,
+        events,
+        started: Instant::now(),
+        config,
+        shutdown: shutdown.clone(),
+    };
+    let result = tonic::transport::Server::builder()
+        .add_service(NeckCheckServer::new(service))
+        .serve_with_shutdown(address, shutdown.cancelled())
+        .await;
+    if let Err(e) = result {
+        println!("Failed to serve gRPC on {}: {}", address, e);
+    }
+}
+
+// Serde's name for a value, e.g. "too_close"
+fn name(value: impl serde::Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[tonic::async_trait]
+impl NeckCheck for Service {
+    async fn status(
+        &self,
+        _request: Request<pb::StatusRequest>,
+    ) -> Result<Response<pb::StatusReply>, tonic::Status> {
+        let snapshot = Snapshot {
+            status: self.status.borrow().clone(),
+            uptime_seconds: self.started.elapsed().as_secs(),
+            config: self.config.clone(),
+        };
+        let status = &snapshot.status;
+        Ok(Response::new(pb::StatusReply {
+            state: name(status.state),
+            since: status
+                .since
+                .map(|since| since.to_rfc3339())
+                .unwrap_or_default(),
+            alerts_today: status.alerts_today,
+            camera_ok: status.camera.ok,
+            camera_error: status.camera.error.clone().unwrap_or_default(),
+            camera_active: status.camera.active,
+            uptime_seconds: snapshot.uptime_seconds,
+            json: serde_json::to_string(&snapshot).unwrap_or_default(),
+        }))
+    }
+
+    async fn control(
+        &self,
+        request: Request<pb::ControlRequest>,
+    ) -> Result<Response<pb::ControlReply>, tonic::Status> {
+        let Some(action) = request.into_inner().action else {
+            return Err(tonic::Status::invalid_argument("No action given"));
+        };
+        let worker = self.worker.clone();
+        // the worker is driven through a blocking channel
+        let result = tokio::task::spawn_blocking(move || match action {
+            Action::Pause(paused) => worker.pause(paused),
+            Action::SnoozeMinutes(minutes) => match Duration::try_from_secs_f64(minutes * 60.0) {
+                Ok(duration) => worker.feedback(Feedback::Snooze(duration)),
+                Err(_) => Err(format!("Invalid snooze duration: {} minutes", minutes)),
+            },
+            Action::Dismiss(_) => worker.feedback(Feedback::Dismiss),
+            Action::Calibrate(calibration) => {
+                match Duration::try_from_secs_f64(calibration.minutes * 60.0) {
+                    Ok(duration) if (0.0..=100.0).contains(&calibration.percentile) => {
+                        worker.observe(duration, calibration.percentile)
+                    }
+                    _ => Err("Invalid calibration period or percentile".to_string()),
+                }
+            }
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+        Ok(Response::new(pb::ControlReply {
+            ok: result.is_ok(),
+            error: result.err().unwrap_or_default(),
+        }))
+    }
+
+    type EventsStream = ReceiverStream<Result<pb::Event, tonic::Status>>;
+
+    async fn events(
+        &self,
+        _request: Request<pb::EventsRequest>,
+    ) -> Result<Response<Self::EventsStream>, tonic::Status> {
+        let mut events = self.events.subscribe();
+        let shutdown = self.shutdown.clone();
+        let (sender, receiver) = mpsc::channel(STREAM_CAPACITY);
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = sender.closed() => return,
+                    event = events.recv() => match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return,
+                    },
+                };
+                // measurements are in the status rather than the stream
+                let Some(alert) = alert_for(&event) else {
+                    continue;
+                };
+                let event = pb::Event {
+                    kind: name(alert.kind),
+                    severity: name(alert.severity),
+                    message: alert.message,
+                    time: Local::now().to_rfc3339(),
+                };
+                if sender.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
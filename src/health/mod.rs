@@ -0,0 +1,211 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use tiny_http::{Header, Method, Response, Server};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::HealthConfig;
+use crate::status::{PostureState, Status};
+use crate::tone::audio_available;
+
+// Whether neckcheck is doing its job, as answered at /healthz
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Health {
+    pub ok: bool,
+    pub camera_ok: bool,
+    // when the latest check finished; none before calibration
+    pub last_check: Option<DateTime<Local>>,
+    pub audio: bool,
+    // what's wrong, when not ok
+    pub problems: Vec<String>,
+}
+
+// Judges the status: unhealthy when the camera is lost, or when no check has finished for longer
+// than `stuck_after` while monitoring. A paused neckcheck isn't checking on purpose, and one with a
+// guest at the computer only rarely.
+pub fn assess(status: &Status, now: DateTime<Local>, stuck_after: Duration, audio: bool) -> Health {
+    let mut problems = Vec::new();
+    if !status.camera.ok {
+        problems.push(format!(
+            "Camera lost: {}",
+            status.camera.error.as_deref().unwrap_or("no frames")
+        ));
+    }
+    if let Some(last) = status.last_check {
+        let since = (now - last).to_std().unwrap_or_default();
+        let idle = matches!(status.state, PostureState::Paused | PostureState::Guest);
+        if !idle && since > stuck_after {
+            problems.push(format!("No check for {} seconds", since.as_secs()));
+        }
+    }
+    Health {
+        ok: problems.is_empty(),
+        camera_ok: status.camera.ok,
+        last_check: status.last_check,
+        audio,
+        problems,
+    }
+}
+
+// How long without a check counts as stuck, given how often checks are made
+fn stuck_after(config: &HealthConfig, period: Duration) -> Duration {
+    period + Duration::from_secs_f64(config.stuck_after)
+}
+
+// Answers /healthz on `address` until shutdown, with 200 when healthy and 503 when not
+pub fn spawn(
+    config: &HealthConfig,
+    period: Duration,
+    status: watch::Receiver<Status>,
+    shutdown: CancellationToken,
+) {
+    let server = match Server::http(config.address) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            println!("Failed to serve /healthz on {}: {}", config.address, e);
+            return;
+        }
+    };
+    let stuck_after = stuck_after(config, period);
+    tokio::spawn({
+        let server = server.clone();
+        async move {
+            shutdown.cancelled().await;
+            server.unblock();
+        }
+    });
+    let spawned = thread::Builder::new()
+        .name("neckcheck-health".to_string())
+        .spawn(move || {
+            for request in server.incoming_requests() {
+                if request.method() != &Method::Get || request.url() != "/healthz" {
+                    let _ = request.respond(Response::empty(404));
+                    continue;
+                }
+                let health = assess(
+                    &status.borrow(),
+                    Local::now(),
+                    stuck_after,
+                    audio_available(),
+                );
+                let code = if health.ok { 200 } else { 503 };
+                let body = serde_json::to_vec(&health).unwrap();
+                let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+                let _ = request.respond(
+                    Response::from_data(body)
+                        .with_status_code(code)
+                        .with_header(header),
+                );
+            }
+        });
+    if let Err(e) = spawned {
+        println!("Failed to spawn the health check thread: {}", e);
+    }
+}
+
+// Under systemd with `WatchdogSec=` set, tells it neckcheck is alive for as long as it's healthy, so
+// that a stuck pipeline gets neckcheck restarted
+#[cfg(unix)]
+pub async fn watchdog(
+    config: HealthConfig,
+    period: Duration,
+    status: watch::Receiver<Status>,
+    shutdown: CancellationToken,
+) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let notify = |message: &str| {
+        if let Err(e) = sd_notify(&socket, message) {
+            println!("Failed to notify systemd: {}", e);
+        }
+    };
+    notify("READY=1");
+    let Some(usec) = std::env::var("WATCHDOG_USEC")
+        .ok()
+        .and_then(|usec| usec.parse::<u64>().ok())
+    else {
+        return;
+    };
+    let stuck_after = stuck_after(&config, period);
+    // twice per timeout, as systemd recommends
+    let mut interval = tokio::time::interval(Duration::from_micros(usec) / 2);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let health = assess(&status.borrow(), Local::now(), stuck_after, true);
+        if health.ok {
+            notify("WATCHDOG=1");
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn watchdog(
+    _config: HealthConfig,
+    _period: Duration,
+    _status: watch::Receiver<Status>,
+    _shutdown: CancellationToken,
+) {
+}
+
+#[cfg(unix)]
+fn sd_notify(socket: &std::ffi::OsStr, message: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(socket);
+    // a leading @ names a socket in the abstract namespace
+    #[cfg(target_os = "linux")]
+    if let Some(name) = bytes.strip_prefix(b"@") {
+        use std::os::linux::net::SocketAddrExt;
+        let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        return datagram
+            .send_to_addr(message.as_bytes(), &address)
+            .map(|_| ());
+    }
+    datagram.send_to(message.as_bytes(), socket).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::status::CameraHealth;
+
+    #[test]
+    fn a_pipeline_without_checks_is_unhealthy() {
+        let now = Local::now();
+        let stuck_after = Duration::from_secs(60);
+        let mut status = Status {
+            last_check: Some(now - TimeDelta::seconds(30)),
+            ..Status::default()
+        };
+        assert!(assess(&status, now, stuck_after, true).ok);
+        // nothing checked before calibration
+        let calibrating = Status::default();
+        assert!(assess(&calibrating, now, stuck_after, false).ok);
+
+        status.last_check = Some(now - TimeDelta::seconds(90));
+        let health = assess(&status, now, stuck_after, true);
+        assert!(!health.ok);
+        assert_eq!(health.problems, vec!["No check for 90 seconds"]);
+        status.state = PostureState::Paused;
+        assert!(assess(&status, now, stuck_after, true).ok);
+
+        status.camera = CameraHealth {
+            ok: false,
+            error: Some("unplugged".to_string()),
+            active: false,
+        };
+        let health = assess(&status, now, stuck_after, true);
+        assert_eq!(health.problems, vec!["Camera lost: unplugged"]);
+    }
+}
@@ -0,0 +1,105 @@
+# Deutsch
+
+## Kalibrierung
+
+calibration-begin = Zum Kalibrieren eine beliebige Taste drücken...
+calibration-bad-posture = Nimm eine Haltung ein, die du für schlecht hältst, und drücke dann eine beliebige Taste.
+calibration-hold-still = Einen Moment stillhalten...
+calibration-try-again = { $problem } Bitte versuche es noch einmal.
+calibration-several-faces = Es wurde mehr als ein Gesicht erkannt. Bitte versuche es noch einmal.
+calibration-no-face = Es wurde kein Gesicht erkannt. Bitte versuche es noch einmal.
+calibration-nobody = Der Sensor sieht niemanden. Bitte versuche es noch einmal.
+calibration-too-few-detections = Du wurdest nur in { $detected } von { $total } Bildern erkannt; sorge für besseres Licht oder schau zur Kamera.
+calibration-unstable = Erkennung instabil ({ $variation } % Schwankung); sorge für besseres Licht oder halte still.
+calibration-done-distance = Kalibrierung erfolgreich. min_distance: { $distance } cm
+calibration-done-face-size = Kalibrierung erfolgreich. max_detection_size: { $width }x{ $height }
+calibration-restored = Gespeicherte Kalibrierung für { $camera } wird verwendet
+calibration-camera-mismatch = Die gespeicherte Kalibrierung stammt von { $saved }, nicht von { $camera }, und wäre daher falsch. Es wird neu kalibriert; mit --ignore-camera-mismatch wird sie trotzdem verwendet.
+calibration-mismatch-ignored = Die Kalibrierung von { $saved } wird wie gewünscht für { $camera } verwendet
+calibration-unknown-camera = (unbekannte Kamera)
+observe-start = Ich beobachte deine Haltung { $minutes } Minuten lang; arbeite einfach wie gewohnt, bis dahin gibt es keine Warnungen
+observe-too-few = Nur { $count } Messungen beim Beobachten, ich beobachte weitere { $minutes } Minuten
+
+## Einrichtung, mit `neckcheck setup` oder beim ersten Start
+
+onboarding-welcome = Willkommen bei neckcheck! Wir richten es ein: Kamera wählen, kalibrieren und festlegen, wie du gewarnt werden möchtest.
+onboarding-replace = { $path } gibt es schon. Ersetzen?
+onboarding-no-camera = Es wurde keine Kamera gefunden; schließ eine an und starte `neckcheck setup` noch einmal.
+onboarding-pick-camera = Welche Kamera soll dich beobachten?
+onboarding-choose = Auswahl [{ $default }]:
+onboarding-preview = Das sieht die Kamera. Sitz wie gewohnt und prüfe, ob dein Gesicht erkannt wird:
+onboarding-preview-face = Gesicht erkannt, { $width }x{ $height }
+onboarding-preview-distance = Du bist { $distance } cm entfernt
+onboarding-preview-nobody = Kein Gesicht erkannt; sorge für mehr Licht oder richte die Kamera auf dich
+onboarding-preview-done = Wurde dein Gesicht erkannt?
+onboarding-usual-posture = Sitz aufrecht, wie du es gewohnt bist, und drücke dann eine beliebige Taste.
+onboarding-not-closer = Das ist kaum näher als deine gewohnte Haltung, so würde jede kleine Bewegung warnen. Lehn dich weiter vor.
+onboarding-pick-style = Wie möchtest du erfahren, dass du zu nah bist?
+onboarding-style-tone = Ein Ton
+onboarding-style-notification = Eine Desktop-Benachrichtigung
+onboarding-style-both = Beides
+onboarding-testing = So warnt neckcheck, wenn du dich zu weit vorlehnst:
+onboarding-noticed = Hast du es bemerkt?
+onboarding-done = Fertig! Die Konfiguration liegt in { $path }; dort lässt sich alles Weitere ändern.
+
+## Gesicht einlernen
+
+enroll-look = Schau ein paar Sekunden lang allein auf den Bildschirm, so wie du sonst auch sitzt...
+enroll-failed = Es war nicht oft genug genau ein Gesicht zu sehen. Prüfe die Beleuchtung und dass niemand sonst im Bild ist, und versuche es dann noch einmal.
+enroll-done = Dein Gesicht ist eingelernt; seine Beschreibung liegt in { $path }.
+
+## Strenges Profil
+
+strict-passcode = Code:
+breathing-start = Zeit für eine Pause. Atme mit dem Kreis:
+breathing-in = Einatmen... { $seconds }
+breathing-hold = Halten... { $seconds }
+breathing-out = Ausatmen... { $seconds }
+
+## Warnungen
+
+alert-too-close = Zu nah! Rück vom Bildschirm ab
+alert-preview = So sieht es aus, wenn du dich über die Grenze vorlehnst
+alert-reminder = Du bist immer noch zu nah am Bildschirm
+alert-sustained = Du bist seit über { $seconds } Sekunden zu nah am Bildschirm
+alert-stand-up = Du sitzt seit { $minutes } Minuten; steh auf und beweg dich
+alert-recovered = Haltung wieder gut
+alert-away = Nicht am Schreibtisch
+alert-returned = Zurück am Schreibtisch
+alert-camera-lost = Kamera verloren: { $reason }
+alert-camera-recovered = Die Kamera ist wieder da
+alert-camera-opened = Kamera an: neckcheck schaut hin
+alert-camera-closed = Kamera aus
+alert-camera-switched = Jetzt mit Kamera { $camera }
+alert-monitoring-failed = neckcheck überwacht deine Haltung nicht: { $reason }
+alert-neck-angle = Dein Kopf ist schon länger nach vorn geneigt; heb das Kinn
+alert-neck-angle-recovered = Nackenwinkel wieder gut
+alert-drowsy = Dir fallen die Augen zu; Zeit für eine Pause?
+alert-awake = Augen wieder offen
+alert-low-blink-rate = Du blinzelst kaum; schau kurz vom Bildschirm weg
+alert-blink-rate-recovered = Du blinzelst wieder normal
+alert-slouch = Du sitzt krumm; Schultern runter und nach hinten
+alert-slouch-recovered = Schultern wieder aufrecht
+alert-sinking = Du sinkst in den Stuhl; setz dich aufrecht hin
+alert-sinking-recovered = Du sitzt wieder aufrecht
+alert-exercise = Zeit für deine Übung: { $exercise }, { $reps } Mal. Danach `neckcheck exercise-done` ausführen.
+alert-posture-check = Zeit, die Haltung zu prüfen: zurücklehnen, Schultern runter, Bildschirm auf Augenhöhe
+alert-eye-break = Augenpause: schau 20 Sekunden lang auf etwas, das 6 Meter entfernt ist
+alert-paused = Überwachung pausiert
+alert-resumed = Überwachung fortgesetzt
+coaching-chin-back = Kinn zurück, Schultern runter
+coaching-eye-level = Die Oberkante des Bildschirms gehört auf Augenhöhe
+coaching-sit-back = Lehn dich zurück und lass dich vom Stuhl stützen
+coaching-feet-flat = Füße flach auf den Boden, Rücken an die Lehne
+coaching-arms = Schultern locker, Ellbogen nah am Körper
+coaching-breathe = Mach den Nacken lang und atme tief durch
+alert-action-snooze = { $minutes } Min. pausieren
+alert-action-fixed = Schon korrigiert
+
+## Tageszusammenfassung
+
+summary-today = Heute: { $at_desk } am Schreibtisch, { $alerts ->
+        [one] eine Haltungswarnung
+       *[other] { $alerts } Haltungswarnungen
+    }, längste Strecke { $best_streak }
+summary-email-subject = Dein Tag mit neckcheck
@@ -0,0 +1,103 @@
+# The text neckcheck shows to its user. Every message here has to be in the other languages too,
+# under the same id; `{ $name }` is filled in by neckcheck.
+
+## calibration
+
+calibration-begin = Press any key to begin calibration...
+calibration-bad-posture = Move to the position that you would consider to be a bad posture and then press any key.
+calibration-hold-still = Hold still for a moment...
+calibration-try-again = { $problem } Please try again.
+calibration-several-faces = More than one face was detected. Please try again.
+calibration-no-face = No face was detected. Please try again.
+calibration-nobody = The sensor reports nobody there. Please try again.
+calibration-too-few-detections = You were only detected in { $detected } of { $total } frames; improve lighting or face the camera.
+calibration-unstable = Detection unstable ({ $variation }% variation); improve lighting or hold still.
+calibration-done-distance = Calibration successful. Using min_distance: { $distance }cm
+calibration-done-face-size = Calibration successful. Using max_detection_size: { $width }x{ $height }
+calibration-restored = Using the saved calibration for { $camera }
+calibration-camera-mismatch = The saved calibration was taken with { $saved }, not { $camera }, so it would be wrong. Calibrating again; run with --ignore-camera-mismatch to use it anyway.
+calibration-mismatch-ignored = Using the calibration taken with { $saved } for { $camera }, as asked
+calibration-unknown-camera = an unknown camera
+observe-start = Observing your posture for { $minutes } minutes; just work as usual, nothing will alert until then
+observe-too-few = Only { $count } measurements while observing, observing for another { $minutes } minutes
+
+## setting up, with `neckcheck setup` or on the first run
+
+onboarding-welcome = Welcome to neckcheck! Let's set it up: pick a camera, calibrate, and choose how you'd like to be alerted.
+onboarding-replace = { $path } already exists. Replace it?
+onboarding-no-camera = No camera was found; plug one in and run `neckcheck setup` again.
+onboarding-pick-camera = Which camera should watch you?
+onboarding-choose = Pick one [{ $default }]:
+onboarding-preview = Here's what the camera sees. Sit as you usually do and check that your face is found:
+onboarding-preview-face = Face found, { $width }x{ $height }
+onboarding-preview-distance = You are { $distance }cm away
+onboarding-preview-nobody = No face found; try more light, or point the camera at you
+onboarding-preview-done = Was your face found?
+onboarding-usual-posture = Sit as you usually do, upright, and then press any key.
+onboarding-not-closer = That's hardly closer than how you usually sit, so every small movement would alert. Lean in further.
+onboarding-pick-style = How would you like to be told that you're too close?
+onboarding-style-tone = A tone
+onboarding-style-notification = A desktop notification
+onboarding-style-both = Both
+onboarding-testing = This is what leaning in too far will do:
+onboarding-noticed = Did you notice it?
+onboarding-done = All set! The config is in { $path }; everything else can be changed there.
+
+## enrolling
+
+enroll-look = Look at the screen as you usually sit, on your own, for a few seconds...
+enroll-failed = Couldn't see exactly one face often enough. Check the lighting and that nobody else is in view, then try again.
+enroll-done = Your face is enrolled; its description is in { $path }.
+
+## strict profile
+
+strict-passcode = Passcode:
+breathing-start = Time for a break. Breathe along with the circle:
+breathing-in = Breathe in... { $seconds }
+breathing-hold = Hold... { $seconds }
+breathing-out = Breathe out... { $seconds }
+
+## alerts
+
+alert-too-close = Too close! Sit back from the screen
+alert-preview = This is what leaning in past the limit will do
+alert-reminder = You are still too close to the screen
+alert-sustained = You have been too close to the screen for over { $seconds } seconds
+alert-stand-up = You've been seated for { $minutes } minutes; stand up and move around
+alert-recovered = Posture recovered
+alert-away = Away from the desk
+alert-returned = Back at the desk
+alert-camera-lost = Lost the camera: { $reason }
+alert-camera-recovered = The camera is back
+alert-camera-opened = Camera on: neckcheck is looking
+alert-camera-closed = Camera off
+alert-camera-switched = Now watching from camera { $camera }
+alert-monitoring-failed = neckcheck is not monitoring your posture: { $reason }
+alert-neck-angle = Your head has been bent forward for a while; lift your chin
+alert-neck-angle-recovered = Neck angle recovered
+alert-drowsy = Your eyes keep closing; time for a break?
+alert-awake = Eyes open again
+alert-low-blink-rate = You are hardly blinking; look away from the screen for a moment
+alert-blink-rate-recovered = Blinking normally again
+alert-slouch = You are slouching; drop and pull back your shoulders
+alert-slouch-recovered = Shoulders upright again
+alert-sinking = You are sinking into your chair; sit up tall
+alert-sinking-recovered = Sitting tall again
+alert-exercise = Time for your exercise: { $exercise }, { $reps } times. Then run `neckcheck exercise-done`.
+alert-posture-check = Time for a posture check: sit back, shoulders down, screen at eye level
+alert-eye-break = Eye break: look at something 20 feet (6 metres) away for 20 seconds
+alert-paused = Monitoring paused
+alert-resumed = Monitoring resumed
+coaching-chin-back = Chin back, shoulders down
+coaching-eye-level = Bring the top of the screen to eye level
+coaching-sit-back = Sit back and let the chair hold you up
+coaching-feet-flat = Feet flat on the floor, back against the chair
+coaching-arms = Relax your shoulders and keep your elbows by your sides
+coaching-breathe = Lengthen your neck and take a deep breath
+alert-action-snooze = Snooze { $minutes } min
+alert-action-fixed = I fixed it
+
+## the daily summary
+
+summary-today = Today: { $at_desk } at desk, { $alerts } posture alerts, best streak { $best_streak }
+summary-email-subject = Your day with neckcheck
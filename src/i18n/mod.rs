@@ -0,0 +1,132 @@
+use std::sync::OnceLock;
+
+use fluent::concurrent::FluentBundle;
+use fluent::{FluentArgs, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+// The translations built in, by language. English is complete and the fallback for any message
+// missing from another language.
+const LANGUAGES: &[(&str, &str)] = &[
+    ("en", include_str!("en.ftl")),
+    ("de", include_str!("de.ftl")),
+];
+
+// The bundles messages are looked up in, in order
+static BUNDLES: OnceLock<Vec<FluentBundle<FluentResource>>> = OnceLock::new();
+
+// Picks the language of the user-facing text: the configured one, or else the system's. Until this
+// is called (e.g. in tests), and for languages without a translation, the text is in English.
+pub fn init(language: Option<&str>) {
+    let requested = language
+        .map(str::to_string)
+        .or_else(sys_locale::get_locale)
+        .unwrap_or_default();
+    let language = match requested.replace('_', "-").parse::<LanguageIdentifier>() {
+        Ok(id) => id.language.as_str().to_string(),
+        Err(_) => {
+            println!("Unknown language {:?}, using English", requested);
+            String::new()
+        }
+    };
+    let _ = BUNDLES.set(bundles(&language));
+}
+
+fn bundles(language: &str) -> Vec<FluentBundle<FluentResource>> {
+    LANGUAGES
+        .iter()
+        .filter(|(id, _)| *id == language)
+        .chain(LANGUAGES.iter().filter(|(id, _)| *id == "en"))
+        .map(|(id, source)| bundle(id, source))
+        .collect()
+}
+
+fn bundle(id: &str, source: &str) -> FluentBundle<FluentResource> {
+    let resource = FluentResource::try_new(source.to_string())
+        .unwrap_or_else(|(_, errors)| panic!("Invalid {}.ftl: {:?}", id, errors));
+    let mut bundle = FluentBundle::new_concurrent(vec![id.parse().unwrap()]);
+    // the text goes to terminals and notifications, which show the bidi isolation marks
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .unwrap_or_else(|errors| panic!("Invalid {}.ftl: {:?}", id, errors));
+    bundle
+}
+
+// The text of a message in the chosen language, see `tr!`
+pub fn text(id: &str, args: Option<&FluentArgs>) -> String {
+    let bundles = BUNDLES.get_or_init(|| bundles("en"));
+    for bundle in bundles {
+        if let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) {
+            let mut errors = Vec::new();
+            return bundle
+                .format_pattern(pattern, args, &mut errors)
+                .into_owned();
+        }
+    }
+    // a missing message is a bug, but not one worth crashing over
+    id.to_string()
+}
+
+// Looks up a user-facing message in the translations, e.g.
+// `tr!("calibration-done-distance", distance = 45)`
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::text($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::text($id, Some(&args))
+    }};
+}
+pub(crate) use tr;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // messages start at the beginning of a line, e.g. `alert-away = Away from the desk`
+    fn message_ids(source: &str) -> Vec<&str> {
+        let mut ids: Vec<&str> = source
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .filter_map(|line| line.split_once(" =").map(|(id, _)| id))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn every_language_has_every_message() {
+        let english = message_ids(LANGUAGES[0].1);
+        for (id, source) in LANGUAGES {
+            assert_eq!(message_ids(source), english, "{}.ftl", id);
+            bundle(id, source);
+        }
+    }
+
+    #[test]
+    fn messages_fall_back_to_english() {
+        let bundles = bundles("de");
+        assert_eq!(bundles.len(), 2);
+        assert_eq!(self::bundles("xx").len(), 1);
+        let german = &bundles[0];
+        let mut args = FluentArgs::new();
+        args.set("at_desk", "5h00m");
+        args.set("alerts", 1);
+        args.set("best_streak", "2h00m");
+        let pattern = german
+            .get_message("summary-today")
+            .unwrap()
+            .value()
+            .unwrap();
+        assert_eq!(
+            german.format_pattern(pattern, Some(&args), &mut Vec::new()),
+            "Heute: 5h00m am Schreibtisch, eine Haltungswarnung, längste Strecke 2h00m"
+        );
+        assert_eq!(
+            tr!("calibration-done-distance", distance = 45),
+            "Calibration successful. Using min_distance: 45cm"
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream as AsyncTcpStream};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::detector::DetectorConfig;
+use crate::events::Feedback;
+use crate::status::Status;
+use crate::worker::WorkerHandle;
+
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+// subscribers hear about a new state straight away, and about new numbers at most this often
+const SUBSCRIBE_INTERVAL: Duration = Duration::from_secs(2);
+
+// A request to the running neckcheck, sent as a single line of JSON, e.g.
+// `{"command": "set_detector", "detector": {"backend": "onnx", "model": "face.onnx"}}`
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    SetDetector {
+        detector: DetectorConfig,
+    },
+    // silence posture alerts for this many minutes. With the strict profile, this, dismissing and
+    // pausing need the passcode.
+    Snooze {
+        minutes: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        passcode: Option<String>,
+    },
+    Dismiss {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        passcode: Option<String>,
+    },
+    // stop monitoring until resumed
+    Pause {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        passcode: Option<String>,
+    },
+    Resume,
+    // record the latest posture alert as a false positive
+    ReportFalsePositive,
+    // reply with a snapshot of the status
+    Status,
+    // after the response, send the status as a line of JSON, e.g. `{"state": "bad", ...}`, now
+    // and whenever it changes, for as long as the connection is open
+    Subscribe,
+}
+
+impl Request {
+    // The same request with the strict profile's passcode, if it is one that needs it
+    pub fn with_passcode(self, passcode: String) -> Request {
+        match self {
+            Request::Snooze { minutes, .. } => Request::Snooze {
+                minutes,
+                passcode: Some(passcode),
+            },
+            Request::Dismiss { .. } => Request::Dismiss {
+                passcode: Some(passcode),
+            },
+            Request::Pause { .. } => Request::Pause {
+                passcode: Some(passcode),
+            },
+            request => request,
+        }
+    }
+}
+
+// The reply, also a single line of JSON
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    // the answer to a status request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Snapshot>,
+}
+
+impl Response {
+    fn from_result(result: Result<(), String>) -> Response {
+        Response {
+            ok: result.is_ok(),
+            error: result.err(),
+            status: None,
+        }
+    }
+}
+
+// Everything a script might want to know about the running neckcheck
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Snapshot {
+    #[serde(flatten)]
+    pub status: Status,
+    pub uptime_seconds: u64,
+    // the config file it was started with, if any
+    pub config: Option<PathBuf>,
+}
+
+// Accepts control connections on `address` (localhost only by default) until shutdown
+pub async fn serve(
+    address: SocketAddr,
+    worker: WorkerHandle,
+    status: watch::Receiver<Status>,
+    config: Option<PathBuf>,
+    shutdown: CancellationToken,
+) {
+    let started = Instant::now();
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!(
+                "Failed to listen for control connections on {}: {}",
+                address, e
+            );
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(
+                        stream,
+                        worker.clone(),
+                        status.clone(),
+                        Snapshotter { started, config: config.clone() },
+                        shutdown.clone(),
+                    ));
+                }
+                Err(e) => println!("Failed to accept a control connection: {}", e),
+            },
+        }
+    }
+}
+
+// Connections hold on to the worker, so they are closed on shutdown to let it stop
+async fn handle_connection(
+    stream: AsyncTcpStream,
+    worker: WorkerHandle,
+    status: watch::Receiver<Status>,
+    snapshotter: Snapshotter,
+    shutdown: CancellationToken,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = AsyncBufReader::new(reader).lines();
+    loop {
+        let line = tokio::select! {
+            _ = shutdown.cancelled() => return,
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => line,
+                _ => return,
+            },
+        };
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Subscribe) => {
+                if write_line(&mut writer, &Response::from_result(Ok(()))).await {
+                    stream_status(&mut writer, status.clone(), &shutdown).await;
+                }
+                return;
+            }
+            Ok(Request::Status) => Response {
+                status: Some(snapshotter.snapshot(&status)),
+                ..Response::from_result(Ok(()))
+            },
+            Ok(request) => handle(request, &worker).await,
+            Err(e) => Response::from_result(Err(format!("Invalid request: {}", e))),
+        };
+        if !write_line(&mut writer, &response).await {
+            return;
+        }
+    }
+}
+
+// What the server knows beyond the status itself
+struct Snapshotter {
+    started: Instant,
+    config: Option<PathBuf>,
+}
+
+impl Snapshotter {
+    fn snapshot(&self, status: &watch::Receiver<Status>) -> Snapshot {
+        Snapshot {
+            status: status.borrow().clone(),
+            uptime_seconds: self.started.elapsed().as_secs(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+// Writes a value as a line of JSON, returning whether that worked
+async fn write_line(writer: &mut OwnedWriteHalf, value: &impl Serialize) -> bool {
+    let mut line = serde_json::to_string(value).unwrap();
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await.is_ok()
+}
+
+// Sends the status whenever it changes, until the subscriber goes away
+async fn stream_status(
+    writer: &mut OwnedWriteHalf,
+    mut status: watch::Receiver<Status>,
+    shutdown: &CancellationToken,
+) {
+    loop {
+        let update = status.borrow_and_update().clone();
+        if !write_line(writer, &update).await {
+            return;
+        }
+        let written = tokio::time::Instant::now();
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            changed = status.changed() => if changed.is_err() {
+                return;
+            },
+        }
+        // the metrics change with every check, so only a new state is sent on at once
+        if status.borrow().state == update.state {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tokio::time::sleep_until(written + SUBSCRIBE_INTERVAL) => {}
+            }
+        }
+    }
+}
+
+async fn handle(request: Request, worker: &WorkerHandle) -> Response {
+    let worker = worker.clone();
+    // the worker is driven through a blocking channel
+    let result = tokio::task::spawn_blocking(move || match request {
+        Request::SetDetector { detector } => worker.set_detector(detector),
+        Request::Snooze { minutes, passcode } => {
+            match Duration::try_from_secs_f64(minutes * 60.0) {
+                Ok(duration) => worker
+                    .unlock(passcode.as_deref())
+                    .and_then(|worker| worker.feedback(Feedback::Snooze(duration))),
+                Err(_) => Err(format!("Invalid snooze duration: {} minutes", minutes)),
+            }
+        }
+        Request::Dismiss { passcode } => worker
+            .unlock(passcode.as_deref())
+            .and_then(|worker| worker.feedback(Feedback::Dismiss)),
+        Request::Pause { passcode } => worker
+            .unlock(passcode.as_deref())
+            .and_then(|worker| worker.pause(true)),
+        Request::Resume => worker.pause(false),
+        Request::ReportFalsePositive => worker.report_false_positive(),
+        // answered in handle_connection
+        Request::Status | Request::Subscribe => Ok(()),
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()));
+    Response::from_result(result)
+}
+
+// Sends a single request to the running neckcheck and waits for its reply
+pub fn send(address: SocketAddr, request: &Request) -> Result<Response, String> {
+    let stream = TcpStream::connect_timeout(&address, CLIENT_TIMEOUT).map_err(|e| {
+        format!(
+            "Is neckcheck running? Failed to connect to {}: {}",
+            address, e
+        )
+    })?;
+    stream
+        .set_read_timeout(Some(CLIENT_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    (&stream)
+        .write_all(line.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let mut reply = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut reply)
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&reply).map_err(|e| format!("Invalid reply: {}", e))
+}
+
+// Subscribes to the status of the running neckcheck, calling `on_status` with every update until
+// the connection ends
+pub fn subscribe(address: SocketAddr, mut on_status: impl FnMut(Status)) -> Result<(), String> {
+    let stream = TcpStream::connect_timeout(&address, CLIENT_TIMEOUT).map_err(|e| {
+        format!(
+            "Is neckcheck running? Failed to connect to {}: {}",
+            address, e
+        )
+    })?;
+    let mut line = serde_json::to_string(&Request::Subscribe).map_err(|e| e.to_string())?;
+    line.push('\n');
+    (&stream)
+        .write_all(line.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let mut lines = BufReader::new(&stream).lines();
+    let response: Response = match lines.next() {
+        Some(line) => serde_json::from_str(&line.map_err(|e| e.to_string())?)
+            .map_err(|e| format!("Invalid reply: {}", e))?,
+        None => return Err("The connection closed".to_string()),
+    };
+    if !response.ok {
+        return Err(response.error.unwrap_or_default());
+    }
+    for line in lines {
+        let status = serde_json::from_str(&line.map_err(|e| e.to_string())?)
+            .map_err(|e| format!("Invalid status: {}", e))?;
+        on_status(status);
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use super::Landmarks;
+
+// the six points around each eye in the 68 point layout: outer corner, two on the upper lid, inner
+// corner, two on the lower lid
+const LEFT_EYE: [usize; 6] = [36, 37, 38, 39, 40, 41];
+const RIGHT_EYE: [usize; 6] = [42, 43, 44, 45, 46, 47];
+
+// The eye aspect ratio (Soukupová & Čech) averaged over both eyes: the height of the eye over its
+// width, around 0.3 when open and falling towards 0 as the lids close
+pub fn eye_aspect_ratio(landmarks: &Landmarks) -> Option<f32> {
+    let left = eye(landmarks, &LEFT_EYE)?;
+    let right = eye(landmarks, &RIGHT_EYE)?;
+    Some((left + right) / 2.0)
+}
+
+// Counts blinks from successive eye aspect ratios: a blink is the eyes closing and opening again.
+// Blinks are short, so this only works with frequent checks (a --interval of 0.1s or so).
+pub struct BlinkCounter {
+    closed_below: f32,
+    closed: bool,
+}
+
+impl BlinkCounter {
+    pub fn new(closed_below: f32) -> BlinkCounter {
+        BlinkCounter {
+            closed_below,
+            closed: false,
+        }
+    }
+
+    // Whether this ratio completes a blink
+    pub fn update(&mut self, ratio: f32) -> bool {
+        let closed = ratio < self.closed_below;
+        let blinked = self.closed && !closed;
+        self.closed = closed;
+        blinked
+    }
+}
+
+fn eye(landmarks: &Landmarks, indices: &[usize; 6]) -> Option<f32> {
+    let point = |i: usize| landmarks.points.get(indices[i]).copied();
+    let distance = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).hypot(a.1 - b.1);
+    let width = distance(point(0)?, point(3)?);
+    if width <= 0.0 {
+        return None;
+    }
+    let height = distance(point(1)?, point(5)?) + distance(point(2)?, point(4)?);
+    Some(height / (2.0 * width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Both eyes 30 wide, with the lids `opening` apart
+    fn face(opening: f32) -> Landmarks {
+        let mut points = vec![(0.0, 0.0); 68];
+        for (eye, x) in [(LEFT_EYE, 100.0), (RIGHT_EYE, 200.0)] {
+            let y = 100.0;
+            points[eye[0]] = (x, y);
+            points[eye[1]] = (x + 10.0, y - opening / 2.0);
+            points[eye[2]] = (x + 20.0, y - opening / 2.0);
+            points[eye[3]] = (x + 30.0, y);
+            points[eye[4]] = (x + 20.0, y + opening / 2.0);
+            points[eye[5]] = (x + 10.0, y + opening / 2.0);
+        }
+        Landmarks { points }
+    }
+
+    #[test]
+    fn open_eyes_have_a_higher_ratio_than_closed_ones() {
+        let open = eye_aspect_ratio(&face(9.0)).unwrap();
+        let closed = eye_aspect_ratio(&face(1.5)).unwrap();
+        assert!((open - 0.3).abs() < 1e-5);
+        assert!((closed - 0.05).abs() < 1e-5);
+    }
+
+    #[test]
+    fn counts_each_close_and_open_once() {
+        let mut counter = BlinkCounter::new(0.2);
+        let ratios = [0.3, 0.3, 0.1, 0.05, 0.3, 0.3, 0.1, 0.3, 0.1];
+        let blinks = ratios
+            .iter()
+            .filter(|&&ratio| counter.update(ratio))
+            .count();
+        assert_eq!(blinks, 2);
+    }
+
+    #[test]
+    fn needs_the_eye_landmarks() {
+        let landmarks = Landmarks {
+            points: vec![(0.0, 0.0); 40],
+        };
+        assert_eq!(eye_aspect_ratio(&landmarks), None);
+    }
+}
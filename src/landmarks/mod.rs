@@ -0,0 +1,60 @@
+mod eyes;
+#[cfg(feature = "onnx")]
+mod onnx;
+mod pose;
+
+pub use eyes::{eye_aspect_ratio, BlinkCounter};
+pub use pose::estimate_head_pose;
+
+use std::path::PathBuf;
+
+use image::GrayImage;
+use imageproc::rect::Rect;
+use serde::{Deserialize, Serialize};
+
+use crate::detector::{DetectorError, ExecutionProvider};
+
+// The 68 points of the iBUG 300-W layout, in frame coordinates: jaw 0-16, eyebrows 17-26, nose
+// 27-35, eyes 36-47, mouth 48-67
+#[derive(Debug, Clone, PartialEq)]
+pub struct Landmarks {
+    pub points: Vec<(f32, f32)>,
+}
+
+// Finds the facial landmarks inside a detected face
+pub trait LandmarkDetector {
+    fn landmarks(&mut self, image: &GrayImage, face: Rect) -> Option<Landmarks>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct LandmarksConfig {
+    // a 68 point landmark model (PFLD style: a 112x112 face crop in, 136 coordinates relative to
+    // the crop out)
+    pub model: Option<PathBuf>,
+    pub execution_provider: ExecutionProvider,
+}
+
+pub fn create_landmark_detector(
+    config: &LandmarksConfig,
+) -> Result<Box<dyn LandmarkDetector>, DetectorError> {
+    #[cfg(feature = "onnx")]
+    {
+        let model = config
+            .model
+            .as_deref()
+            .ok_or_else(|| DetectorError::MissingModel("landmark".to_string()))?;
+        Ok(Box::new(onnx::OnnxLandmarks::new(
+            model,
+            config.execution_provider,
+        )?))
+    }
+    #[cfg(not(feature = "onnx"))]
+    {
+        let _ = config;
+        Err(DetectorError::Unavailable(
+            "landmark".to_string(),
+            "onnx".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::GrayImage;
+use imageproc::rect::Rect;
+use ort::session::Session;
+use ort::value::Tensor;
+
+use super::{LandmarkDetector, Landmarks};
+use crate::detector::{onnx_session, DetectorError, ExecutionProvider};
+
+const INPUT_SIZE: u32 = 112;
+const LANDMARK_COUNT: usize = 68;
+// the face box is widened by this fraction on each side, as landmark models are trained on
+// crops with some margin around the face
+const MARGIN: f32 = 0.1;
+
+pub struct OnnxLandmarks {
+    session: Session,
+}
+
+impl OnnxLandmarks {
+    pub fn new(
+        model: &Path,
+        preference: ExecutionProvider,
+    ) -> Result<OnnxLandmarks, DetectorError> {
+        let (session, _) = onnx_session(model, preference)?;
+        Ok(OnnxLandmarks { session })
+    }
+
+    fn try_landmarks(&mut self, image: &GrayImage, face: Rect) -> ort::Result<Option<Landmarks>> {
+        let margin_x = (face.width() as f32 * MARGIN) as i32;
+        let margin_y = (face.height() as f32 * MARGIN) as i32;
+        let left = (face.left() - margin_x).max(0) as u32;
+        let top = (face.top() - margin_y).max(0) as u32;
+        let right = ((face.right() + margin_x).max(0) as u32).min(image.width());
+        let bottom = ((face.bottom() + margin_y).max(0) as u32).min(image.height());
+        if right <= left || bottom <= top {
+            return Ok(None);
+        }
+        let (width, height) = (right - left, bottom - top);
+        let crop = imageops::crop_imm(image, left, top, width, height).to_image();
+        let resized = imageops::resize(&crop, INPUT_SIZE, INPUT_SIZE, FilterType::Triangle);
+        let plane: Vec<f32> = resized
+            .as_raw()
+            .iter()
+            .map(|&value| value as f32 / 255.0)
+            .collect();
+        let input = plane.repeat(3);
+        let shape = [1, 3, INPUT_SIZE as usize, INPUT_SIZE as usize];
+        let input_name = self.session.inputs[0].name.clone();
+        let output_name = self.session.outputs[0].name.clone();
+        let outputs = self
+            .session
+            .run(ort::inputs![input_name => Tensor::from_array((shape, input))?])?;
+        let (_, coordinates) = outputs[output_name.as_str()].try_extract_tensor::<f32>()?;
+        if coordinates.len() < LANDMARK_COUNT * 2 {
+            return Ok(None);
+        }
+        let points = coordinates[..LANDMARK_COUNT * 2]
+            .chunks_exact(2)
+            .map(|point| {
+                (
+                    left as f32 + point[0] * width as f32,
+                    top as f32 + point[1] * height as f32,
+                )
+            })
+            .collect();
+        Ok(Some(Landmarks { points }))
+    }
+}
+
+impl LandmarkDetector for OnnxLandmarks {
+    fn landmarks(&mut self, image: &GrayImage, face: Rect) -> Option<Landmarks> {
+        self.try_landmarks(image, face).unwrap_or_else(|e| {
+            println!("ONNX landmark detection failed: {}", e);
+            None
+        })
+    }
+}
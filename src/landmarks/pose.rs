@@ -0,0 +1,217 @@
+use super::Landmarks;
+
+// A generic 3D face in arbitrary units, in camera-style axes (x right, y down, z away from the
+// viewer), with the tip of the nose at the origin
+const MODEL_POINTS: [[f32; 3]; 6] = [
+    [0.0, 0.0, 0.0],         // nose tip
+    [0.0, 330.0, 65.0],      // chin
+    [-225.0, -170.0, 135.0], // outer corner of the left eye (on the image)
+    [225.0, -170.0, 135.0],  // outer corner of the right eye
+    [-150.0, 150.0, 125.0],  // left corner of the mouth
+    [150.0, 150.0, 125.0],   // right corner of the mouth
+];
+// the same points in the 68 point layout
+const LANDMARK_INDICES: [usize; 6] = [30, 8, 36, 45, 48, 54];
+const ITERATIONS: usize = 30;
+
+// The orientation of the head relative to the camera, in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeadPose {
+    // positive with the chin tucked down towards the chest
+    pub pitch: f32,
+    // positive turned towards the right of the image
+    pub yaw: f32,
+    pub roll: f32,
+}
+
+// Solves for the head pose with POSIT (DeMenthon & Davis), which needs no more than a few 3x3
+// matrices. The camera is assumed to have its principal point in the middle of the frame and a
+// focal length of about the frame width, which is close enough for webcams.
+pub fn estimate_head_pose(
+    landmarks: &Landmarks,
+    frame_width: u32,
+    frame_height: u32,
+) -> Option<HeadPose> {
+    let image: Vec<(f32, f32)> = LANDMARK_INDICES
+        .iter()
+        .map(|&i| landmarks.points.get(i).copied())
+        .collect::<Option<_>>()?;
+    let (cx, cy) = (frame_width as f32 / 2.0, frame_height as f32 / 2.0);
+    let image: Vec<(f32, f32)> = image.iter().map(|(x, y)| (x - cx, y - cy)).collect();
+    posit(&image, frame_width as f32)
+}
+
+fn posit(image: &[(f32, f32)], focal_length: f32) -> Option<HeadPose> {
+    let origin = MODEL_POINTS[0];
+    let vectors: Vec<[f32; 3]> = MODEL_POINTS[1..].iter().map(|p| sub(*p, origin)).collect();
+    let object = pseudo_inverse(&vectors)?;
+    let mut epsilon = vec![0.0; vectors.len()];
+    let (mut i, mut k) = ([0.0; 3], [0.0; 3]);
+    for _ in 0..ITERATIONS {
+        let (x0, y0) = image[0];
+        let xs: Vec<f32> = (0..vectors.len())
+            .map(|n| image[n + 1].0 * (1.0 + epsilon[n]) - x0)
+            .collect();
+        let ys: Vec<f32> = (0..vectors.len())
+            .map(|n| image[n + 1].1 * (1.0 + epsilon[n]) - y0)
+            .collect();
+        let big_i = multiply(&object, &xs);
+        let big_j = multiply(&object, &ys);
+        let (scale_i, scale_j) = (norm(big_i), norm(big_j));
+        if scale_i == 0.0 || scale_j == 0.0 {
+            return None;
+        }
+        i = scale(big_i, 1.0 / scale_i);
+        let j = scale(big_j, 1.0 / scale_j);
+        k = normalize(cross(i, j))?;
+        let z0 = focal_length / ((scale_i + scale_j) / 2.0);
+        epsilon = vectors.iter().map(|v| dot(*v, k) / z0).collect();
+    }
+    // the two estimated axes are only nearly orthogonal
+    let j = cross(k, i);
+    Some(HeadPose {
+        // the direction the camera looks in, seen from the head: tilting the chin down moves the
+        // camera above the line of sight
+        pitch: k[1].atan2(k[2]).to_degrees(),
+        yaw: k[0].atan2((k[1] * k[1] + k[2] * k[2]).sqrt()).to_degrees(),
+        roll: j[0].atan2(i[0]).to_degrees(),
+    })
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn norm(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn scale(a: [f32; 3], factor: f32) -> [f32; 3] {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+fn normalize(a: [f32; 3]) -> Option<[f32; 3]> {
+    let length = norm(a);
+    (length > 0.0).then(|| scale(a, 1.0 / length))
+}
+
+// (AᵀA)⁻¹Aᵀ for the rows of A, as its three rows
+fn pseudo_inverse(rows: &[[f32; 3]]) -> Option<Vec<Vec<f32>>> {
+    let mut ata = [[0.0f32; 3]; 3];
+    for row in rows {
+        for a in 0..3 {
+            for b in 0..3 {
+                ata[a][b] += row[a] * row[b];
+            }
+        }
+    }
+    let inverse = invert(ata)?;
+    Some(
+        (0..3)
+            .map(|a| {
+                rows.iter()
+                    .map(|row| (0..3).map(|b| inverse[a][b] * row[b]).sum())
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+fn invert(m: [[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let determinant = m[0][0] * cofactor(1, 2, 1, 2) - m[0][1] * cofactor(1, 2, 0, 2)
+        + m[0][2] * cofactor(1, 2, 0, 1);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+    let adjugate = [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ];
+    Some(adjugate.map(|row| row.map(|value| value / determinant)))
+}
+
+fn multiply(matrix: &[Vec<f32>], vector: &[f32]) -> [f32; 3] {
+    let mut result = [0.0; 3];
+    for (a, row) in matrix.iter().enumerate() {
+        result[a] = row.iter().zip(vector).map(|(m, v)| m * v).sum();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Projects the model, rotated by `pitch` degrees about the x axis and then `yaw` degrees about
+    // the y axis, from 2m in front of a 640x480 camera
+    fn project(pitch: f32, yaw: f32) -> Landmarks {
+        let (sp, cp) = pitch.to_radians().sin_cos();
+        let (sy, cy) = yaw.to_radians().sin_cos();
+        let mut points = vec![(0.0, 0.0); 68];
+        for (model, &index) in MODEL_POINTS.iter().zip(&LANDMARK_INDICES) {
+            let [x, y, z] = *model;
+            let (y, z) = (cp * y - sp * z, sp * y + cp * z);
+            let (x, z) = (cy * x + sy * z, -sy * x + cy * z);
+            let z = z + 2000.0;
+            points[index] = (320.0 + 640.0 * x / z, 240.0 + 640.0 * y / z);
+        }
+        Landmarks { points }
+    }
+
+    #[test]
+    fn frontal_face_is_level() {
+        let pose = estimate_head_pose(&project(0.0, 0.0), 640, 480).unwrap();
+        assert!(pose.pitch.abs() < 1.0, "{:?}", pose);
+        assert!(pose.yaw.abs() < 1.0, "{:?}", pose);
+        assert!(pose.roll.abs() < 1.0, "{:?}", pose);
+    }
+
+    #[test]
+    fn recovers_a_tucked_chin() {
+        let pose = estimate_head_pose(&project(25.0, 0.0), 640, 480).unwrap();
+        assert!((pose.pitch - 25.0).abs() < 2.0, "{:?}", pose);
+    }
+
+    #[test]
+    fn recovers_a_turned_head() {
+        let pose = estimate_head_pose(&project(0.0, 20.0), 640, 480).unwrap();
+        assert!(pose.pitch.abs() < 2.0, "{:?}", pose);
+        // the nose turned towards the left of the image
+        assert!((pose.yaw + 20.0).abs() < 2.0, "{:?}", pose);
+    }
+
+    #[test]
+    fn needs_the_landmarks() {
+        let landmarks = Landmarks {
+            points: vec![(0.0, 0.0); 10],
+        };
+        assert_eq!(estimate_head_pose(&landmarks, 640, 480), None);
+    }
+}
@@ -1,276 +1,594 @@
+mod adaptive;
+mod alert;
+mod analyze;
+mod bench;
+mod body;
+mod breathing;
+mod calendar;
+mod cli;
+mod clock;
+mod completions;
+mod config;
+mod dashboard;
+mod dataset;
+mod desktop;
+mod detector;
+mod doctor;
+mod dump;
+mod eventlog;
+mod events;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod health;
+mod i18n;
+mod ipc;
+mod landmarks;
+mod memory;
+mod neckcheck;
+mod ntfy;
+mod observe;
+mod onboarding;
+mod output;
+mod posture;
+mod power;
+mod preflight;
+mod privacy;
+mod program;
+mod proximity;
+mod quiet;
+mod recognition;
+mod reminders;
+mod remote;
+mod report;
+mod script;
+mod snapshot;
+mod source;
+mod stats;
+mod status;
+mod strict;
+mod summary;
+mod timelapse;
 mod tone;
+#[cfg(feature = "self-update")]
+mod update;
+mod worker;
 
 extern crate nokhwa;
 extern crate rustface;
 
-use std::sync::{Arc, Mutex};
 use std::thread;
-
-use thiserror::Error;
-
-use rustface::{Detector, ImageData};
-
-use image::{DynamicImage, GrayImage, Rgb, RgbImage};
-
-use nokhwa::pixel_format::RgbFormat;
-use nokhwa::utils::{CameraIndex, RequestedFormat, RequestedFormatType};
-use nokhwa::Camera;
-
-use imageproc::drawing::draw_hollow_rect_mut;
-use imageproc::rect::Rect;
-
-use console::Term;
-use tone::play_tone;
-
-#[derive(Error, Debug, Clone)]
-pub enum WebCamError {
-    #[error("Failed to grab a frame: {0}")]
-    FrameGrabError(String),
-    #[error("Failed to open camera stream: {0}")]
-    StreamOpenError(String),
-    #[error("Failed to close camera stream {0}")]
-    StreamCloseError(String),
-    #[error("Failed to decode image: {0}")]
-    FrameDecodeError(String),
-}
-
-enum WebCamMode {
-    Continuous,
-    Discrete,
-}
-
-struct WebCam {
-    camera: Camera,
-    mode: WebCamMode,
-}
-
-impl WebCam {
-    pub fn new(index: u32, mode: WebCamMode) -> WebCam {
-        let index = CameraIndex::Index(index);
-        // request the absolute highest resolution CameraFormat that can be decoded to RGB.
-        let requested =
-            RequestedFormat::new::<RgbFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
-        // make the camera
-        let camera = match Camera::new(index.clone(), requested) {
-            Ok(c) => c,
-            Err(e) => panic!("Failed to open camera {}: {}", index.clone(), e),
-        };
-        WebCam { camera, mode }
-    }
-
-    // Captures a single frame from the camera
-    pub fn capture(&mut self) -> Result<RgbImage, WebCamError> {
-        if !self.camera.is_stream_open() {
-            println!("Opening Camera Stream");
-            let _ = self.open();
+use std::time::Duration;
+
+use clap::Parser;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+use alert::AlertManager;
+use cli::{Args, Command, ConfigCommand, StatsCommand};
+use config::Config;
+use eventlog::EventLog;
+use events::EventBus;
+use i18n::tr;
+use stats::StatsLog;
+use worker::Worker;
+
+#[global_allocator]
+static ALLOCATOR: memory::CountingAllocator = memory::CountingAllocator;
+
+// how long to wait for the pipeline to wind down after ctrl-c, e.g. if it is stuck in a prompt
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[tokio::main]
+async fn main() {
+    let mut args = Args::parse();
+    if let Some(Command::Timelapse { dir, date, out }) = &args.command {
+        let date = date.unwrap_or_else(|| chrono::Local::now().date_naive());
+        if let Err(e) = timelapse::assemble(dir, date, out) {
+            println!("{}", e);
         }
-
-        // get a frame
-        let frame = self
-            .camera
-            .frame()
-            .map_err(|e| WebCamError::FrameGrabError(e.to_string()))?;
-        println!("Captured Single Frame of {} bytes", frame.buffer().len());
-
-        // decode into an ImageBuffer
-        let decoded = frame
-            .decode_image::<RgbFormat>()
-            .map_err(|e| WebCamError::FrameDecodeError(e.to_string()))?;
-
-        if matches!(self.mode, WebCamMode::Discrete) {
-            let _ = self.close();
+        return;
+    }
+    if let Some(Command::Config { command }) = &args.command {
+        match command {
+            ConfigCommand::Init { force } => config::init(args.config.as_deref(), *force),
+            ConfigCommand::Validate => config::validate(args.config.as_deref()),
         }
-
-        return Ok(
-            RgbImage::from_raw(decoded.width(), decoded.height(), decoded.into_raw()).unwrap(),
-        );
+        return;
     }
-
-    fn open(&mut self) -> Result<(), WebCamError> {
-        let _ = self
-            .camera
-            .open_stream()
-            .map_err(|e| WebCamError::StreamOpenError(e.to_string()))?;
-        return Ok(());
+    if let Some(Command::Doctor) = &args.command {
+        doctor::run(&args);
+        return;
     }
-
-    fn close(&mut self) -> Result<(), WebCamError> {
-        let _ = self
-            .camera
-            .stop_stream()
-            .map_err(|e| WebCamError::StreamCloseError(e.to_string()))?;
-        return Ok(());
+    if let Some(Command::SelfUpdate { check }) = &args.command {
+        #[cfg(feature = "self-update")]
+        if let Err(e) = update::run(*check) {
+            println!("{}", e);
+        }
+        #[cfg(not(feature = "self-update"))]
+        {
+            let _ = check;
+            println!("Updating itself needs neckcheck built with the `self-update` feature");
+        }
+        return;
     }
-}
 
-struct FaceDetector {
-    detector: Box<dyn Detector>,
-}
-
-impl FaceDetector {
-    pub fn new() -> FaceDetector {
-        let mut detector = match rustface::create_detector("seeta_fd_frontal_v1.0.bin") {
-            Ok(d) => d,
-            Err(e) => panic!("Failed to create detector: {}", e),
+    let mut config = match Config::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    i18n::init(config.language.as_deref());
+    let setup = matches!(args.command, Some(Command::Setup));
+    if setup || (args.command.is_none() && onboarding::needed(&args)) {
+        let Some(path) = args.config.clone().or_else(Config::default_path) else {
+            println!("No config directory to write the config to; pass --config");
+            return;
         };
-        detector.set_min_face_size(20);
-        detector.set_score_thresh(2.0);
-        detector.set_pyramid_scale_factor(0.8);
-        detector.set_slide_window_step(4, 4);
-        FaceDetector { detector }
+        let privacy = privacy::PrivacyMode::from_strict(args.privacy_strict);
+        if let Err(e) = onboarding::run(&path, &config, privacy) {
+            println!("{}", e);
+        }
+        if setup {
+            return;
+        }
+        if path.exists() {
+            config = match Config::load(Some(&path)) {
+                Ok(config) => config,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+        }
     }
-
-    pub fn detect(&mut self, image: &GrayImage) -> Vec<Rect> {
-        let mut image = ImageData::new(image.as_raw(), image.width(), image.height());
-        return self
-            .detector
-            .detect(&mut image)
-            .iter()
-            .map(|f| {
-                Rect::at(f.bbox().x(), f.bbox().y()).of_size(f.bbox().width(), f.bbox().height())
-            })
-            .collect();
+    if let Some(backend) = args.detector {
+        config.detector.backend = backend;
     }
-
-    pub fn draw(image: &mut RgbImage, faces: Vec<Rect>) {
-        for face in faces {
-            draw_hollow_rect_mut(image, face, Rgb([255, 0, 0]));
+    args.camera = cli::cameras(&args, &config);
+    if let Some(Command::TestAlert { sink }) = &args.command {
+        alert::test_sinks(&config.alerts, &config.accessibility, *sink);
+        return;
+    }
+    if let Some(Command::Enroll) = &args.command {
+        let privacy = privacy::PrivacyMode::from_strict(args.privacy_strict);
+        if let Err(e) = recognition::enroll(&config, args.camera[0], privacy) {
+            println!("{}", e);
         }
+        return;
     }
-}
-
-#[derive(Debug, Clone)]
-struct Size {
-    width: u32,
-    height: u32,
-}
-
-impl Size {
-    pub fn new(width: u32, height: u32) -> Size {
-        Size { width, height }
+    if let Some(Command::Bench { images, iterations }) = &args.command {
+        bench::run(&config.detector, images, *iterations);
+        return;
     }
-}
-
-struct NeckCheckCalibration {
-    max_detection_size: Size, // the maximum allowed size of the face detection box before it is
-                              // deemed that the user is too close to the camera
-}
-
-struct NeckCheck {
-    webcam: WebCam,
-    detector: FaceDetector,
-    calibration: Option<NeckCheckCalibration>,
-}
-
-impl NeckCheck {
-    pub fn new(webcam: WebCam, detector: FaceDetector) -> NeckCheck {
-        NeckCheck {
-            webcam,
-            detector,
-            calibration: None,
+    if let Some(Command::SetDetector { backend }) = &args.command {
+        let mut detector = config.detector.clone();
+        detector.backend = *backend;
+        match ipc::send(config.ipc.address, &ipc::Request::SetDetector { detector }) {
+            Ok(ipc::Response { ok: true, .. }) => println!("Switched to {}", backend),
+            Ok(ipc::Response { error, .. }) => println!("{}", error.unwrap_or_default()),
+            Err(e) => println!("{}", e),
         }
+        return;
     }
-
-    // pub fn with_calibration(
-    //     webcam: WebCam,
-    //     detector: FaceDetector,
-    //     calibration: NeckCheckCalibration,
-    // ) -> NeckCheck {
-    //     NeckCheck {
-    //         webcam,
-    //         detector,
-    //         calibration: Some(calibration),
-    //     }
-    // }
-
-    pub fn calibrate(&mut self) {
-        let term = Term::stdout();
-        let _ = term.write_line("Press any key to begin calibration...");
-        let _ = term.read_line();
-        let mut faces = Vec::new();
-        while faces.is_empty() {
-            let _ = term.write_line("Move to the position that you would consider to be a bad posture and then press any key.");
-            let _ = term.read_line();
-            faces = self.detect();
-            if faces.is_empty() {
-                println!("No face was detected. Please try again.");
+    if let Some(Command::Status { json }) = &args.command {
+        match ipc::send(config.ipc.address, &ipc::Request::Status) {
+            Ok(ipc::Response {
+                status: Some(snapshot),
+                ..
+            }) => status::print_snapshot(&snapshot, *json),
+            Ok(ipc::Response { error, .. }) => println!("{}", error.unwrap_or_default()),
+            Err(e) => println!("{}", e),
+        }
+        return;
+    }
+    if let Some(Command::Waybar) = &args.command {
+        status::run_waybar(config.ipc.address);
+        return;
+    }
+    let request = match &args.command {
+        Some(Command::Snooze { minutes }) => Some(ipc::Request::Snooze {
+            minutes: *minutes,
+            passcode: None,
+        }),
+        Some(Command::Dismiss) => Some(ipc::Request::Dismiss { passcode: None }),
+        Some(Command::Pause) => Some(ipc::Request::Pause { passcode: None }),
+        Some(Command::Resume) => Some(ipc::Request::Resume),
+        Some(Command::ReportFp) => Some(ipc::Request::ReportFalsePositive),
+        _ => None,
+    };
+    if let Some(request) = request {
+        let mut response = ipc::send(config.ipc.address, &request);
+        let locked = matches!(
+            &response,
+            Ok(ipc::Response { error: Some(error), .. }) if error == strict::LOCKED
+        );
+        if locked && console::user_attended() {
+            let term = console::Term::stdout();
+            let _ = term.write_str(&format!("{} ", tr!("strict-passcode")));
+            if let Ok(passcode) = term.read_secure_line() {
+                response = ipc::send(config.ipc.address, &request.with_passcode(passcode));
             }
-            if faces.len() > 1 {
-                println!("More than one face was detected. Please try again.");
-                faces.clear();
+        }
+        match response {
+            Ok(ipc::Response { ok: true, .. }) => {}
+            Ok(ipc::Response { error, .. }) => println!("{}", error.unwrap_or_default()),
+            Err(e) => println!("{}", e),
+        }
+        return;
+    }
+    let min_break = chrono::Duration::seconds(config.alerts.min_break as i64);
+    let stats_path = config.stats.path.clone().or_else(StatsLog::default_path);
+    if let Some(Command::Stats { filter, command }) = &args.command {
+        let Some(path) = &stats_path else {
+            println!("No data directory to find the stats in");
+            return;
+        };
+        let log = StatsLog::new(path.clone()).with_filter(filter.clone());
+        match command {
+            None => stats::print_summary(&log, min_break),
+            Some(StatsCommand::Merge { file, host }) => {
+                let host = host.clone().unwrap_or_else(|| {
+                    file.file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                });
+                match log.merge(file, &host) {
+                    Ok((added, duplicates)) => println!(
+                        "Added {} records from {} ({} were already there)",
+                        added,
+                        file.display(),
+                        duplicates
+                    ),
+                    Err(e) => println!("Failed to merge {}: {}", file.display(), e),
+                }
             }
         }
-        let face = faces.first().unwrap();
-        let size = Size::new(face.width(), face.height());
-        self.calibration = Some(NeckCheckCalibration {
-            max_detection_size: size.clone(),
+        return;
+    }
+    if let Some(Command::Completions { shell }) = &args.command {
+        // a missing or unreadable stats log only means there are no profiles to offer
+        let records = stats_path
+            .as_ref()
+            .and_then(|path| StatsLog::new(path.clone()).read().ok())
+            .unwrap_or_default();
+        let names = completions::Names::find(&records);
+        completions::write(*shell, &names, &mut std::io::stdout());
+        return;
+    }
+    if let Some(Command::ExerciseDone { exercise }) = &args.command {
+        let Some(path) = &stats_path else {
+            println!("No data directory to keep the stats in");
+            return;
+        };
+        let log = StatsLog::new(path.clone());
+        let today = chrono::Local::now().date_naive();
+        let exercise = exercise.clone().or_else(|| {
+            let records = log.read().unwrap_or_default();
+            program::latest_prompted(&records, today)
         });
-
-        println!(
-            "Calibration successful. Using max_detection_size: {:?}",
-            size
-        );
+        match exercise {
+            Some(exercise) => {
+                log.record(stats::StatsEntry::ExerciseDone {
+                    exercise: exercise.clone(),
+                });
+                println!("Marked {} as done", exercise);
+            }
+            None => println!("No exercise was prompted today; name the one you did"),
+        }
+        return;
     }
-
-    pub fn check(&mut self) -> bool {
-        let faces = self.detect();
-        if faces.is_empty() {
-            return true;
+    if let Some(Command::Report { out, days, filter }) = &args.command {
+        match &stats_path {
+            Some(path) => {
+                let log = StatsLog::new(path.clone()).with_filter(filter.clone());
+                match report::write(&log, out, *days, min_break) {
+                    Ok(()) => println!("Wrote {}", out.display()),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            None => println!("No data directory to find the stats in"),
         }
-        if self.calibration.is_none() {
-            panic!("No calibration!");
+        return;
+    }
+    if let Some(Command::Breaks {
+        out,
+        count,
+        minutes,
+        filter,
+    }) = &args.command
+    {
+        match &stats_path {
+            Some(path) => {
+                let log = StatsLog::new(path.clone()).with_filter(filter.clone());
+                match calendar::write_breaks(&log, out, *count, *minutes) {
+                    Ok(()) => println!("Wrote {}", out.display()),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            None => println!("No data directory to find the stats in"),
         }
-        let face = faces.first().unwrap();
-        let calib = &self.calibration.as_ref().unwrap();
-        if face.width() > calib.max_detection_size.width
-            || face.height() > calib.max_detection_size.height
-        {
-            return false;
+        return;
+    }
+
+    if let Some(Command::Analyze { threshold, days }) = &args.command {
+        match config.event_log.dir.clone().or_else(EventLog::default_dir) {
+            Some(dir) => {
+                if let Err(e) = analyze::run(&dir, *days, *threshold) {
+                    println!("{}", e);
+                }
+            }
+            None => println!("No data directory to find the event log in"),
+        }
+        return;
+    }
+    if let Some(Command::Events { date }) = &args.command {
+        let date = date.unwrap_or_else(|| chrono::Local::now().date_naive());
+        match config.event_log.dir.clone().or_else(EventLog::default_dir) {
+            Some(dir) => {
+                if let Err(e) = eventlog::print_day(&dir, date) {
+                    println!("{}", e);
+                }
+            }
+            None => println!("No data directory to find the event log in"),
         }
-        return true;
+        return;
     }
 
-    fn detect(&mut self) -> Vec<Rect> {
-        let rgb_image = self.webcam.capture().unwrap();
-        let image = DynamicImage::ImageRgb8(rgb_image);
-        return self.detector.detect(&image.to_luma8());
+    // the local cameras, as opposed to a network or depth camera or a sensor
+    let local = args.url.is_none()
+        && !args.realsense
+        && args.phone.is_none()
+        && args.serial.is_none()
+        && !args.no_camera;
+    if local {
+        for index in args.camera.clone() {
+            if let Err(e) = preflight::camera_access(index) {
+                println!("{}", e);
+                // the reminders stand in until the camera can be used
+                if !config.reminders.fallback {
+                    return;
+                }
+            }
+        }
     }
-}
 
-unsafe impl Send for NeckCheck {}
+    let shutdown = CancellationToken::new();
+    let events = EventBus::new();
 
-fn main() {
-    let neckcheck: Arc<Mutex<NeckCheck>> = Arc::new(Mutex::new(NeckCheck::new(
-        WebCam::new(0, WebCamMode::Continuous),
-        FaceDetector::new(),
-    )));
-    neckcheck.lock().unwrap().calibrate();
+    let (feedback, answers) = std::sync::mpsc::channel();
+    let (quiet, quiet_updates) = tokio::sync::watch::channel(quiet::Quiet::Normal);
+    if config.quiet.any() {
+        tokio::spawn(quiet::run(config.quiet.clone(), quiet, shutdown.clone()));
+    }
+    let period = args.discrete.unwrap_or(args.interval);
+    let config_path = args.config.clone().or_else(Config::default_path);
+    let event_log_dir = config.event_log.dir.clone().or_else(EventLog::default_dir);
+    let event_log = event_log_dir
+        .filter(|_| config.event_log.enabled)
+        .map(|dir| {
+            let text = config_path
+                .as_ref()
+                .and_then(|path| std::fs::read_to_string(path).ok());
+            EventLog::spawn(dir, config.event_log.keep_days, period, text)
+        });
+    let mut alerts = AlertManager::new(
+        &config.alerts,
+        &config.accessibility,
+        feedback,
+        quiet_updates,
+    );
+    if let Some(coaching) = alert::Coaching::new(&config.coaching) {
+        alerts = alerts.with_coaching(coaching);
+    }
+    if config.strict.enabled && config.strict.lock_after > 0.0 {
+        if config.alerts.stand_up_after <= 0.0 {
+            println!("Warning: breaks can't be enforced with alerts.stand_up_after turned off");
+        }
+        alerts = alerts.with_enforced_breaks(
+            Duration::from_secs_f64(config.strict.lock_after),
+            config.breathing.enabled.then(|| config.breathing.clone()),
+        );
+    }
+    if let Some(event_log) = &event_log {
+        alerts = alerts.with_event_log(event_log.clone());
+        tokio::spawn(eventlog::run(
+            event_log.clone(),
+            events.subscribe(),
+            shutdown.clone(),
+        ));
+    }
+    tokio::spawn(alerts.run(events.subscribe(), shutdown.clone()));
+    let (status, status_updates) = tokio::sync::watch::channel(status::Status::default());
+    tokio::spawn(status::run(events.subscribe(), status, shutdown.clone()));
+    if config.memory.enabled {
+        tokio::spawn(memory::run(
+            config.memory.clone(),
+            events.clone(),
+            shutdown.clone(),
+        ));
+    }
 
-    // Create a thread for proximity checking
-    let proximity_thread = {
-        thread::spawn(move || {
-            loop {
-                let is_close = !neckcheck.lock().unwrap().check();
-                if is_close {
-                    println!("Too close!");
-                    play_tone(1.0);
+    let worker = Worker::spawn(args.clone(), &config, events.clone());
+    let (power_saving, power_updates) = tokio::sync::watch::channel(false);
+    if config.power.enabled {
+        tokio::spawn(power::run(
+            config.power.clone(),
+            worker.handle(),
+            events.clone(),
+            power_saving,
+            shutdown.clone(),
+        ));
+    }
+    // answers to alerts, passed on from the sinks' threads
+    let handle = worker.handle();
+    thread::Builder::new()
+        .name("neckcheck-feedback".to_string())
+        .spawn(move || {
+            for answer in answers {
+                match handle.feedback(answer) {
+                    Ok(()) => {}
+                    // the strict profile ignores the buttons on alerts
+                    Err(e) if e == strict::LOCKED => println!("{}", e),
+                    Err(_) => return,
                 }
-                // thread::sleep(Duration::from_secs(1));
             }
         })
-    };
+        .expect("Failed to spawn the feedback thread");
+    match args.observe {
+        Some(seconds) => worker.observe(Duration::from_secs_f64(seconds), args.observe_percentile),
+        None if args.no_camera => {}
+        None => worker.calibrate(),
+    }
+    if args.no_camera || config.reminders.fallback {
+        tokio::spawn(reminders::run(
+            config.reminders.clone(),
+            !args.no_camera,
+            events.clone(),
+            shutdown.clone(),
+        ));
+    }
 
-    // Wait for the proximity checking thread to finish
-    proximity_thread.join().unwrap();
+    if !config.program.is_empty() {
+        tokio::spawn(program::run(
+            config.program.clone(),
+            stats_path.clone().map(StatsLog::new),
+            events.clone(),
+            shutdown.clone(),
+        ));
+    }
+    if let (true, Some(path)) = (config.summary.enabled, stats_path.clone()) {
+        tokio::spawn(summary::run(
+            config.summary.clone(),
+            StatsLog::new(path),
+            min_break,
+            events.clone(),
+            shutdown.clone(),
+        ));
+    }
+
+    if config.ipc.enabled {
+        tokio::spawn(ipc::serve(
+            config.ipc.address,
+            worker.handle(),
+            status_updates.clone(),
+            args.config.clone().or_else(Config::default_path),
+            shutdown.clone(),
+        ));
+    }
+    if config.grpc.enabled {
+        #[cfg(feature = "grpc")]
+        tokio::spawn(grpc::serve(
+            config.grpc.address,
+            worker.handle(),
+            status_updates.clone(),
+            events.clone(),
+            args.config.clone().or_else(Config::default_path),
+            shutdown.clone(),
+        ));
+        #[cfg(not(feature = "grpc"))]
+        println!("The gRPC API needs neckcheck built with the `grpc` feature");
+    }
+    if config.health.enabled {
+        health::spawn(
+            &config.health,
+            Duration::from_secs_f64(period),
+            status_updates.clone(),
+            shutdown.clone(),
+        );
+    }
+    tokio::spawn(health::watchdog(
+        config.health.clone(),
+        Duration::from_secs_f64(period),
+        status_updates.clone(),
+        shutdown.clone(),
+    ));
+    if config.dashboard.enabled {
+        dashboard::spawn(
+            &config.dashboard,
+            worker.handle(),
+            status_updates.clone(),
+            stats_path.map(StatsLog::new),
+            shutdown.clone(),
+        );
+    }
+    if config.ntfy.enabled {
+        if config.ntfy.topic.is_empty() {
+            println!("No topic to take commands from under [ntfy]");
+        } else {
+            ntfy::spawn(
+                &config.ntfy,
+                worker.handle(),
+                status_updates.clone(),
+                shutdown.clone(),
+            );
+        }
+    }
+    if config.remote.enabled {
+        if config.remote.url.is_empty() {
+            println!("No url to report to under [remote]");
+        } else {
+            tokio::spawn(remote::run(
+                config.remote.clone(),
+                status_updates.clone(),
+                shutdown.clone(),
+            ));
+        }
+    }
+    if let Some(path) = config_path {
+        // only the detector is picked up from a changed config for now
+        let handle = worker.handle();
+        let mut detector = config.detector.clone();
+        let backend_override = args.detector;
+        let changed = path.clone();
+        tokio::spawn(config::watch(path, shutdown.clone(), move |config| {
+            if let (Some(event_log), Ok(text)) = (&event_log, std::fs::read_to_string(&changed)) {
+                event_log.record(eventlog::Entry::Config(text));
+            }
+            let mut new = config.detector;
+            if let Some(backend) = backend_override {
+                new.backend = backend;
+            }
+            if new == detector {
+                return;
+            }
+            detector = new.clone();
+            let handle = handle.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = handle.set_detector(new) {
+                    println!("Failed to switch the detector: {}", e);
+                }
+            });
+        }));
+    }
 
-    // let mut rgb_image = webcam.capture().unwrap();
-    // let image = DynamicImage::ImageRgb8(rgb_image.clone());
-    // let faces = detector.detect(&image.to_luma8());
-    //
-    // FaceDetector::draw(&mut rgb_image, faces);
-    //
-    // match rgb_image.save("output.png") {
-    //     Ok(_) => println!("Saved result to {}", "output.png"),
-    //     Err(message) => println!("Failed to save result to a file. Reason: {}", message),
-    // }
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(period));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut ticks = 0usize;
+    loop {
+        tokio::select! {
+            _ = &mut ctrl_c => {
+                println!("Shutting down");
+                break;
+            }
+            _ = interval.tick() => {
+                ticks += 1;
+                // fewer checks while saving power
+                if *power_updates.borrow() && !ticks.is_multiple_of(config.power.slow_down) {
+                    continue;
+                }
+                if !worker.request_check() || worker.is_finished() {
+                    println!("Pipeline stopped");
+                    break;
+                }
+            }
+        }
+    }
+    shutdown.cancel();
+    // a worker that is still blocked (e.g. waiting on a prompt) is simply abandoned; returning
+    // from main ends the process regardless
+    let _ = tokio::task::spawn_blocking(move || worker.shutdown(SHUTDOWN_TIMEOUT)).await;
 }
@@ -0,0 +1,175 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::config::MemoryConfig;
+use crate::events::{EventBus, MemoryUsage, PostureEvent};
+
+const MB: u64 = 1024 * 1024;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+// The system allocator, counting allocations along the way. neckcheck runs for weeks at a time, so
+// a check that allocates more and more each time, or memory that keeps growing, has to be noticed.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+// How many allocations were made since launch
+pub fn allocations() -> u64 {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+// The memory the process has in RAM, where the OS tells
+#[cfg(target_os = "linux")]
+pub fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn resident_bytes() -> Option<u64> {
+    None
+}
+
+// Tells when memory grows beyond what it settled at after launch, or beyond the cap
+pub struct MemoryMonitor {
+    config: MemoryConfig,
+    started: Instant,
+    // the least memory used during the warm-up, once it is over
+    baseline: Option<u64>,
+    lowest: Option<u64>,
+    // the growth last warned about, so that each further step is warned about once
+    warned_growth: u64,
+    over_cap: bool,
+}
+
+impl MemoryMonitor {
+    pub fn new(config: &MemoryConfig, now: Instant) -> MemoryMonitor {
+        MemoryMonitor {
+            config: config.clone(),
+            started: now,
+            baseline: None,
+            lowest: None,
+            warned_growth: 0,
+            over_cap: false,
+        }
+    }
+
+    // Takes note of the memory in use, returning what is worth a warning
+    pub fn update(&mut self, resident: u64, now: Instant) -> (MemoryUsage, Vec<String>) {
+        let mut warnings = Vec::new();
+        let lowest = self.lowest.map_or(resident, |lowest| lowest.min(resident));
+        self.lowest = Some(lowest);
+        if self.baseline.is_none()
+            && now - self.started >= Duration::from_secs_f64(self.config.warm_up)
+        {
+            self.baseline = Some(lowest);
+        }
+        let growth = self
+            .baseline
+            .map_or(0, |baseline| resident.saturating_sub(baseline));
+        let step = (self.config.warn_growth * MB as f64) as u64;
+        if step > 0 && growth >= self.warned_growth + step {
+            self.warned_growth = growth / step * step;
+            warnings.push(format!(
+                "Memory has grown by {} MB since launch, to {} MB; neckcheck may be leaking",
+                growth / MB,
+                resident / MB
+            ));
+        }
+        if let Some(cap) = self.config.max_mb {
+            let over = resident > (cap * MB as f64) as u64;
+            if over && !self.over_cap {
+                warnings.push(format!(
+                    "Using {} MB, over the {} MB cap",
+                    resident / MB,
+                    cap
+                ));
+            }
+            self.over_cap = over;
+        }
+        let usage = MemoryUsage {
+            resident_mb: resident as f64 / MB as f64,
+            growth_mb: growth as f64 / MB as f64,
+            allocations: allocations(),
+        };
+        (usage, warnings)
+    }
+}
+
+// Checks the memory in use every so often until shutdown, publishing it for status displays
+pub async fn run(config: MemoryConfig, events: EventBus, shutdown: CancellationToken) {
+    let mut monitor = MemoryMonitor::new(&config, Instant::now());
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(config.interval));
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let Some(resident) = resident_bytes() else {
+            return;
+        };
+        let (usage, warnings) = monitor.update(resident, Instant::now());
+        for warning in warnings {
+            println!("{}", warning);
+        }
+        events.publish(PostureEvent::Memory(usage));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn growth_after_the_warm_up_is_warned_about_once_per_step() {
+        let start = Instant::now();
+        let at = |minutes: u64| start + Duration::from_secs(minutes * 60);
+        let config = MemoryConfig {
+            warm_up: 600.0,
+            warn_growth: 50.0,
+            max_mb: Some(200.0),
+            ..MemoryConfig::default()
+        };
+        let mut monitor = MemoryMonitor::new(&config, start);
+        // loading models takes a while to settle
+        assert!(monitor.update(120 * MB, at(0)).1.is_empty());
+        assert!(monitor.update(80 * MB, at(5)).1.is_empty());
+        let (usage, warnings) = monitor.update(90 * MB, at(10));
+        assert!(warnings.is_empty());
+        assert_eq!(usage.growth_mb, 10.0);
+
+        assert_eq!(monitor.update(140 * MB, at(60)).1.len(), 1);
+        assert!(monitor.update(150 * MB, at(120)).1.is_empty());
+        assert_eq!(monitor.update(190 * MB, at(180)).1.len(), 1);
+        // over the cap
+        let (_, warnings) = monitor.update(210 * MB, at(240));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("cap"));
+        assert!(monitor.update(220 * MB, at(300)).1.is_empty());
+    }
+}
@@ -0,0 +1,1370 @@
+mod saved;
+
+pub use saved::CalibrationStore;
+
+use std::fmt;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::Timelike;
+use console::Term;
+use serde::{Deserialize, Serialize};
+
+use crate::alert::Severity;
+use crate::body::{is_slouching, SlouchBaseline, SlouchMetrics};
+use crate::config::{
+    BlinkRateConfig, DrowsinessConfig, FaceDriftConfig, NeckAngleConfig, SlouchConfig,
+};
+use crate::dataset::DatasetRecorder;
+use crate::detector::Detector;
+use crate::dump::{DebugDump, Transition};
+use crate::events::{Metrics, PostureEvent};
+use crate::i18n::tr;
+use crate::landmarks::{estimate_head_pose, eye_aspect_ratio, BlinkCounter};
+use crate::memory;
+use crate::output::{annotate, OverlayStyle, VirtualCamera};
+use crate::posture::{
+    sensitivity_at, Check, Conditions, DutyCycle, JumpFilter, Posture, PostureStateMachine,
+    PostureThresholds, Proximity, Rule, SensitivityRange, SustainedCondition, DEFAULT_GRACE_PERIOD,
+};
+use crate::privacy::PrivacyMode;
+use crate::proximity::Observation;
+use crate::proximity::{CameraIdentity, ProximitySample, ProximitySource};
+use crate::script::{Input, PostureScript};
+use crate::snapshot::SnapshotRecorder;
+use crate::source::{CameraActivity, CaptureError};
+use crate::timelapse::TimelapseRecorder;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NeckCheckCalibration {
+    // the measurement in the bad posture position: the maximum allowed size of the face detection
+    // box, or the minimum distance for inputs that can measure it
+    limit: Proximity,
+    // the camera it was taken with, for inputs that use one
+    camera: Option<CameraIdentity>,
+}
+
+impl NeckCheckCalibration {
+    // The limit for frames from `camera`. A face size is held as a fraction of the frame it was
+    // measured in, so a calibration taken at 1080p still holds when the camera comes up at 720p.
+    fn limit_for(&self, camera: Option<&CameraIdentity>) -> Proximity {
+        match (self.limit, &self.camera, camera) {
+            (Proximity::FaceSize { width, height }, Some(calibrated), Some(camera)) => {
+                Proximity::FaceSize {
+                    width: (width as f32 / calibrated.width as f32 * camera.width as f32).round()
+                        as u32,
+                    height: (height as f32 / calibrated.height as f32 * camera.height as f32)
+                        .round() as u32,
+                }
+            }
+            (limit, _, _) => limit,
+        }
+    }
+
+    // Whether this calibration holds for frames from `camera`
+    fn fits(&self, camera: Option<&CameraIdentity>) -> bool {
+        match (&self.camera, camera) {
+            (Some(calibrated), Some(camera)) => calibrated.same_camera(camera),
+            (calibrated, camera) => calibrated.as_ref() == camera,
+        }
+    }
+}
+
+pub struct NeckCheck {
+    source: Box<dyn ProximitySource>,
+    calibration: Option<NeckCheckCalibration>,
+    // every camera's calibration, when there are several; the one above is in use
+    calibrations: Vec<NeckCheckCalibration>,
+    // the camera of the previous check, when there are several
+    camera: Option<String>,
+    // and the camera and resolution it was taken with
+    identity: Option<CameraIdentity>,
+    machine: Option<PostureStateMachine>,
+    virtual_camera: Option<VirtualCamera>,
+    snapshots: Option<SnapshotRecorder>,
+    timelapse: Option<TimelapseRecorder>,
+    debug_dump: Option<DebugDump>,
+    dataset: Option<DatasetRecorder>,
+    // the frame it saved of the latest change of posture
+    last_debug_frame: Option<PathBuf>,
+    privacy: PrivacyMode,
+    grace_period: Duration,
+    duty_cycle: Option<DutyCycle>,
+    rule: Rule,
+    // has the last word on each check after the rule, and is told of what the checks caused
+    script: Option<Box<dyn PostureScript>>,
+    // how serious it found the latest check, if it said
+    severity: Option<Severity>,
+    // measurements that jumped too far to be real are kept from the state machine
+    outliers: Option<JumpFilter>,
+    // faces narrower than this fraction of the calibrated one are left out as background
+    min_face_size: Option<f32>,
+    // how much more lenient than the calibration to be, see `PostureStateMachine::set_limit_scale`
+    limit_scale: f32,
+    // stricter or more lenient limits by time of day
+    schedule: Vec<SensitivityRange>,
+    neck_angle: Option<NeckAngleMonitor>,
+    drowsiness: Option<DrowsinessMonitor>,
+    blink_rate: Option<BlinkRateMonitor>,
+    slouch: Option<SlouchMonitor>,
+    face_drift: Option<FaceDriftMonitor>,
+    // monotonic and wall clock time of the previous check, to notice suspend/resume
+    last_check: Option<(Instant, SystemTime)>,
+    // the measurement of the previous check
+    last_metric: Option<Proximity>,
+    // whether the previous check saw more than one face
+    others_present: bool,
+    // whether someone other than the enrolled user was alone at the computer at the previous check
+    guest: bool,
+    // how long the source took to take it, and how long after it was captured it was decided on
+    last_latency: Duration,
+    last_decision_latency: Duration,
+    last_allocations: u64,
+    // how the virtual camera and timelapse frames are annotated
+    overlay: OverlayStyle,
+}
+
+// a gap between checks longer than this means the machine was asleep
+const SUSPEND_GAP: Duration = Duration::from_secs(30);
+// samples the source itself doesn't trust this much are ignored
+const MIN_CONFIDENCE: f32 = 0.5;
+// the calibration position is measured this many more times, this far apart
+const VALIDATION_SAMPLES: usize = 10;
+const VALIDATION_INTERVAL: Duration = Duration::from_millis(200);
+// the share of validation samples that have to measure something
+const MIN_DETECTION_RATE: f32 = 0.8;
+// the largest acceptable spread of the validation samples, as standard deviation over mean
+const MAX_VARIATION: f32 = 0.1;
+
+#[derive(Debug, PartialEq)]
+pub enum CalibrationProblem {
+    TooFewDetections(usize, usize),
+    Unstable(f32),
+}
+
+impl fmt::Display for CalibrationProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            CalibrationProblem::TooFewDetections(detected, total) => tr!(
+                "calibration-too-few-detections",
+                detected = *detected,
+                total = *total
+            ),
+            CalibrationProblem::Unstable(variation) => tr!(
+                "calibration-unstable",
+                variation = (variation * 100.0).round()
+            ),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for CalibrationProblem {}
+
+// Checks that the measurements of the calibration position agree with each other, returning
+// their median as the calibration
+fn assess_calibration(samples: &[Option<Proximity>]) -> Result<Proximity, CalibrationProblem> {
+    let first = samples.iter().flatten().next().copied();
+    // measurements of a different kind (e.g. a frame without depth) count as missing
+    let same_kind = |m: &&Proximity| {
+        first.is_some_and(|first| std::mem::discriminant(*m) == std::mem::discriminant(&first))
+    };
+    let measured: Vec<Proximity> = samples
+        .iter()
+        .flatten()
+        .filter(same_kind)
+        .copied()
+        .collect();
+    if (measured.len() as f32) < samples.len() as f32 * MIN_DETECTION_RATE || measured.is_empty() {
+        return Err(CalibrationProblem::TooFewDetections(
+            measured.len(),
+            samples.len(),
+        ));
+    }
+    // each dimension is judged on its own
+    let dimensions: Vec<Vec<f32>> = match first.unwrap() {
+        Proximity::Distance(_) => vec![measured
+            .iter()
+            .map(|m| match m {
+                Proximity::Distance(distance) => *distance,
+                _ => unreachable!(),
+            })
+            .collect()],
+        Proximity::FaceSize { .. } => {
+            let (widths, heights) = measured
+                .iter()
+                .map(|m| match m {
+                    Proximity::FaceSize { width, height } => (*width as f32, *height as f32),
+                    _ => unreachable!(),
+                })
+                .unzip();
+            vec![widths, heights]
+        }
+    };
+    let mut medians = Vec::new();
+    for mut values in dimensions {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32;
+        let variation = if mean > 0.0 {
+            variance.sqrt() / mean
+        } else {
+            0.0
+        };
+        if variation > MAX_VARIATION {
+            return Err(CalibrationProblem::Unstable(variation));
+        }
+        values.sort_unstable_by(f32::total_cmp);
+        medians.push(values[values.len() / 2]);
+    }
+    Ok(match first.unwrap() {
+        Proximity::Distance(_) => Proximity::Distance(medians[0]),
+        Proximity::FaceSize { .. } => Proximity::FaceSize {
+            width: medians[0] as u32,
+            height: medians[1] as u32,
+        },
+    })
+}
+
+fn print_calibration(limit: Proximity) {
+    match limit {
+        Proximity::Distance(distance) => println!(
+            "{}",
+            tr!("calibration-done-distance", distance = distance.round())
+        ),
+        Proximity::FaceSize { width, height } => println!(
+            "{}",
+            tr!("calibration-done-face-size", width = width, height = height)
+        ),
+    }
+}
+
+impl NeckCheck {
+    // The virtual camera, snapshots and timelapse only do anything with sources that produce
+    // frames
+    pub fn new(source: Box<dyn ProximitySource>, privacy: PrivacyMode) -> NeckCheck {
+        NeckCheck {
+            source,
+            calibration: None,
+            calibrations: Vec::new(),
+            camera: None,
+            identity: None,
+            machine: None,
+            virtual_camera: None,
+            snapshots: None,
+            timelapse: None,
+            debug_dump: None,
+            dataset: None,
+            last_debug_frame: None,
+            privacy,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            duty_cycle: None,
+            rule: Rule::default(),
+            script: None,
+            severity: None,
+            outliers: None,
+            min_face_size: None,
+            limit_scale: 1.0,
+            schedule: Vec::new(),
+            neck_angle: None,
+            drowsiness: None,
+            blink_rate: None,
+            slouch: None,
+            face_drift: None,
+            last_check: None,
+            last_metric: None,
+            others_present: false,
+            guest: false,
+            last_latency: Duration::ZERO,
+            last_decision_latency: Duration::ZERO,
+            last_allocations: 0,
+            overlay: OverlayStyle::default(),
+        }
+    }
+
+    pub fn set_virtual_camera(&mut self, virtual_camera: VirtualCamera) {
+        self.virtual_camera = Some(virtual_camera);
+    }
+
+    pub fn set_snapshots(&mut self, snapshots: SnapshotRecorder) {
+        self.snapshots = Some(snapshots);
+    }
+
+    pub fn set_timelapse(&mut self, timelapse: TimelapseRecorder) {
+        self.timelapse = Some(timelapse);
+    }
+
+    pub fn set_debug_dump(&mut self, debug_dump: DebugDump) {
+        self.debug_dump = Some(debug_dump);
+    }
+
+    pub fn set_dataset(&mut self, dataset: DatasetRecorder) {
+        self.dataset = Some(dataset);
+    }
+
+    pub fn set_overlay_style(&mut self, overlay: OverlayStyle) {
+        self.overlay = overlay;
+    }
+
+    // pub fn with_calibration(
+    //     webcam: WebCam,
+    //     detector: FaceDetector,
+    //     calibration: NeckCheckCalibration,
+    // ) -> NeckCheck {
+    //     NeckCheck {
+    //         webcam,
+    //         detector,
+    //         calibration: Some(calibration),
+    //     }
+    // }
+
+    pub fn calibrate(&mut self) {
+        let term = Term::stdout();
+        let _ = term.write_line(&tr!("calibration-begin"));
+        let _ = term.read_line();
+        let limit = loop {
+            let _ = term.write_line(&tr!("calibration-bad-posture"));
+            let _ = term.read_line();
+            if let Some(limit) = self.measure_position() {
+                break limit;
+            }
+        };
+        self.set_calibration(NeckCheckCalibration {
+            limit,
+            camera: self.source.camera_identity(),
+        });
+        print_calibration(limit);
+    }
+
+    // Measures the position the user is holding, or returns None (after telling them why) to try
+    // again
+    pub fn measure_position(&mut self) -> Option<Proximity> {
+        let first = self.calibration_measurement()?;
+        // a calibration taken from a single flickering detection would cause constant false
+        // alerts, so it is only accepted if it holds steady for a moment
+        println!("{}", tr!("calibration-hold-still"));
+        let mut samples = vec![Some(first)];
+        samples.extend(self.validation_samples());
+        match assess_calibration(&samples) {
+            Ok(median) => Some(median),
+            Err(problem) => {
+                println!(
+                    "{}",
+                    tr!("calibration-try-again", problem = problem.to_string())
+                );
+                None
+            }
+        }
+    }
+
+    // Uses the saved calibration of the camera in use, returning false if there is none. One
+    // taken with another camera, or at another resolution, would make for wrong limits, so it is
+    // only used if `any_camera` is set.
+    pub fn restore_calibration(&mut self, any_camera: bool) -> bool {
+        if self.calibrations.is_empty() {
+            return false;
+        }
+        // a sample tells which camera is in use
+        if let Ok(mut sample) = self.source.sample() {
+            if let Some(observation) = &mut sample.observation {
+                self.privacy.scrub(&mut observation.frame);
+            }
+        }
+        let identity = self.source.camera_identity();
+        let describe = |identity: &Option<CameraIdentity>| match identity {
+            Some(identity) => identity.to_string(),
+            None => tr!("calibration-unknown-camera"),
+        };
+        let own = self
+            .calibrations
+            .iter()
+            .rev()
+            .find(|calibration| calibration.fits(identity.as_ref()))
+            .cloned();
+        if let Some(calibration) = own {
+            println!(
+                "{}",
+                tr!("calibration-restored", camera = describe(&identity))
+            );
+            self.apply_calibration(calibration);
+            return true;
+        }
+        let latest = self.calibrations[self.calibrations.len() - 1].clone();
+        let (saved, camera) = (describe(&latest.camera), describe(&identity));
+        if !any_camera {
+            println!(
+                "{}",
+                tr!(
+                    "calibration-camera-mismatch",
+                    saved = saved,
+                    camera = camera
+                )
+            );
+            return false;
+        }
+        println!(
+            "{}",
+            tr!(
+                "calibration-mismatch-ignored",
+                saved = saved,
+                camera = camera
+            )
+        );
+        self.apply_calibration(latest);
+        true
+    }
+
+    // Calibrations from an earlier run, to be picked from by `restore_calibration` and when the
+    // camera changes
+    pub fn set_saved_calibrations(&mut self, calibrations: Vec<NeckCheckCalibration>) {
+        self.calibrations = calibrations;
+    }
+
+    // A measurement of the user working as usual, for observe mode: nothing is alerted on, and
+    // frames are scrubbed straight away
+    pub fn observe(&mut self) -> Result<Option<Proximity>, CaptureError> {
+        let mut sample = self.source.sample()?;
+        let faces = sample.observation.as_ref().map(|o| o.faces.len());
+        if let Some(observation) = &mut sample.observation {
+            self.privacy.scrub(&mut observation.frame);
+        }
+        // with someone else in view the measurement could be theirs
+        if sample.confidence < MIN_CONFIDENCE || faces.is_some_and(|faces| faces > 1) {
+            return Ok(None);
+        }
+        Ok(sample.metric)
+    }
+
+    // Calibrates from an observed limit rather than the interactive prompts
+    pub fn calibrate_to(&mut self, limit: Proximity) {
+        self.set_calibration(NeckCheckCalibration {
+            limit,
+            camera: self.source.camera_identity(),
+        });
+        print_calibration(limit);
+    }
+
+    fn validation_samples(&mut self) -> Vec<Option<Proximity>> {
+        (0..VALIDATION_SAMPLES)
+            .map(|_| {
+                thread::sleep(VALIDATION_INTERVAL);
+                match self.source.sample() {
+                    Ok(mut sample) => {
+                        if let Some(observation) = &mut sample.observation {
+                            self.privacy.scrub(&mut observation.frame);
+                        }
+                        sample.metric
+                    }
+                    Err(_) => None,
+                }
+            })
+            .collect()
+    }
+
+    // A single measurement to calibrate with, or None (after telling the user why) to try again
+    fn calibration_measurement(&mut self) -> Option<Proximity> {
+        let mut sample = match self.source.sample() {
+            Ok(sample) => sample,
+            Err(e) => {
+                println!("{}", e);
+                return None;
+            }
+        };
+        let faces = sample.observation.as_ref().map(|o| o.faces.len());
+        if let Some(observation) = &mut sample.observation {
+            self.privacy.scrub(&mut observation.frame);
+        }
+        if faces.is_some_and(|faces| faces > 1) {
+            println!("{}", tr!("calibration-several-faces"));
+            return None;
+        }
+        if sample.metric.is_none() {
+            match faces {
+                Some(_) => println!("{}", tr!("calibration-no-face")),
+                None => println!("{}", tr!("calibration-nobody")),
+            }
+            return None;
+        }
+        sample.metric
+    }
+
+    // Runs a single check, returning the posture events it caused
+    pub fn check(&mut self) -> Result<Vec<PostureEvent>, CaptureError> {
+        let started = Instant::now();
+        let allocations = memory::allocations();
+        let sample = self.source.sample()?;
+        self.last_latency = started.elapsed();
+        let captured = sample.timestamp;
+        let switched = self.follow_camera();
+        let events = switched.into_iter().chain(self.process(sample)).collect();
+        self.last_decision_latency = Instant::now().saturating_duration_since(captured);
+        self.last_allocations = memory::allocations() - allocations;
+        Ok(events)
+    }
+
+    // Notices when a source with several cameras moved on to another one, and switches to that
+    // camera's calibration if it has one of its own
+    fn follow_camera(&mut self) -> Option<PostureEvent> {
+        let identity = self.source.camera_identity();
+        if identity.is_some() && identity != self.identity {
+            self.identity = identity.clone();
+            let own = self
+                .calibrations
+                .iter()
+                .rev()
+                .find(|calibration| calibration.fits(identity.as_ref()))
+                .cloned();
+            if let Some(calibration) = own {
+                if self.calibration.as_ref().map(|c| &c.camera) != Some(&identity) {
+                    if let Some(identity) = &identity {
+                        println!("Using the calibration for {}", identity);
+                    }
+                    self.apply_calibration(calibration);
+                }
+            }
+        }
+        let camera = self.source.active_camera();
+        if camera == self.camera {
+            return None;
+        }
+        let previous = std::mem::replace(&mut self.camera, camera.clone());
+        previous.and(camera).map(PostureEvent::CameraSwitched)
+    }
+
+    fn process(&mut self, sample: ProximitySample) -> Vec<PostureEvent> {
+        let ProximitySample {
+            timestamp,
+            metric,
+            confidence,
+            observation,
+        } = sample;
+        // a guest's posture is none of neckcheck's business, and the user's starts over once they
+        // are back
+        let guest = observation.as_ref().is_some_and(|o| o.guest);
+        let mut events = Vec::new();
+        if guest != self.guest {
+            self.guest = guest;
+            if guest {
+                events.push(PostureEvent::GuestArrived);
+            } else {
+                self.reset();
+                events.push(PostureEvent::GuestLeft);
+            }
+        }
+        if guest {
+            self.last_check = Some((Instant::now(), SystemTime::now()));
+            if let Some(mut observation) = observation {
+                self.privacy.scrub(&mut observation.frame);
+            }
+            return events;
+        }
+        let machine = self.machine.as_mut().expect("No calibration!");
+        let now = Instant::now();
+        let wall_now = SystemTime::now();
+        if let Some((last, last_wall)) = self.last_check {
+            // the monotonic clock stops during suspend on some platforms and not on others
+            let elapsed = now.saturating_duration_since(last);
+            let wall_elapsed = wall_now.duration_since(last_wall).unwrap_or_default();
+            if elapsed > SUSPEND_GAP || wall_elapsed.saturating_sub(elapsed) > SUSPEND_GAP {
+                println!(
+                    "Resumed from suspend, holding off alerts for {:?}",
+                    self.grace_period
+                );
+                machine.start_grace(now);
+            }
+        }
+        self.last_check = Some((now, wall_now));
+        self.last_metric = metric;
+        machine.set_sensitivity(sensitivity_at(&self.schedule, chrono::Local::now().time()));
+        let plausible = |outliers: &mut Option<JumpFilter>| {
+            outliers
+                .as_mut()
+                .is_none_or(|outliers| outliers.plausible(metric, timestamp))
+        };
+        // the other monitors first, as the decision rule may combine what they see with the
+        // measurement; their events still come after the posture's
+        let mut monitored = Vec::new();
+        let mut conditions = Conditions::default();
+        if let Some(observation) = &observation {
+            if let Some(neck_angle) = &mut self.neck_angle {
+                monitored.extend(neck_angle.update(observation, timestamp));
+                conditions.tilt_down = neck_angle.holds;
+            }
+            if let Some(drowsiness) = &mut self.drowsiness {
+                monitored.extend(drowsiness.update(observation, timestamp));
+            }
+            if let Some(blink_rate) = &mut self.blink_rate {
+                monitored.extend(blink_rate.update(observation, timestamp));
+            }
+            if let Some(slouch) = &mut self.slouch {
+                monitored.extend(slouch.update(observation, timestamp));
+                conditions.slouching = slouch.holds;
+            }
+            if let Some(face_drift) = &mut self.face_drift {
+                monitored.extend(face_drift.update(observation, timestamp));
+                conditions.low_position = face_drift.holds;
+            }
+        }
+        // where the middle of the face is in the frame, for the script
+        let face = observation.as_ref().and_then(|observation| {
+            let (width, height) = observation.frame.dimensions();
+            observation.faces.first().map(|face| {
+                (
+                    (face.left() as f32 + face.width() as f32 / 2.0) / width as f32,
+                    (face.top() as f32 + face.height() as f32 / 2.0) / height as f32,
+                )
+            })
+        });
+        self.severity = None;
+        let transitions = if confidence >= MIN_CONFIDENCE && plausible(&mut self.outliers) {
+            match &mut self.script {
+                Some(script) => {
+                    let hour = chrono::Local::now().hour();
+                    let severity = &mut self.severity;
+                    let mut judge = |check: &Check| {
+                        let judgement = script.check(&Input::new(check, face, hour));
+                        *severity = judgement.severity;
+                        judgement.too_close
+                    };
+                    machine.update_judged(metric, conditions, Some(&mut judge), timestamp)
+                }
+                None => machine.update_with(metric, conditions, timestamp),
+            }
+        } else {
+            Vec::new()
+        };
+        events.extend(transitions.iter().cloned());
+        events.extend(monitored);
+        if let Some(script) = &mut self.script {
+            for event in &events {
+                script.transition(event);
+            }
+        }
+        let ok = machine.state() != Posture::TooClose;
+        self.others_present = observation.as_ref().is_some_and(|o| o.faces.len() > 1);
+        let mut observation = match observation {
+            Some(observation) => observation,
+            None => return events,
+        };
+        let threshold = match self
+            .calibration
+            .as_ref()
+            .map(|c| c.limit_for(self.identity.as_ref()))
+        {
+            Some(Proximity::FaceSize { width, height }) => Some((width, height)),
+            _ => None,
+        };
+        let (frame, faces) = (&mut observation.frame, &observation.faces);
+        if let Some(debug_dump) = self.debug_dump.as_ref().filter(|_| !transitions.is_empty()) {
+            let transition = Transition {
+                events: &transitions,
+                frame: frame.rgb(),
+                candidates: &observation.candidates,
+                chosen: faces.first().copied(),
+                threshold,
+                metric,
+                ok,
+            };
+            self.last_debug_frame = debug_dump.save(&transition, &self.overlay);
+        }
+        if let (Some(dataset), Some(face)) = (&mut self.dataset, faces.first()) {
+            dataset.record(frame.rgb(), *face, observation.score, !ok, timestamp);
+        }
+        if let Some(snapshots) = &self.snapshots {
+            // only the first frame of a bad posture episode is kept
+            if events.contains(&PostureEvent::TooClose) {
+                snapshots.save(frame.rgb(), faces);
+            }
+        }
+        if let Some(timelapse) = &mut self.timelapse {
+            timelapse.record(frame.rgb(), faces, threshold, ok, &self.overlay);
+        }
+        if let Some(virtual_camera) = &mut self.virtual_camera {
+            annotate(frame.rgb(), faces, threshold, ok, &self.overlay);
+            if let Err(e) = virtual_camera.write(frame.rgb()) {
+                println!("{}", e);
+            }
+        }
+        self.privacy.scrub(frame);
+        events
+    }
+
+    // Swaps the face detector of a camera input, keeping the camera running
+    pub fn set_detector(&mut self, detector: Box<dyn Detector>) -> Result<(), String> {
+        self.source.set_detector(detector)
+    }
+
+    // Detects faces at no more than this fraction of the frame's resolution, e.g. to save power
+    pub fn limit_detection_scale(&mut self, max: f32) {
+        self.source.limit_detection_scale(max);
+    }
+
+    // How long after launch or resume from suspend before alerts may fire
+    pub fn set_grace_period(&mut self, grace_period: Duration) {
+        self.grace_period = grace_period;
+    }
+
+    pub fn set_outliers(&mut self, outliers: JumpFilter) {
+        self.outliers = Some(outliers);
+    }
+
+    // Ignores faces far smaller than the calibrated one, e.g. people across the room, from the next
+    // calibration on
+    pub fn set_min_face_size(&mut self, fraction: f32) {
+        self.min_face_size = Some(fraction);
+    }
+
+    // What counts as bad posture, from the next calibration on
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+    }
+
+    // Lets a script overrule the rule, and tells it of every event
+    pub fn set_script(&mut self, script: Box<dyn PostureScript>) {
+        self.script = Some(script);
+    }
+
+    // Too close once the user was for part of a recent window, rather than for a while in a row
+    pub fn set_duty_cycle(&mut self, duty_cycle: DutyCycle) {
+        self.duty_cycle = Some(duty_cycle);
+    }
+
+    // Calibrates the camera the calibration was taken with, and uses it from now on
+    pub fn set_calibration(&mut self, calibration: NeckCheckCalibration) {
+        self.calibrations
+            .retain(|other| other.camera != calibration.camera);
+        self.calibrations.push(calibration.clone());
+        self.apply_calibration(calibration);
+    }
+
+    fn apply_calibration(&mut self, calibration: NeckCheckCalibration) {
+        let limit = calibration.limit_for(self.source.camera_identity().as_ref());
+        if let (Some(fraction), Proximity::FaceSize { width, .. }) = (self.min_face_size, limit) {
+            self.source
+                .ignore_faces_narrower_than((width as f32 * fraction) as u32);
+        }
+        let mut thresholds = PostureThresholds::new(limit);
+        thresholds.grace_period = self.grace_period;
+        thresholds.duty_cycle = self.duty_cycle;
+        thresholds.rule = self.rule.clone();
+        let mut machine = PostureStateMachine::new(thresholds);
+        machine.set_limit_scale(self.limit_scale);
+        self.machine = Some(machine);
+        self.calibration = Some(calibration);
+    }
+
+    // Alerts on the neck angle too, which needs landmarks in the observations
+    pub fn set_neck_angle(&mut self, config: NeckAngleConfig) {
+        self.neck_angle = Some(NeckAngleMonitor::new(config));
+    }
+
+    // Alerts on eyes that stay closed, which also needs landmarks
+    pub fn set_drowsiness(&mut self, config: DrowsinessConfig) {
+        self.drowsiness = Some(DrowsinessMonitor::new(config));
+    }
+
+    // Counts blinks, which also needs landmarks
+    pub fn set_blink_rate(&mut self, config: BlinkRateConfig) {
+        self.blink_rate = Some(BlinkRateMonitor::new(config));
+    }
+
+    // Alerts on slouching, which needs body keypoints in the observations
+    pub fn set_slouch(&mut self, config: SlouchConfig) {
+        self.slouch = Some(SlouchMonitor::new(config));
+    }
+
+    // Alerts when the face sinks in the frame
+    pub fn set_face_drift(&mut self, config: FaceDriftConfig) {
+        self.face_drift = Some(FaceDriftMonitor::new(config));
+    }
+
+    pub fn set_schedule(&mut self, schedule: Vec<SensitivityRange>) {
+        self.schedule = schedule;
+    }
+
+    pub fn set_limit_scale(&mut self, scale: f32) {
+        self.limit_scale = scale;
+        if let Some(machine) = &mut self.machine {
+            machine.set_limit_scale(scale);
+        }
+    }
+
+    // Every camera's calibration, the one in use last
+    pub fn calibrations(&self) -> Vec<NeckCheckCalibration> {
+        let mut calibrations: Vec<NeckCheckCalibration> = self
+            .calibrations
+            .iter()
+            .filter(|c| self.calibration.as_ref().map(|active| &active.camera) != Some(&c.camera))
+            .cloned()
+            .collect();
+        calibrations.extend(self.calibration.clone());
+        calibrations
+    }
+
+    // The --debug-dump frame of the latest change of posture, if one was saved
+    pub fn last_debug_frame(&self) -> Option<PathBuf> {
+        self.last_debug_frame.clone()
+    }
+
+    // Whether someone else was in view at the latest check, e.g. on a shared computer
+    pub fn others_present(&self) -> bool {
+        self.others_present
+    }
+
+    pub fn is_guest(&self) -> bool {
+        self.guest
+    }
+
+    // The measurements of the latest check, once calibrated
+    pub fn metrics(&self) -> Option<Metrics> {
+        let machine = self.machine.as_ref()?;
+        Some(Metrics {
+            current: self.last_metric,
+            smoothed: machine.smoothed_metric(),
+            limit: machine.limit(),
+            latency_ms: self.last_latency.as_millis().min(u32::MAX as u128) as u32,
+            decision_latency_ms: self.last_decision_latency.as_millis().min(u32::MAX as u128)
+                as u32,
+            detection_scale: self.source.detection_scale(),
+            allocations: self.last_allocations.min(u32::MAX as u64) as u32,
+            severity: self.severity,
+        })
+    }
+
+    // When the camera was switched on and off since this was last asked
+    pub fn take_camera_activity(&mut self) -> Vec<CameraActivity> {
+        self.source.take_camera_activity()
+    }
+
+    pub fn is_calibrated(&self) -> bool {
+        self.machine.is_some()
+    }
+
+    // Forgets the current posture, e.g. after the camera was lost
+    pub fn reset(&mut self) {
+        if let Some(machine) = &mut self.machine {
+            machine.reset();
+        }
+        if let Some(outliers) = &mut self.outliers {
+            outliers.forget();
+        }
+        if let Some(neck_angle) = &mut self.neck_angle {
+            neck_angle.condition.reset();
+        }
+        if let Some(drowsiness) = &mut self.drowsiness {
+            drowsiness.condition.reset();
+        }
+        if let Some(blink_rate) = &mut self.blink_rate {
+            blink_rate.window_start = None;
+        }
+        if let Some(slouch) = &mut self.slouch {
+            slouch.condition.reset();
+        }
+        if let Some(face_drift) = &mut self.face_drift {
+            face_drift.condition.reset();
+        }
+    }
+}
+
+// Tracks how far the head is tilted forward, from the head pose
+struct NeckAngleMonitor {
+    config: NeckAngleConfig,
+    condition: SustainedCondition,
+    // whether the head was tilted down too far at the latest check
+    holds: bool,
+}
+
+impl NeckAngleMonitor {
+    fn new(config: NeckAngleConfig) -> NeckAngleMonitor {
+        let condition = SustainedCondition::new(Duration::from_secs_f64(config.sustained_for));
+        NeckAngleMonitor {
+            config,
+            condition,
+            holds: false,
+        }
+    }
+
+    fn update(&mut self, observation: &Observation, now: Instant) -> Option<PostureEvent> {
+        let (width, height) = observation.frame.dimensions();
+        let angle = observation
+            .landmarks
+            .as_ref()
+            .and_then(|landmarks| estimate_head_pose(landmarks, width, height))
+            .map(|pose| pose.pitch + self.config.offset);
+        // without a face there is no neck to worry about
+        self.holds = angle.is_some_and(|angle| angle > self.config.max_angle);
+        // running only for the decision rule
+        if !self.config.enabled {
+            return None;
+        }
+        match self.condition.update(self.holds, now)? {
+            true => Some(PostureEvent::NeckTooSteep),
+            false => Some(PostureEvent::NeckRecovered),
+        }
+    }
+}
+
+// Tracks whether the eyes stay closed, from their aspect ratio
+struct DrowsinessMonitor {
+    config: DrowsinessConfig,
+    condition: SustainedCondition,
+}
+
+impl DrowsinessMonitor {
+    fn new(config: DrowsinessConfig) -> DrowsinessMonitor {
+        let condition = SustainedCondition::new(Duration::from_secs_f64(config.sustained_for));
+        DrowsinessMonitor { config, condition }
+    }
+
+    fn update(&mut self, observation: &Observation, now: Instant) -> Option<PostureEvent> {
+        let ratio = observation.landmarks.as_ref().and_then(eye_aspect_ratio);
+        let closed = ratio.is_some_and(|ratio| ratio < self.config.closed_below);
+        match self.condition.update(closed, now)? {
+            true => Some(PostureEvent::Drowsy),
+            false => Some(PostureEvent::Awake),
+        }
+    }
+}
+
+// Compares the shoulders with how they were at the start of the session
+struct SlouchMonitor {
+    config: SlouchConfig,
+    baseline: SlouchBaseline,
+    condition: SustainedCondition,
+    holds: bool,
+}
+
+impl SlouchMonitor {
+    fn new(config: SlouchConfig) -> SlouchMonitor {
+        SlouchMonitor {
+            baseline: SlouchBaseline::new(config.baseline_samples),
+            condition: SustainedCondition::new(Duration::from_secs_f64(config.sustained_for)),
+            holds: false,
+            config,
+        }
+    }
+
+    fn update(&mut self, observation: &Observation, now: Instant) -> Option<PostureEvent> {
+        let metrics = observation
+            .body
+            .as_ref()
+            .and_then(SlouchMetrics::from_keypoints);
+        self.holds = match metrics {
+            Some(metrics) => match self.baseline.update(metrics) {
+                Some(baseline) => is_slouching(metrics, baseline, self.config.tolerance),
+                None => false,
+            },
+            None => false,
+        };
+        if !self.config.enabled {
+            return None;
+        }
+        match self.condition.update(self.holds, now)? {
+            true => Some(PostureEvent::Slouching),
+            false => Some(PostureEvent::SlouchRecovered),
+        }
+    }
+}
+
+// Compares the height of the face in the frame with where it usually is
+struct FaceDriftMonitor {
+    config: FaceDriftConfig,
+    // the vertical centres of the first faces, until the baseline is known
+    samples: Vec<f32>,
+    baseline: Option<f32>,
+    condition: SustainedCondition,
+    holds: bool,
+}
+
+impl FaceDriftMonitor {
+    fn new(config: FaceDriftConfig) -> FaceDriftMonitor {
+        FaceDriftMonitor {
+            samples: Vec::new(),
+            baseline: None,
+            condition: SustainedCondition::new(Duration::from_secs_f64(config.sustained_for)),
+            holds: false,
+            config,
+        }
+    }
+
+    fn update(&mut self, observation: &Observation, now: Instant) -> Option<PostureEvent> {
+        // only a single face is surely the user
+        self.holds = match observation.faces.as_slice() {
+            [face] => {
+                let centre = face.top() as f32 + face.height() as f32 / 2.0;
+                match self.baseline {
+                    Some(baseline) => {
+                        (centre - baseline) / face.height() as f32 > self.config.max_drop
+                    }
+                    None => {
+                        self.learn(centre);
+                        false
+                    }
+                }
+            }
+            _ => false,
+        };
+        if !self.config.enabled {
+            return None;
+        }
+        match self.condition.update(self.holds, now)? {
+            true => Some(PostureEvent::Sinking),
+            false => Some(PostureEvent::SinkingRecovered),
+        }
+    }
+
+    fn learn(&mut self, centre: f32) {
+        self.samples.push(centre);
+        if self.samples.len() >= self.config.baseline_samples.max(1) {
+            self.samples.sort_unstable_by(f32::total_cmp);
+            self.baseline = Some(self.samples[self.samples.len() / 2]);
+            self.samples.clear();
+        }
+    }
+}
+
+// Counts blinks over fixed windows of time
+struct BlinkRateMonitor {
+    config: BlinkRateConfig,
+    counter: BlinkCounter,
+    // when the current window started, and the blinks in it so far
+    window_start: Option<Instant>,
+    blinks: u32,
+    // whether the rate was too low at the end of the last window
+    low: bool,
+}
+
+impl BlinkRateMonitor {
+    fn new(config: BlinkRateConfig) -> BlinkRateMonitor {
+        BlinkRateMonitor {
+            counter: BlinkCounter::new(config.closed_below),
+            config,
+            window_start: None,
+            blinks: 0,
+            low: false,
+        }
+    }
+
+    fn update(&mut self, observation: &Observation, now: Instant) -> Vec<PostureEvent> {
+        let ratio = match observation.landmarks.as_ref().and_then(eye_aspect_ratio) {
+            Some(ratio) => ratio,
+            None => {
+                // the rate only means something over a window spent looking at the screen
+                self.window_start = None;
+                return Vec::new();
+            }
+        };
+        let start = *self.window_start.get_or_insert_with(|| {
+            self.blinks = 0;
+            now
+        });
+        if self.counter.update(ratio) {
+            self.blinks += 1;
+        }
+        let window = Duration::from_secs_f64(self.config.window);
+        if now.saturating_duration_since(start) < window {
+            return Vec::new();
+        }
+        self.window_start = None;
+        let per_minute = self.blinks as f32 * 60.0 / window.as_secs_f32();
+        let mut events = vec![PostureEvent::BlinkRate {
+            blinks: self.blinks,
+            seconds: window.as_secs() as u32,
+        }];
+        let low = per_minute < self.config.min_per_minute;
+        if low != self.low {
+            self.low = low;
+            events.push(match low {
+                true => PostureEvent::LowBlinkRate,
+                false => PostureEvent::BlinkRateRecovered,
+            });
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    // Plays back a fixed list of measurements, one per check
+    struct Scripted {
+        samples: VecDeque<ProximitySample>,
+    }
+
+    impl ProximitySource for Scripted {
+        fn sample(&mut self) -> Result<ProximitySample, CaptureError> {
+            self.samples
+                .pop_front()
+                .ok_or_else(|| CaptureError::SensorRead("script finished".to_string()))
+        }
+    }
+
+    // Samples from whichever camera the script says
+    struct Cameras {
+        samples: VecDeque<&'static str>,
+        current: Option<String>,
+    }
+
+    impl ProximitySource for Cameras {
+        fn sample(&mut self) -> Result<ProximitySample, CaptureError> {
+            let camera = self
+                .samples
+                .pop_front()
+                .ok_or_else(|| CaptureError::SensorRead("script finished".to_string()))?;
+            self.current = Some(camera.to_string());
+            Ok(ProximitySample {
+                timestamp: Instant::now(),
+                metric: Some(Proximity::Distance(60.0)),
+                confidence: 1.0,
+                observation: None,
+            })
+        }
+
+        fn active_camera(&self) -> Option<String> {
+            self.current.clone()
+        }
+
+        fn camera_identity(&self) -> Option<CameraIdentity> {
+            self.current.as_deref().map(identity)
+        }
+    }
+
+    fn identity(camera: &str) -> CameraIdentity {
+        CameraIdentity {
+            name: camera.to_string(),
+            width: 640,
+            height: 480,
+        }
+    }
+
+    #[test]
+    fn each_camera_uses_its_own_calibration() {
+        let source = Cameras {
+            samples: VecDeque::from(["laptop", "laptop", "desk", "laptop"]),
+            current: None,
+        };
+        let mut neckcheck = NeckCheck::new(Box::new(source), PrivacyMode::Normal);
+        for (camera, limit) in [("laptop", 50.0), ("desk", 40.0)] {
+            neckcheck.set_calibration(NeckCheckCalibration {
+                limit: Proximity::Distance(limit),
+                camera: Some(identity(camera)),
+            });
+        }
+        let limit = |neckcheck: &NeckCheck| neckcheck.metrics().unwrap().limit;
+        assert!(!neckcheck
+            .check()
+            .unwrap()
+            .contains(&PostureEvent::CameraSwitched("laptop".to_string())));
+        assert_eq!(limit(&neckcheck), Proximity::Distance(50.0));
+        neckcheck.check().unwrap();
+        assert!(neckcheck
+            .check()
+            .unwrap()
+            .contains(&PostureEvent::CameraSwitched("desk".to_string())));
+        assert_eq!(limit(&neckcheck), Proximity::Distance(40.0));
+        assert!(neckcheck
+            .check()
+            .unwrap()
+            .contains(&PostureEvent::CameraSwitched("laptop".to_string())));
+        assert_eq!(neckcheck.calibrations().len(), 2);
+        assert_eq!(neckcheck.calibrations()[1].camera, Some(identity("laptop")));
+    }
+
+    #[test]
+    fn face_size_calibrations_hold_at_other_resolutions() {
+        let at = |width, height| CameraIdentity {
+            name: "laptop".to_string(),
+            width,
+            height,
+        };
+        let calibration = NeckCheckCalibration {
+            limit: Proximity::FaceSize {
+                width: 300,
+                height: 300,
+            },
+            camera: Some(at(1920, 1080)),
+        };
+        assert!(calibration.fits(Some(&at(1280, 720))));
+        assert_eq!(
+            calibration.limit_for(Some(&at(1280, 720))),
+            Proximity::FaceSize {
+                width: 200,
+                height: 200
+            }
+        );
+        // a 4:3 mode crops the frame differently
+        assert!(!calibration.fits(Some(&at(640, 480))));
+    }
+
+    #[test]
+    fn a_calibration_from_another_camera_is_only_used_when_asked() {
+        let saved = vec![NeckCheckCalibration {
+            limit: Proximity::Distance(50.0),
+            camera: Some(identity("desk")),
+        }];
+        let source = Cameras {
+            samples: VecDeque::from(["laptop", "laptop"]),
+            current: None,
+        };
+        let mut neckcheck = NeckCheck::new(Box::new(source), PrivacyMode::Normal);
+        neckcheck.set_saved_calibrations(saved.clone());
+        assert!(!neckcheck.restore_calibration(false));
+        assert!(!neckcheck.is_calibrated());
+        assert!(neckcheck.restore_calibration(true));
+        assert!(neckcheck.is_calibrated());
+
+        let source = Cameras {
+            samples: VecDeque::from(["desk"]),
+            current: None,
+        };
+        let mut neckcheck = NeckCheck::new(Box::new(source), PrivacyMode::Normal);
+        neckcheck.set_saved_calibrations(saved);
+        assert!(neckcheck.restore_calibration(false));
+    }
+
+    fn neckcheck(samples: Vec<(f32, Option<Proximity>, f32)>) -> NeckCheck {
+        let start = Instant::now();
+        let samples = samples
+            .into_iter()
+            .map(|(secs, metric, confidence)| ProximitySample {
+                timestamp: start + Duration::from_secs_f32(secs),
+                metric,
+                confidence,
+                observation: None,
+            })
+            .collect();
+        let mut neckcheck = NeckCheck::new(Box::new(Scripted { samples }), PrivacyMode::Normal);
+        neckcheck.set_grace_period(Duration::ZERO);
+        neckcheck.set_calibration(NeckCheckCalibration {
+            limit: Proximity::Distance(50.0),
+            camera: None,
+        });
+        neckcheck
+    }
+
+    fn run(neckcheck: &mut NeckCheck) -> Vec<PostureEvent> {
+        let mut events = Vec::new();
+        while let Ok(mut new) = neckcheck.check() {
+            events.append(&mut new);
+        }
+        events
+    }
+
+    #[test]
+    fn sensor_samples_drive_the_state_machine() {
+        let close = Some(Proximity::Distance(40.0));
+        let far = Some(Proximity::Distance(70.0));
+        let mut samples = Vec::new();
+        for i in 0..10 {
+            samples.push((i as f32, close, 1.0));
+        }
+        for i in 10..20 {
+            samples.push((i as f32, far, 1.0));
+        }
+        let mut neckcheck = neckcheck(samples);
+        assert_eq!(
+            run(&mut neckcheck),
+            vec![PostureEvent::TooClose, PostureEvent::Recovered]
+        );
+    }
+
+    #[test]
+    fn decisions_are_timed_from_the_capture() {
+        let mut neckcheck = neckcheck(Vec::new());
+        let captured = Instant::now() - Duration::from_millis(300);
+        neckcheck.source = Box::new(Scripted {
+            samples: VecDeque::from([ProximitySample {
+                timestamp: captured,
+                metric: Some(Proximity::Distance(60.0)),
+                confidence: 1.0,
+                observation: None,
+            }]),
+        });
+        neckcheck.check().unwrap();
+        let metrics = neckcheck.metrics().unwrap();
+        assert!(metrics.decision_latency_ms >= 300);
+        assert!(metrics.decision_latency_ms >= metrics.latency_ms);
+    }
+
+    fn size(width: u32, height: u32) -> Option<Proximity> {
+        Some(Proximity::FaceSize { width, height })
+    }
+
+    #[test]
+    fn steady_calibration_uses_the_median() {
+        let samples = vec![size(100, 100), size(102, 98), size(98, 104), size(101, 100)];
+        assert_eq!(assess_calibration(&samples), Ok(size(101, 100).unwrap()));
+    }
+
+    #[test]
+    fn unstable_calibration_is_refused() {
+        let samples = vec![
+            size(100, 100),
+            size(140, 100),
+            size(70, 100),
+            size(100, 100),
+        ];
+        assert!(matches!(
+            assess_calibration(&samples),
+            Err(CalibrationProblem::Unstable(_))
+        ));
+    }
+
+    #[test]
+    fn calibration_with_missed_detections_is_refused() {
+        let samples = vec![size(100, 100), None, None, size(100, 100), size(100, 100)];
+        assert_eq!(
+            assess_calibration(&samples),
+            Err(CalibrationProblem::TooFewDetections(3, 5))
+        );
+    }
+
+    #[test]
+    fn low_confidence_samples_are_ignored() {
+        let close = Some(Proximity::Distance(40.0));
+        let samples = (0..10).map(|i| (i as f32, close, 0.1)).collect();
+        let mut neckcheck = neckcheck(samples);
+        assert!(run(&mut neckcheck).is_empty());
+    }
+
+    fn face_at(top: i32) -> Observation {
+        Observation {
+            frame: image::RgbImage::new(640, 480).into(),
+            faces: vec![imageproc::rect::Rect::at(300, top).of_size(100, 100)],
+            candidates: Vec::new(),
+            score: Some(1.0),
+            landmarks: None,
+            body: None,
+            guest: false,
+        }
+    }
+
+    #[test]
+    fn sinking_face_is_noticed_after_a_while() {
+        let mut monitor = FaceDriftMonitor::new(FaceDriftConfig {
+            enabled: true,
+            max_drop: 0.5,
+            sustained_for: 60.0,
+            baseline_samples: 3,
+        });
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        for secs in 0..3 {
+            assert_eq!(monitor.update(&face_at(100), at(secs)), None);
+        }
+        // a little lower is fine
+        assert_eq!(monitor.update(&face_at(140), at(10)), None);
+        assert_eq!(monitor.update(&face_at(170), at(20)), None);
+        assert_eq!(
+            monitor.update(&face_at(170), at(80)),
+            Some(PostureEvent::Sinking)
+        );
+        assert_eq!(
+            monitor.update(&face_at(100), at(81)),
+            Some(PostureEvent::SinkingRecovered)
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use std::fs;
+use std::path::PathBuf;
+
+use super::NeckCheckCalibration;
+
+// The calibrations kept between runs, one per camera and resolution, as JSON
+pub struct CalibrationStore {
+    path: PathBuf,
+}
+
+impl CalibrationStore {
+    pub fn new(path: PathBuf) -> CalibrationStore {
+        CalibrationStore { path }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("neckcheck").join("calibrations.json"))
+    }
+
+    // The saved calibrations; a missing or unreadable file has none, as if never calibrated
+    pub fn load(&self) -> Vec<NeckCheckCalibration> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+            Err(e) => {
+                println!("Failed to read {}: {}", self.path.display(), e);
+                return Vec::new();
+            }
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            println!(
+                "Ignoring the calibrations in {}: {}",
+                self.path.display(),
+                e
+            );
+            Vec::new()
+        })
+    }
+
+    // Losing the calibrations only means calibrating again, so failing to save them is only
+    // reported
+    pub fn save(&self, calibrations: &[NeckCheckCalibration]) {
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| {
+                let json = serde_json::to_string_pretty(calibrations)?;
+                fs::write(&self.path, json)
+            });
+        if let Err(e) = result {
+            println!(
+                "Failed to save the calibration to {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
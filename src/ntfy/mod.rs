@@ -0,0 +1,190 @@
+use std::io::{BufRead, BufReader};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use serde::Deserialize;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::NtfyConfig;
+use crate::events::Feedback;
+use crate::status::{tooltip, Status};
+use crate::worker::WorkerHandle;
+
+// How long to wait before subscribing again after the connection drops
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+// Replies are published with this title, so that they can be told apart from commands
+const TITLE: &str = "neckcheck";
+const DEFAULT_SNOOZE_MINUTES: f64 = 10.0;
+
+// A command sent to the topic as a message, e.g. "snooze 30"
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Pause,
+    Resume,
+    Status,
+    Dismiss,
+    Snooze { minutes: f64 },
+}
+
+pub fn parse(text: &str) -> Result<Command, String> {
+    let mut words = text.split_whitespace();
+    let command = words.next().unwrap_or_default().to_lowercase();
+    let argument = words.next();
+    match (command.as_str(), argument) {
+        ("pause", None) => Ok(Command::Pause),
+        ("resume", None) => Ok(Command::Resume),
+        ("status", None) => Ok(Command::Status),
+        ("dismiss", None) => Ok(Command::Dismiss),
+        ("snooze", None) => Ok(Command::Snooze {
+            minutes: DEFAULT_SNOOZE_MINUTES,
+        }),
+        ("snooze", Some(minutes)) => match minutes.parse::<f64>() {
+            Ok(minutes) if minutes > 0.0 && minutes.is_finite() && words.next().is_none() => {
+                Ok(Command::Snooze { minutes })
+            }
+            _ => Err(format!("Invalid snooze duration: {}", minutes)),
+        },
+        _ => Err(format!(
+            "Unknown command {:?}; try pause, resume, status, dismiss or snooze [minutes]",
+            text.trim()
+        )),
+    }
+}
+
+// One line of ntfy's JSON stream
+#[derive(Deserialize, Debug)]
+struct Message {
+    id: String,
+    event: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+struct Client {
+    config: NtfyConfig,
+    worker: WorkerHandle,
+    status: watch::Receiver<Status>,
+}
+
+impl Client {
+    fn url(&self) -> String {
+        format!(
+            "{}/{}",
+            self.config.server.trim_end_matches('/'),
+            self.config.topic
+        )
+    }
+
+    fn authorize(&self, request: ureq::Request) -> ureq::Request {
+        match &self.config.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    fn reply(&self, text: &str) {
+        let request = self.authorize(ureq::post(&self.url())).set("Title", TITLE);
+        if let Err(e) = request.send_string(text) {
+            println!("Failed to reply on ntfy: {}", e);
+        }
+    }
+
+    fn run(&self, command: Command) -> Result<Option<String>, String> {
+        match command {
+            Command::Pause => self.worker.pause(true).map(|_| None),
+            Command::Resume => self.worker.pause(false).map(|_| None),
+            Command::Dismiss => self.worker.feedback(Feedback::Dismiss).map(|_| None),
+            Command::Snooze { minutes } => self
+                .worker
+                .feedback(Feedback::Snooze(Duration::from_secs_f64(minutes * 60.0)))
+                .map(|_| None),
+            Command::Status => Ok(Some(tooltip(&self.status.borrow(), Local::now()))),
+        }
+    }
+
+    // Follows the topic until the connection drops, starting after the message with `since`
+    fn subscribe(
+        &self,
+        since: &mut Option<String>,
+        shutdown: &CancellationToken,
+    ) -> Result<(), String> {
+        let mut request = self.authorize(ureq::get(&format!("{}/json", self.url())));
+        if let Some(id) = since {
+            request = request.query("since", id);
+        }
+        let response = request.call().map_err(|e| e.to_string())?;
+        for line in BufReader::new(response.into_reader()).lines() {
+            if shutdown.is_cancelled() {
+                return Ok(());
+            }
+            let line = line.map_err(|e| e.to_string())?;
+            let Ok(message) = serde_json::from_str::<Message>(&line) else {
+                continue;
+            };
+            if message.event != "message" {
+                continue;
+            }
+            *since = Some(message.id);
+            if message.title.as_deref() == Some(TITLE) {
+                continue;
+            }
+            let text = message.message.unwrap_or_default();
+            match parse(&text).and_then(|command| self.run(command)) {
+                Ok(Some(reply)) => self.reply(&reply),
+                Ok(None) => self.reply(&format!("Done: {}", text.trim())),
+                Err(e) => self.reply(&e),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Takes commands from the ntfy topic on a background thread until shutdown
+pub fn spawn(
+    config: &NtfyConfig,
+    worker: WorkerHandle,
+    status: watch::Receiver<Status>,
+    shutdown: CancellationToken,
+) {
+    let client = Client {
+        config: config.clone(),
+        worker,
+        status,
+    };
+    println!("Taking commands from {}", client.url());
+    let spawned = thread::Builder::new()
+        .name("neckcheck-ntfy".to_string())
+        .spawn(move || {
+            let mut since = None;
+            while !shutdown.is_cancelled() {
+                if let Err(e) = client.subscribe(&mut since, &shutdown) {
+                    println!("Lost the ntfy subscription: {}", e);
+                }
+                thread::sleep(RECONNECT_INTERVAL);
+            }
+        });
+    if let Err(e) = spawned {
+        println!("Failed to spawn the ntfy thread: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(parse("Pause"), Ok(Command::Pause));
+        assert_eq!(parse(" status "), Ok(Command::Status));
+        assert_eq!(parse("snooze"), Ok(Command::Snooze { minutes: 10.0 }));
+        assert_eq!(parse("snooze 30"), Ok(Command::Snooze { minutes: 30.0 }));
+        assert!(parse("snooze -5").is_err());
+        assert!(parse("snooze 5 more").is_err());
+        assert!(parse("pause now").is_err());
+        assert!(parse("").is_err());
+    }
+}
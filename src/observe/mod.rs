@@ -0,0 +1,214 @@
+use std::time::{Duration, Instant};
+
+use crate::i18n::tr;
+use crate::posture::Proximity;
+
+// fewer measurements than this over the whole observation say too little about normal posture
+const MIN_SAMPLES: usize = 30;
+const HISTOGRAM_BINS: usize = 10;
+const HISTOGRAM_WIDTH: usize = 40;
+
+// Watches the user work normally for a while instead of asking them to lean in badly, and
+// proposes a calibration at a percentile of what it saw: e.g. at the 95th percentile, only the
+// closest 5% of normal working posture would count as too close.
+pub struct Observer {
+    duration: Duration,
+    until: Instant,
+    percentile: f32,
+    samples: Vec<Proximity>,
+}
+
+impl Observer {
+    pub fn new(duration: Duration, percentile: f32, now: Instant) -> Observer {
+        Observer {
+            duration,
+            until: now + duration,
+            percentile,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, metric: Option<Proximity>) {
+        if let Some(metric) = metric {
+            // only measurements of the same kind can share a histogram
+            if self
+                .samples
+                .first()
+                .is_none_or(|first| same_kind(first, &metric))
+            {
+                self.samples.push(metric);
+            }
+        }
+    }
+
+    // The proposed calibration once the observation period is over. With too few measurements
+    // (e.g. the user was away the whole time), the observation starts over.
+    pub fn finish(&mut self, now: Instant) -> Option<Proximity> {
+        if now < self.until {
+            return None;
+        }
+        if self.samples.len() < MIN_SAMPLES {
+            println!(
+                "{}",
+                tr!(
+                    "observe-too-few",
+                    count = self.samples.len(),
+                    minutes = (self.duration.as_secs_f64() / 60.0).round()
+                )
+            );
+            self.until = now + self.duration;
+            return None;
+        }
+        self.print_histogram();
+        propose(&self.samples, self.percentile)
+    }
+
+    fn print_histogram(&self) {
+        let (label, values) = closeness_values(&self.samples);
+        println!("Observed {} measurements of {}:", values.len(), label);
+        for (low, high, count) in histogram(&values, HISTOGRAM_BINS) {
+            let bar = count * HISTOGRAM_WIDTH / values.len().max(1);
+            println!(
+                "  {:>7.1} - {:<7.1} {:>5} {}",
+                low,
+                high,
+                count,
+                "#".repeat(bar)
+            );
+        }
+    }
+}
+
+fn same_kind(a: &Proximity, b: &Proximity) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+// The values the histogram is drawn over: the face width, or the distance
+fn closeness_values(samples: &[Proximity]) -> (&'static str, Vec<f32>) {
+    match samples.first() {
+        Some(Proximity::Distance(_)) => (
+            "distance (cm)",
+            samples
+                .iter()
+                .filter_map(|m| match m {
+                    Proximity::Distance(distance) => Some(*distance),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        _ => (
+            "face width (px)",
+            samples
+                .iter()
+                .filter_map(|m| match m {
+                    Proximity::FaceSize { width, .. } => Some(*width as f32),
+                    _ => None,
+                })
+                .collect(),
+        ),
+    }
+}
+
+// Equal width bins from the smallest to the largest value, as (low, high, count)
+fn histogram(values: &[f32], bins: usize) -> Vec<(f32, f32, usize)> {
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    if values.is_empty() || bins == 0 {
+        return Vec::new();
+    }
+    let width = ((max - min) / bins as f32).max(f32::EPSILON);
+    let mut counts = vec![0; bins];
+    for value in values {
+        let bin = (((value - min) / width) as usize).min(bins - 1);
+        counts[bin] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let low = min + width * i as f32;
+            (low, low + width, count)
+        })
+        .collect()
+}
+
+fn percentile(values: &mut [f32], percentile: f32) -> f32 {
+    values.sort_unstable_by(f32::total_cmp);
+    let rank = (percentile / 100.0 * (values.len() - 1) as f32).round() as usize;
+    values[rank.min(values.len() - 1)]
+}
+
+// The calibration at the given percentile of closeness: larger face boxes, or shorter distances
+fn propose(samples: &[Proximity], closeness_percentile: f32) -> Option<Proximity> {
+    match samples.first()? {
+        Proximity::Distance(_) => {
+            let (_, mut distances) = closeness_values(samples);
+            Some(Proximity::Distance(percentile(
+                &mut distances,
+                100.0 - closeness_percentile,
+            )))
+        }
+        Proximity::FaceSize { .. } => {
+            let (mut widths, mut heights): (Vec<f32>, Vec<f32>) = samples
+                .iter()
+                .filter_map(|m| match m {
+                    Proximity::FaceSize { width, height } => Some((*width as f32, *height as f32)),
+                    _ => None,
+                })
+                .unzip();
+            Some(Proximity::FaceSize {
+                width: percentile(&mut widths, closeness_percentile) as u32,
+                height: percentile(&mut heights, closeness_percentile) as u32,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face(size: u32) -> Proximity {
+        Proximity::FaceSize {
+            width: size,
+            height: size,
+        }
+    }
+
+    #[test]
+    fn proposes_face_size_at_the_percentile() {
+        let samples: Vec<Proximity> = (1..=100).map(face).collect();
+        assert_eq!(propose(&samples, 95.0), Some(face(95)));
+    }
+
+    #[test]
+    fn proposes_distance_at_the_opposite_percentile() {
+        let samples: Vec<Proximity> = (1..=100).map(|d| Proximity::Distance(d as f32)).collect();
+        assert_eq!(propose(&samples, 95.0), Some(Proximity::Distance(6.0)));
+    }
+
+    #[test]
+    fn histogram_counts_every_value_once() {
+        let values: Vec<f32> = (0..50).map(|v| v as f32).collect();
+        let bins = histogram(&values, 10);
+        assert_eq!(bins.len(), 10);
+        assert_eq!(bins.iter().map(|(_, _, count)| count).sum::<usize>(), 50);
+    }
+
+    #[test]
+    fn keeps_observing_without_enough_samples() {
+        let start = Instant::now();
+        let mut observer = Observer::new(Duration::from_secs(60), 95.0, start);
+        observer.record(Some(face(100)));
+        assert_eq!(observer.finish(start + Duration::from_secs(30)), None);
+        assert_eq!(observer.finish(start + Duration::from_secs(60)), None);
+        for _ in 0..MIN_SAMPLES {
+            observer.record(Some(face(100)));
+        }
+        assert_eq!(observer.finish(start + Duration::from_secs(90)), None);
+        assert_eq!(
+            observer.finish(start + Duration::from_secs(120)),
+            Some(face(100))
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use std::path::Path;
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+use console::Term;
+use nokhwa::utils::ApiBackend;
+use toml_edit::{value, Array, DocumentMut};
+
+use crate::alert::{alert_for, build_sink};
+use crate::cli::Args;
+use crate::config::{Config, DEFAULT_CONFIG};
+use crate::detector::create_detector;
+use crate::events::PostureEvent;
+use crate::i18n::tr;
+use crate::neckcheck::{CalibrationStore, NeckCheck};
+use crate::posture::Proximity;
+use crate::preflight;
+use crate::privacy::PrivacyMode;
+use crate::proximity::CameraProximity;
+use crate::source::{WebCam, WebCamMode};
+
+// how long the camera preview runs for
+const PREVIEW: Duration = Duration::from_secs(8);
+const PREVIEW_INTERVAL: Duration = Duration::from_millis(250);
+// how much closer than usual the bad posture has to be; any less and every small movement would
+// alert
+const MIN_LEAN: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AlertStyle {
+    Tone,
+    Notification,
+    Both,
+}
+
+const STYLES: [AlertStyle; 3] = [AlertStyle::Tone, AlertStyle::Notification, AlertStyle::Both];
+
+impl AlertStyle {
+    fn describe(self) -> String {
+        match self {
+            AlertStyle::Tone => tr!("onboarding-style-tone"),
+            AlertStyle::Notification => tr!("onboarding-style-notification"),
+            AlertStyle::Both => tr!("onboarding-style-both"),
+        }
+    }
+}
+
+// Whether to set neckcheck up before its first run: there is neither a config nor a calibration
+// yet, nothing else was asked for on the command line and someone is there to answer
+pub fn needed(args: &Args) -> bool {
+    let missing = |path: Option<std::path::PathBuf>| path.is_some_and(|path| !path.exists());
+    !asked_for_input(args)
+        && missing(Config::default_path())
+        && missing(CalibrationStore::default_path())
+        && console::user_attended()
+}
+
+// Whether the command line says which config or input to use, which setup would otherwise ask
+fn asked_for_input(args: &Args) -> bool {
+    args.config.is_some()
+        || args.observe.is_some()
+        || args.url.is_some()
+        || args.realsense
+        || args.phone.is_some()
+        || args.serial.is_some()
+        || args.no_camera
+        || !args.camera.is_empty()
+}
+
+// How much closer `lean` is than `usual`, as a fraction, or None if they can't be compared
+fn lean(usual: Proximity, lean: Proximity) -> Option<f32> {
+    match (usual, lean) {
+        (Proximity::FaceSize { width, .. }, Proximity::FaceSize { width: leaning, .. }) => {
+            (width > 0).then(|| leaning as f32 / width as f32 - 1.0)
+        }
+        (Proximity::Distance(usual), Proximity::Distance(leaning)) => {
+            (leaning > 0.0).then(|| usual / leaning - 1.0)
+        }
+        _ => None,
+    }
+}
+
+// The default config with the chosen camera and posture alerts
+fn config_text(camera: u32, style: AlertStyle) -> String {
+    let mut document: DocumentMut = DEFAULT_CONFIG.parse().expect("The default config is TOML");
+    document["camera"] = value(Array::from_iter([camera as i64]));
+    let sinks = document["alerts"]["sinks"]
+        .as_array_of_tables_mut()
+        .expect("The default config has sinks");
+    // the first sink of the default config is the posture alerts' tone
+    let mut notification = sinks.get(0).expect("The default config has sinks").clone();
+    notification["type"] = value("notification");
+    notification.remove("duration");
+    match style {
+        AlertStyle::Tone => {}
+        AlertStyle::Notification => *sinks.get_mut(0).unwrap() = notification,
+        AlertStyle::Both => sinks.push(notification),
+    }
+    document.to_string()
+}
+
+// The number the user picks from 1 to `count`, or `default` if they just press enter
+fn choose(term: &Term, count: usize, default: usize) -> usize {
+    loop {
+        let _ = term.write_str(&format!(
+            "{} ",
+            tr!("onboarding-choose", default = default + 1)
+        ));
+        let answer = term.read_line().unwrap_or_default();
+        if answer.trim().is_empty() {
+            return default;
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(number) if (1..=count).contains(&number) => return number - 1,
+            _ => {}
+        }
+    }
+}
+
+fn confirm(term: &Term, question: &str) -> bool {
+    let _ = term.write_str(&format!("{} [Y/n] ", question));
+    let answer = term.read_line().unwrap_or_default();
+    !answer.trim().to_lowercase().starts_with('n')
+}
+
+// Shows live what the camera makes of the user, so that they can fix the lighting or the camera's
+// position before calibrating
+fn preview(term: &Term, neckcheck: &mut NeckCheck) {
+    let _ = term.write_line(&tr!("onboarding-preview"));
+    let end = Instant::now() + PREVIEW;
+    while Instant::now() < end {
+        let line = match neckcheck.observe() {
+            Ok(Some(Proximity::FaceSize { width, height })) => {
+                tr!("onboarding-preview-face", width = width, height = height)
+            }
+            Ok(Some(Proximity::Distance(distance))) => {
+                tr!("onboarding-preview-distance", distance = distance.round())
+            }
+            Ok(None) => tr!("onboarding-preview-nobody"),
+            Err(e) => e.to_string(),
+        };
+        let _ = term.clear_line();
+        let _ = term.write_str(&line);
+        std::thread::sleep(PREVIEW_INTERVAL);
+    }
+    let _ = term.write_line("");
+}
+
+// Your usual posture and then a bad one, returning the bad one once it is far enough from the
+// usual one to tell them apart
+fn calibrate(term: &Term, neckcheck: &mut NeckCheck) -> Proximity {
+    let usual = loop {
+        let _ = term.write_line(&tr!("onboarding-usual-posture"));
+        let _ = term.read_line();
+        if let Some(usual) = neckcheck.measure_position() {
+            break usual;
+        }
+    };
+    loop {
+        let _ = term.write_line(&tr!("calibration-bad-posture"));
+        let _ = term.read_line();
+        let Some(bad) = neckcheck.measure_position() else {
+            continue;
+        };
+        match lean(usual, bad) {
+            Some(lean) if lean >= MIN_LEAN => return bad,
+            _ => println!("{}", tr!("onboarding-not-closer")),
+        }
+    }
+}
+
+// Sends a posture alert through the sinks of `config`, as they would be in use
+fn test_alert(config: &Config) {
+    let Some(alert) = alert_for(&PostureEvent::TooClose) else {
+        return;
+    };
+    let (feedback, _answers) = channel();
+    for sink in &config.alerts.sinks {
+        let (mut sink, route) = build_sink(sink, &config.accessibility, &feedback);
+        if route.matches(&alert) {
+            sink.handle(&alert);
+        }
+    }
+}
+
+// Walks the user through picking a camera, seeing what it sees, calibrating and choosing how to be
+// alerted, then writes the config to `path` and saves the calibration
+pub fn run(path: &Path, config: &Config, privacy: PrivacyMode) -> Result<(), String> {
+    let term = Term::stdout();
+    let _ = term.write_line(&tr!("onboarding-welcome"));
+    if path.exists()
+        && !confirm(
+            &term,
+            &tr!("onboarding-replace", path = path.display().to_string()),
+        )
+    {
+        return Ok(());
+    }
+
+    let cameras: Vec<(u32, String)> = nokhwa::query(ApiBackend::Auto)
+        .map_err(|e| format!("Failed to list the cameras: {}", e))?
+        .into_iter()
+        .filter_map(|camera| Some((camera.index().as_index().ok()?, camera.human_name())))
+        .collect();
+    let camera = match cameras.as_slice() {
+        [] => return Err(tr!("onboarding-no-camera")),
+        [(index, _)] => *index,
+        cameras => {
+            let _ = term.write_line(&tr!("onboarding-pick-camera"));
+            for (number, (_, name)) in cameras.iter().enumerate() {
+                let _ = term.write_line(&format!("  {}. {}", number + 1, name));
+            }
+            cameras[choose(&term, cameras.len(), 0)].0
+        }
+    };
+    preflight::camera_access(camera)?;
+    let detector = create_detector(&config.detector).map_err(|e| e.to_string())?;
+    let source = CameraProximity::new(
+        Box::new(WebCam::new(camera, WebCamMode::Continuous)),
+        detector,
+        privacy,
+    );
+    let mut neckcheck = NeckCheck::new(Box::new(source), privacy);
+    loop {
+        preview(&term, &mut neckcheck);
+        if confirm(&term, &tr!("onboarding-preview-done")) {
+            break;
+        }
+    }
+    let limit = calibrate(&term, &mut neckcheck);
+    neckcheck.calibrate_to(limit);
+    let calibrations = neckcheck.calibrations();
+    // the camera is off while choosing the alerts
+    drop(neckcheck);
+
+    let text = loop {
+        let _ = term.write_line(&tr!("onboarding-pick-style"));
+        for (number, style) in STYLES.iter().enumerate() {
+            let _ = term.write_line(&format!("  {}. {}", number + 1, style.describe()));
+        }
+        let style = STYLES[choose(&term, STYLES.len(), 0)];
+        let text = config_text(camera, style);
+        let (chosen, _) = Config::parse(&text)?;
+        let _ = term.write_line(&tr!("onboarding-testing"));
+        test_alert(&chosen);
+        if confirm(&term, &tr!("onboarding-noticed")) {
+            break text;
+        }
+    };
+
+    let written = match path.parent() {
+        Some(dir) => std::fs::create_dir_all(dir).and_then(|_| std::fs::write(path, &text)),
+        None => std::fs::write(path, &text),
+    };
+    written.map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    if let Some(store) = CalibrationStore::default_path().map(CalibrationStore::new) {
+        store.save(&calibrations);
+    }
+    let _ = term.write_line(&tr!("onboarding-done", path = path.display().to_string()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::cameras;
+    use crate::config::SinkKind;
+    use clap::Parser;
+
+    #[test]
+    fn the_choices_make_a_valid_config() {
+        let (config, unknown) = Config::parse(&config_text(2, AlertStyle::Both)).unwrap();
+        assert!(unknown.is_empty(), "{:?}", unknown);
+        assert_eq!(config.camera, vec![2]);
+        let kinds: Vec<_> = config.alerts.sinks.iter().map(|sink| &sink.kind).collect();
+        assert!(matches!(
+            kinds.as_slice(),
+            [
+                SinkKind::Tone { .. },
+                SinkKind::Notification,
+                SinkKind::Notification
+            ]
+        ));
+        let (config, _) = Config::parse(&config_text(0, AlertStyle::Notification)).unwrap();
+        assert!(matches!(
+            config.alerts.sinks[0].kind,
+            SinkKind::Notification
+        ));
+        assert_eq!(config.alerts.sinks.len(), 2);
+
+        let usual = Proximity::FaceSize {
+            width: 100,
+            height: 100,
+        };
+        let leaning = Proximity::FaceSize {
+            width: 103,
+            height: 103,
+        };
+        assert!(lean(usual, leaning).unwrap() < MIN_LEAN);
+        assert!(lean(Proximity::Distance(60.0), Proximity::Distance(45.0)).unwrap() > MIN_LEAN);
+        assert_eq!(lean(usual, Proximity::Distance(45.0)), None);
+    }
+
+    #[test]
+    fn the_camera_comes_from_the_command_line_then_the_config() {
+        let args = |line: &[&str]| Args::try_parse_from(line).unwrap();
+        let bare = args(&["neckcheck"]);
+        // nothing was asked for, so a first run sets up
+        assert!(!asked_for_input(&bare));
+        assert!(asked_for_input(&args(&["neckcheck", "--camera", "1"])));
+        assert!(asked_for_input(&args(&["neckcheck", "--no-camera"])));
+
+        let (configured, _) = Config::parse("camera = [2]\n").unwrap();
+        assert_eq!(cameras(&bare, &configured), vec![2]);
+        assert_eq!(cameras(&bare, &Config::default()), vec![0]);
+        assert_eq!(
+            cameras(&args(&["neckcheck", "--camera", "1"]), &configured),
+            vec![1]
+        );
+    }
+}
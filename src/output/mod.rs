@@ -0,0 +1,94 @@
+mod virtual_camera;
+
+pub use virtual_camera::VirtualCamera;
+
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut};
+use imageproc::rect::Rect;
+use thiserror::Error;
+
+use crate::config::AccessibilityConfig;
+
+#[derive(Error, Debug, Clone)]
+pub enum OutputError {
+    #[error("Failed to open output {0}")]
+    OpenError(String),
+    #[error("Failed to write frame: {0}")]
+    WriteError(String),
+}
+
+// How the annotations look
+#[derive(Debug, Clone, Copy)]
+pub struct OverlayStyle {
+    ok: Rgb<u8>,
+    too_close: Rgb<u8>,
+    threshold: Rgb<u8>,
+    // the banner is this fraction of the frame's height
+    banner_divisor: u32,
+    line_width: u32,
+}
+
+impl OverlayStyle {
+    pub fn new(config: &AccessibilityConfig) -> OverlayStyle {
+        let mut style = OverlayStyle::default();
+        if config.high_contrast {
+            // also tell apart without seeing red and green
+            style.ok = Rgb([0, 90, 255]);
+            style.too_close = Rgb([255, 230, 0]);
+            style.threshold = Rgb([255, 255, 255]);
+        }
+        if config.large_overlay {
+            style.banner_divisor = 5;
+            style.line_width = 4;
+        }
+        style
+    }
+}
+
+impl Default for OverlayStyle {
+    fn default() -> Self {
+        OverlayStyle {
+            ok: Rgb([0, 200, 0]),
+            too_close: Rgb([255, 0, 0]),
+            threshold: Rgb([255, 200, 0]),
+            banner_divisor: 20,
+            line_width: 1,
+        }
+    }
+}
+
+fn draw_outline(image: &mut RgbImage, rect: Rect, colour: Rgb<u8>, width: u32) {
+    for inset in 0..width.min(rect.width() / 2).min(rect.height() / 2).max(1) {
+        let rect = Rect::at(rect.left() + inset as i32, rect.top() + inset as i32)
+            .of_size(rect.width() - 2 * inset, rect.height() - 2 * inset);
+        draw_hollow_rect_mut(image, rect, colour);
+    }
+}
+
+// Draws what neckcheck currently sees onto the frame: the detected faces, the calibrated maximum
+// face size (centred on the first face) and a banner along the top showing the posture state.
+pub fn annotate(
+    image: &mut RgbImage,
+    faces: &[Rect],
+    threshold: Option<(u32, u32)>,
+    ok: bool,
+    style: &OverlayStyle,
+) {
+    let colour = if ok { style.ok } else { style.too_close };
+    for face in faces {
+        draw_outline(image, *face, colour, style.line_width);
+    }
+    if let (Some(face), Some((width, height))) = (faces.first(), threshold) {
+        let centre_x = face.left() + face.width() as i32 / 2;
+        let centre_y = face.top() + face.height() as i32 / 2;
+        let rect = Rect::at(centre_x - width as i32 / 2, centre_y - height as i32 / 2)
+            .of_size(width.max(1), height.max(1));
+        draw_outline(image, rect, style.threshold, style.line_width);
+    }
+    let banner_height = (image.height() / style.banner_divisor).max(4);
+    draw_filled_rect_mut(
+        image,
+        Rect::at(0, 0).of_size(image.width(), banner_height),
+        colour,
+    );
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use image::RgbImage;
+
+use super::OutputError;
+use crate::privacy::ImageAccess;
+
+// Writes frames to a v4l2loopback device (which is also what OBS' virtual camera uses on Linux) so
+// that the annotated feed can be watched from any video app. The frames are piped as raw RGB into
+// an ffmpeg child process, which takes care of negotiating the device format.
+pub struct VirtualCamera {
+    device: String,
+    process: Option<(Child, ChildStdin, u32, u32)>,
+}
+
+impl VirtualCamera {
+    pub fn new(device: &str, _access: ImageAccess) -> VirtualCamera {
+        VirtualCamera {
+            device: device.to_string(),
+            process: None,
+        }
+    }
+
+    pub fn write(&mut self, frame: &RgbImage) -> Result<(), OutputError> {
+        let size_changed = matches!(
+            &self.process,
+            Some((_, _, width, height)) if (*width, *height) != frame.dimensions()
+        );
+        if size_changed {
+            self.close();
+        }
+        if self.process.is_none() {
+            self.process = Some(self.spawn(frame.width(), frame.height())?);
+        }
+        let (_, stdin, _, _) = self.process.as_mut().unwrap();
+        if let Err(e) = stdin.write_all(frame.as_raw()) {
+            self.close();
+            return Err(OutputError::WriteError(e.to_string()));
+        }
+        Ok(())
+    }
+
+    fn spawn(&self, width: u32, height: u32) -> Result<(Child, ChildStdin, u32, u32), OutputError> {
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-i", "-"])
+            .args(["-f", "v4l2", "-pix_fmt", "yuv420p", &self.device])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| OutputError::OpenError(format!("{} (ffmpeg: {})", self.device, e)))?;
+        let stdin = child.stdin.take().unwrap();
+        println!("Writing annotated frames to {}", self.device);
+        Ok((child, stdin, width, height))
+    }
+
+    fn close(&mut self) {
+        if let Some((mut child, stdin, _, _)) = self.process.take() {
+            drop(stdin);
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for VirtualCamera {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
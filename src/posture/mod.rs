@@ -0,0 +1,1082 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::events::PostureEvent;
+
+mod outliers;
+mod rule;
+
+pub use outliers::JumpFilter;
+pub use rule::{Check, Condition, Conditions, Judge, Rule};
+
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+// What the state machine currently believes about the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Posture {
+    Good,
+    TooClose,
+    // no face in view
+    Away,
+}
+
+// A single measurement of how close the user is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Proximity {
+    // the size of the face box in the frame; the bigger, the closer
+    FaceSize { width: u32, height: u32 },
+    // the measured distance in centimetres, e.g. from a depth camera
+    Distance(f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostureThresholds {
+    // the measurement beyond which the user is too close: a larger face box, or a shorter distance
+    pub limit: Proximity,
+    // once too close, the user has to move back this fraction past the limit to count as
+    // recovered, so that hovering right at the threshold doesn't toggle the state every frame
+    pub hysteresis: f32,
+    // how long a new observation has to persist before the state changes to it
+    pub trigger_after: Duration,
+    pub recover_after: Duration,
+    pub away_after: Duration,
+    // measurements are smoothed with a median over this many samples; the filter has to be full
+    // before any decision is made about a visible face
+    pub smoothing_window: usize,
+    // no events at all for this long after the first update (launch) or `start_grace` (resume)
+    pub grace_period: Duration,
+    // if set, too close goes by how much of a recent window was too close instead of
+    // `trigger_after` in a row
+    pub duty_cycle: Option<DutyCycle>,
+    // what counts as bad posture, if more than being too close
+    pub rule: Rule,
+}
+
+impl PostureThresholds {
+    pub fn new(limit: Proximity) -> PostureThresholds {
+        PostureThresholds {
+            limit,
+            hysteresis: 0.05,
+            trigger_after: Duration::from_secs(2),
+            recover_after: Duration::from_secs(1),
+            away_after: Duration::from_secs(3),
+            smoothing_window: 5,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            duty_cycle: None,
+            rule: Rule::default(),
+        }
+    }
+}
+
+// Too close once `too_close_for` of the last `window` was, however it was interrupted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DutyCycle {
+    pub too_close_for: Duration,
+    pub window: Duration,
+}
+
+// The observations of the last `window`, oldest first, each holding until the next one. The oldest
+// may have started before the window did.
+struct DutyWindow {
+    cycle: DutyCycle,
+    observations: VecDeque<(Instant, bool)>,
+}
+
+impl DutyWindow {
+    fn new(cycle: DutyCycle) -> DutyWindow {
+        DutyWindow {
+            cycle,
+            observations: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, too_close: bool, now: Instant) {
+        // time going backwards says nothing about how long anything lasted
+        if self.observations.back().is_some_and(|(at, _)| *at > now) {
+            return;
+        }
+        self.observations.push_back((now, too_close));
+        if let Some(start) = now.checked_sub(self.cycle.window) {
+            while self.observations.get(1).is_some_and(|(at, _)| *at <= start) {
+                self.observations.pop_front();
+            }
+        }
+    }
+
+    // How much of the window up to `now` was too close
+    fn too_close(&self, now: Instant) -> Duration {
+        let start = now.checked_sub(self.cycle.window);
+        let ends = self.observations.iter().skip(1).map(|(at, _)| *at);
+        self.observations
+            .iter()
+            .zip(ends.chain([now]))
+            .filter(|((_, too_close), _)| *too_close)
+            .map(|((from, _), to)| {
+                let from = start.map_or(*from, |start| (*from).max(start));
+                to.saturating_duration_since(from)
+            })
+            .sum()
+    }
+
+    fn is_met(&self, now: Instant) -> bool {
+        self.too_close(now) >= self.cycle.too_close_for
+    }
+
+    fn clear(&mut self) {
+        self.observations.clear();
+    }
+}
+
+// A stricter (sensitivity above 1) or more lenient (below 1) limit during part of the day, e.g.
+// `{ from = "16:00", to = "23:00", sensitivity = 1.2 }`. Ranges may wrap past midnight.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SensitivityRange {
+    #[serde(deserialize_with = "time_of_day")]
+    pub from: NaiveTime,
+    #[serde(deserialize_with = "time_of_day")]
+    pub to: NaiveTime,
+    #[serde(deserialize_with = "positive")]
+    pub sensitivity: f32,
+}
+
+impl SensitivityRange {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.to {
+            self.from <= time && time < self.to
+        } else {
+            time >= self.from || time < self.to
+        }
+    }
+}
+
+// The sensitivity at a given time of day: that of the first range containing it, or 1
+pub fn sensitivity_at(schedule: &[SensitivityRange], time: NaiveTime) -> f32 {
+    schedule
+        .iter()
+        .find(|range| range.contains(time))
+        .map_or(1.0, |range| range.sensitivity)
+}
+
+// Reads a time of day written as HH:MM
+pub fn time_of_day<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&value, "%H:%M")
+        .map_err(|e| serde::de::Error::custom(format!("invalid time {:?} (HH:MM): {}", value, e)))
+}
+
+fn positive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value = f32::deserialize(deserializer)?;
+    if !(value > 0.0 && value.is_finite()) {
+        return Err(serde::de::Error::custom(
+            "sensitivity must be a positive number",
+        ));
+    }
+    Ok(value)
+}
+
+// A yes/no condition that only counts once it has held for a while, e.g. a bent neck for two
+// minutes, and that clears as soon as it stops
+pub struct SustainedCondition {
+    after: Duration,
+    since: Option<Instant>,
+    active: bool,
+}
+
+impl SustainedCondition {
+    pub fn new(after: Duration) -> SustainedCondition {
+        SustainedCondition {
+            after,
+            since: None,
+            active: false,
+        }
+    }
+
+    // Some(true) when the condition becomes sustained, Some(false) when it ends after that
+    pub fn update(&mut self, holds: bool, now: Instant) -> Option<bool> {
+        if !holds {
+            self.since = None;
+            return std::mem::take(&mut self.active).then_some(false);
+        }
+        let since = *self.since.get_or_insert(now);
+        if !self.active && now.saturating_duration_since(since) >= self.after {
+            self.active = true;
+            return Some(true);
+        }
+        None
+    }
+
+    pub fn reset(&mut self) {
+        self.since = None;
+        self.active = false;
+    }
+}
+
+// The decision logic of neckcheck, free of any camera or detector so that it can be driven (and
+// tested) with plain measurements and timestamps.
+pub struct PostureStateMachine {
+    thresholds: PostureThresholds,
+    state: Posture,
+    // an observation that differs from the current state, and when it was first seen
+    pending: Option<(Posture, Instant)>,
+    samples: VecDeque<Proximity>,
+    grace_until: Option<Instant>,
+    // how much more lenient than the calibrated limit to be: above 1 a larger face box (or a
+    // shorter distance) is tolerated, below 1 less is
+    limit_scale: f32,
+    // how much stricter than that to be at this time of day
+    sensitivity: f32,
+    duty: Option<DutyWindow>,
+    // what else was true of the posture at the latest update, for the rule
+    conditions: Conditions,
+}
+
+impl PostureStateMachine {
+    pub fn new(thresholds: PostureThresholds) -> PostureStateMachine {
+        PostureStateMachine {
+            state: Posture::Good,
+            pending: None,
+            samples: VecDeque::new(),
+            grace_until: None,
+            limit_scale: 1.0,
+            sensitivity: 1.0,
+            duty: thresholds.duty_cycle.map(DutyWindow::new),
+            conditions: Conditions::default(),
+            thresholds,
+        }
+    }
+
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity;
+    }
+
+    // The factor the calibrated limit is scaled by; the larger, the more lenient
+    fn effective_scale(&self) -> f32 {
+        self.limit_scale / self.sensitivity
+    }
+
+    // Loosens or tightens the limit without forgetting the current state
+    pub fn set_limit_scale(&mut self, scale: f32) {
+        self.limit_scale = scale;
+    }
+
+    pub fn state(&self) -> Posture {
+        self.state
+    }
+
+    // The calibrated limit as it currently applies, after learning and the schedule
+    pub fn limit(&self) -> Proximity {
+        let scale = self.effective_scale();
+        match self.thresholds.limit {
+            Proximity::FaceSize { width, height } => Proximity::FaceSize {
+                width: (width as f32 * scale) as u32,
+                height: (height as f32 * scale) as u32,
+            },
+            Proximity::Distance(distance) => Proximity::Distance(distance / scale),
+        }
+    }
+
+    // The smoothed measurement the posture is judged on, if there is one
+    pub fn smoothed_metric(&self) -> Option<Proximity> {
+        (!self.samples.is_empty()).then(|| self.smoothed())
+    }
+
+    // Forgets the current state without emitting any events, e.g. after the camera was lost
+    pub fn reset(&mut self) {
+        self.state = Posture::Good;
+        self.pending = None;
+        self.samples.clear();
+        if let Some(duty) = &mut self.duty {
+            duty.clear();
+        }
+    }
+
+    // Holds back all events for the grace period and re-primes the smoothing filter, e.g. after
+    // resuming from suspend while the user is still getting settled
+    pub fn start_grace(&mut self, now: Instant) {
+        self.grace_until = Some(now + self.thresholds.grace_period);
+        self.pending = None;
+        self.samples.clear();
+        if let Some(duty) = &mut self.duty {
+            duty.clear();
+        }
+    }
+
+    pub fn update(&mut self, metrics: Option<Proximity>, now: Instant) -> Vec<PostureEvent> {
+        self.update_with(metrics, Conditions::default(), now)
+    }
+
+    // The same, with the conditions besides the measurement that the rule may combine it with
+    pub fn update_with(
+        &mut self,
+        metrics: Option<Proximity>,
+        conditions: Conditions,
+        now: Instant,
+    ) -> Vec<PostureEvent> {
+        self.update_judged(metrics, conditions, None, now)
+    }
+
+    // The same, with a judge that may overrule the rule
+    pub fn update_judged(
+        &mut self,
+        metrics: Option<Proximity>,
+        conditions: Conditions,
+        judge: Option<&mut Judge>,
+        now: Instant,
+    ) -> Vec<PostureEvent> {
+        self.conditions = conditions;
+        if self.grace_until.is_none() {
+            self.start_grace(now);
+        }
+        if let Some(metrics) = metrics {
+            // a median across different kinds of measurement means nothing
+            if self.samples.back().is_some_and(|last| {
+                std::mem::discriminant(last) != std::mem::discriminant(&metrics)
+            }) {
+                self.samples.clear();
+            }
+            self.samples.push_back(metrics);
+            while self.samples.len() > self.thresholds.smoothing_window.max(1) {
+                self.samples.pop_front();
+            }
+        }
+        let in_grace = self.grace_until.is_some_and(|until| now < until);
+        let warming_up = metrics.is_some() && self.samples.len() < self.thresholds.smoothing_window;
+        if in_grace || warming_up {
+            return Vec::new();
+        }
+        let observed = self.classify(metrics.map(|current| (current, self.smoothed())), judge);
+        if let (Some(duty), Posture::Good, true) =
+            (&mut self.duty, self.state, observed != Posture::Away)
+        {
+            // too close by the window rather than in a row; being away still goes by `away_after`
+            duty.record(observed == Posture::TooClose, now);
+            self.pending = None;
+            if !duty.is_met(now) {
+                return Vec::new();
+            }
+            return self.change_to(Posture::TooClose);
+        }
+        if observed == self.state {
+            self.pending = None;
+            return Vec::new();
+        }
+        let since = match self.pending {
+            Some((pending, since)) if pending == observed => since,
+            _ => {
+                self.pending = Some((observed, now));
+                now
+            }
+        };
+        if now.saturating_duration_since(since) < self.dwell(observed) {
+            return Vec::new();
+        }
+        self.change_to(observed)
+    }
+
+    fn change_to(&mut self, posture: Posture) -> Vec<PostureEvent> {
+        let events = transition_events(self.state, posture);
+        self.state = posture;
+        self.pending = None;
+        // the next episode is counted afresh
+        if let Some(duty) = &mut self.duty {
+            duty.clear();
+        }
+        events
+    }
+
+    fn classify(
+        &self,
+        metrics: Option<(Proximity, Proximity)>,
+        judge: Option<&mut Judge>,
+    ) -> Posture {
+        let (current, metrics) = match metrics {
+            Some(metrics) => metrics,
+            None => return Posture::Away,
+        };
+        let hysteresis = if self.state == Posture::TooClose {
+            self.thresholds.hysteresis
+        } else {
+            0.0
+        };
+        let too_close = match (metrics, self.thresholds.limit) {
+            (
+                Proximity::FaceSize { width, height },
+                Proximity::FaceSize {
+                    width: max_width,
+                    height: max_height,
+                },
+            ) => {
+                let scale = self.effective_scale() * (1.0 - hysteresis);
+                width as f32 > max_width as f32 * scale || height as f32 > max_height as f32 * scale
+            }
+            (Proximity::Distance(distance), Proximity::Distance(min_distance)) => {
+                distance < min_distance / self.effective_scale() * (1.0 + hysteresis)
+            }
+            // a measurement that can't be compared with the calibration says nothing about posture
+            _ => return self.state,
+        };
+        let too_close = self.thresholds.rule.evaluate(too_close, &self.conditions);
+        let check = Check {
+            current,
+            smoothed: metrics,
+            limit: self.limit(),
+            too_close,
+            conditions: self.conditions,
+            state: self.state,
+        };
+        if judge.and_then(|judge| judge(&check)).unwrap_or(too_close) {
+            return Posture::TooClose;
+        }
+        Posture::Good
+    }
+
+    // The median of the recent samples, which ignores the odd misdetection entirely
+    fn smoothed(&self) -> Proximity {
+        match self.samples.back() {
+            Some(Proximity::Distance(_)) => {
+                let mut distances: Vec<f32> = self
+                    .samples
+                    .iter()
+                    .filter_map(|m| match m {
+                        Proximity::Distance(distance) => Some(*distance),
+                        _ => None,
+                    })
+                    .collect();
+                distances.sort_unstable_by(f32::total_cmp);
+                Proximity::Distance(distances[distances.len() / 2])
+            }
+            _ => {
+                let (mut widths, mut heights): (Vec<u32>, Vec<u32>) = self
+                    .samples
+                    .iter()
+                    .filter_map(|m| match m {
+                        Proximity::FaceSize { width, height } => Some((*width, *height)),
+                        _ => None,
+                    })
+                    .unzip();
+                widths.sort_unstable();
+                heights.sort_unstable();
+                Proximity::FaceSize {
+                    width: widths[widths.len() / 2],
+                    height: heights[heights.len() / 2],
+                }
+            }
+        }
+    }
+
+    fn dwell(&self, posture: Posture) -> Duration {
+        match posture {
+            Posture::TooClose => self.thresholds.trigger_after,
+            Posture::Good => self.thresholds.recover_after,
+            Posture::Away => self.thresholds.away_after,
+        }
+    }
+}
+
+// The events describing a change from one posture to the next
+fn transition_events(from: Posture, to: Posture) -> Vec<PostureEvent> {
+    match (from, to) {
+        (Posture::Good, Posture::TooClose) => vec![PostureEvent::TooClose],
+        (Posture::TooClose, Posture::Good) => vec![PostureEvent::Recovered],
+        (Posture::Good | Posture::TooClose, Posture::Away) => vec![PostureEvent::Away],
+        (Posture::Away, Posture::Good) => vec![PostureEvent::Returned],
+        (Posture::Away, Posture::TooClose) => {
+            vec![PostureEvent::Returned, PostureEvent::TooClose]
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOOD: Option<Proximity> = Some(Proximity::FaceSize {
+        width: 90,
+        height: 90,
+    });
+    const CLOSE: Option<Proximity> = Some(Proximity::FaceSize {
+        width: 120,
+        height: 120,
+    });
+    // between the recovery threshold (95) and the maximum (100)
+    const BORDERLINE: Option<Proximity> = Some(Proximity::FaceSize {
+        width: 98,
+        height: 98,
+    });
+
+    // no smoothing or grace period, so that the dwell logic can be tested on its own
+    fn thresholds() -> PostureThresholds {
+        let mut thresholds = PostureThresholds::new(Proximity::FaceSize {
+            width: 100,
+            height: 100,
+        });
+        thresholds.smoothing_window = 1;
+        thresholds.grace_period = Duration::ZERO;
+        thresholds
+    }
+
+    fn machine() -> PostureStateMachine {
+        PostureStateMachine::new(thresholds())
+    }
+
+    fn immediate() -> PostureStateMachine {
+        let mut thresholds = thresholds();
+        thresholds.trigger_after = Duration::ZERO;
+        thresholds.recover_after = Duration::ZERO;
+        thresholds.away_after = Duration::ZERO;
+        PostureStateMachine::new(thresholds)
+    }
+
+    fn secs(start: Instant, secs: f32) -> Instant {
+        start + Duration::from_secs_f32(secs)
+    }
+
+    #[test]
+    fn starts_good() {
+        assert_eq!(machine().state(), Posture::Good);
+    }
+
+    #[test]
+    fn good_posture_emits_nothing() {
+        let mut machine = machine();
+        let start = Instant::now();
+        for i in 0..10 {
+            assert!(machine.update(GOOD, secs(start, i as f32)).is_empty());
+        }
+        assert_eq!(machine.state(), Posture::Good);
+    }
+
+    #[test]
+    fn too_close_after_trigger_delay() {
+        let mut machine = machine();
+        let start = Instant::now();
+        assert!(machine.update(CLOSE, start).is_empty());
+        assert!(machine.update(CLOSE, secs(start, 1.9)).is_empty());
+        assert_eq!(machine.state(), Posture::Good);
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 2.0)),
+            vec![PostureEvent::TooClose]
+        );
+        assert_eq!(machine.state(), Posture::TooClose);
+        assert!(machine.update(CLOSE, secs(start, 3.0)).is_empty());
+    }
+
+    #[test]
+    fn either_dimension_over_threshold_is_too_close() {
+        let mut machine = immediate();
+        let wide = Some(Proximity::FaceSize {
+            width: 101,
+            height: 50,
+        });
+        assert_eq!(
+            machine.update(wide, Instant::now()),
+            vec![PostureEvent::TooClose]
+        );
+        let mut machine = immediate();
+        let tall = Some(Proximity::FaceSize {
+            width: 50,
+            height: 101,
+        });
+        assert_eq!(
+            machine.update(tall, Instant::now()),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn exactly_at_threshold_is_good() {
+        let mut machine = immediate();
+        let at = Some(Proximity::FaceSize {
+            width: 100,
+            height: 100,
+        });
+        assert!(machine.update(at, Instant::now()).is_empty());
+        assert_eq!(machine.state(), Posture::Good);
+    }
+
+    #[test]
+    fn interrupted_trigger_starts_over() {
+        let mut machine = machine();
+        let start = Instant::now();
+        machine.update(CLOSE, start);
+        machine.update(CLOSE, secs(start, 1.5));
+        machine.update(GOOD, secs(start, 1.6));
+        assert!(machine.update(CLOSE, secs(start, 2.5)).is_empty());
+        assert!(machine.update(CLOSE, secs(start, 4.4)).is_empty());
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 4.5)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn recovers_after_recover_delay() {
+        let mut machine = machine();
+        let start = Instant::now();
+        machine.update(CLOSE, start);
+        machine.update(CLOSE, secs(start, 2.0));
+        assert!(machine.update(GOOD, secs(start, 3.0)).is_empty());
+        assert!(machine.update(GOOD, secs(start, 3.5)).is_empty());
+        assert_eq!(
+            machine.update(GOOD, secs(start, 4.0)),
+            vec![PostureEvent::Recovered]
+        );
+        assert_eq!(machine.state(), Posture::Good);
+    }
+
+    #[test]
+    fn hysteresis_holds_too_close_in_band() {
+        let mut machine = immediate();
+        let start = Instant::now();
+        machine.update(CLOSE, start);
+        assert_eq!(machine.state(), Posture::TooClose);
+        assert!(machine.update(BORDERLINE, secs(start, 1.0)).is_empty());
+        assert_eq!(machine.state(), Posture::TooClose);
+        assert_eq!(
+            machine.update(GOOD, secs(start, 2.0)),
+            vec![PostureEvent::Recovered]
+        );
+    }
+
+    #[test]
+    fn hysteresis_does_not_trigger_from_good() {
+        let mut machine = immediate();
+        assert!(machine.update(BORDERLINE, Instant::now()).is_empty());
+        assert_eq!(machine.state(), Posture::Good);
+    }
+
+    #[test]
+    fn away_and_return() {
+        let mut machine = machine();
+        let start = Instant::now();
+        assert!(machine.update(None, start).is_empty());
+        assert!(machine.update(None, secs(start, 2.9)).is_empty());
+        assert_eq!(
+            machine.update(None, secs(start, 3.0)),
+            vec![PostureEvent::Away]
+        );
+        assert_eq!(machine.state(), Posture::Away);
+        machine.update(GOOD, secs(start, 4.0));
+        assert_eq!(
+            machine.update(GOOD, secs(start, 5.0)),
+            vec![PostureEvent::Returned]
+        );
+    }
+
+    #[test]
+    fn away_while_too_close() {
+        let mut machine = immediate();
+        let start = Instant::now();
+        machine.update(CLOSE, start);
+        assert_eq!(
+            machine.update(None, secs(start, 1.0)),
+            vec![PostureEvent::Away]
+        );
+    }
+
+    #[test]
+    fn return_too_close() {
+        let mut machine = immediate();
+        let start = Instant::now();
+        machine.update(None, start);
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 1.0)),
+            vec![PostureEvent::Returned, PostureEvent::TooClose]
+        );
+        assert_eq!(machine.state(), Posture::TooClose);
+    }
+
+    #[test]
+    fn brief_dropout_is_not_away() {
+        let mut machine = machine();
+        let start = Instant::now();
+        machine.update(GOOD, start);
+        machine.update(None, secs(start, 1.0));
+        machine.update(None, secs(start, 2.0));
+        assert!(machine.update(GOOD, secs(start, 3.0)).is_empty());
+        assert!(machine.update(None, secs(start, 4.0)).is_empty());
+        assert_eq!(machine.state(), Posture::Good);
+    }
+
+    #[test]
+    fn reset_is_silent() {
+        let mut machine = immediate();
+        let start = Instant::now();
+        machine.update(CLOSE, start);
+        machine.reset();
+        assert_eq!(machine.state(), Posture::Good);
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 1.0)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn time_going_backwards_does_not_trigger() {
+        let mut machine = machine();
+        let start = Instant::now() + Duration::from_secs(10);
+        machine.update(CLOSE, start);
+        assert!(machine
+            .update(CLOSE, start - Duration::from_secs(5))
+            .is_empty());
+        assert_eq!(machine.state(), Posture::Good);
+    }
+
+    #[test]
+    fn grace_period_holds_back_events() {
+        let mut thresholds = thresholds();
+        thresholds.trigger_after = Duration::ZERO;
+        thresholds.grace_period = Duration::from_secs(10);
+        let mut machine = PostureStateMachine::new(thresholds);
+        let start = Instant::now();
+        assert!(machine.update(CLOSE, start).is_empty());
+        assert!(machine.update(CLOSE, secs(start, 9.9)).is_empty());
+        assert!(machine.update(None, secs(start, 9.95)).is_empty());
+        assert_eq!(machine.state(), Posture::Good);
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 10.0)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn grace_period_restarts() {
+        let mut thresholds = thresholds();
+        thresholds.trigger_after = Duration::ZERO;
+        thresholds.grace_period = Duration::from_secs(10);
+        let mut machine = PostureStateMachine::new(thresholds);
+        let start = Instant::now();
+        machine.update(GOOD, start);
+        machine.start_grace(secs(start, 60.0));
+        assert!(machine.update(CLOSE, secs(start, 61.0)).is_empty());
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 70.0)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn warm_up_fills_the_filter_first() {
+        let mut thresholds = thresholds();
+        thresholds.trigger_after = Duration::ZERO;
+        thresholds.smoothing_window = 3;
+        let mut machine = PostureStateMachine::new(thresholds);
+        let start = Instant::now();
+        assert!(machine.update(CLOSE, start).is_empty());
+        assert!(machine.update(CLOSE, secs(start, 1.0)).is_empty());
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 2.0)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn warm_up_does_not_delay_away() {
+        let mut thresholds = thresholds();
+        thresholds.away_after = Duration::ZERO;
+        thresholds.smoothing_window = 3;
+        let mut machine = PostureStateMachine::new(thresholds);
+        assert_eq!(
+            machine.update(None, Instant::now()),
+            vec![PostureEvent::Away]
+        );
+    }
+
+    #[test]
+    fn smoothing_ignores_a_single_outlier() {
+        let mut thresholds = thresholds();
+        thresholds.trigger_after = Duration::ZERO;
+        thresholds.smoothing_window = 3;
+        let mut machine = PostureStateMachine::new(thresholds);
+        let start = Instant::now();
+        machine.update(GOOD, start);
+        machine.update(GOOD, secs(start, 1.0));
+        assert!(machine.update(CLOSE, secs(start, 2.0)).is_empty());
+        assert!(machine.update(GOOD, secs(start, 3.0)).is_empty());
+        assert_eq!(machine.state(), Posture::Good);
+        // two out of the last three are too close
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 4.0)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn distance_below_limit_is_too_close() {
+        let mut thresholds = thresholds();
+        thresholds.limit = Proximity::Distance(50.0);
+        thresholds.trigger_after = Duration::ZERO;
+        thresholds.recover_after = Duration::ZERO;
+        let mut machine = PostureStateMachine::new(thresholds);
+        let start = Instant::now();
+        assert!(machine
+            .update(Some(Proximity::Distance(60.0)), start)
+            .is_empty());
+        assert_eq!(
+            machine.update(Some(Proximity::Distance(45.0)), secs(start, 1.0)),
+            vec![PostureEvent::TooClose]
+        );
+        // within the hysteresis band above the limit
+        assert!(machine
+            .update(Some(Proximity::Distance(51.0)), secs(start, 2.0))
+            .is_empty());
+        assert_eq!(
+            machine.update(Some(Proximity::Distance(55.0)), secs(start, 3.0)),
+            vec![PostureEvent::Recovered]
+        );
+    }
+
+    #[test]
+    fn limit_scale_loosens_the_limit() {
+        let mut machine = immediate();
+        let start = Instant::now();
+        machine.set_limit_scale(1.3);
+        assert!(machine.update(CLOSE, start).is_empty());
+        machine.set_limit_scale(1.1);
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 1.0)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn sensitivity_tightens_the_limit() {
+        let mut machine = immediate();
+        let start = Instant::now();
+        assert!(machine.update(GOOD, start).is_empty());
+        machine.set_sensitivity(1.2);
+        assert_eq!(
+            machine.update(GOOD, secs(start, 1.0)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn a_rule_combines_the_measurement_with_other_conditions() {
+        let mut thresholds = thresholds();
+        thresholds.trigger_after = Duration::ZERO;
+        thresholds.recover_after = Duration::ZERO;
+        thresholds.away_after = Duration::ZERO;
+        thresholds.rule = Rule::parse("too_close and not slouching or tilt_down").unwrap();
+        let mut machine = PostureStateMachine::new(thresholds);
+        let start = Instant::now();
+        let tilted = Conditions {
+            tilt_down: true,
+            ..Conditions::default()
+        };
+        let slouching = Conditions {
+            slouching: true,
+            ..Conditions::default()
+        };
+        assert!(machine.update_with(CLOSE, slouching, start).is_empty());
+        assert_eq!(
+            machine.update_with(GOOD, tilted, secs(start, 1.0)),
+            vec![PostureEvent::TooClose]
+        );
+        assert_eq!(
+            machine.update(GOOD, secs(start, 2.0)),
+            vec![PostureEvent::Recovered]
+        );
+        // being away still goes by the measurement alone
+        assert_eq!(
+            machine.update_with(None, tilted, secs(start, 3.0)),
+            vec![PostureEvent::Away]
+        );
+    }
+
+    #[test]
+    fn a_judge_has_the_last_word() {
+        let mut machine = immediate();
+        let start = Instant::now();
+        let mut seen = Vec::new();
+        let mut verdicts = [None, Some(false), Some(true)].into_iter();
+        let mut judge = |check: &Check| {
+            seen.push((check.too_close, check.state));
+            verdicts.next().flatten()
+        };
+        assert!(machine
+            .update_judged(GOOD, Conditions::default(), Some(&mut judge), start)
+            .is_empty());
+        assert!(machine
+            .update_judged(
+                CLOSE,
+                Conditions::default(),
+                Some(&mut judge),
+                secs(start, 1.0)
+            )
+            .is_empty());
+        assert_eq!(
+            machine.update_judged(
+                GOOD,
+                Conditions::default(),
+                Some(&mut judge),
+                secs(start, 2.0)
+            ),
+            vec![PostureEvent::TooClose]
+        );
+        assert_eq!(
+            seen,
+            vec![
+                (false, Posture::Good),
+                (true, Posture::Good),
+                (false, Posture::Good)
+            ]
+        );
+    }
+
+    fn duty_cycle(too_close_for: f32, window: f32) -> PostureStateMachine {
+        let mut thresholds = thresholds();
+        thresholds.duty_cycle = Some(DutyCycle {
+            too_close_for: Duration::from_secs_f32(too_close_for),
+            window: Duration::from_secs_f32(window),
+        });
+        PostureStateMachine::new(thresholds)
+    }
+
+    #[test]
+    fn duty_cycle_counts_interrupted_time() {
+        let mut machine = duty_cycle(6.0, 10.0);
+        let start = Instant::now();
+        // too close for 3 seconds, sitting back for 1, then too close again
+        for i in 0..3 {
+            assert!(machine.update(CLOSE, secs(start, i as f32)).is_empty());
+        }
+        assert!(machine.update(GOOD, secs(start, 3.0)).is_empty());
+        for i in 4..7 {
+            assert!(machine.update(CLOSE, secs(start, i as f32)).is_empty());
+        }
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 7.0)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn duty_cycle_forgets_what_left_the_window() {
+        let mut machine = duty_cycle(6.0, 10.0);
+        let start = Instant::now();
+        // too close for 5 out of every 10 seconds never adds up to 6
+        for i in 0..40 {
+            let metric = if i % 10 < 5 { CLOSE } else { GOOD };
+            assert!(machine.update(metric, secs(start, i as f32)).is_empty());
+        }
+        assert_eq!(machine.state(), Posture::Good);
+    }
+
+    #[test]
+    fn duty_cycle_starts_over_after_recovering() {
+        let mut machine = duty_cycle(2.0, 10.0);
+        let start = Instant::now();
+        machine.update(CLOSE, start);
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 2.0)),
+            vec![PostureEvent::TooClose]
+        );
+        // recovering still takes `recover_after` in a row
+        assert!(machine.update(GOOD, secs(start, 3.0)).is_empty());
+        assert_eq!(
+            machine.update(GOOD, secs(start, 4.0)),
+            vec![PostureEvent::Recovered]
+        );
+        assert!(machine.update(CLOSE, secs(start, 5.0)).is_empty());
+        assert!(machine.update(CLOSE, secs(start, 6.0)).is_empty());
+        assert_eq!(
+            machine.update(CLOSE, secs(start, 7.0)),
+            vec![PostureEvent::TooClose]
+        );
+    }
+
+    #[test]
+    fn duty_window_keeps_only_what_overlaps_it() {
+        let mut window = DutyWindow::new(DutyCycle {
+            too_close_for: Duration::from_secs(6),
+            window: Duration::from_secs(10),
+        });
+        let start = Instant::now();
+        window.record(true, start);
+        window.record(false, secs(start, 4.0));
+        window.record(true, secs(start, 8.0));
+        assert_eq!(window.too_close(secs(start, 8.0)), Duration::from_secs(4));
+        // the first 2 seconds have left the window
+        assert_eq!(window.too_close(secs(start, 12.0)), Duration::from_secs(6));
+        window.record(true, secs(start, 20.0));
+        assert_eq!(window.observations.len(), 2);
+        assert_eq!(window.too_close(secs(start, 20.0)), Duration::from_secs(10));
+        // nor does it go back in time
+        window.record(false, secs(start, 15.0));
+        assert_eq!(window.observations.len(), 2);
+    }
+
+    #[test]
+    fn schedule_ranges_may_wrap_past_midnight() {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let schedule = vec![
+            SensitivityRange {
+                from: time(16, 0),
+                to: time(1, 0),
+                sensitivity: 1.2,
+            },
+            SensitivityRange {
+                from: time(6, 0),
+                to: time(9, 0),
+                sensitivity: 0.9,
+            },
+        ];
+        assert_eq!(sensitivity_at(&schedule, time(15, 59)), 1.0);
+        assert_eq!(sensitivity_at(&schedule, time(16, 0)), 1.2);
+        assert_eq!(sensitivity_at(&schedule, time(0, 30)), 1.2);
+        assert_eq!(sensitivity_at(&schedule, time(7, 0)), 0.9);
+        assert_eq!(sensitivity_at(&schedule, time(12, 0)), 1.0);
+    }
+
+    #[test]
+    fn sustained_condition_fires_once_and_clears() {
+        let mut condition = SustainedCondition::new(Duration::from_secs(120));
+        let start = Instant::now();
+        assert_eq!(condition.update(true, start), None);
+        assert_eq!(condition.update(true, secs(start, 119.0)), None);
+        assert_eq!(condition.update(true, secs(start, 120.0)), Some(true));
+        assert_eq!(condition.update(true, secs(start, 200.0)), None);
+        assert_eq!(condition.update(false, secs(start, 201.0)), Some(false));
+        assert_eq!(condition.update(false, secs(start, 202.0)), None);
+    }
+
+    #[test]
+    fn sustained_condition_restarts_when_interrupted() {
+        let mut condition = SustainedCondition::new(Duration::from_secs(120));
+        let start = Instant::now();
+        condition.update(true, start);
+        assert_eq!(condition.update(false, secs(start, 100.0)), None);
+        assert_eq!(condition.update(true, secs(start, 130.0)), None);
+        assert_eq!(condition.update(true, secs(start, 249.0)), None);
+        assert_eq!(condition.update(true, secs(start, 250.0)), Some(true));
+    }
+
+    #[test]
+    fn transition_table() {
+        use Posture::*;
+        assert!(transition_events(Good, Good).is_empty());
+        assert!(transition_events(TooClose, TooClose).is_empty());
+        assert!(transition_events(Away, Away).is_empty());
+        assert_eq!(
+            transition_events(Good, TooClose),
+            vec![PostureEvent::TooClose]
+        );
+        assert_eq!(
+            transition_events(TooClose, Good),
+            vec![PostureEvent::Recovered]
+        );
+        assert_eq!(transition_events(Good, Away), vec![PostureEvent::Away]);
+        assert_eq!(transition_events(TooClose, Away), vec![PostureEvent::Away]);
+        assert_eq!(transition_events(Away, Good), vec![PostureEvent::Returned]);
+        assert_eq!(
+            transition_events(Away, TooClose),
+            vec![PostureEvent::Returned, PostureEvent::TooClose]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::{Duration, Instant};
+
+use super::Proximity;
+use crate::config::OutliersConfig;
+
+// measurements further apart than this say nothing about how fast anyone moves
+const MAX_GAP: Duration = Duration::from_secs(5);
+
+// Rejects measurements that changed more since the previous one than a person moves between two
+// checks, e.g. the detector latching onto a poster for a frame. A new size that keeps coming back is
+// believed after a few checks, as when the camera was moved.
+pub struct JumpFilter {
+    // the largest believable ratio between two measurements
+    max_ratio: f32,
+    accept_after: u32,
+    // the latest believable measurement, and when it was taken
+    last: Option<(Proximity, Instant)>,
+    // the latest of the measurements rejected since, and how many in a row were about that size
+    candidate: Option<Proximity>,
+    rejected: u32,
+}
+
+impl JumpFilter {
+    pub fn new(config: &OutliersConfig) -> JumpFilter {
+        JumpFilter {
+            max_ratio: 1.0 + config.max_jump,
+            accept_after: config.accept_after,
+            last: None,
+            candidate: None,
+            rejected: 0,
+        }
+    }
+
+    // Whether to believe the measurement taken at `now`; nobody in view is always believed
+    pub fn plausible(&mut self, metric: Option<Proximity>, now: Instant) -> bool {
+        let Some(metric) = metric else {
+            self.forget();
+            return true;
+        };
+        let jumped = self.last.is_some_and(|(last, at)| {
+            now.saturating_duration_since(at) <= MAX_GAP
+                && ratio(last, metric).is_some_and(|ratio| ratio > self.max_ratio)
+        });
+        if jumped {
+            // only outliers of about the same size add up to a new one; a different one starts over
+            let persists = self.candidate.is_some_and(|candidate| {
+                ratio(candidate, metric).is_some_and(|ratio| ratio <= self.max_ratio)
+            });
+            self.rejected = if persists { self.rejected + 1 } else { 1 };
+            if self.rejected < self.accept_after {
+                self.candidate = Some(metric);
+                return false;
+            }
+        }
+        self.last = Some((metric, now));
+        self.candidate = None;
+        self.rejected = 0;
+        true
+    }
+
+    pub fn forget(&mut self) {
+        self.last = None;
+        self.candidate = None;
+        self.rejected = 0;
+    }
+}
+
+// How many times larger the bigger of the two is, by whichever dimension changed most; None if they
+// can't be compared
+fn ratio(a: Proximity, b: Proximity) -> Option<f32> {
+    let ratio = |a: f32, b: f32| (a > 0.0 && b > 0.0).then(|| a.max(b) / a.min(b));
+    match (a, b) {
+        (
+            Proximity::FaceSize { width, height },
+            Proximity::FaceSize {
+                width: other_width,
+                height: other_height,
+            },
+        ) => Some(
+            ratio(width as f32, other_width as f32)?
+                .max(ratio(height as f32, other_height as f32)?),
+        ),
+        (Proximity::Distance(a), Proximity::Distance(b)) => ratio(a, b),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn impossible_jumps_are_rejected_until_they_persist() {
+        let mut filter = JumpFilter::new(&OutliersConfig {
+            enabled: true,
+            max_jump: 0.5,
+            accept_after: 3,
+        });
+        let size = |size| {
+            Some(Proximity::FaceSize {
+                width: size,
+                height: size,
+            })
+        };
+        let start = Instant::now();
+        let at = |secs: f32| start + Duration::from_secs_f32(secs);
+        assert!(filter.plausible(size(100), at(0.0)));
+        assert!(filter.plausible(size(130), at(0.5)));
+        // a poster twice the size, for a frame
+        assert!(!filter.plausible(size(260), at(1.0)));
+        assert!(filter.plausible(size(125), at(1.5)));
+        // outliers that don't agree with each other never add up to a new size
+        assert!(!filter.plausible(size(260), at(1.6)));
+        assert!(!filter.plausible(size(30), at(1.7)));
+        assert!(!filter.plausible(size(400), at(1.8)));
+        assert!(filter.plausible(size(120), at(1.9)));
+        // the camera was moved closer, and the new size holds
+        assert!(!filter.plausible(size(250), at(2.0)));
+        assert!(!filter.plausible(size(250), at(2.5)));
+        assert!(filter.plausible(size(250), at(3.0)));
+        assert!(filter.plausible(size(255), at(3.5)));
+        // after a while, or after nobody was there, anything goes
+        assert!(filter.plausible(size(100), at(10.0)));
+        assert!(filter.plausible(None, at(10.5)));
+        assert!(filter.plausible(size(300), at(11.0)));
+        // distances the same
+        assert!(filter.plausible(Some(Proximity::Distance(60.0)), at(11.5)));
+        assert!(!filter.plausible(Some(Proximity::Distance(25.0)), at(12.0)));
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer};
+
+use super::{Posture, Proximity};
+
+// What can be said about the posture at a check, for a rule to combine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    // closer than the calibrated limit
+    TooClose,
+    // the head bent forward further than neck_angle.max_angle
+    TiltDown,
+    // the face lower in the frame than usual by more than face_drift.max_drop
+    LowPosition,
+    // the shoulders raised or rolled forward, by slouch.tolerance
+    Slouching,
+}
+
+impl Condition {
+    const ALL: [Condition; 4] = [
+        Condition::TooClose,
+        Condition::TiltDown,
+        Condition::LowPosition,
+        Condition::Slouching,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Condition::TooClose => "too_close",
+            Condition::TiltDown => "tilt_down",
+            Condition::LowPosition => "low_position",
+            Condition::Slouching => "slouching",
+        }
+    }
+}
+
+// The conditions besides too_close at a check, from the monitors that are running; false for any
+// that isn't
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Conditions {
+    pub tilt_down: bool,
+    pub low_position: bool,
+    pub slouching: bool,
+}
+
+// What a judge besides the rule is shown of a check
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Check {
+    // the latest measurement, and the median the posture is judged on
+    pub current: Proximity,
+    pub smoothed: Proximity,
+    pub limit: Proximity,
+    // what the rule made of it
+    pub too_close: bool,
+    pub conditions: Conditions,
+    pub state: Posture,
+}
+
+// Has the last word on whether a check is too close, e.g. a user's script; None leaves it to the
+// rule
+pub type Judge<'a> = dyn FnMut(&Check) -> Option<bool> + 'a;
+
+// How the conditions combine into bad posture, e.g. `too_close or (tilt_down and low_position)`.
+// `not` binds tightest, then `and`, then `or`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    Is(Condition),
+    Not(Box<Rule>),
+    And(Box<Rule>, Box<Rule>),
+    Or(Box<Rule>, Box<Rule>),
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::Is(Condition::TooClose)
+    }
+}
+
+impl Rule {
+    pub fn parse(text: &str) -> Result<Rule, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, next: 0 };
+        let rule = parser.or()?;
+        match parser.tokens.get(parser.next) {
+            None => Ok(rule),
+            Some(token) => Err(format!("Unexpected {} in the rule {:?}", token, text)),
+        }
+    }
+
+    pub fn evaluate(&self, too_close: bool, conditions: &Conditions) -> bool {
+        match self {
+            Rule::Is(Condition::TooClose) => too_close,
+            Rule::Is(Condition::TiltDown) => conditions.tilt_down,
+            Rule::Is(Condition::LowPosition) => conditions.low_position,
+            Rule::Is(Condition::Slouching) => conditions.slouching,
+            Rule::Not(rule) => !rule.evaluate(too_close, conditions),
+            Rule::And(a, b) => {
+                a.evaluate(too_close, conditions) && b.evaluate(too_close, conditions)
+            }
+            Rule::Or(a, b) => {
+                a.evaluate(too_close, conditions) || b.evaluate(too_close, conditions)
+            }
+        }
+    }
+
+    // Whether the rule looks at the condition, so that whatever measures it has to run
+    pub fn uses(&self, condition: Condition) -> bool {
+        match self {
+            Rule::Is(is) => *is == condition,
+            Rule::Not(rule) => rule.uses(condition),
+            Rule::And(a, b) | Rule::Or(a, b) => a.uses(condition) || b.uses(condition),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Rule, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Rule::parse(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(Condition),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Name(condition) => write!(f, "{}", condition.name()),
+            Token::And => write!(f, "and"),
+            Token::Or => write!(f, "or"),
+            Token::Not => write!(f, "not"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = String::new();
+                while let Some(&c) = chars
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || **c == '_')
+                {
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    name => match Condition::ALL.into_iter().find(|c| c.name() == name) {
+                        Some(condition) => Token::Name(condition),
+                        None => {
+                            let known: Vec<&str> =
+                                Condition::ALL.iter().map(|c| c.name()).collect();
+                            return Err(format!(
+                                "Unknown condition {:?} in the rule; try {}",
+                                word,
+                                known.join(", ")
+                            ));
+                        }
+                    },
+                });
+            }
+            _ => return Err(format!("Unexpected {:?} in the rule {:?}", c, text)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn take(&mut self, token: &Token) -> bool {
+        let found = self.tokens.get(self.next) == Some(token);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Rule, String> {
+        let mut rule = self.and()?;
+        while self.take(&Token::Or) {
+            rule = Rule::Or(Box::new(rule), Box::new(self.and()?));
+        }
+        Ok(rule)
+    }
+
+    fn and(&mut self) -> Result<Rule, String> {
+        let mut rule = self.not()?;
+        while self.take(&Token::And) {
+            rule = Rule::And(Box::new(rule), Box::new(self.not()?));
+        }
+        Ok(rule)
+    }
+
+    fn not(&mut self) -> Result<Rule, String> {
+        if self.take(&Token::Not) {
+            return Ok(Rule::Not(Box::new(self.not()?)));
+        }
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        match token {
+            Some(Token::Name(condition)) => Ok(Rule::Is(condition)),
+            Some(Token::Open) => {
+                let rule = self.or()?;
+                if !self.take(&Token::Close) {
+                    return Err("A \"(\" in the rule isn't closed".to_string());
+                }
+                Ok(rule)
+            }
+            Some(token) => Err(format!("Expected a condition in the rule, not {}", token)),
+            None => Err("The rule ends where a condition was expected".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_combine_the_conditions() {
+        let rule = Rule::parse("too_close OR (tilt_down and low_position)").unwrap();
+        let conditions = |tilt_down, low_position| Conditions {
+            tilt_down,
+            low_position,
+            slouching: false,
+        };
+        assert!(rule.evaluate(true, &conditions(false, false)));
+        assert!(!rule.evaluate(false, &conditions(true, false)));
+        assert!(rule.evaluate(false, &conditions(true, true)));
+        assert!(rule.uses(Condition::LowPosition));
+        assert!(!rule.uses(Condition::Slouching));
+
+        // and before or, not before and
+        let rule = Rule::parse("not slouching and too_close or tilt_down").unwrap();
+        assert_eq!(
+            rule,
+            Rule::Or(
+                Box::new(Rule::And(
+                    Box::new(Rule::Not(Box::new(Rule::Is(Condition::Slouching)))),
+                    Box::new(Rule::Is(Condition::TooClose)),
+                )),
+                Box::new(Rule::Is(Condition::TiltDown)),
+            )
+        );
+        assert_eq!(Rule::parse(" too_close ").unwrap(), Rule::default());
+
+        for wrong in [
+            "",
+            "too_close and",
+            "(too_close",
+            "too_close)",
+            "too_close tilt_down",
+            "leaning",
+            "too_close & tilt_down",
+        ] {
+            assert!(Rule::parse(wrong).is_err(), "{:?}", wrong);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::PowerConfig;
+use crate::desktop::{self, Battery, ThermalMonitor};
+use crate::events::{EventBus, PostureEvent};
+use crate::worker::WorkerHandle;
+
+// What the machine says about its power and heat
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PowerState {
+    pub battery: Option<Battery>,
+    // since the last look
+    pub throttled: bool,
+}
+
+// Why monitoring should be reduced to save power, if it should
+pub fn saving_reason(config: &PowerConfig, state: &PowerState) -> Option<String> {
+    if let Some(battery) = state.battery {
+        if battery.discharging && battery.percent < config.min_battery {
+            return Some(format!("on battery at {:.0}%", battery.percent));
+        }
+    }
+    if config.thermal && state.throttled {
+        return Some("the CPU is throttled to keep cool".to_string());
+    }
+    None
+}
+
+// Looks at the battery and the CPU's temperature every so often until shutdown. While monitoring
+// is reduced, faces are detected at a lower resolution, `saving` is set for the main loop to
+// check less often, and the status displays hear why.
+pub async fn run(
+    config: PowerConfig,
+    worker: WorkerHandle,
+    events: EventBus,
+    saving: watch::Sender<bool>,
+    shutdown: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(config.interval));
+    let mut thermal = config.thermal.then(ThermalMonitor::new);
+    let mut reduced = None;
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let battery = match tokio::task::spawn_blocking(desktop::battery).await {
+            Ok(Ok(battery)) => battery,
+            Ok(Err(e)) => {
+                println!("Failed to read the battery: {}", e);
+                None
+            }
+            Err(_) => None,
+        };
+        let mut throttled = false;
+        if let Some(mut monitor) = thermal.take() {
+            match tokio::task::spawn_blocking(move || (monitor.throttled(), monitor)).await {
+                Ok((Ok(now), monitor)) => {
+                    throttled = now;
+                    thermal = Some(monitor);
+                }
+                Ok((Err(e), _)) => println!("Not reducing monitoring while hot: {}", e),
+                Err(_) => {}
+            }
+        }
+        let reason = saving_reason(&config, &PowerState { battery, throttled });
+        if reason == reduced {
+            continue;
+        }
+        match &reason {
+            Some(reason) => println!("Reducing monitoring to save power: {}", reason),
+            None => println!("Monitoring back to normal"),
+        }
+        let scale = if reason.is_some() {
+            config.max_scale
+        } else {
+            1.0
+        };
+        let handle = worker.clone();
+        let limited = tokio::task::spawn_blocking(move || handle.limit_detection_scale(scale))
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+        if let Err(e) = limited {
+            println!("Failed to change the detection resolution: {}", e);
+        }
+        saving.send_replace(reason.is_some());
+        events.publish(PostureEvent::PowerSaving(reason.clone()));
+        reduced = reason;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitoring_is_reduced_on_a_low_battery_or_when_hot() {
+        let config = PowerConfig::default();
+        let battery = |discharging, percent| PowerState {
+            battery: Some(Battery {
+                discharging,
+                percent,
+            }),
+            throttled: false,
+        };
+        assert_eq!(saving_reason(&config, &PowerState::default()), None);
+        assert_eq!(saving_reason(&config, &battery(true, 50.0)), None);
+        assert_eq!(
+            saving_reason(&config, &battery(true, 20.0)).as_deref(),
+            Some("on battery at 20%")
+        );
+        // charging, however low
+        assert_eq!(saving_reason(&config, &battery(false, 5.0)), None);
+
+        let hot = PowerState {
+            throttled: true,
+            ..PowerState::default()
+        };
+        assert!(saving_reason(&config, &hot).is_some());
+        let config = PowerConfig {
+            thermal: false,
+            ..config
+        };
+        assert_eq!(saving_reason(&config, &hot), None);
+    }
+}
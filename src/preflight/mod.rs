@@ -0,0 +1,155 @@
+// Checks that neckcheck may use the camera before it first opens it, so that a missing permission
+// comes with what to do about it rather than a failure deep inside the camera library
+
+#[cfg(target_os = "macos")]
+use std::time::Duration;
+
+// how long to wait for an answer to macOS's camera prompt
+#[cfg(target_os = "macos")]
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Whether the local camera with the given index may be opened, or what to do so that it can
+pub fn camera_access(index: u32) -> Result<(), String> {
+    check(index)
+}
+
+#[cfg(target_os = "linux")]
+fn check(index: u32) -> Result<(), String> {
+    use std::fs::OpenOptions;
+    use std::io::ErrorKind;
+    use std::os::unix::fs::MetadataExt;
+
+    let device = format!("/dev/video{}", index);
+    let metadata = match std::fs::metadata(&device) {
+        Ok(metadata) => metadata,
+        Err(_) => return Err(missing_device(&device)),
+    };
+    match OpenOptions::new().read(true).write(true).open(&device) {
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            let group = std::fs::read_to_string("/etc/group")
+                .ok()
+                .and_then(|groups| group_name(&groups, metadata.gid()))
+                .unwrap_or_else(|| "video".to_string());
+            Err(format!(
+                "Not allowed to open {}, which belongs to the {} group. Add yourself to it with \
+                 `sudo usermod -aG {} $USER`, then log out and back in.",
+                device, group, group
+            ))
+        }
+        // anything else, e.g. the camera being busy, is for the camera to report
+        _ => Ok(()),
+    }
+}
+
+// What to do about a camera that isn't there, which in a sandbox is usually one it can't see
+#[cfg(target_os = "linux")]
+fn missing_device(device: &str) -> String {
+    if let Ok(app) = std::env::var("FLATPAK_ID") {
+        return format!(
+            "{} can't be seen from inside the Flatpak. Give it access to the camera with \
+             `flatpak override --user --device=all {}` (or in Flatseal), then start it again.",
+            device, app
+        );
+    }
+    if let Ok(snap) = std::env::var("SNAP_NAME") {
+        return format!(
+            "{} can't be seen from inside the snap. Give it access to the camera with \
+             `sudo snap connect {}:camera`, then start it again.",
+            device, snap
+        );
+    }
+    format!(
+        "There is no camera at {}. Check that it is plugged in (`ls /dev/video*` lists the \
+         cameras), or pick another with --camera.",
+        device
+    )
+}
+
+// The name of the group with the given id, from /etc/group
+#[cfg(any(target_os = "linux", test))]
+fn group_name(groups: &str, gid: u32) -> Option<String> {
+    groups.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let id = fields.nth(1)?.parse::<u32>().ok()?;
+        (id == gid).then(|| name.to_string())
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn check(_index: u32) -> Result<(), String> {
+    const DENIED: &str = "neckcheck isn't allowed to use the camera. Turn it on for your terminal \
+        (or neckcheck) in System Settings > Privacy & Security > Camera, then start it again. If \
+        it isn't listed there, `tccutil reset Camera` makes macOS ask again.";
+    if nokhwa::nokhwa_check() {
+        return Ok(());
+    }
+    // asks the user the first time, and answers straight away with what they said before after
+    // that; the answer comes on another thread
+    let (answer, answered) = std::sync::mpsc::channel();
+    nokhwa::nokhwa_initialize(move |granted| {
+        let _ = answer.send(granted);
+    });
+    match answered.recv_timeout(PROMPT_TIMEOUT) {
+        Ok(true) => Ok(()),
+        _ => Err(DENIED.to_string()),
+    }
+}
+
+#[cfg(windows)]
+fn check(_index: u32) -> Result<(), String> {
+    use std::process::Command;
+
+    const DENIED: &str = "Windows doesn't let apps use the camera. Turn on \"Camera access\" and \
+        \"Let desktop apps access your camera\" in Settings > Privacy & security > Camera, then \
+        start neckcheck again.";
+    const CONSENT: &str =
+        r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\webcam";
+    // for everyone, for this user, and for this user's desktop apps
+    let keys = [
+        format!(r"HKLM\{}", CONSENT),
+        format!(r"HKCU\{}", CONSENT),
+        format!(r"HKCU\{}\NonPackaged", CONSENT),
+    ];
+    let denied = keys.iter().any(|key| {
+        Command::new("reg")
+            .args(["query", key, "/v", "Value"])
+            .output()
+            .is_ok_and(|output| consent_denied(&String::from_utf8_lossy(&output.stdout)))
+    });
+    if denied {
+        return Err(DENIED.to_string());
+    }
+    Ok(())
+}
+
+// Whether `reg query` shows a consent value of Deny, e.g. `    Value    REG_SZ    Deny`
+#[cfg(any(windows, test))]
+fn consent_denied(output: &str) -> bool {
+    output.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        matches!(fields.as_slice(), ["Value", "REG_SZ", "Deny"])
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn check(_index: u32) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_details_are_read() {
+        let groups = "root:x:0:\nvideo:x:44:alice\nplugdev:x:46:alice,bob\n";
+        assert_eq!(group_name(groups, 44).as_deref(), Some("video"));
+        assert_eq!(group_name(groups, 1000), None);
+
+        let output =
+            "\r\nHKEY_CURRENT_USER\\Software\\...\\webcam\r\n    Value    REG_SZ    Deny\r\n";
+        assert!(consent_denied(output));
+        assert!(!consent_denied(&output.replace("Deny", "Allow")));
+    }
+}
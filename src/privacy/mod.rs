@@ -0,0 +1,49 @@
+use std::ops::DerefMut;
+
+use thiserror::Error;
+use zeroize::Zeroize;
+
+#[derive(Error, Debug, Clone)]
+pub enum PrivacyError {
+    #[error("Refusing to give {0} access to camera frames in strict privacy mode")]
+    ImageAccessRefused(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivacyMode {
+    Normal,
+    // frames never leave the process: no sink may receive image data and every image buffer is
+    // zeroed as soon as the check that used it is finished
+    Strict,
+}
+
+// Proof that the privacy mode allows image data to leave the detection pipeline. Every sink that
+// consumes frames (and so could write them to disk, a device or the network) requires one to be
+// constructed, and the only way to get one is `PrivacyMode::image_access`.
+pub struct ImageAccess {
+    _private: (),
+}
+
+impl PrivacyMode {
+    pub fn from_strict(strict: bool) -> PrivacyMode {
+        if strict {
+            PrivacyMode::Strict
+        } else {
+            PrivacyMode::Normal
+        }
+    }
+
+    pub fn image_access(&self, sink: &str) -> Result<ImageAccess, PrivacyError> {
+        match self {
+            PrivacyMode::Normal => Ok(ImageAccess { _private: () }),
+            PrivacyMode::Strict => Err(PrivacyError::ImageAccessRefused(sink.to_string())),
+        }
+    }
+
+    // Zeroes an image (or any other byte buffer) once it is no longer needed
+    pub fn scrub<T: DerefMut<Target = [u8]>>(&self, buffer: &mut T) {
+        if *self == PrivacyMode::Strict {
+            buffer.deref_mut().zeroize();
+        }
+    }
+}
@@ -0,0 +1,222 @@
+// A physiotherapy program: exercises prompted on certain days of the week at certain times, with
+// whether they were done kept in the stats
+
+use std::collections::BTreeMap;
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, Weekday};
+use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::clock::ClockWatch;
+use crate::events::{EventBus, PostureEvent};
+use crate::posture::time_of_day;
+use crate::stats::{StatsEntry, StatsLog, StatsRecord, WEEKDAYS};
+use crate::summary::next_due;
+
+// as for the summary, the wall clock is polled rather than slept on
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(60);
+
+// One exercise, e.g. `{ name = "Chin tucks", reps = 10, at = "11:00", days = ["mon", "thu"] }`;
+// every day if no days are given
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Exercise {
+    pub name: String,
+    #[serde(default = "one")]
+    pub reps: u32,
+    #[serde(deserialize_with = "time_of_day")]
+    pub at: NaiveTime,
+    #[serde(default = "every_day")]
+    pub days: Vec<Weekday>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn every_day() -> Vec<Weekday> {
+    WEEKDAYS.to_vec()
+}
+
+impl Exercise {
+    // The first time after `now` that the exercise is due
+    fn next_due(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut due = now;
+        // a week ahead at most; no days at all is never
+        for _ in 0..8 {
+            due = next_due(self.at, due);
+            if self.days.contains(&due.weekday()) {
+                return Some(due);
+            }
+        }
+        None
+    }
+}
+
+// Prompts each exercise of the program when it is due, and records that it was
+pub async fn run(
+    program: Vec<Exercise>,
+    stats: Option<StatsLog>,
+    events: EventBus,
+    shutdown: CancellationToken,
+) {
+    let mut due: Vec<Option<DateTime<Local>>> = program
+        .iter()
+        .map(|exercise| exercise.next_due(Local::now()))
+        .collect();
+    let mut clock = ClockWatch::new();
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let now = Local::now();
+        let clock_changed = clock.check(Instant::now(), now).is_some();
+        for (exercise, due) in program.iter().zip(&mut due) {
+            let Some(at) = *due else {
+                continue;
+            };
+            if now < at {
+                if clock_changed {
+                    *due = exercise.next_due(now);
+                }
+                continue;
+            }
+            *due = exercise.next_due(now);
+            events.publish(PostureEvent::Exercise {
+                name: exercise.name.clone(),
+                reps: exercise.reps,
+            });
+            if let Some(stats) = &stats {
+                stats.record(StatsEntry::ExercisePrompted {
+                    exercise: exercise.name.clone(),
+                });
+            }
+        }
+    }
+}
+
+// The exercise prompted most recently today, to be marked as done when none is named
+pub fn latest_prompted(records: &[StatsRecord], today: NaiveDate) -> Option<String> {
+    records
+        .iter()
+        .rev()
+        .take_while(|record| record.time.date_naive() == today)
+        .find_map(|record| match &record.entry {
+            StatsEntry::ExercisePrompted { exercise } => Some(exercise.clone()),
+            _ => None,
+        })
+}
+
+// How often each exercise was prompted and done, in a week
+#[derive(Debug, Default, PartialEq)]
+pub struct Adherence {
+    pub prompted: u32,
+    pub done: u32,
+}
+
+// The exercises of each week (by its Monday), by name
+pub fn weekly_adherence(
+    records: &[StatsRecord],
+) -> BTreeMap<NaiveDate, BTreeMap<String, Adherence>> {
+    let mut weeks: BTreeMap<NaiveDate, BTreeMap<String, Adherence>> = BTreeMap::new();
+    for record in records {
+        let (exercise, done) = match &record.entry {
+            StatsEntry::ExercisePrompted { exercise } => (exercise, false),
+            StatsEntry::ExerciseDone { exercise } => (exercise, true),
+            _ => continue,
+        };
+        let date = record.time.date_naive();
+        let monday = date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64);
+        let adherence = weeks
+            .entry(monday)
+            .or_default()
+            .entry(exercise.clone())
+            .or_default();
+        if done {
+            adherence.done += 1;
+        } else {
+            adherence.prompted += 1;
+        }
+    }
+    weeks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn record(time: DateTime<Local>, entry: StatsEntry) -> StatsRecord {
+        StatsRecord {
+            time,
+            host: None,
+            profile: None,
+            session: None,
+            others_present: false,
+            entry,
+        }
+    }
+
+    #[test]
+    fn exercises_are_due_on_their_days_and_tallied_by_week() {
+        let exercise = Exercise {
+            name: "Chin tucks".to_string(),
+            reps: 10,
+            at: NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+            days: vec![Weekday::Mon, Weekday::Thu],
+        };
+        // a Tuesday
+        let tuesday = Local.with_ymd_and_hms(2024, 3, 5, 12, 0, 0).unwrap();
+        let thursday = Local.with_ymd_and_hms(2024, 3, 7, 11, 0, 0).unwrap();
+        assert_eq!(exercise.next_due(tuesday), Some(thursday));
+        let never = Exercise {
+            days: Vec::new(),
+            ..exercise.clone()
+        };
+        assert_eq!(never.next_due(tuesday), None);
+
+        let prompted = |time| {
+            record(
+                time,
+                StatsEntry::ExercisePrompted {
+                    exercise: "Chin tucks".to_string(),
+                },
+            )
+        };
+        let done = |time| {
+            record(
+                time,
+                StatsEntry::ExerciseDone {
+                    exercise: "Chin tucks".to_string(),
+                },
+            )
+        };
+        let next_monday = Local.with_ymd_and_hms(2024, 3, 11, 11, 0, 0).unwrap();
+        let records = vec![
+            prompted(Local.with_ymd_and_hms(2024, 3, 4, 11, 0, 0).unwrap()),
+            done(Local.with_ymd_and_hms(2024, 3, 4, 11, 5, 0).unwrap()),
+            prompted(thursday),
+            prompted(next_monday),
+        ];
+        let weeks = weekly_adherence(&records);
+        let first = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        assert_eq!(
+            weeks[&first]["Chin tucks"],
+            Adherence {
+                prompted: 2,
+                done: 1
+            }
+        );
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(
+            latest_prompted(&records, next_monday.date_naive()).as_deref(),
+            Some("Chin tucks")
+        );
+        assert_eq!(
+            latest_prompted(&records[..3], next_monday.date_naive()),
+            None
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::time::Instant;
+
+use image::GrayImage;
+use imageproc::rect::Rect;
+
+use super::{
+    preprocess, CameraIdentity, FaceTracker, LatencyWatchdog, Observation, ProximitySample,
+    ProximitySource,
+};
+use crate::body::BodyPoseDetector;
+use crate::detector::{Detector, Face};
+use crate::landmarks::LandmarkDetector;
+use crate::posture::Proximity;
+use crate::privacy::PrivacyMode;
+use crate::recognition::Recognition;
+use crate::source::{CameraActivity, CaptureError, FrameSource};
+
+// The classic neckcheck input: frames from a camera, measured by finding the face in them. The
+// measurement is the distance to the face if the camera has a depth sensor, otherwise the size of
+// the face box.
+pub struct CameraProximity {
+    source: Box<dyn FrameSource>,
+    detector: Box<dyn Detector>,
+    landmarks: Option<Box<dyn LandmarkDetector>>,
+    body: Option<Box<dyn BodyPoseDetector>>,
+    privacy: PrivacyMode,
+    latency: Option<LatencyWatchdog>,
+    // the most of the resolution the detector may use, whatever the latency
+    max_scale: f32,
+    tracker: Option<FaceTracker>,
+    // of the latest frame
+    identity: Option<CameraIdentity>,
+    // with several faces in view, measure the largest and most central one
+    main_face: bool,
+    // tells the enrolled user from guests, and whether the latest face recognised was a guest's
+    recognition: Option<Recognition>,
+    guest: bool,
+    // faces narrower than this are someone in the background, or a photo on the wall
+    min_face_width: Option<u32>,
+    // the frame shrunk for the detector at the latest check, kept to be scrubbed like the others
+    downscaled: Option<GrayImage>,
+}
+
+impl CameraProximity {
+    pub fn new(
+        source: Box<dyn FrameSource>,
+        detector: Box<dyn Detector>,
+        privacy: PrivacyMode,
+    ) -> CameraProximity {
+        CameraProximity {
+            source,
+            detector,
+            landmarks: None,
+            body: None,
+            privacy,
+            latency: None,
+            max_scale: 1.0,
+            tracker: None,
+            identity: None,
+            main_face: false,
+            recognition: None,
+            guest: false,
+            min_face_width: None,
+            downscaled: None,
+        }
+    }
+
+    // Times every check, detecting faces at a lower resolution while they are too slow
+    pub fn set_latency_watchdog(&mut self, latency: LatencyWatchdog) {
+        self.latency = Some(latency);
+    }
+
+    // Only looks for faces in some of the frames, predicting where they are in the others
+    pub fn set_face_tracker(&mut self, tracker: FaceTracker) {
+        self.tracker = Some(tracker);
+    }
+
+    // With several people in view, e.g. on a shared family computer, keeps measuring the one in
+    // front of the screen rather than whichever the detector lists first
+    pub fn pick_main_face(&mut self) {
+        self.main_face = true;
+    }
+
+    // Notices when the only face in view isn't the enrolled user's
+    pub fn set_recognition(&mut self, recognition: Recognition) {
+        self.recognition = Some(recognition);
+    }
+
+    // Also finds the landmarks of the face, e.g. for the neck angle
+    pub fn set_landmark_detector(&mut self, landmarks: Box<dyn LandmarkDetector>) {
+        self.landmarks = Some(landmarks);
+    }
+
+    // Also finds the shoulders, e.g. to tell slouching
+    pub fn set_body_pose_detector(&mut self, body: Box<dyn BodyPoseDetector>) {
+        self.body = Some(body);
+    }
+
+    // The faces in the frame, at the resolution the latency watchdog allows
+    fn detect(&mut self, gray: &GrayImage) -> Vec<Face> {
+        let scale = self.detection_scale();
+        if scale < 1.0 {
+            let small = self.downscaled.insert(preprocess::downscale(gray, scale));
+            let faces = self
+                .detector
+                .detect(small)
+                .into_iter()
+                .map(|face| Face {
+                    rect: upscale(face.rect, scale),
+                    ..face
+                })
+                .collect();
+            self.privacy.scrub(small);
+            faces
+        } else {
+            self.detector.detect(gray)
+        }
+    }
+
+    fn measure_face(&mut self, face: Rect) -> Proximity {
+        match self.source.distance_at(face) {
+            Some(distance) => Proximity::Distance(distance),
+            None => Proximity::FaceSize {
+                width: face.width(),
+                height: face.height(),
+            },
+        }
+    }
+}
+
+impl ProximitySource for CameraProximity {
+    fn sample(&mut self) -> Result<ProximitySample, CaptureError> {
+        let started = Instant::now();
+        let mut frame = self.source.capture()?;
+        let timestamp = Instant::now();
+        let (width, height) = frame.dimensions();
+        if self
+            .identity
+            .as_ref()
+            .is_none_or(|identity| identity.width != width || identity.height != height)
+        {
+            self.identity = Some(CameraIdentity {
+                name: self.source.name(),
+                width,
+                height,
+            });
+        }
+        let mut gray = frame
+            .luma()
+            .unwrap_or_else(|| preprocess::grayscale(frame.rgb()));
+        let converted = Instant::now();
+        let (detections, candidates) = match &mut self.tracker {
+            Some(tracker) if !tracker.should_detect() => {
+                let predicted = tracker.predict(timestamp);
+                (predicted.clone(), predicted)
+            }
+            _ => {
+                let mut detections = self.detect(&gray);
+                let candidates = detections.clone();
+                if let Some(min_width) = self.min_face_width {
+                    ignore_background(&mut detections, min_width);
+                }
+                if self.main_face {
+                    main_face_first(&mut detections, width, height);
+                }
+                if let Some(tracker) = &mut self.tracker {
+                    tracker.detected(detections.clone(), timestamp);
+                }
+                // only the frames the faces were detected in are recognised, which is plenty
+                // for someone sitting down or getting up
+                if let Some(recognition) = &mut self.recognition {
+                    self.guest = match detections.as_slice() {
+                        [face] => recognition.is_guest(&gray, face.rect).unwrap_or(self.guest),
+                        _ => false,
+                    };
+                }
+                (detections, candidates)
+            }
+        };
+        let score = detections.first().map(|face| face.score);
+        let faces: Vec<Rect> = detections.into_iter().map(|face| face.rect).collect();
+        let detected = Instant::now();
+        let landmarks = match (&mut self.landmarks, faces.first()) {
+            (Some(detector), Some(face)) => detector.landmarks(&gray, *face),
+            _ => None,
+        };
+        let found_landmarks = Instant::now();
+        let body = self
+            .body
+            .as_mut()
+            .and_then(|detector| detector.keypoints(frame.rgb()));
+        let found_body = Instant::now();
+        if let Some(latency) = &mut self.latency {
+            latency.record(
+                &[
+                    ("capture", timestamp - started),
+                    ("grayscale", converted - timestamp),
+                    ("detection", detected - converted),
+                    ("landmarks", found_landmarks - detected),
+                    ("body pose", found_body - found_landmarks),
+                ],
+                found_body,
+            );
+        }
+        self.privacy.scrub(&mut gray);
+        // the first face is taken to be the user; with several in view and no main face picked,
+        // that is anyone's guess
+        let metric = faces.first().map(|face| self.measure_face(*face));
+        Ok(ProximitySample {
+            timestamp,
+            metric,
+            confidence: 1.0,
+            observation: Some(Observation {
+                frame,
+                faces,
+                candidates,
+                score,
+                landmarks,
+                body,
+                guest: self.guest,
+            }),
+        })
+    }
+
+    fn set_detector(&mut self, detector: Box<dyn Detector>) -> Result<(), String> {
+        self.detector = detector;
+        Ok(())
+    }
+
+    fn detection_scale(&self) -> f32 {
+        self.latency
+            .as_ref()
+            .map_or(1.0, LatencyWatchdog::scale)
+            .min(self.max_scale)
+    }
+
+    fn limit_detection_scale(&mut self, max: f32) {
+        self.max_scale = max;
+    }
+
+    fn ignore_faces_narrower_than(&mut self, width: u32) {
+        self.min_face_width = Some(width);
+    }
+
+    fn take_camera_activity(&mut self) -> Vec<CameraActivity> {
+        self.source.take_activity()
+    }
+
+    fn release(&mut self) {
+        self.source.release();
+    }
+
+    fn camera_identity(&self) -> Option<CameraIdentity> {
+        self.identity.clone()
+    }
+}
+
+// Moves the face of whoever is most likely using the computer to the front: the largest one, less
+// so the further it is from the middle of the frame
+fn main_face_first(faces: &mut [Face], width: u32, height: u32) {
+    let (centre_x, centre_y) = (width as f32 / 2.0, height as f32 / 2.0);
+    let half_diagonal = centre_x.hypot(centre_y).max(1.0);
+    let weight = |face: &Face| {
+        let rect = face.rect;
+        let x = rect.left() as f32 + rect.width() as f32 / 2.0;
+        let y = rect.top() as f32 + rect.height() as f32 / 2.0;
+        let off_centre = ((x - centre_x).hypot(y - centre_y) / half_diagonal).min(1.0);
+        (rect.width() * rect.height()) as f32 * (1.0 - off_centre / 2.0)
+    };
+    if let Some(main) =
+        (0..faces.len()).max_by(|&a, &b| weight(&faces[a]).total_cmp(&weight(&faces[b])))
+    {
+        faces.swap(0, main);
+    }
+}
+
+// Leaves out the faces too small to be the user's, so that they count neither as someone there nor
+// as someone else in view
+fn ignore_background(faces: &mut Vec<Face>, min_width: u32) {
+    faces.retain(|face| face.rect.width() >= min_width);
+}
+
+// A face found in a frame shrunk by `scale`, in the coordinates of the full frame
+fn upscale(face: Rect, scale: f32) -> Rect {
+    Rect::at(
+        (face.left() as f32 / scale) as i32,
+        (face.top() as f32 / scale) as i32,
+    )
+    .of_size(
+        ((face.width() as f32 / scale) as u32).max(1),
+        ((face.height() as f32 / scale) as u32).max(1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::Frame;
+    use image::{Rgb, RgbImage};
+
+    struct StillCamera;
+
+    impl FrameSource for StillCamera {
+        fn capture(&mut self) -> Result<Frame, CaptureError> {
+            Ok(RgbImage::from_pixel(64, 48, Rgb([90, 120, 150])).into())
+        }
+
+        fn name(&self) -> String {
+            "still".to_string()
+        }
+    }
+
+    struct OneFace;
+
+    impl Detector for OneFace {
+        fn name(&self) -> &'static str {
+            "one face"
+        }
+
+        fn detect(&mut self, _image: &GrayImage) -> Vec<Face> {
+            vec![Face {
+                rect: Rect::at(4, 4).of_size(10, 10),
+                score: 1.0,
+            }]
+        }
+    }
+
+    #[test]
+    fn frames_shrunk_for_the_detector_are_scrubbed_in_strict_mode() {
+        for privacy in [PrivacyMode::Normal, PrivacyMode::Strict] {
+            let mut camera =
+                CameraProximity::new(Box::new(StillCamera), Box::new(OneFace), privacy);
+            camera.limit_detection_scale(0.5);
+            let sample = camera.sample().unwrap();
+            assert_eq!(
+                sample.metric,
+                Some(Proximity::FaceSize {
+                    width: 20,
+                    height: 20
+                })
+            );
+            let downscaled = camera.downscaled.unwrap();
+            assert_eq!(downscaled.dimensions(), (32, 24));
+            let zeroed = downscaled.iter().all(|&pixel| pixel == 0);
+            assert_eq!(zeroed, privacy == PrivacyMode::Strict);
+        }
+    }
+
+    #[test]
+    fn the_main_face_is_large_and_central() {
+        let face = |x: i32, y: i32, size: u32| Face {
+            rect: Rect::at(x, y).of_size(size, size),
+            score: 1.0,
+        };
+        // someone small behind, the user in the middle, someone as large at the edge
+        let mut faces = vec![
+            face(500, 100, 60),
+            face(1100, 500, 200),
+            face(220, 260, 200),
+        ];
+        main_face_first(&mut faces, 1280, 720);
+        assert_eq!(faces[0].rect, Rect::at(220, 260).of_size(200, 200));
+        // a much larger face wins even off centre
+        let mut faces = vec![face(560, 280, 120), face(0, 0, 300)];
+        main_face_first(&mut faces, 1280, 720);
+        assert_eq!(faces[0].rect.width(), 300);
+
+        // the one small behind is left out altogether
+        let mut faces = vec![face(500, 100, 60), face(220, 260, 200)];
+        ignore_background(&mut faces, 80);
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].rect.width(), 200);
+    }
+}
@@ -0,0 +1,228 @@
+use std::time::{Duration, Instant};
+
+use super::{CameraIdentity, ProximitySample, ProximitySource};
+use crate::detector::Detector;
+use crate::source::{CameraActivity, CaptureError};
+
+// how long a camera that failed is left alone before it is tried again, in failover mode
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+// How the camera to go by is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraSelection {
+    // every camera watches, and whichever sees a face with the highest detector score counts
+    Best,
+    // only the first camera that works watches; the others are opened when it fails, e.g.
+    // because another app is using it, and closed again once it is back
+    Failover,
+}
+
+// Several cameras, e.g. the laptop's and an external one, so that monitoring carries on whether
+// the laptop is docked or not, or while one of them is busy. A camera that fails is left out until
+// it works again.
+pub struct FusedProximity {
+    sources: Vec<(String, Box<dyn ProximitySource>)>,
+    selection: CameraSelection,
+    // the camera the previous measurement came from
+    current: Option<usize>,
+    // when each camera last failed, in failover mode
+    failed_at: Vec<Option<Instant>>,
+}
+
+impl FusedProximity {
+    pub fn new(
+        sources: Vec<(String, Box<dyn ProximitySource>)>,
+        selection: CameraSelection,
+    ) -> FusedProximity {
+        FusedProximity {
+            failed_at: vec![None; sources.len()],
+            sources,
+            selection,
+            current: None,
+        }
+    }
+
+    fn sample_all(&mut self) -> (Vec<(usize, ProximitySample)>, Option<CaptureError>) {
+        let mut samples = Vec::new();
+        let mut error = None;
+        for (i, (name, source)) in self.sources.iter_mut().enumerate() {
+            match source.sample() {
+                Ok(sample) => samples.push((i, sample)),
+                Err(e) => {
+                    if self.current == Some(i) {
+                        println!("Camera {} failed: {}", name, e);
+                    }
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        (samples, error)
+    }
+
+    // The first camera that works, leaving out the ones that failed a moment ago unless none of
+    // the others work either
+    fn sample_first(&mut self) -> (Vec<(usize, ProximitySample)>, Option<CaptureError>) {
+        let now = Instant::now();
+        let (rested, recent): (Vec<usize>, Vec<usize>) = (0..self.sources.len())
+            .partition(|&i| self.failed_at[i].is_none_or(|at| now - at >= RETRY_INTERVAL));
+        let mut error = None;
+        for i in rested.into_iter().chain(recent) {
+            let (name, source) = &mut self.sources[i];
+            match source.sample() {
+                Ok(sample) => {
+                    self.failed_at[i] = None;
+                    return (vec![(i, sample)], error);
+                }
+                Err(e) => {
+                    if self.current == Some(i) {
+                        println!("Camera {} failed: {}", name, e);
+                    }
+                    self.failed_at[i] = Some(now);
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        (Vec::new(), error)
+    }
+}
+
+// How strongly a sample suggests the user is in view of its camera
+fn face_score(sample: &ProximitySample) -> Option<f32> {
+    sample.metric?;
+    sample.observation.as_ref().and_then(|o| o.score)
+}
+
+// The sample to go by: the best face, staying with the current camera on a tie, or else the
+// current camera's view of nobody
+fn pick(samples: &[(usize, ProximitySample)], current: Option<usize>) -> Option<usize> {
+    let best = samples
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (source, sample))| Some((i, *source, face_score(sample)?)))
+        .max_by(|(_, a, a_score), (_, b, b_score)| {
+            a_score
+                .total_cmp(b_score)
+                .then_with(|| (Some(*a) == current).cmp(&(Some(*b) == current)))
+        });
+    match best {
+        Some((i, _, _)) => Some(i),
+        None => samples
+            .iter()
+            .position(|(source, _)| Some(*source) == current)
+            .or((!samples.is_empty()).then_some(0)),
+    }
+}
+
+impl ProximitySource for FusedProximity {
+    fn sample(&mut self) -> Result<ProximitySample, CaptureError> {
+        let (mut samples, error) = match self.selection {
+            CameraSelection::Best => self.sample_all(),
+            CameraSelection::Failover => self.sample_first(),
+        };
+        let Some(picked) = pick(&samples, self.current) else {
+            return Err(error.expect("No cameras"));
+        };
+        let (source, sample) = samples.swap_remove(picked);
+        if self.current != Some(source) {
+            if let Some(previous) = self.current {
+                println!("Now watching from camera {}", self.sources[source].0);
+                if self.selection == CameraSelection::Failover {
+                    self.sources[previous].1.release();
+                }
+            }
+            self.current = Some(source);
+        }
+        Ok(sample)
+    }
+
+    fn set_detector(&mut self, _detector: Box<dyn Detector>) -> Result<(), String> {
+        Err("With several cameras, switching the detector needs a restart".to_string())
+    }
+
+    fn detection_scale(&self) -> f32 {
+        self.sources
+            .iter()
+            .map(|(_, source)| source.detection_scale())
+            .fold(1.0, f32::min)
+    }
+
+    fn limit_detection_scale(&mut self, max: f32) {
+        for (_, source) in &mut self.sources {
+            source.limit_detection_scale(max);
+        }
+    }
+
+    // the cameras' calibrations are seldom far apart, and this is only meant to catch faces far
+    // smaller than any of them
+    fn ignore_faces_narrower_than(&mut self, width: u32) {
+        for (_, source) in &mut self.sources {
+            source.ignore_faces_narrower_than(width);
+        }
+    }
+
+    fn take_camera_activity(&mut self) -> Vec<CameraActivity> {
+        self.sources
+            .iter_mut()
+            .flat_map(|(_, source)| source.take_camera_activity())
+            .collect()
+    }
+
+    fn active_camera(&self) -> Option<String> {
+        self.current.map(|i| self.sources[i].0.clone())
+    }
+
+    fn camera_identity(&self) -> Option<CameraIdentity> {
+        self.current
+            .and_then(|i| self.sources[i].1.camera_identity())
+    }
+
+    fn release(&mut self) {
+        for (_, source) in &mut self.sources {
+            source.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use image::RgbImage;
+
+    use super::*;
+    use crate::posture::Proximity;
+    use crate::proximity::Observation;
+
+    fn sample(score: Option<f32>) -> ProximitySample {
+        ProximitySample {
+            timestamp: Instant::now(),
+            metric: score.map(|_| Proximity::Distance(50.0)),
+            confidence: 1.0,
+            observation: Some(Observation {
+                frame: RgbImage::new(1, 1).into(),
+                faces: Vec::new(),
+                candidates: Vec::new(),
+                score,
+                landmarks: None,
+                body: None,
+                guest: false,
+            }),
+        }
+    }
+
+    #[test]
+    fn the_camera_with_the_best_face_is_used() {
+        let samples = vec![(0, sample(Some(2.0))), (1, sample(Some(5.0)))];
+        assert_eq!(pick(&samples, Some(0)), Some(1));
+        // a tie stays with the current camera
+        let samples = vec![(0, sample(Some(5.0))), (1, sample(Some(5.0)))];
+        assert_eq!(pick(&samples, Some(1)), Some(1));
+        // nobody in view of either
+        let samples = vec![(0, sample(None)), (1, sample(None))];
+        assert_eq!(pick(&samples, Some(1)), Some(1));
+        // the current camera failed
+        let samples = vec![(1, sample(None))];
+        assert_eq!(pick(&samples, Some(0)), Some(0));
+        assert_eq!(pick(&[], Some(0)), None);
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::{Duration, Instant};
+
+use crate::config::LatencyConfig;
+
+// checks in a row over the budget before the detection resolution is lowered
+const OVER_BUDGET_CHECKS: u32 = 3;
+// checks in a row within half the budget before it is raised again
+const UNDER_BUDGET_CHECKS: u32 = 60;
+// slow checks are logged at most this often
+const LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+// Keeps an eye on how long each check takes, so that a huge frame or a busy CPU doesn't leave the
+// alerts lagging: while checks keep going over the budget, faces are detected on a smaller copy of
+// the frame, down to `min_scale`, and once they are comfortably quick again the resolution goes
+// back up.
+pub struct LatencyWatchdog {
+    budget: Duration,
+    min_scale: f32,
+    scale: f32,
+    over: u32,
+    under: u32,
+    last_logged: Option<Instant>,
+}
+
+impl LatencyWatchdog {
+    pub fn new(config: &LatencyConfig) -> LatencyWatchdog {
+        LatencyWatchdog {
+            budget: Duration::from_secs_f64(config.budget),
+            min_scale: config.min_scale,
+            scale: 1.0,
+            over: 0,
+            under: 0,
+            last_logged: None,
+        }
+    }
+
+    // The fraction of the frame's resolution to detect faces at
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    // Takes note of how long each stage of a check took
+    pub fn record(&mut self, stages: &[(&str, Duration)], now: Instant) {
+        let total: Duration = stages.iter().map(|(_, duration)| *duration).sum();
+        if total > self.budget {
+            self.under = 0;
+            self.over += 1;
+            let lowered = self.over >= OVER_BUDGET_CHECKS && self.scale > self.min_scale;
+            if lowered {
+                self.over = 0;
+                self.scale = (self.scale / 2.0).max(self.min_scale);
+            }
+            if lowered
+                || self
+                    .last_logged
+                    .is_none_or(|last| now - last >= LOG_INTERVAL)
+            {
+                self.last_logged = Some(now);
+                println!(
+                    "Slow check: {} ms ({}), over the budget of {} ms{}",
+                    total.as_millis(),
+                    stages
+                        .iter()
+                        .map(|(stage, duration)| format!("{} {} ms", stage, duration.as_millis()))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    self.budget.as_millis(),
+                    if lowered {
+                        format!("; detecting faces at {:.0}% resolution", self.scale * 100.0)
+                    } else {
+                        String::new()
+                    }
+                );
+            }
+        } else if total < self.budget / 2 && self.scale < 1.0 {
+            self.over = 0;
+            self.under += 1;
+            if self.under >= UNDER_BUDGET_CHECKS {
+                self.under = 0;
+                self.scale = (self.scale * 2.0).min(1.0);
+                println!(
+                    "Checks are quick again; detecting faces at {:.0}% resolution",
+                    self.scale * 100.0
+                );
+            }
+        } else {
+            self.over = 0;
+            self.under = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(watchdog: &mut LatencyWatchdog, millis: u64, now: Instant) {
+        watchdog.record(&[("detect", Duration::from_millis(millis))], now);
+    }
+
+    #[test]
+    fn the_resolution_drops_while_over_budget_and_recovers() {
+        let now = Instant::now();
+        let mut watchdog = LatencyWatchdog::new(&LatencyConfig {
+            enabled: true,
+            budget: 0.2,
+            min_scale: 0.25,
+        });
+        // a single slow check is not enough
+        check(&mut watchdog, 500, now);
+        check(&mut watchdog, 50, now);
+        check(&mut watchdog, 500, now);
+        assert_eq!(watchdog.scale(), 1.0);
+        for _ in 0..OVER_BUDGET_CHECKS * 3 {
+            check(&mut watchdog, 500, now);
+        }
+        assert_eq!(watchdog.scale(), 0.25);
+
+        for _ in 0..UNDER_BUDGET_CHECKS {
+            check(&mut watchdog, 50, now);
+        }
+        assert_eq!(watchdog.scale(), 0.5);
+        // within budget but not comfortably so keeps the resolution where it is
+        for _ in 0..UNDER_BUDGET_CHECKS {
+            check(&mut watchdog, 150, now);
+        }
+        assert_eq!(watchdog.scale(), 0.5);
+    }
+}
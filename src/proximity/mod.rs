@@ -0,0 +1,120 @@
+mod camera;
+mod fused;
+mod latency;
+mod phone;
+mod preprocess;
+mod serial;
+mod tracker;
+
+pub use camera::CameraProximity;
+pub use fused::{CameraSelection, FusedProximity};
+pub use latency::LatencyWatchdog;
+pub use phone::PhoneSensor;
+pub use serial::SerialSensor;
+pub use tracker::FaceTracker;
+
+use std::fmt;
+use std::time::Instant;
+
+use imageproc::rect::Rect;
+use serde::{Deserialize, Serialize};
+
+use crate::body::BodyKeypoints;
+use crate::detector::{Detector, Face};
+use crate::landmarks::Landmarks;
+use crate::posture::Proximity;
+use crate::source::{CameraActivity, CaptureError, Frame};
+
+// The camera frame a sample was measured from, for the outputs that want to show it
+pub struct Observation {
+    pub frame: Frame,
+    pub faces: Vec<Rect>,
+    // everything the detector found, with its scores, including the faces left out as background
+    pub candidates: Vec<Face>,
+    // the detector's score for the first face; only comparable between detectors of the same
+    // backend
+    pub score: Option<f32>,
+    // of the first face, if a landmark detector is in use
+    pub landmarks: Option<Landmarks>,
+    // the upper body, if a body pose detector is in use
+    pub body: Option<BodyKeypoints>,
+    // the only face in view isn't the enrolled user's, if faces are recognised
+    pub guest: bool,
+}
+
+// Which camera, at which resolution, a measurement was taken with. Face sizes from one don't mean
+// the same on another.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CameraIdentity {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CameraIdentity {
+    // Whether both are the same camera with the same field of view, so that face sizes measured
+    // as fractions of the frame mean the same, whatever the resolution
+    pub fn same_camera(&self, other: &CameraIdentity) -> bool {
+        self.name == other.name
+            && self.width as u64 * other.height as u64 == other.width as u64 * self.height as u64
+    }
+}
+
+impl fmt::Display for CameraIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}x{}", self.name, self.width, self.height)
+    }
+}
+
+pub struct ProximitySample {
+    // when the measurement was taken, which can be a while before it is read
+    pub timestamp: Instant,
+    // None if nobody is there
+    pub metric: Option<Proximity>,
+    // how much the source trusts the measurement, from 0 to 1
+    pub confidence: f32,
+    pub observation: Option<Observation>,
+}
+
+// Anything that can tell how close the user is: a camera and a face detector, a depth camera, a
+// phone on the desk, a distance sensor, or a test double. The decision logic only ever sees the
+// samples.
+pub trait ProximitySource {
+    fn sample(&mut self) -> Result<ProximitySample, CaptureError>;
+
+    // Replaces the face detector, for sources that have one
+    fn set_detector(&mut self, _detector: Box<dyn Detector>) -> Result<(), String> {
+        Err("This input does not use a face detector".to_string())
+    }
+
+    // The fraction of the frame's resolution faces are detected at, lowered while checks are slow
+    fn detection_scale(&self) -> f32 {
+        1.0
+    }
+
+    // Caps the resolution faces are detected at, e.g. to save power, for sources that detect them
+    fn limit_detection_scale(&mut self, _max: f32) {}
+
+    // Leaves out faces narrower than this many pixels as someone in the background, for sources
+    // that detect them
+    fn ignore_faces_narrower_than(&mut self, _width: u32) {}
+
+    // When the camera was switched on and off, for sources that use one, see
+    // `FrameSource::take_activity`
+    fn take_camera_activity(&mut self) -> Vec<CameraActivity> {
+        Vec::new()
+    }
+
+    // Which of several cameras the latest sample came from, for sources that have several
+    fn active_camera(&self) -> Option<String> {
+        None
+    }
+
+    // The camera the latest sample came from, for sources that use one
+    fn camera_identity(&self) -> Option<CameraIdentity> {
+        None
+    }
+
+    // Switches the camera off until the next sample, for sources that use one
+    fn release(&mut self) {}
+}
@@ -0,0 +1,135 @@
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tiny_http::{Method, Request, Response, Server};
+
+use super::{ProximitySample, ProximitySource};
+use crate::posture::Proximity;
+use crate::source::CaptureError;
+
+// readings are expected every second or so; this long without one and the phone counts as lost
+const STALE_AFTER: Duration = Duration::from_secs(10);
+const MAX_BODY_SIZE: u64 = 4 * 1024;
+
+// What the phone POSTs to /reading, e.g. `{"distance": 48.5}`, `{"distance": 48.5, "confidence":
+// 0.8}` or `{"present": false}`
+#[derive(Deserialize, Debug)]
+struct Reading {
+    // centimetres from the phone to the user
+    distance: Option<f32>,
+    // whether anyone is there at all; assumed when a distance is given
+    present: Option<bool>,
+    // from 0 to 1, if the app knows how good its measurement is
+    confidence: Option<f32>,
+}
+
+type Latest = Arc<Mutex<Option<ProximitySample>>>;
+
+// A phone (or anything else that can make an HTTP request) feeding distance and presence readings
+// to neckcheck over the network. The readings are accepted on a background thread and each
+// measurement is simply the latest one.
+pub struct PhoneSensor {
+    server: Arc<Server>,
+    latest: Latest,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PhoneSensor {
+    pub fn new(address: SocketAddr) -> PhoneSensor {
+        let server = match Server::http(address) {
+            Ok(server) => Arc::new(server),
+            Err(e) => panic!("Failed to listen for phone readings on {}: {}", address, e),
+        };
+        println!("Waiting for readings on http://{}/reading", address);
+        let latest: Latest = Arc::new(Mutex::new(None));
+        let thread = thread::Builder::new()
+            .name("neckcheck-phone".to_string())
+            .spawn({
+                let server = server.clone();
+                let latest = latest.clone();
+                move || {
+                    for request in server.incoming_requests() {
+                        handle(request, &latest);
+                    }
+                }
+            })
+            .expect("Failed to spawn the phone listener thread");
+        PhoneSensor {
+            server,
+            latest,
+            thread: Some(thread),
+        }
+    }
+}
+
+fn handle(mut request: Request, latest: &Latest) {
+    if request.method() != &Method::Post || request.url() != "/reading" {
+        let _ = request.respond(Response::empty(404));
+        return;
+    }
+    let reading: Reading =
+        match serde_json::from_reader(Read::take(request.as_reader(), MAX_BODY_SIZE)) {
+            Ok(reading) => reading,
+            Err(e) => {
+                let _ = request.respond(Response::from_string(e.to_string()).with_status_code(400));
+                return;
+            }
+        };
+    let confidence = reading.confidence.unwrap_or(1.0).clamp(0.0, 1.0);
+    let metric = match reading {
+        Reading {
+            present: Some(false),
+            ..
+        } => None,
+        Reading {
+            distance: Some(distance),
+            ..
+        } if distance.is_finite() && distance > 0.0 => Some(Proximity::Distance(distance)),
+        _ => {
+            let message = "expected a positive distance or \"present\": false";
+            let _ = request.respond(Response::from_string(message).with_status_code(400));
+            return;
+        }
+    };
+    *latest.lock().unwrap() = Some(ProximitySample {
+        timestamp: Instant::now(),
+        metric,
+        confidence,
+        observation: None,
+    });
+    let _ = request.respond(Response::empty(204));
+}
+
+impl ProximitySource for PhoneSensor {
+    fn sample(&mut self) -> Result<ProximitySample, CaptureError> {
+        match &*self.latest.lock().unwrap() {
+            Some(sample) if sample.timestamp.elapsed() < STALE_AFTER => Ok(ProximitySample {
+                timestamp: sample.timestamp,
+                metric: sample.metric,
+                confidence: sample.confidence,
+                observation: None,
+            }),
+            Some(_) => Err(CaptureError::SensorRead(format!(
+                "No reading from the phone in the last {:?}",
+                STALE_AFTER
+            ))),
+            None => Err(CaptureError::SensorRead(
+                "No reading from the phone yet".to_string(),
+            )),
+        }
+    }
+}
+
+impl Drop for PhoneSensor {
+    fn drop(&mut self) {
+        // stop accepting readings and free the port, e.g. for the pipeline's restart
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
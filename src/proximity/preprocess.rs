@@ -0,0 +1,111 @@
+use image::{GrayImage, RgbImage};
+
+// Rec. 709 luma weights in 16 bit fixed point, the same as image's grayscale, summing to 1 << 16
+const RED: u32 = 13933;
+const GREEN: u32 = 46871;
+const BLUE: u32 = 4732;
+
+// pixels converted at a time; fixed size chunks with plain integer maths let the compiler use SIMD
+const LANES: usize = 16;
+
+// The grayscale copy of a frame the detector looks at. Does the same as
+// `image::imageops::grayscale`, but in fixed point and a chunk at a time rather than a pixel at a
+// time through floats.
+pub fn grayscale(frame: &RgbImage) -> GrayImage {
+    let rgb = frame.as_raw();
+    let mut gray = vec![0u8; rgb.len() / 3];
+    let mut out = gray.chunks_exact_mut(LANES);
+    let mut pixels = rgb.chunks_exact(LANES * 3);
+    for (out, pixels) in (&mut out).zip(&mut pixels) {
+        let mut luma = [0u32; LANES];
+        for (i, luma) in luma.iter_mut().enumerate() {
+            *luma = pixels[i * 3] as u32 * RED
+                + pixels[i * 3 + 1] as u32 * GREEN
+                + pixels[i * 3 + 2] as u32 * BLUE;
+        }
+        for (out, luma) in out.iter_mut().zip(luma) {
+            *out = ((luma + (1 << 15)) >> 16) as u8;
+        }
+    }
+    for (out, pixel) in out
+        .into_remainder()
+        .iter_mut()
+        .zip(pixels.remainder().chunks_exact(3))
+    {
+        let luma = pixel[0] as u32 * RED + pixel[1] as u32 * GREEN + pixel[2] as u32 * BLUE;
+        *out = ((luma + (1 << 15)) >> 16) as u8;
+    }
+    GrayImage::from_raw(frame.width(), frame.height(), gray).expect("One byte per pixel")
+}
+
+// Shrinks to `scale` of the size by averaging the pixels each one covers. Detection only needs a
+// box filter, which is much cheaper than the triangle filter of `image::imageops::resize`.
+pub fn downscale(gray: &GrayImage, scale: f32) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    let out_width = ((width as f32 * scale) as u32).clamp(1, width);
+    let out_height = ((height as f32 * scale) as u32).clamp(1, height);
+    // the first source column or row of each output one, and the end of the last
+    let columns: Vec<usize> = (0..=out_width)
+        .map(|x| (x as u64 * width as u64 / out_width as u64) as usize)
+        .collect();
+    let rows: Vec<usize> = (0..=out_height)
+        .map(|y| (y as u64 * height as u64 / out_height as u64) as usize)
+        .collect();
+    let source = gray.as_raw();
+    let mut sums = vec![0u32; width as usize];
+    let mut out = Vec::with_capacity(out_width as usize * out_height as usize);
+    for band in rows.windows(2) {
+        sums.fill(0);
+        // whole rows at a time, which vectorizes
+        for row in source
+            .chunks_exact(width as usize)
+            .skip(band[0])
+            .take(band[1] - band[0])
+        {
+            for (sum, &pixel) in sums.iter_mut().zip(row) {
+                *sum += pixel as u32;
+            }
+        }
+        let rows = (band[1] - band[0]) as u32;
+        for span in columns.windows(2) {
+            let count = rows * (span[1] - span[0]) as u32;
+            let total: u32 = sums[span[0]..span[1]].iter().sum();
+            out.push(((total + count / 2) / count) as u8);
+        }
+    }
+    GrayImage::from_raw(out_width, out_height, out).expect("One byte per pixel")
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Luma, Rgb};
+
+    use super::*;
+
+    #[test]
+    fn grayscale_matches_image() {
+        let frame = RgbImage::from_fn(37, 5, |x, y| {
+            Rgb([(x * 7) as u8, (y * 50) as u8, (x * y * 3) as u8])
+        });
+        let expected = image::imageops::grayscale(&frame);
+        let gray = grayscale(&frame);
+        for (a, b) in gray.pixels().zip(expected.pixels()) {
+            assert!(a[0].abs_diff(b[0]) <= 1, "{} vs {}", a[0], b[0]);
+        }
+    }
+
+    #[test]
+    fn downscale_averages_the_pixels_covered() {
+        let gray = GrayImage::from_fn(4, 4, |x, _| Luma([if x < 2 { 10 } else { 30 }]));
+        let small = downscale(&gray, 0.5);
+        assert_eq!(small.dimensions(), (2, 2));
+        assert_eq!(small.as_raw(), &vec![10, 30, 10, 30]);
+
+        let small = downscale(&gray, 0.25);
+        assert_eq!(small.as_raw(), &vec![20]);
+        // an uneven scale
+        let small = downscale(&GrayImage::from_pixel(640, 480, Luma([77])), 0.3);
+        assert_eq!(small.dimensions(), (192, 144));
+        assert!(small.pixels().all(|pixel| pixel[0] == 77));
+    }
+}
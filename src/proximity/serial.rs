@@ -0,0 +1,102 @@
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+use serialport::SerialPort;
+
+use super::{ProximitySample, ProximitySource};
+use crate::posture::Proximity;
+use crate::source::CaptureError;
+
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+// an HC-SR04 reads up to about 4m; anything further than this is the wall behind an empty chair
+const ABSENT_BEYOND: f32 = 150.0;
+// a line this long without a newline isn't coming from a distance sensor
+const MAX_LINE_LENGTH: usize = 256;
+
+// A distance sensor on a serial port, e.g. an Arduino with an HC-SR04 ultrasonic sensor printing
+// one reading in centimetres per line ("48.5", "Distance: 48 cm", ...)
+pub struct SerialSensor {
+    port: Box<dyn SerialPort>,
+    // the incomplete line at the end of the last read
+    pending: String,
+}
+
+impl SerialSensor {
+    pub fn new(path: &str, baud_rate: u32) -> SerialSensor {
+        let port = match serialport::new(path, baud_rate)
+            .timeout(READ_TIMEOUT)
+            .open()
+        {
+            Ok(port) => port,
+            Err(e) => panic!("Failed to open serial port {}: {}", path, e),
+        };
+        SerialSensor {
+            port,
+            pending: String::new(),
+        }
+    }
+
+    // Reads everything the sensor has sent since the last call (waiting for at least one
+    // complete line) and returns the newest line, so that readings never lag behind
+    fn latest_line(&mut self) -> Result<String, CaptureError> {
+        let mut latest = None;
+        let mut chunk = [0u8; 256];
+        loop {
+            let available = self
+                .port
+                .bytes_to_read()
+                .map_err(|e| CaptureError::SensorRead(e.to_string()))?;
+            if available == 0 {
+                if let Some(line) = latest {
+                    return Ok(line);
+                }
+            }
+            let read = self
+                .port
+                .read(&mut chunk)
+                .map_err(|e| CaptureError::SensorRead(e.to_string()))?;
+            self.pending
+                .push_str(&String::from_utf8_lossy(&chunk[..read]));
+            while let Some(end) = self.pending.find('\n') {
+                let line: String = self.pending.drain(..=end).collect();
+                if !line.trim().is_empty() {
+                    latest = Some(line.trim().to_string());
+                }
+            }
+            if self.pending.len() > MAX_LINE_LENGTH {
+                self.pending.clear();
+                return Err(CaptureError::SensorRead(
+                    "The serial device is not sending distance readings".to_string(),
+                ));
+            }
+        }
+    }
+}
+
+// The first number in the line, ignoring any label or unit around it
+fn parse_distance(line: &str) -> Option<f32> {
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let number: String = line[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    number.parse().ok()
+}
+
+impl ProximitySource for SerialSensor {
+    fn sample(&mut self) -> Result<ProximitySample, CaptureError> {
+        let line = self.latest_line()?;
+        let timestamp = Instant::now();
+        let distance = parse_distance(&line).ok_or_else(|| {
+            CaptureError::SensorRead(format!("Not a distance reading: {}", line))
+        })?;
+        // the sensor reports 0 when the echo never came back, i.e. nothing in range
+        let present = distance > 0.0 && distance <= ABSENT_BEYOND;
+        Ok(ProximitySample {
+            timestamp,
+            metric: present.then_some(Proximity::Distance(distance)),
+            confidence: 1.0,
+            observation: None,
+        })
+    }
+}
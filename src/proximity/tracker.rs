@@ -0,0 +1,116 @@
+use std::time::Instant;
+
+use imageproc::rect::Rect;
+
+use crate::config::SkipFramesConfig;
+use crate::detector::Face;
+
+// Runs the face detector on only every few frames and predicts the faces in between, trading how
+// quickly a change is noticed for CPU. The user's face is assumed to keep moving the way it moved
+// between the last two detections; any others are assumed to stay put.
+pub struct FaceTracker {
+    detect_every: usize,
+    // frames predicted since the last detection
+    skipped: usize,
+    last: Option<Detection>,
+}
+
+struct Detection {
+    at: Instant,
+    faces: Vec<Face>,
+    // of the first face, in pixels per second
+    velocity: (f32, f32),
+}
+
+impl FaceTracker {
+    pub fn new(config: &SkipFramesConfig) -> FaceTracker {
+        FaceTracker {
+            detect_every: config.detect_every,
+            skipped: 0,
+            last: None,
+        }
+    }
+
+    // Whether the detector should look at this frame
+    pub fn should_detect(&self) -> bool {
+        self.last.is_none() || self.skipped + 1 >= self.detect_every
+    }
+
+    // Takes note of what the detector found
+    pub fn detected(&mut self, faces: Vec<Face>, at: Instant) {
+        let velocity = match (&self.last, faces.first()) {
+            (Some(last), Some(face)) => match last.faces.first() {
+                Some(previous) if at > last.at => {
+                    let seconds = (at - last.at).as_secs_f32();
+                    (
+                        (face.rect.left() - previous.rect.left()) as f32 / seconds,
+                        (face.rect.top() - previous.rect.top()) as f32 / seconds,
+                    )
+                }
+                _ => (0.0, 0.0),
+            },
+            _ => (0.0, 0.0),
+        };
+        self.skipped = 0;
+        self.last = Some(Detection {
+            at,
+            faces,
+            velocity,
+        });
+    }
+
+    // The faces in a frame the detector skipped
+    pub fn predict(&mut self, at: Instant) -> Vec<Face> {
+        self.skipped += 1;
+        let Some(last) = &self.last else {
+            return Vec::new();
+        };
+        let seconds = at.saturating_duration_since(last.at).as_secs_f32();
+        let mut faces = last.faces.clone();
+        if let Some(face) = faces.first_mut() {
+            face.rect = Rect::at(
+                face.rect.left() + (last.velocity.0 * seconds).round() as i32,
+                face.rect.top() + (last.velocity.1 * seconds).round() as i32,
+            )
+            .of_size(face.rect.width(), face.rect.height());
+        }
+        faces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn face(left: i32) -> Face {
+        Face {
+            rect: Rect::at(left, 100).of_size(80, 80),
+            score: 1.0,
+        }
+    }
+
+    #[test]
+    fn faces_are_predicted_between_detections() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut tracker = FaceTracker::new(&SkipFramesConfig { detect_every: 3 });
+        assert!(tracker.should_detect());
+        tracker.detected(vec![face(100)], at(0));
+        assert!(!tracker.should_detect());
+        tracker.predict(at(500));
+        assert!(!tracker.should_detect());
+        tracker.predict(at(1000));
+        assert!(tracker.should_detect());
+        // moving right at 20 pixels a second
+        tracker.detected(vec![face(130)], at(1500));
+        let predicted = tracker.predict(at(2000));
+        assert_eq!(predicted[0].rect.left(), 140);
+        assert_eq!(predicted[0].rect.width(), 80);
+
+        tracker.predict(at(2500));
+        tracker.detected(Vec::new(), at(3000));
+        assert!(tracker.predict(at(3500)).is_empty());
+    }
+}
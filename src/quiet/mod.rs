@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::QuietConfig;
+use crate::desktop::{self, FocusedWindow};
+
+// How far alerts are toned down, from not at all to the most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Quiet {
+    #[default]
+    Normal,
+    // only sinks that are seen rather than heard, e.g. while in a call
+    Visual,
+    // only notification sinks, e.g. while watching a video
+    Notification,
+    // no alerts at all
+    Silent,
+}
+
+// How quiet to be while a window has the focus, and which of the configured names it matched
+pub fn for_window(config: &QuietConfig, window: &FocusedWindow) -> (Quiet, Option<String>) {
+    let app = window.app.to_lowercase();
+    let title = window.title.to_lowercase();
+    let matching = |names: &[String]| {
+        names
+            .iter()
+            .find(|name| {
+                let name = name.to_lowercase();
+                !name.is_empty() && (app.contains(&name) || title.contains(&name))
+            })
+            .cloned()
+    };
+    if let Some(name) = matching(&config.silence_apps) {
+        (Quiet::Silent, Some(name))
+    } else if let Some(name) = matching(&config.soften_apps) {
+        (Quiet::Notification, Some(name))
+    } else {
+        (Quiet::Normal, None)
+    }
+}
+
+// Looks at what's going on on the desktop every so often until shutdown, keeping `quiet` up to
+// date for the alert manager
+pub async fn run(config: QuietConfig, quiet: watch::Sender<Quiet>, shutdown: CancellationToken) {
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(config.interval));
+    let mut idle_inhibit = config.idle_inhibit;
+    let mut microphone = config.microphone;
+    let mut focus = !config.silence_apps.is_empty() || !config.soften_apps.is_empty();
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let mut level = Quiet::Normal;
+        let mut reason = None;
+        if idle_inhibit {
+            match tokio::task::spawn_blocking(desktop::idle_inhibited).await {
+                Ok(Ok(true)) => {
+                    level = Quiet::Notification;
+                    reason = Some(
+                        "the screen is kept awake, e.g. for a video or presentation".to_string(),
+                    );
+                }
+                Ok(Ok(false)) => {}
+                Ok(Err(e)) => {
+                    println!("Not softening alerts while the screen is kept awake: {}", e);
+                    idle_inhibit = false;
+                }
+                Err(_) => {}
+            }
+        }
+        if microphone {
+            match tokio::task::spawn_blocking(desktop::microphone_in_use).await {
+                Ok(Ok(true)) if level < Quiet::Visual => {
+                    level = Quiet::Visual;
+                    reason = Some("the microphone is in use, e.g. in a call".to_string());
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => {
+                    println!("Not silencing alerts while the microphone is in use: {}", e);
+                    microphone = false;
+                }
+                Err(_) => {}
+            }
+        }
+        if focus {
+            match tokio::task::spawn_blocking(desktop::focused_window).await {
+                Ok(Ok(window)) => {
+                    let (app_level, name) = for_window(&config, &window);
+                    if app_level > level {
+                        level = app_level;
+                        reason = name.map(|name| format!("{} has the focus", name));
+                    }
+                }
+                Ok(Err(e)) => {
+                    println!("Not quieting alerts for focused apps: {}", e);
+                    focus = false;
+                }
+                Err(_) => {}
+            }
+        }
+        let previous = quiet.send_replace(level);
+        if level != previous {
+            match (level, reason) {
+                (Quiet::Silent, Some(reason)) => println!("Silencing alerts while {}", reason),
+                (Quiet::Visual, Some(reason)) => {
+                    println!("Alerts are seen and not heard while {}", reason)
+                }
+                (_, Some(reason)) => println!("Softening alerts while {}", reason),
+                _ => println!("Alerts back to normal"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(app: &str, title: &str) -> FocusedWindow {
+        FocusedWindow {
+            app: app.to_string(),
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn focused_apps_are_matched_by_name_or_title() {
+        let config = QuietConfig {
+            silence_apps: vec!["zoom".to_string()],
+            soften_apps: vec!["OBS".to_string(), "mpv".to_string()],
+            ..QuietConfig::default()
+        };
+        assert_eq!(
+            for_window(&config, &window("zoom.us", "Zoom Meeting")),
+            (Quiet::Silent, Some("zoom".to_string()))
+        );
+        assert_eq!(
+            for_window(
+                &config,
+                &window("com.obsproject.Studio", "OBS 30.0 - Profile")
+            ),
+            (Quiet::Notification, Some("OBS".to_string()))
+        );
+        assert_eq!(
+            for_window(&config, &window("firefox", "Watching in mpv")).0,
+            Quiet::Notification
+        );
+        assert_eq!(
+            for_window(&config, &window("code", "main.rs")),
+            (Quiet::Normal, None)
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use std::thread;
+use std::time::Duration;
+
+use super::{create_face_recognizer, EnrolledFaces};
+use crate::config::Config;
+use crate::detector::create_detector;
+use crate::i18n::tr;
+use crate::preflight;
+use crate::privacy::PrivacyMode;
+use crate::source::{FrameSource, WebCam, WebCamMode};
+
+// how many pictures of the face are kept, taken this far apart so that they differ a little
+const SAMPLES: usize = 8;
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+// frames without exactly one face don't count, but give up after this many
+const MAX_FRAMES: usize = 60;
+
+// Takes a few pictures of the user's face with the given camera and keeps their embeddings, for
+// guest mode to tell the user from anyone else. Nothing but the embeddings is kept.
+pub fn enroll(config: &Config, camera: u32, privacy: PrivacyMode) -> Result<(), String> {
+    let mut recognizer =
+        create_face_recognizer(&config.face_recognition, privacy).map_err(|e| e.to_string())?;
+    let mut detector = create_detector(&config.detector).map_err(|e| e.to_string())?;
+    let path = EnrolledFaces::default_path()
+        .ok_or("There is no data directory to keep the enrolled face in")?;
+    preflight::camera_access(camera)?;
+    let mut source = WebCam::new(camera, WebCamMode::Continuous);
+    println!("{}", tr!("enroll-look"));
+    let mut embeddings = Vec::new();
+    for _ in 0..MAX_FRAMES {
+        if embeddings.len() == SAMPLES {
+            break;
+        }
+        thread::sleep(SAMPLE_INTERVAL);
+        let mut frame = source.capture().map_err(|e| e.to_string())?;
+        let mut gray = image::imageops::grayscale(frame.rgb());
+        privacy.scrub(&mut frame);
+        let faces = detector.detect(&gray);
+        if let [face] = faces.as_slice() {
+            if let Some(embedding) = recognizer.embedding(&gray, face.rect) {
+                embeddings.push(embedding);
+            }
+        }
+        privacy.scrub(&mut gray);
+    }
+    if embeddings.len() < SAMPLES {
+        return Err(tr!("enroll-failed"));
+    }
+    EnrolledFaces::new(path.clone()).save(&embeddings)?;
+    println!("{}", tr!("enroll-done", path = path.display().to_string()));
+    Ok(())
+}
@@ -0,0 +1,149 @@
+mod enroll;
+#[cfg(feature = "onnx")]
+mod onnx;
+
+pub use enroll::enroll;
+
+use std::fs;
+use std::path::PathBuf;
+
+use image::GrayImage;
+use imageproc::rect::Rect;
+use serde::{Deserialize, Serialize};
+
+use crate::detector::{DetectorError, ExecutionProvider};
+use crate::privacy::PrivacyMode;
+
+// Describes a face so that it can be told apart from other people's
+pub trait FaceRecognizer {
+    // A vector close to the ones of the same person's face and far from anyone else's
+    fn embedding(&mut self, image: &GrayImage, face: Rect) -> Option<Vec<f32>>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct FaceRecognitionConfig {
+    // a face embedding model (ArcFace style: a 112x112 face crop in, an embedding out)
+    pub model: Option<PathBuf>,
+    pub execution_provider: ExecutionProvider,
+}
+
+pub fn create_face_recognizer(
+    config: &FaceRecognitionConfig,
+    privacy: PrivacyMode,
+) -> Result<Box<dyn FaceRecognizer>, DetectorError> {
+    #[cfg(feature = "onnx")]
+    {
+        let model = config
+            .model
+            .as_deref()
+            .ok_or_else(|| DetectorError::MissingModel("face recognition".to_string()))?;
+        Ok(Box::new(onnx::OnnxRecognizer::new(
+            model,
+            config.execution_provider,
+            privacy,
+        )?))
+    }
+    #[cfg(not(feature = "onnx"))]
+    {
+        let _ = (config, privacy);
+        Err(DetectorError::Unavailable(
+            "face recognition".to_string(),
+            "onnx".to_string(),
+        ))
+    }
+}
+
+// Where the embeddings of the enrolled user's face are kept. They never leave the machine.
+pub struct EnrolledFaces {
+    path: PathBuf,
+}
+
+impl EnrolledFaces {
+    pub fn new(path: PathBuf) -> EnrolledFaces {
+        EnrolledFaces { path }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("neckcheck").join("faces.json"))
+    }
+
+    pub fn load(&self) -> Result<Vec<Vec<f32>>, String> {
+        let contents = fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))
+    }
+
+    pub fn save(&self, embeddings: &[Vec<f32>]) -> Result<(), String> {
+        self.path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| {
+                let json = serde_json::to_string(embeddings)?;
+                fs::write(&self.path, json)
+            })
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+// Tells the enrolled user from everyone else
+pub struct Recognition {
+    recognizer: Box<dyn FaceRecognizer>,
+    enrolled: Vec<Vec<f32>>,
+    // the least similarity to one of the enrolled faces that is still the user
+    threshold: f32,
+}
+
+impl Recognition {
+    pub fn new(
+        recognizer: Box<dyn FaceRecognizer>,
+        enrolled: Vec<Vec<f32>>,
+        threshold: f32,
+    ) -> Recognition {
+        Recognition {
+            recognizer,
+            enrolled,
+            threshold,
+        }
+    }
+
+    // Whether `face` is someone other than the enrolled user, or None if it can't be told
+    pub fn is_guest(&mut self, image: &GrayImage, face: Rect) -> Option<bool> {
+        let embedding = self.recognizer.embedding(image, face)?;
+        Some(!is_enrolled(&embedding, &self.enrolled, self.threshold))
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms > 0.0 {
+        dot / norms
+    } else {
+        0.0
+    }
+}
+
+fn is_enrolled(embedding: &[f32], enrolled: &[Vec<f32>], threshold: f32) -> bool {
+    enrolled.iter().any(|face| {
+        face.len() == embedding.len() && cosine_similarity(face, embedding) >= threshold
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_faces_like_an_enrolled_one_are_the_user() {
+        let enrolled = vec![vec![1.0, 0.0, 0.0], vec![0.9, 0.1, 0.0]];
+        assert!(is_enrolled(&[2.0, 0.1, 0.0], &enrolled, 0.5));
+        assert!(!is_enrolled(&[0.0, 1.0, 0.0], &enrolled, 0.5));
+        assert!(!is_enrolled(&[0.0, 0.0, 0.0], &enrolled, 0.5));
+        // from another model
+        assert!(!is_enrolled(&[1.0, 0.0], &enrolled, 0.5));
+        assert!(!is_enrolled(&[1.0, 0.0, 0.0], &[], 0.5));
+    }
+}
@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::GrayImage;
+use imageproc::rect::Rect;
+use ort::session::Session;
+use ort::value::TensorRef;
+use zeroize::Zeroize;
+
+use super::FaceRecognizer;
+use crate::detector::{onnx_session, DetectorError, ExecutionProvider};
+use crate::privacy::PrivacyMode;
+
+const INPUT_SIZE: u32 = 112;
+
+// A face embedding model such as ArcFace or MobileFaceNet exported to ONNX: a 112x112 NCHW face
+// crop scaled to -1..1 in, and an embedding out
+pub struct OnnxRecognizer {
+    session: Session,
+    privacy: PrivacyMode,
+    // what went wrong the last time, so that a model failing on every frame is only reported once
+    last_error: Option<String>,
+}
+
+impl OnnxRecognizer {
+    pub fn new(
+        model: &Path,
+        preference: ExecutionProvider,
+        privacy: PrivacyMode,
+    ) -> Result<OnnxRecognizer, DetectorError> {
+        let (session, _) = onnx_session(model, preference)?;
+        Ok(OnnxRecognizer {
+            session,
+            privacy,
+            last_error: None,
+        })
+    }
+
+    fn try_embedding(&mut self, image: &GrayImage, face: Rect) -> ort::Result<Option<Vec<f32>>> {
+        let left = face.left().max(0) as u32;
+        let top = face.top().max(0) as u32;
+        let right = (face.right().max(0) as u32).min(image.width());
+        let bottom = (face.bottom().max(0) as u32).min(image.height());
+        if right <= left || bottom <= top {
+            return Ok(None);
+        }
+        // every copy of the face is zeroed once the model has seen it, in strict mode
+        let mut crop = imageops::crop_imm(image, left, top, right - left, bottom - top).to_image();
+        let mut resized = imageops::resize(&crop, INPUT_SIZE, INPUT_SIZE, FilterType::Triangle);
+        self.privacy.scrub(&mut crop);
+        let plane = (INPUT_SIZE * INPUT_SIZE) as usize;
+        let mut input = vec![0.0f32; 3 * plane];
+        for (i, &value) in resized.as_raw().iter().enumerate() {
+            let value = (value as f32 - 127.5) / 127.5;
+            for channel in 0..3 {
+                input[channel * plane + i] = value;
+            }
+        }
+        self.privacy.scrub(&mut resized);
+        let shape = [1, 3, INPUT_SIZE as usize, INPUT_SIZE as usize];
+        let input_name = self.session.inputs[0].name.clone();
+        let output_name = self.session.outputs[0].name.clone();
+        // a view, so that the input stays ours to zero
+        let embedding = TensorRef::from_array_view((shape, input.as_slice())).and_then(|tensor| {
+            let outputs = self.session.run(ort::inputs![input_name => tensor])?;
+            let (_, embedding) = outputs[output_name.as_str()].try_extract_tensor::<f32>()?;
+            Ok((!embedding.is_empty()).then(|| embedding.to_vec()))
+        });
+        if self.privacy == PrivacyMode::Strict {
+            input.zeroize();
+        }
+        embedding
+    }
+}
+
+impl FaceRecognizer for OnnxRecognizer {
+    fn embedding(&mut self, image: &GrayImage, face: Rect) -> Option<Vec<f32>> {
+        match self.try_embedding(image, face) {
+            Ok(embedding) => {
+                self.last_error = None;
+                embedding
+            }
+            Err(e) => {
+                let e = e.to_string();
+                if self.last_error.as_ref() != Some(&e) {
+                    println!("ONNX face recognition failed: {}", e);
+                    self.last_error = Some(e);
+                }
+                None
+            }
+        }
+    }
+}
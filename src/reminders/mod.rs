@@ -0,0 +1,168 @@
+// Reminders on a timer rather than from what the camera sees, for running without one or while it
+// is unavailable: to check the posture, and the 20-20-20 rule's eye breaks. Standing up is left to
+// the alert manager's timer, as it is with a camera.
+
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{sleep_until, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::RemindersConfig;
+use crate::events::{EventBus, PostureEvent};
+
+// When each reminder is due next, if it is turned on
+struct Timer {
+    every: Duration,
+    due: Instant,
+    event: PostureEvent,
+}
+
+struct Timers {
+    timers: Vec<Timer>,
+}
+
+impl Timers {
+    fn new(config: &RemindersConfig, now: Instant) -> Timers {
+        let timers = [
+            (config.posture_every, PostureEvent::PostureCheck),
+            (config.eye_break_every, PostureEvent::EyeBreak),
+        ]
+        .into_iter()
+        .filter(|(every, _)| *every > 0.0)
+        .map(|(every, event)| {
+            let every = Duration::from_secs_f64(every);
+            Timer {
+                every,
+                due: now + every,
+                event,
+            }
+        })
+        .collect();
+        Timers { timers }
+    }
+
+    fn next(&self) -> Option<Instant> {
+        self.timers.iter().map(|timer| timer.due).min()
+    }
+
+    // The reminders due by `now`, each once however many were missed (e.g. while suspended)
+    fn due(&mut self, now: Instant) -> Vec<PostureEvent> {
+        let mut due = Vec::new();
+        for timer in self.timers.iter_mut().filter(|timer| timer.due <= now) {
+            timer.due = now + timer.every;
+            due.push(timer.event.clone());
+        }
+        due
+    }
+
+    // Starts every timer over, e.g. after a pause
+    fn restart(&mut self, now: Instant) {
+        for timer in &mut self.timers {
+            timer.due = now + timer.every;
+        }
+    }
+}
+
+// Whether a camera in use can be relied on after `event`, given whether it could before
+fn camera_working(event: &PostureEvent, working: bool) -> bool {
+    match event {
+        PostureEvent::CameraLost(_) | PostureEvent::MonitoringFailed(_) => false,
+        PostureEvent::CameraRecovered => true,
+        _ => working,
+    }
+}
+
+// Publishes the reminders as they come due, holding them while monitoring is paused. As a
+// `fallback`, only while the camera is unavailable, from when it was lost.
+pub async fn run(
+    config: RemindersConfig,
+    fallback: bool,
+    events: EventBus,
+    shutdown: CancellationToken,
+) {
+    let mut timers = Timers::new(&config, Instant::now());
+    let mut updates = events.subscribe();
+    let mut paused = false;
+    let mut camera = fallback;
+    loop {
+        let next = timers.next().filter(|_| !paused && !camera);
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            event = updates.recv() => match event {
+                Ok(PostureEvent::Paused) => paused = true,
+                Ok(PostureEvent::Resumed) => {
+                    paused = false;
+                    timers.restart(Instant::now());
+                }
+                Ok(event) if fallback => {
+                    let working = camera_working(&event, camera);
+                    if camera && !working {
+                        println!("The camera is unavailable, reminding on a timer until it is back");
+                        timers.restart(Instant::now());
+                    } else if !camera && working {
+                        println!("The camera is back, alerting on what it sees again");
+                    }
+                    camera = working;
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return,
+            },
+            _ = sleep_until(next.unwrap_or_else(Instant::now)), if next.is_some() => {
+                for event in timers.due(Instant::now()) {
+                    events.publish(event);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reminders_come_due_on_their_own_timers() {
+        let config = RemindersConfig {
+            fallback: false,
+            posture_every: 900.0,
+            eye_break_every: 1200.0,
+        };
+        let start = Instant::now();
+        let minutes = |minutes: u64| start + Duration::from_secs(minutes * 60);
+        let mut timers = Timers::new(&config, start);
+        assert_eq!(timers.next(), Some(minutes(15)));
+        assert!(timers.due(minutes(10)).is_empty());
+        assert_eq!(timers.due(minutes(15)), [PostureEvent::PostureCheck]);
+        assert_eq!(timers.next(), Some(minutes(20)));
+        assert_eq!(timers.due(minutes(20)), [PostureEvent::EyeBreak]);
+        // a long suspend makes up for each of them once
+        assert_eq!(
+            timers.due(minutes(120)),
+            [PostureEvent::PostureCheck, PostureEvent::EyeBreak]
+        );
+        timers.restart(minutes(200));
+        assert_eq!(timers.next(), Some(minutes(215)));
+
+        let off = RemindersConfig {
+            posture_every: 0.0,
+            ..config
+        };
+        let mut timers = Timers::new(&off, start);
+        assert_eq!(timers.next(), Some(minutes(20)));
+        assert_eq!(timers.due(minutes(60)), [PostureEvent::EyeBreak]);
+    }
+
+    #[test]
+    fn the_fallback_follows_the_camera() {
+        let lost = PostureEvent::CameraLost("busy".to_string());
+        assert!(!camera_working(&lost, true));
+        assert!(!camera_working(&PostureEvent::Away, false));
+        assert!(camera_working(&PostureEvent::CameraRecovered, false));
+        assert!(camera_working(&PostureEvent::CameraClosed, true));
+        assert!(!camera_working(
+            &PostureEvent::MonitoringFailed("unplugged".to_string()),
+            true
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::RemoteConfig;
+use crate::stats::this_host;
+use crate::status::{PostureState, Status};
+
+// How long to wait before trying a report the collector didn't take again
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+// What the collector is told, e.g.
+// `{"host":"desk","state":"bad","since":"...","alerts_today":4,"time":"..."}`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Report {
+    pub host: String,
+    pub state: PostureState,
+    pub since: Option<DateTime<Local>>,
+    pub alerts_today: u32,
+    pub time: DateTime<Local>,
+}
+
+// Decides when the collector has to hear about the status: whenever the state changes, and every
+// so often otherwise so that it can tell a quiet machine from one that's gone. A report that
+// couldn't be delivered stays due until one is.
+pub struct Forwarder {
+    heartbeat: Option<Duration>,
+    // the state and when it started, as last delivered
+    delivered: Option<(PostureState, Option<DateTime<Local>>)>,
+    delivered_at: Option<Instant>,
+    failed_at: Option<Instant>,
+}
+
+impl Forwarder {
+    pub fn new(config: &RemoteConfig) -> Forwarder {
+        Forwarder {
+            heartbeat: (config.heartbeat > 0.0).then(|| Duration::from_secs_f64(config.heartbeat)),
+            delivered: None,
+            delivered_at: None,
+            failed_at: None,
+        }
+    }
+
+    // The report to send about `status`, if one is due
+    pub fn due(&self, status: &Status, now: Instant, local: DateTime<Local>) -> Option<Report> {
+        if self
+            .failed_at
+            .is_some_and(|failed| now < failed + RETRY_INTERVAL)
+        {
+            return None;
+        }
+        let changed = self.delivered != Some((status.state, status.since));
+        let heartbeat = match (self.heartbeat, self.delivered_at) {
+            (Some(heartbeat), Some(at)) => now >= at + heartbeat,
+            _ => false,
+        };
+        (changed || heartbeat).then(|| Report {
+            host: this_host().to_string(),
+            state: status.state,
+            since: status.since,
+            alerts_today: status.alerts_today,
+            time: local,
+        })
+    }
+
+    pub fn delivered(&mut self, report: &Report, now: Instant) {
+        self.delivered = Some((report.state, report.since));
+        self.delivered_at = Some(now);
+        self.failed_at = None;
+    }
+
+    pub fn failed(&mut self, now: Instant) {
+        self.failed_at = Some(now);
+    }
+}
+
+fn send(url: &str, token: Option<&str>, report: &Report) -> Result<(), String> {
+    let mut request = ureq::post(url).timeout(Duration::from_secs(10));
+    if let Some(token) = token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    request
+        .send_json(report)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// Forwards the state to the collector as it changes until shutdown
+pub async fn run(
+    config: RemoteConfig,
+    mut status: watch::Receiver<Status>,
+    shutdown: CancellationToken,
+) {
+    let mut forwarder = Forwarder::new(&config);
+    let mut tick = tokio::time::interval(RETRY_INTERVAL);
+    loop {
+        let report = forwarder.due(&status.borrow_and_update(), Instant::now(), Local::now());
+        if let Some(report) = report {
+            let url = config.url.clone();
+            let token = config.token.clone();
+            let sent = report.clone();
+            let result = tokio::task::spawn_blocking(move || send(&url, token.as_deref(), &sent))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()));
+            match result {
+                Ok(()) => forwarder.delivered(&report, Instant::now()),
+                Err(e) => {
+                    // the token stays out of the message
+                    println!("Failed to report to {}: {}", config.url, e);
+                    forwarder.failed(Instant::now());
+                }
+            }
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            changed = status.changed() => if changed.is_err() {
+                return;
+            },
+            _ = tick.tick() => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_changes_and_heartbeats_are_reported() {
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+        let local = Local::now();
+        let mut forwarder = Forwarder::new(&RemoteConfig {
+            heartbeat: 300.0,
+            ..RemoteConfig::default()
+        });
+        let mut status = Status {
+            since: Some(local),
+            ..Status::default()
+        };
+        let report = forwarder.due(&status, at(0), local).unwrap();
+        forwarder.delivered(&report, at(0));
+        // new measurements alone aren't worth a report
+        status.alerts_today = 1;
+        assert!(forwarder.due(&status, at(10), local).is_none());
+
+        status.state = PostureState::Bad;
+        let report = forwarder.due(&status, at(20), local).unwrap();
+        assert_eq!(report.state, PostureState::Bad);
+        // the collector is down, so it's tried again a little later
+        forwarder.failed(at(20));
+        assert!(forwarder.due(&status, at(30), local).is_none());
+        let report = forwarder.due(&status, at(50), local).unwrap();
+        forwarder.delivered(&report, at(50));
+        assert!(forwarder.due(&status, at(300), local).is_none());
+        assert!(forwarder.due(&status, at(350), local).is_some());
+    }
+}
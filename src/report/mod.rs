@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use chrono::{Duration, Local, NaiveDate, Timelike};
+
+use crate::alert::AlertKind;
+use crate::program::{weekly_adherence, Adherence};
+use crate::stats::{
+    bad_posture, format_duration, heatmap, merge_short_breaks, segments, weekly_false_positives,
+    FalsePositives, Heatmap, Segment, StatsEntry, StatsLog, StatsRecord, WEEKDAYS,
+};
+
+const CHART_WIDTH: f32 = 720.0;
+const CHART_HEIGHT: f32 = 200.0;
+const ROW_HEIGHT: f32 = 18.0;
+const LABEL_WIDTH: f32 = 90.0;
+
+// Writes a self-contained HTML report (inline SVG charts, no scripts or external files) of the
+// last `days` days of stats, e.g. to share with a physiotherapist
+pub fn write(log: &StatsLog, out: &Path, days: u32, min_break: Duration) -> Result<(), String> {
+    let records = log
+        .read()
+        .map_err(|e| format!("Failed to read {}: {}", log.path().display(), e))?;
+    let today = Local::now().date_naive();
+    let first = today - Duration::days(days.max(1) as i64 - 1);
+    let in_range: Vec<StatsRecord> = records
+        .into_iter()
+        .filter(|record| record.time.date_naive() >= first)
+        .collect();
+    if in_range.is_empty() {
+        return Err(format!(
+            "No stats recorded in the last {} days in {}",
+            days,
+            log.path().display()
+        ));
+    }
+    let dates: Vec<NaiveDate> = first.iter_days().take_while(|d| *d <= today).collect();
+    let seated = merge_short_breaks(&segments(&in_range), min_break);
+    let bad = bad_posture(&in_range);
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>neckcheck report</title>\
+         <style>{}</style></head><body>\n<h1>neckcheck report</h1>\n<p>{} to {}</p>\n",
+        STYLE,
+        first.format("%-d %B %Y"),
+        today.format("%-d %B %Y")
+    );
+    html.push_str(&overview(&seated, &bad, &in_range));
+    html.push_str(
+        "<h2>Posture score</h2>\n\
+         <p>The share of time at the desk spent not too close to the screen.</p>\n",
+    );
+    html.push_str(&score_chart(&dates, &seated, &bad));
+    html.push_str("<h2>Posture alerts by hour of day</h2>\n");
+    html.push_str(&alerts_chart(&in_range));
+    let false_positives = weekly_false_positives(&in_range);
+    if false_positives.values().any(|week| week.reported > 0) {
+        html.push_str(
+            "<h2>False positives</h2>\n\
+             <p>Posture alerts reported as wrong with <code>neckcheck report-fp</code>, by week.</p>\n",
+        );
+        html.push_str(&false_positive_table(&false_positives));
+    }
+    html.push_str(
+        "<h2>Bad posture by hour and day of the week</h2>\n\
+         <p>Minutes too close to the screen, summed over the period.</p>\n",
+    );
+    html.push_str(&heatmap_chart(&heatmap(&bad)));
+    html.push_str(
+        "<h2>Sitting and standing</h2>\n\
+         <p><span class=\"seated\">&#9632;</span> seated \
+         <span class=\"away\">&#9632;</span> away</p>\n",
+    );
+    html.push_str(&timeline(&dates, &seated));
+    let adherence = weekly_adherence(&in_range);
+    if !adherence.is_empty() {
+        html.push_str(
+            "<h2>Exercises</h2>\n\
+             <p>How often each exercise of the program was done, of the times it was prompted, by \
+             week.</p>\n",
+        );
+        html.push_str(&exercise_table(&adherence));
+    }
+    html.push_str("</body></html>\n");
+    fs::write(out, html).map_err(|e| format!("Failed to write {}: {}", out.display(), e))
+}
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:800px;margin:2em auto;color:#222}\
+    svg{display:block;margin:1em 0}text{font-size:11px;fill:#555}\
+    .seated{color:#4a7fb5}.away{color:#c9d6e3}.bar{fill:#4a7fb5}.bad{fill:#d9534f}\
+    td,th{padding:2px 12px 2px 0;text-align:left}";
+
+// Each week's exercises, e.g. "Chin tucks: 2 of 3"
+fn exercise_table(weeks: &BTreeMap<NaiveDate, BTreeMap<String, Adherence>>) -> String {
+    let mut html = String::from("<table><tr><th>Week of</th><th>Exercise</th><th>Done</th></tr>\n");
+    for (monday, exercises) in weeks {
+        for (exercise, adherence) in exercises {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{} of {}</td></tr>",
+                monday.format("%-d %B"),
+                escape(exercise),
+                adherence.done,
+                adherence.prompted
+            );
+        }
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+// Each week's false positives, e.g. "2 of 31 (6.5%)"
+fn false_positive_table(weeks: &BTreeMap<NaiveDate, FalsePositives>) -> String {
+    let mut html = String::from("<table><tr><th>Week of</th><th>Reported</th></tr>\n");
+    for (monday, week) in weeks {
+        let rate = week
+            .rate()
+            .map_or(String::new(), |rate| format!(" ({:.1}%)", rate * 100.0));
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{} of {}{}</td></tr>",
+            monday.format("%-d %B"),
+            week.reported,
+            week.alerts,
+            rate
+        );
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+// Text from the config, safe to put in the HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn total(segments: &[Segment]) -> Duration {
+    segments.iter().fold(Duration::zero(), |total, segment| {
+        total + segment.duration()
+    })
+}
+
+fn overview(seated: &[Segment], bad: &[Segment], records: &[StatsRecord]) -> String {
+    let at_desk: Vec<Segment> = seated.iter().filter(|s| s.seated).copied().collect();
+    let alerts = records
+        .iter()
+        .filter(|record| {
+            record.entry
+                == StatsEntry::Event {
+                    event: AlertKind::TooClose,
+                }
+        })
+        .count();
+    format!(
+        "<ul><li>{} at the desk</li><li>{} too close to the screen</li>\
+         <li>{} posture alerts</li><li>longest time seated without a break: {}</li></ul>\n",
+        format_duration(total(&at_desk)),
+        format_duration(total(bad)),
+        alerts,
+        format_duration(
+            at_desk
+                .iter()
+                .map(Segment::duration)
+                .max()
+                .unwrap_or_else(Duration::zero)
+        )
+    )
+}
+
+fn on_day(segments: &[Segment], date: NaiveDate) -> Vec<Segment> {
+    segments
+        .iter()
+        .filter(|segment| segment.start.date_naive() == date)
+        .copied()
+        .collect()
+}
+
+// One bar per day with the posture score in percent, or none for days without monitoring
+fn score_chart(dates: &[NaiveDate], seated: &[Segment], bad: &[Segment]) -> String {
+    let width = (CHART_WIDTH - LABEL_WIDTH) / dates.len() as f32;
+    let mut svg = svg_open(CHART_WIDTH, CHART_HEIGHT + 20.0);
+    for percent in [0, 50, 100] {
+        let y = CHART_HEIGHT * (1.0 - percent as f32 / 100.0);
+        let _ = write!(
+            svg,
+            "<text x=\"0\" y=\"{:.1}\">{}%</text>",
+            y + 4.0,
+            percent
+        );
+    }
+    for (i, date) in dates.iter().enumerate() {
+        let x = LABEL_WIDTH + i as f32 * width;
+        let at_desk = total(
+            &on_day(seated, *date)
+                .into_iter()
+                .filter(|s| s.seated)
+                .collect::<Vec<_>>(),
+        );
+        if at_desk > Duration::zero() {
+            let too_close = total(&on_day(bad, *date)).min(at_desk);
+            let score = 1.0 - too_close.num_seconds() as f32 / at_desk.num_seconds() as f32;
+            let height = CHART_HEIGHT * score;
+            let _ = write!(
+                svg,
+                "<rect class=\"bar\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\">\
+                 <title>{}: {:.0}%</title></rect>",
+                x + 2.0,
+                CHART_HEIGHT - height,
+                width - 4.0,
+                height,
+                date.format("%a %-d %b"),
+                score * 100.0
+            );
+        }
+        let _ = write!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            x + 2.0,
+            CHART_HEIGHT + 14.0,
+            date.format("%d/%m")
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// One bar per hour of the day with the number of posture alerts in that hour over all days
+fn alerts_chart(records: &[StatsRecord]) -> String {
+    let mut counts = [0usize; 24];
+    for record in records {
+        if record.entry
+            == (StatsEntry::Event {
+                event: AlertKind::TooClose,
+            })
+        {
+            counts[record.time.hour() as usize] += 1;
+        }
+    }
+    let most = counts.iter().copied().max().unwrap_or(0).max(1);
+    let width = (CHART_WIDTH - LABEL_WIDTH) / 24.0;
+    let mut svg = svg_open(CHART_WIDTH, CHART_HEIGHT + 20.0);
+    let _ = write!(svg, "<text x=\"0\" y=\"10\">{}</text>", most);
+    for (hour, count) in counts.iter().enumerate() {
+        let x = LABEL_WIDTH + hour as f32 * width;
+        let height = CHART_HEIGHT * *count as f32 / most as f32;
+        let _ = write!(
+            svg,
+            "<rect class=\"bad\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\">\
+             <title>{:02}:00: {}</title></rect>",
+            x + 1.0,
+            CHART_HEIGHT - height,
+            width - 2.0,
+            height,
+            hour,
+            count
+        );
+        if hour % 3 == 0 {
+            let _ = write!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{:.1}\">{:02}</text>",
+                x,
+                CHART_HEIGHT + 14.0,
+                hour
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// One row per day, with the seated and away stretches laid out over the 24 hours
+fn timeline(dates: &[NaiveDate], segments: &[Segment]) -> String {
+    let scale = (CHART_WIDTH - LABEL_WIDTH) / (24.0 * 60.0);
+    let height = ROW_HEIGHT * dates.len() as f32 + 20.0;
+    let mut svg = svg_open(CHART_WIDTH, height);
+    let minute_of = |segment_date: NaiveDate, time: chrono::DateTime<Local>| {
+        if time.date_naive() == segment_date {
+            (time.hour() * 60 + time.minute()) as f32
+        } else {
+            24.0 * 60.0
+        }
+    };
+    for (row, date) in dates.iter().enumerate() {
+        let y = row as f32 * ROW_HEIGHT;
+        let _ = write!(
+            svg,
+            "<text x=\"0\" y=\"{:.1}\">{}</text>",
+            y + 12.0,
+            date.format("%a %-d %b")
+        );
+        for segment in on_day(segments, *date) {
+            let start = minute_of(*date, segment.start);
+            let end = minute_of(*date, segment.end);
+            let _ = write!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\">\
+                 <title>{} {}-{}</title></rect>",
+                LABEL_WIDTH + start * scale,
+                y + 2.0,
+                ((end - start) * scale).max(1.0),
+                ROW_HEIGHT - 4.0,
+                if segment.seated { "#4a7fb5" } else { "#c9d6e3" },
+                if segment.seated { "seated" } else { "away" },
+                segment.start.format("%H:%M"),
+                segment.end.format("%H:%M")
+            );
+        }
+    }
+    for hour in (0..=24).step_by(3) {
+        let _ = write!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\">{:02}</text>",
+            LABEL_WIDTH + hour as f32 * 60.0 * scale,
+            height - 4.0,
+            hour
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// A grid of hours by days of the week, shaded by the minutes of bad posture in each
+fn heatmap_chart(heatmap: &Heatmap) -> String {
+    let width = (CHART_WIDTH - LABEL_WIDTH) / 24.0;
+    let max = heatmap.max().max(1.0);
+    let mut svg = svg_open(CHART_WIDTH, ROW_HEIGHT * 7.0 + 20.0);
+    for (row, weekday) in WEEKDAYS.iter().enumerate() {
+        let y = row as f32 * ROW_HEIGHT;
+        let _ = write!(
+            svg,
+            "<text x=\"0\" y=\"{:.1}\">{}</text>",
+            y + 12.0,
+            weekday
+        );
+        for hour in 0..24 {
+            let minutes = heatmap.minutes(*weekday, hour);
+            let _ = write!(
+                svg,
+                "<rect class=\"bad\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" \
+                 fill-opacity=\"{:.2}\"><title>{} {:02}:00: {:.0} minutes</title></rect>",
+                LABEL_WIDTH + hour as f32 * width + 1.0,
+                y + 1.0,
+                width - 2.0,
+                ROW_HEIGHT - 2.0,
+                0.05 + 0.95 * minutes / max,
+                weekday,
+                hour,
+                minutes
+            );
+        }
+    }
+    for hour in (0..24).step_by(3) {
+        let _ = write!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\">{:02}</text>",
+            LABEL_WIDTH + hour as f32 * width,
+            ROW_HEIGHT * 7.0 + 14.0,
+            hour
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn svg_open(width: f32, height: f32) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        width, height, width, height
+    )
+}
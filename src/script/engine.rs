@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+use rhai::serde::{from_dynamic, to_dynamic};
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+
+use super::{Input, Judgement, PostureScript};
+use crate::events::PostureEvent;
+
+// the functions a script may define, each taking one argument
+const CHECK: &str = "check";
+const ON_TRANSITION: &str = "on_transition";
+// a script still running after this many operations is stopped, rather than holding up the check
+const MAX_OPERATIONS: u64 = 100_000;
+
+pub struct RhaiScript {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    // what the script keeps from one call to the next, as `this`
+    state: Dynamic,
+    check: bool,
+    on_transition: bool,
+    // what went wrong the last time, so that an error on every check is only printed once
+    last_error: Option<String>,
+}
+
+impl RhaiScript {
+    pub fn load(path: &Path) -> Result<RhaiScript, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| format!("Failed to load the script {}: {}", path.display(), e))?;
+        let defines = |name: &str| {
+            ast.iter_functions()
+                .any(|function| function.name == name && function.params.len() == 1)
+        };
+        let (check, on_transition) = (defines(CHECK), defines(ON_TRANSITION));
+        if !check && !on_transition {
+            return Err(format!(
+                "The script {} defines neither check(input) nor on_transition(event)",
+                path.display()
+            ));
+        }
+        Ok(RhaiScript {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+            state: Dynamic::from_map(Map::new()),
+            check,
+            on_transition,
+            last_error: None,
+        })
+    }
+
+    fn call(&mut self, name: &str, argument: Dynamic) -> Result<Dynamic, String> {
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        self.engine
+            .call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &self.ast,
+                name,
+                (argument,),
+            )
+            .map_err(|e| format!("{}(): {}", name, e))
+    }
+
+    // The result, or None after printing what went wrong unless it went wrong the same way last time
+    fn settle<T>(&mut self, result: Result<T, String>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.last_error = None;
+                Some(value)
+            }
+            Err(e) => {
+                if self.last_error.as_ref() != Some(&e) {
+                    println!("The script {} failed: {}", self.path.display(), e);
+                    self.last_error = Some(e);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl PostureScript for RhaiScript {
+    fn check(&mut self, input: &Input) -> Judgement {
+        if !self.check {
+            return Judgement::default();
+        }
+        let judged = to_dynamic(input)
+            .map_err(|e| e.to_string())
+            .and_then(|input| self.call(CHECK, input))
+            .and_then(|answer| {
+                from_dynamic::<Judgement>(&answer).map_err(|_| {
+                    format!(
+                        "{}(): returned {}, not true, false, () or a map of too_close and severity",
+                        CHECK,
+                        answer.type_name()
+                    )
+                })
+            });
+        self.settle(judged).unwrap_or_default()
+    }
+
+    fn transition(&mut self, event: &PostureEvent) {
+        if !self.on_transition {
+            return;
+        }
+        let called = to_dynamic(event)
+            .map_err(|e| e.to_string())
+            .and_then(|event| self.call(ON_TRANSITION, event));
+        self.settle(called);
+    }
+}
@@ -0,0 +1,200 @@
+// A script of the user's own with the last word on every check, for what the [decision] rule can't
+// say, e.g. to let being close pass while standing at a standing desk. Scripts are written in rhai.
+
+#[cfg(feature = "scripting")]
+mod engine;
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::alert::Severity;
+use crate::events::PostureEvent;
+use crate::posture::{Check, Posture, Proximity};
+
+pub trait PostureScript {
+    // What it makes of a check
+    fn check(&mut self, input: &Input) -> Judgement;
+    // Told of every event a check caused, in order
+    fn transition(&mut self, event: &PostureEvent);
+}
+
+pub fn load(path: &Path) -> Result<Box<dyn PostureScript>, String> {
+    #[cfg(feature = "scripting")]
+    {
+        Ok(Box::new(engine::RhaiScript::load(path)?))
+    }
+    #[cfg(not(feature = "scripting"))]
+    {
+        Err(format!(
+            "Running the script {} needs neckcheck built with the `scripting` feature",
+            path.display()
+        ))
+    }
+}
+
+// A measurement as a script sees it; whichever the input doesn't measure is ()
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    width: Option<u32>,
+    height: Option<u32>,
+    distance: Option<f32>,
+}
+
+impl From<Proximity> for Measurement {
+    fn from(proximity: Proximity) -> Measurement {
+        match proximity {
+            Proximity::FaceSize { width, height } => Measurement {
+                width: Some(width),
+                height: Some(height),
+                distance: None,
+            },
+            Proximity::Distance(distance) => Measurement {
+                distance: Some(distance),
+                ..Measurement::default()
+            },
+        }
+    }
+}
+
+// What check(input) is given
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Input {
+    current: Measurement,
+    smoothed: Measurement,
+    limit: Measurement,
+    // what the rule made of it
+    too_close: bool,
+    tilt_down: bool,
+    low_position: bool,
+    slouching: bool,
+    // the posture before this check: good, too_close or away
+    state: &'static str,
+    // where the middle of the face is, from 0 at the left and top of the frame to 1; () without a
+    // frame
+    face_x: Option<f32>,
+    face_y: Option<f32>,
+    // of the local time
+    hour: u32,
+}
+
+impl Input {
+    pub fn new(check: &Check, face: Option<(f32, f32)>, hour: u32) -> Input {
+        Input {
+            current: check.current.into(),
+            smoothed: check.smoothed.into(),
+            limit: check.limit.into(),
+            too_close: check.too_close,
+            tilt_down: check.conditions.tilt_down,
+            low_position: check.conditions.low_position,
+            slouching: check.conditions.slouching,
+            state: match check.state {
+                Posture::Good => "good",
+                Posture::TooClose => "too_close",
+                Posture::Away => "away",
+            },
+            face_x: face.map(|(x, _)| x),
+            face_y: face.map(|(_, y)| y),
+            hour,
+        }
+    }
+}
+
+// What a script made of a check; None leaves it to the rule, and the alert its usual severity
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(from = "Answer")]
+pub struct Judgement {
+    pub too_close: Option<bool>,
+    pub severity: Option<Severity>,
+}
+
+// What check(input) may return: nothing, whether the check is too close, or a map with either or
+// both of too_close and severity
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Answer {
+    Nothing(()),
+    TooClose(bool),
+    Judged {
+        #[serde(default)]
+        too_close: Option<bool>,
+        #[serde(default)]
+        severity: Option<Severity>,
+    },
+}
+
+impl From<Answer> for Judgement {
+    fn from(answer: Answer) -> Judgement {
+        match answer {
+            Answer::Nothing(()) => Judgement::default(),
+            Answer::TooClose(too_close) => Judgement {
+                too_close: Some(too_close),
+                severity: None,
+            },
+            Answer::Judged {
+                too_close,
+                severity,
+            } => Judgement {
+                too_close,
+                severity,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::posture::Conditions;
+
+    #[test]
+    fn scripts_are_shown_the_check_and_may_answer_in_several_ways() {
+        let check = Check {
+            current: Proximity::FaceSize {
+                width: 130,
+                height: 140,
+            },
+            smoothed: Proximity::FaceSize {
+                width: 125,
+                height: 135,
+            },
+            limit: Proximity::FaceSize {
+                width: 120,
+                height: 130,
+            },
+            too_close: true,
+            conditions: Conditions {
+                slouching: true,
+                ..Conditions::default()
+            },
+            state: Posture::Good,
+        };
+        let input = serde_json::to_value(Input::new(&check, Some((0.5, 0.25)), 14)).unwrap();
+        assert_eq!(input["smoothed"]["width"], 125);
+        assert!(input["limit"]["distance"].is_null());
+        assert_eq!(input["too_close"], true);
+        assert_eq!(input["slouching"], true);
+        assert_eq!(input["state"], "good");
+        assert_eq!(input["face_y"], 0.25);
+        assert_eq!(input["hour"], 14);
+
+        let answer = |json| serde_json::from_str::<Judgement>(json).unwrap();
+        assert_eq!(answer("null"), Judgement::default());
+        assert_eq!(answer("false").too_close, Some(false));
+        assert_eq!(
+            answer(r#"{"severity": "critical"}"#),
+            Judgement {
+                too_close: None,
+                severity: Some(Severity::Critical)
+            }
+        );
+        assert_eq!(
+            answer(r#"{"too_close": true, "severity": "info"}"#),
+            Judgement {
+                too_close: Some(true),
+                severity: Some(Severity::Info)
+            }
+        );
+        assert!(serde_json::from_str::<Judgement>(r#""too_close""#).is_err());
+    }
+}
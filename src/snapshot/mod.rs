@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use image::{imageops, DynamicImage, RgbImage};
+use imageproc::rect::Rect;
+
+use crate::privacy::ImageAccess;
+
+const FILE_PREFIX: &str = "violation-";
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotPrivacy {
+    /// store the whole frame with every detected face blurred beyond recognition
+    Blur,
+    /// store only the grayscale crop of the face that the detector saw
+    Crop,
+}
+
+// Saves a snapshot of the frame that started a bad posture episode so that it can be reviewed
+// later, and deletes snapshots once they are older than the retention period.
+pub struct SnapshotRecorder {
+    dir: PathBuf,
+    privacy: SnapshotPrivacy,
+    retention: Duration,
+}
+
+impl SnapshotRecorder {
+    pub fn new(
+        dir: PathBuf,
+        privacy: SnapshotPrivacy,
+        retention_days: u64,
+        _access: ImageAccess,
+    ) -> SnapshotRecorder {
+        if let Err(e) = fs::create_dir_all(&dir) {
+            println!("Failed to create snapshot directory {}: {}", dir.display(), e);
+        }
+        let recorder = SnapshotRecorder {
+            dir,
+            privacy,
+            retention: Duration::from_secs(retention_days * 24 * 60 * 60),
+        };
+        recorder.cleanup();
+        recorder
+    }
+
+    pub fn save(&self, frame: &RgbImage, faces: &[Rect]) {
+        let image = match self.privacy {
+            SnapshotPrivacy::Blur => DynamicImage::ImageRgb8(blur_faces(frame, faces)),
+            SnapshotPrivacy::Crop => match faces.first() {
+                Some(face) => DynamicImage::ImageLuma8(crop_face(frame, face)),
+                None => return,
+            },
+        };
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = self.dir.join(format!("{}{}.png", FILE_PREFIX, millis));
+        match image.save(&path) {
+            Ok(_) => println!("Saved snapshot to {}", path.display()),
+            Err(e) => println!("Failed to save snapshot to {}: {}", path.display(), e),
+        }
+        self.cleanup();
+    }
+
+    // Removes snapshots older than the retention period. Only files that look like our own
+    // snapshots are touched, in case the directory is shared with something else.
+    pub fn cleanup(&self) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let now = SystemTime::now();
+        for entry in entries.flatten() {
+            let is_snapshot = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(FILE_PREFIX));
+            let age = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok());
+            if is_snapshot && age.is_some_and(|age| age > self.retention) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+// Clamps the face box to the frame, as detections can extend past the edges.
+fn clamp_to_frame(frame: &RgbImage, face: &Rect) -> Option<(u32, u32, u32, u32)> {
+    let left = face.left().max(0) as u32;
+    let top = face.top().max(0) as u32;
+    let right = (face.right().max(0) as u32 + 1).min(frame.width());
+    let bottom = (face.bottom().max(0) as u32 + 1).min(frame.height());
+    if left >= right || top >= bottom {
+        return None;
+    }
+    Some((left, top, right - left, bottom - top))
+}
+
+pub fn blur_faces(frame: &RgbImage, faces: &[Rect]) -> RgbImage {
+    let mut frame = frame.clone();
+    for face in faces {
+        if let Some((x, y, width, height)) = clamp_to_frame(&frame, face) {
+            let region = imageops::crop_imm(&frame, x, y, width, height).to_image();
+            // a sigma relative to the face size makes the result equally unrecognisable at any
+            // distance from the camera
+            let sigma = width.max(height) as f32 / 6.0;
+            imageops::replace(&mut frame, &imageops::blur(&region, sigma), x as i64, y as i64);
+        }
+    }
+    frame
+}
+
+fn crop_face(frame: &RgbImage, face: &Rect) -> image::GrayImage {
+    match clamp_to_frame(frame, face) {
+        Some((x, y, width, height)) => {
+            imageops::grayscale(&imageops::crop_imm(frame, x, y, width, height).to_image())
+        }
+        None => image::GrayImage::new(0, 0),
+    }
+}
@@ -0,0 +1,134 @@
+use std::ops::{Deref, DerefMut};
+
+use image::{GrayImage, RgbImage};
+use zeroize::Zeroize;
+
+// A captured frame, kept in the format the camera delivered it in for as long as possible. Most
+// webcams send YUYV, whose Y samples already are the grayscale image the detector needs, so the
+// RGB image is only decoded when something, e.g. a snapshot or the virtual camera, asks for it.
+pub struct Frame {
+    width: u32,
+    height: u32,
+    pixels: Pixels,
+}
+
+enum Pixels {
+    Rgb(RgbImage),
+    // Y0 U Y1 V for every two pixels
+    Yuyv(Vec<u8>),
+}
+
+impl Frame {
+    // None if `data` is not the size of a `width` x `height` YUYV frame
+    pub fn from_yuyv(width: u32, height: u32, data: Vec<u8>) -> Option<Frame> {
+        if !width.is_multiple_of(2) || data.len() != width as usize * height as usize * 2 {
+            return None;
+        }
+        Some(Frame {
+            width,
+            height,
+            pixels: Pixels::Yuyv(data),
+        })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // The grayscale image, if it comes for free with the camera's format
+    pub fn luma(&self) -> Option<GrayImage> {
+        match &self.pixels {
+            Pixels::Rgb(_) => None,
+            Pixels::Yuyv(data) => GrayImage::from_raw(
+                self.width,
+                self.height,
+                data.iter().step_by(2).copied().collect(),
+            ),
+        }
+    }
+
+    // The RGB image, decoding it the first time it is needed
+    pub fn rgb(&mut self) -> &mut RgbImage {
+        if let Pixels::Yuyv(data) = &mut self.pixels {
+            let rgb = RgbImage::from_raw(self.width, self.height, yuyv_to_rgb(data))
+                .expect("Three bytes per pixel");
+            // zeroed whatever the privacy mode, as scrubbing the frame later only reaches the RGB
+            data.zeroize();
+            self.pixels = Pixels::Rgb(rgb);
+        }
+        match &mut self.pixels {
+            Pixels::Rgb(rgb) => rgb,
+            Pixels::Yuyv(_) => unreachable!(),
+        }
+    }
+}
+
+impl From<RgbImage> for Frame {
+    fn from(rgb: RgbImage) -> Frame {
+        Frame {
+            width: rgb.width(),
+            height: rgb.height(),
+            pixels: Pixels::Rgb(rgb),
+        }
+    }
+}
+
+// The raw bytes in whichever format, e.g. to scrub them
+impl Deref for Frame {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.pixels {
+            Pixels::Rgb(rgb) => rgb,
+            Pixels::Yuyv(data) => data,
+        }
+    }
+}
+
+impl DerefMut for Frame {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &mut self.pixels {
+            Pixels::Rgb(rgb) => rgb,
+            Pixels::Yuyv(data) => data,
+        }
+    }
+}
+
+// BT.601 limited range, as webcams send it
+fn yuyv_to_rgb(data: &[u8]) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(data.len() / 2 * 3);
+    for chunk in data.chunks_exact(4) {
+        let (u, v) = (chunk[1] as f32 - 128.0, chunk[3] as f32 - 128.0);
+        for y in [chunk[0], chunk[2]] {
+            let y = 1.164 * (y as f32 - 16.0);
+            rgb.extend([
+                (y + 1.596 * v).round().clamp(0.0, 255.0) as u8,
+                (y - 0.392 * u - 0.813 * v).round().clamp(0.0, 255.0) as u8,
+                (y + 2.017 * u).round().clamp(0.0, 255.0) as u8,
+            ]);
+        }
+    }
+    rgb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yuyv_frames_are_only_decoded_when_needed() {
+        // a white and a black pixel, then two mid gray ones
+        let data = vec![235, 128, 16, 128, 126, 128, 126, 128];
+        assert!(Frame::from_yuyv(2, 2, data[..6].to_vec()).is_none());
+        let mut frame = Frame::from_yuyv(2, 2, data).unwrap();
+        assert_eq!(frame.luma().unwrap().as_raw(), &vec![235, 16, 126, 126]);
+        assert_eq!(frame.len(), 8);
+
+        let rgb = frame.rgb();
+        assert_eq!(rgb.get_pixel(0, 0).0, [255, 255, 255]);
+        assert_eq!(rgb.get_pixel(1, 0).0, [0, 0, 0]);
+        assert_eq!(rgb.get_pixel(0, 1).0, [128, 128, 128]);
+        assert!(frame.luma().is_none());
+        assert_eq!(frame.len(), 12);
+    }
+}
@@ -0,0 +1,59 @@
+mod frame;
+mod network;
+#[cfg(feature = "realsense")]
+mod realsense;
+mod webcam;
+
+pub use frame::Frame;
+pub use network::NetworkSource;
+#[cfg(feature = "realsense")]
+pub use realsense::RealSense;
+pub use webcam::{WebCam, WebCamMode};
+
+use std::time::Instant;
+
+use imageproc::rect::Rect;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum CaptureError {
+    #[error("Failed to grab a frame: {0}")]
+    FrameGrab(String),
+    #[error("Failed to open camera stream: {0}")]
+    StreamOpen(String),
+    #[error("Failed to close camera stream {0}")]
+    StreamClose(String),
+    #[error("Failed to decode image: {0}")]
+    FrameDecode(String),
+    #[error("Failed to read the sensor: {0}")]
+    SensorRead(String),
+}
+
+// Anything that can produce frames for the detector: a local webcam, a network camera, ...
+pub trait FrameSource {
+    fn capture(&mut self) -> Result<Frame, CaptureError>;
+
+    // What the camera calls itself, to tell cameras apart, e.g. "Integrated Camera"
+    fn name(&self) -> String;
+
+    // The distance in centimetres to whatever is in `region` of the last captured frame, for
+    // sources that have a depth sensor
+    fn distance_at(&mut self, _region: Rect) -> Option<f32> {
+        None
+    }
+
+    // When the camera was switched on and off since this was last asked, for sources where that
+    // lights up an LED on the user's desk
+    fn take_activity(&mut self) -> Vec<CameraActivity> {
+        Vec::new()
+    }
+
+    // Switches the camera off until the next capture, e.g. when another camera took over
+    fn release(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraActivity {
+    Opened(Instant),
+    Closed(Instant),
+}
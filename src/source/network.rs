@@ -0,0 +1,307 @@
+use std::io::Read;
+#[cfg(feature = "ffmpeg")]
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use image::RgbImage;
+use zeroize::Zeroize;
+
+use super::{CaptureError, Frame, FrameSource};
+
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+// give up on a stream that produces this much data without a complete JPEG in it
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+// a stream that sends nothing for this long is taken for dead
+const FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Splits a byte stream of concatenated JPEGs (MJPEG over HTTP, ffmpeg image2pipe output) into
+// individual frames by scanning for the start/end of image markers.
+struct JpegStream<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> JpegStream<R> {
+    fn new(reader: R) -> JpegStream<R> {
+        JpegStream {
+            reader,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn next_frame(&mut self) -> Result<Vec<u8>, CaptureError> {
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+        loop {
+            if let Some(start) = find_marker(&self.buffer, 0, [0xFF, 0xD8]) {
+                if let Some(end) = find_marker(&self.buffer, start + 2, [0xFF, 0xD9]) {
+                    let frame = self.buffer[start..end + 2].to_vec();
+                    self.buffer.drain(..end + 2);
+                    return Ok(frame);
+                }
+                // drop anything before the start marker (multipart boundaries and headers)
+                self.buffer.drain(..start);
+            }
+            if self.buffer.len() > MAX_FRAME_SIZE {
+                return Err(CaptureError::FrameDecode(
+                    "No JPEG frame found in stream".to_string(),
+                ));
+            }
+            let read = self
+                .reader
+                .read(&mut chunk)
+                .map_err(|e| CaptureError::FrameGrab(e.to_string()))?;
+            if read == 0 {
+                return Err(CaptureError::FrameGrab("Stream ended".to_string()));
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+// What the reading thread has for the next capture
+#[derive(Default)]
+struct Latest {
+    frame: Option<Vec<u8>>,
+    error: Option<CaptureError>,
+    closed: bool,
+}
+
+// Reads a stream's frames as fast as they come, on a thread of its own, keeping only the newest.
+// Cameras send many frames between two checks, and reading them one per check would fall further
+// and further behind.
+struct LatestFrame {
+    shared: Arc<(Mutex<Latest>, Condvar)>,
+}
+
+impl LatestFrame {
+    fn spawn<R: Read + Send + 'static>(reader: R) -> LatestFrame {
+        let shared = Arc::new((Mutex::new(Latest::default()), Condvar::new()));
+        let reading = shared.clone();
+        thread::spawn(move || {
+            let mut stream = JpegStream::new(reader);
+            loop {
+                let frame = stream.next_frame();
+                let (latest, arrived) = &*reading;
+                let mut latest = latest.lock().unwrap();
+                if latest.closed {
+                    return;
+                }
+                let failed = frame.is_err();
+                match frame {
+                    Ok(frame) => {
+                        if let Some(mut skipped) = latest.frame.replace(frame) {
+                            skipped.zeroize();
+                        }
+                    }
+                    Err(e) => latest.error = Some(e),
+                }
+                arrived.notify_all();
+                if failed {
+                    return;
+                }
+            }
+        });
+        LatestFrame { shared }
+    }
+
+    // The newest frame that hasn't been taken yet, waiting for one if need be
+    fn take(&self) -> Result<Vec<u8>, CaptureError> {
+        let (latest, arrived) = &*self.shared;
+        let mut latest = latest.lock().unwrap();
+        loop {
+            if let Some(frame) = latest.frame.take() {
+                return Ok(frame);
+            }
+            if let Some(e) = &latest.error {
+                return Err(e.clone());
+            }
+            let (next, waited) = arrived.wait_timeout(latest, FRAME_TIMEOUT).unwrap();
+            latest = next;
+            if waited.timed_out() && latest.frame.is_none() {
+                return Err(CaptureError::FrameGrab(format!(
+                    "No frame for {} seconds",
+                    FRAME_TIMEOUT.as_secs()
+                )));
+            }
+        }
+    }
+}
+
+impl Drop for LatestFrame {
+    // the thread stops at the next frame, or when the stream is closed
+    fn drop(&mut self) {
+        let mut latest = self.shared.0.lock().unwrap();
+        latest.closed = true;
+        if let Some(frame) = &mut latest.frame {
+            frame.zeroize();
+        }
+    }
+}
+
+fn find_marker(buffer: &[u8], from: usize, marker: [u8; 2]) -> Option<usize> {
+    if from >= buffer.len() {
+        return None;
+    }
+    buffer[from..]
+        .windows(2)
+        .position(|w| w == marker)
+        .map(|i| i + from)
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Result<RgbImage, CaptureError> {
+    image::load_from_memory(bytes)
+        .map(|image| image.to_rgb8())
+        .map_err(|e| CaptureError::FrameDecode(e.to_string()))
+}
+
+enum Connection {
+    // a multipart/x-mixed-replace MJPEG stream that is kept open between captures
+    Mjpeg(LatestFrame),
+    // rtsp:// and friends, decoded by an ffmpeg child process writing JPEGs to stdout
+    #[cfg(feature = "ffmpeg")]
+    Ffmpeg(Child, LatestFrame),
+}
+
+// A phone or network camera, either serving MJPEG over HTTP or (with the `ffmpeg` feature) RTSP.
+// Plain HTTP endpoints that return a single JPEG per request (e.g. `/shot.jpg`) are also
+// supported and are simply requested again for every capture.
+pub struct NetworkSource {
+    url: String,
+    connection: Option<Connection>,
+}
+
+impl NetworkSource {
+    pub fn new(url: &str) -> NetworkSource {
+        NetworkSource {
+            url: url.to_string(),
+            connection: None,
+        }
+    }
+
+    fn is_http(&self) -> bool {
+        self.url.starts_with("http://") || self.url.starts_with("https://")
+    }
+
+    fn connect(&mut self) -> Result<Option<RgbImage>, CaptureError> {
+        if self.is_http() {
+            return self.connect_http();
+        }
+        #[cfg(feature = "ffmpeg")]
+        {
+            self.connection = Some(self.spawn_ffmpeg()?);
+            Ok(None)
+        }
+        #[cfg(not(feature = "ffmpeg"))]
+        {
+            Err(CaptureError::StreamOpen(format!(
+                "{} is not an http(s) url; rebuild with the `ffmpeg` feature for RTSP support",
+                self.url
+            )))
+        }
+    }
+
+    // Returns the frame directly if the endpoint served a single image rather than a stream
+    fn connect_http(&mut self) -> Result<Option<RgbImage>, CaptureError> {
+        let response = ureq::get(&self.url)
+            .call()
+            .map_err(|e| CaptureError::StreamOpen(e.to_string()))?;
+        let content_type = response.content_type().to_string();
+        if content_type.starts_with("multipart/") {
+            println!("Opened MJPEG stream {}", self.url);
+            self.connection = Some(Connection::Mjpeg(LatestFrame::spawn(
+                response.into_reader(),
+            )));
+            return Ok(None);
+        }
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .take(MAX_FRAME_SIZE as u64)
+            .read_to_end(&mut bytes)
+            .map_err(|e| CaptureError::FrameGrab(e.to_string()))?;
+        decode_jpeg(&bytes).map(Some)
+    }
+
+    #[cfg(feature = "ffmpeg")]
+    fn spawn_ffmpeg(&self) -> Result<Connection, CaptureError> {
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-fflags", "nobuffer"])
+            .args(["-rtsp_transport", "tcp"])
+            .args(["-i", &self.url])
+            .args(["-f", "image2pipe", "-c:v", "mjpeg", "-q:v", "5", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| CaptureError::StreamOpen(format!("Failed to run ffmpeg: {}", e)))?;
+        let stdout = child.stdout.take().unwrap();
+        println!("Opened {} via ffmpeg", self.url);
+        Ok(Connection::Ffmpeg(child, LatestFrame::spawn(stdout)))
+    }
+
+    fn close(&mut self) {
+        #[cfg(feature = "ffmpeg")]
+        if let Some(Connection::Ffmpeg(mut child, _)) = self.connection.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        self.connection = None;
+    }
+}
+
+impl FrameSource for NetworkSource {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    fn capture(&mut self) -> Result<Frame, CaptureError> {
+        if self.connection.is_none() {
+            if let Some(image) = self.connect()? {
+                return Ok(image.into());
+            }
+        }
+        let frame = match self.connection.as_mut().unwrap() {
+            Connection::Mjpeg(stream) => stream.take(),
+            #[cfg(feature = "ffmpeg")]
+            Connection::Ffmpeg(_, stream) => stream.take(),
+        };
+        match frame {
+            Ok(mut bytes) => {
+                let image = decode_jpeg(&bytes);
+                bytes.zeroize();
+                image.map(Frame::from)
+            }
+            Err(e) => {
+                // drop the broken connection so that the next capture reconnects
+                self.close();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for NetworkSource {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn captures_get_the_newest_frame_of_a_stream() {
+        let parts: Vec<u8> = [1, 2, 3]
+            .into_iter()
+            .flat_map(|n| [b'-', b'-', 0xFF, 0xD8, n, 0xFF, 0xD9])
+            .collect();
+        let stream = LatestFrame::spawn(Cursor::new(parts));
+        // by then the thread has read all three and hit the end
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(stream.take().unwrap(), vec![0xFF, 0xD8, 3, 0xFF, 0xD9]);
+        assert!(matches!(stream.take(), Err(CaptureError::FrameGrab(_))));
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use image::RgbImage;
+use imageproc::rect::Rect;
+
+use realsense_rust::config::Config;
+use realsense_rust::context::Context;
+use realsense_rust::frame::{ColorFrame, DepthFrame, PixelKind};
+use realsense_rust::kind::{Rs2Format, Rs2StreamKind};
+use realsense_rust::pipeline::{ActivePipeline, InactivePipeline};
+
+use super::{CaptureError, Frame, FrameSource};
+
+const WIDTH: usize = 640;
+const HEIGHT: usize = 480;
+const FRAME_RATE: usize = 30;
+const FRAME_TIMEOUT: Duration = Duration::from_secs(2);
+// only the middle of the face box is sampled, so that the background around the head is ignored
+const SAMPLED_FRACTION: f32 = 0.5;
+
+// An Intel RealSense depth camera. The colour stream feeds the detector as usual, and the depth
+// stream gives the actual distance to the detected face.
+pub struct RealSense {
+    pipeline: ActivePipeline,
+    // the depth frame captured together with the last colour frame
+    depth: Option<DepthFrame>,
+}
+
+impl RealSense {
+    pub fn new() -> RealSense {
+        let pipeline = match RealSense::start() {
+            Ok(pipeline) => pipeline,
+            Err(e) => panic!("Failed to open the RealSense camera: {}", e),
+        };
+        RealSense {
+            pipeline,
+            depth: None,
+        }
+    }
+
+    fn start() -> Result<ActivePipeline, String> {
+        let context = Context::new().map_err(|e| e.to_string())?;
+        let pipeline = InactivePipeline::try_from(&context).map_err(|e| e.to_string())?;
+        let mut config = Config::new();
+        // both streams at the same resolution, so that a face box in the colour frame maps onto
+        // (roughly) the same region of the depth frame
+        config
+            .enable_stream(
+                Rs2StreamKind::Depth,
+                None,
+                WIDTH,
+                HEIGHT,
+                Rs2Format::Z16,
+                FRAME_RATE,
+            )
+            .map_err(|e| e.to_string())?
+            .enable_stream(
+                Rs2StreamKind::Color,
+                None,
+                WIDTH,
+                HEIGHT,
+                Rs2Format::Rgb8,
+                FRAME_RATE,
+            )
+            .map_err(|e| e.to_string())?;
+        pipeline.start(Some(config)).map_err(|e| e.to_string())
+    }
+}
+
+impl FrameSource for RealSense {
+    fn name(&self) -> String {
+        "Intel RealSense".to_string()
+    }
+
+    fn capture(&mut self) -> Result<Frame, CaptureError> {
+        let frames = self
+            .pipeline
+            .wait(Some(FRAME_TIMEOUT))
+            .map_err(|e| CaptureError::FrameGrab(e.to_string()))?;
+        let color = frames
+            .frames_of_type::<ColorFrame>()
+            .into_iter()
+            .next()
+            .ok_or_else(|| CaptureError::FrameGrab("No colour frame".to_string()))?;
+        self.depth = frames.frames_of_type::<DepthFrame>().into_iter().next();
+
+        let mut image = RgbImage::new(color.width() as u32, color.height() as u32);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            if let Some(PixelKind::Rgb8 { r, g, b }) = color.get(x as usize, y as usize) {
+                *pixel = image::Rgb([*r, *g, *b]);
+            }
+        }
+        Ok(image.into())
+    }
+
+    // The median depth over the middle of the region; pixels without a depth reading are skipped
+    fn distance_at(&mut self, region: Rect) -> Option<f32> {
+        let depth = self.depth.as_ref()?;
+        let margin_x = region.width() as f32 * (1.0 - SAMPLED_FRACTION) / 2.0;
+        let margin_y = region.height() as f32 * (1.0 - SAMPLED_FRACTION) / 2.0;
+        let left = (region.left() as f32 + margin_x).max(0.0) as usize;
+        let top = (region.top() as f32 + margin_y).max(0.0) as usize;
+        let right = ((region.right() as f32 - margin_x) as usize).min(depth.width() - 1);
+        let bottom = ((region.bottom() as f32 - margin_y) as usize).min(depth.height() - 1);
+        let mut distances: Vec<f32> = (top..=bottom)
+            .flat_map(|y| (left..=right).map(move |x| (x, y)))
+            .filter_map(|(x, y)| depth.distance(x, y).ok())
+            .filter(|metres| *metres > 0.0)
+            .collect();
+        if distances.is_empty() {
+            return None;
+        }
+        distances.sort_unstable_by(f32::total_cmp);
+        Some(distances[distances.len() / 2] * 100.0)
+    }
+}
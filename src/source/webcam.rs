@@ -0,0 +1,136 @@
+use std::time::Instant;
+
+use image::RgbImage;
+
+use nokhwa::pixel_format::RgbFormat;
+use nokhwa::utils::{CameraIndex, FrameFormat, RequestedFormat, RequestedFormatType};
+use nokhwa::{Buffer, Camera};
+
+use super::{CameraActivity, CaptureError, Frame, FrameSource};
+
+pub enum WebCamMode {
+    Continuous,
+    // the camera is only opened for each capture, and its light is off in between. The first
+    // frames after opening are often dark while the exposure settles, so `burst` frames are
+    // grabbed and the last one kept.
+    Discrete { burst: u32 },
+}
+
+pub struct WebCam {
+    camera: Camera,
+    mode: WebCamMode,
+    activity: Vec<CameraActivity>,
+}
+
+impl WebCam {
+    pub fn new(index: u32, mode: WebCamMode) -> WebCam {
+        let index = CameraIndex::Index(index);
+        // request the absolute highest resolution CameraFormat that can be decoded to RGB.
+        let requested =
+            RequestedFormat::new::<RgbFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
+        // make the camera
+        let camera = match Camera::new(index.clone(), requested) {
+            Ok(c) => c,
+            Err(e) => panic!("Failed to open camera {}: {}", index.clone(), e),
+        };
+        WebCam {
+            camera,
+            mode,
+            activity: Vec::new(),
+        }
+    }
+
+    fn open(&mut self) -> Result<(), CaptureError> {
+        self.camera
+            .open_stream()
+            .map_err(|e| CaptureError::StreamOpen(e.to_string()))?;
+        self.activity.push(CameraActivity::Opened(Instant::now()));
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), CaptureError> {
+        self.camera
+            .stop_stream()
+            .map_err(|e| CaptureError::StreamClose(e.to_string()))?;
+        self.activity.push(CameraActivity::Closed(Instant::now()));
+        Ok(())
+    }
+}
+
+impl WebCam {
+    // Reads the next frame of the open stream as the camera sent it
+    fn read(&mut self) -> Result<Buffer, CaptureError> {
+        self.camera
+            .frame()
+            .map_err(|e| CaptureError::FrameGrab(e.to_string()))
+    }
+
+    // Grabs a frame from the open stream
+    fn grab(&mut self) -> Result<Frame, CaptureError> {
+        let frame = self.read()?;
+
+        // YUYV is kept as it is, since the detector only needs its Y samples
+        if frame.source_frame_format() == FrameFormat::YUYV {
+            let resolution = frame.resolution();
+            if let Some(frame) = Frame::from_yuyv(
+                resolution.width(),
+                resolution.height(),
+                frame.buffer().to_vec(),
+            ) {
+                return Ok(frame);
+            }
+        }
+        // decode into an ImageBuffer
+        let decoded = frame
+            .decode_image::<RgbFormat>()
+            .map_err(|e| CaptureError::FrameDecode(e.to_string()))?;
+        Ok(
+            RgbImage::from_raw(decoded.width(), decoded.height(), decoded.into_raw())
+                .unwrap()
+                .into(),
+        )
+    }
+
+    // Opens the camera, grabs a burst of frames and closes it again
+    fn grab_burst(&mut self, burst: u32) -> Result<Frame, CaptureError> {
+        self.open()?;
+        // the frames before the last are thrown away as the camera sent them, without decoding
+        for _ in 1..burst {
+            let _ = self.read();
+        }
+        let result = self.grab();
+        // the camera is closed even if grabbing failed
+        let closed = self.close();
+        let frame = result?;
+        closed?;
+        Ok(frame)
+    }
+}
+
+impl FrameSource for WebCam {
+    fn name(&self) -> String {
+        self.camera.info().human_name()
+    }
+
+    // Captures a single frame from the camera
+    fn capture(&mut self) -> Result<Frame, CaptureError> {
+        if let WebCamMode::Discrete { burst } = self.mode {
+            return self.grab_burst(burst.max(1));
+        }
+        if !self.camera.is_stream_open() {
+            println!("Opening Camera Stream");
+            let _ = self.open();
+        }
+        self.grab()
+    }
+
+    fn take_activity(&mut self) -> Vec<CameraActivity> {
+        std::mem::take(&mut self.activity)
+    }
+
+    fn release(&mut self) {
+        if self.camera.is_stream_open() {
+            let _ = self.close();
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{DateTime, Duration, Local, NaiveDate};
+
+use super::{merge_short_breaks, segments, StatsEntry, StatsRecord};
+use crate::alert::AlertKind;
+use crate::i18n::tr;
+
+// How a day went
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub at_desk: Duration,
+    pub posture_alerts: usize,
+    // the longest time seated without a posture alert
+    pub best_streak: Duration,
+}
+
+impl DailySummary {
+    pub fn message(&self) -> String {
+        tr!(
+            "summary-today",
+            at_desk = super::format_duration(self.at_desk),
+            alerts = self.posture_alerts,
+            best_streak = super::format_duration(self.best_streak)
+        )
+    }
+}
+
+pub fn daily_summary(
+    records: &[StatsRecord],
+    date: NaiveDate,
+    min_break: Duration,
+) -> DailySummary {
+    let on_day = |time: &DateTime<Local>| time.date_naive() == date;
+    let alerts: Vec<DateTime<Local>> = records
+        .iter()
+        .filter(|record| on_day(&record.time))
+        .filter(|record| {
+            record.entry
+                == StatsEntry::Event {
+                    event: AlertKind::TooClose,
+                }
+        })
+        .map(|record| record.time)
+        .collect();
+    let seated: Vec<_> = merge_short_breaks(&segments(records), min_break)
+        .into_iter()
+        .filter(|segment| segment.seated && on_day(&segment.start))
+        .collect();
+    let at_desk = seated.iter().fold(Duration::zero(), |total, segment| {
+        total + segment.duration()
+    });
+    // each alert ends a streak and starts the next
+    let best_streak = seated
+        .iter()
+        .flat_map(|segment| {
+            let mut bounds = vec![segment.start];
+            bounds.extend(
+                alerts
+                    .iter()
+                    .filter(|&&alert| segment.start < alert && alert < segment.end),
+            );
+            bounds.push(segment.end);
+            bounds
+                .windows(2)
+                .map(|pair| pair[1] - pair[0])
+                .collect::<Vec<_>>()
+        })
+        .max()
+        .unwrap_or_else(Duration::zero);
+    DailySummary {
+        date,
+        at_desk,
+        posture_alerts: alerts.len(),
+        best_streak,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn record(minutes: i64, entry: StatsEntry) -> StatsRecord {
+        StatsRecord {
+            time: Local.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap() + Duration::minutes(minutes),
+            host: None,
+            profile: None,
+            session: None,
+            others_present: false,
+            entry,
+        }
+    }
+
+    fn event(minutes: i64, event: AlertKind) -> StatsRecord {
+        record(minutes, StatsEntry::Event { event })
+    }
+
+    #[test]
+    fn summarises_the_day() {
+        let records = vec![
+            record(0, StatsEntry::SessionStarted),
+            event(30, AlertKind::TooClose),
+            event(31, AlertKind::Recovered),
+            event(60, AlertKind::Away),
+            event(90, AlertKind::Returned),
+            event(100, AlertKind::TooClose),
+            record(200, StatsEntry::SessionEnded),
+        ];
+        let summary = daily_summary(
+            &records,
+            NaiveDate::from_ymd_opt(2024, 3, 4).unwrap(),
+            Duration::minutes(1),
+        );
+        assert_eq!(summary.at_desk, Duration::minutes(170));
+        assert_eq!(summary.posture_alerts, 2);
+        assert_eq!(summary.best_streak, Duration::minutes(100));
+        assert_eq!(
+            summary.message(),
+            "Today: 2h50m at desk, 2 posture alerts, best streak 1h40m"
+        );
+    }
+}
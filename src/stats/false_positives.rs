@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate};
+
+use super::{StatsEntry, StatsRecord};
+use crate::alert::is_posture_alert;
+
+// How many posture alerts there were in a week, and how many of them the user reported as wrong
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FalsePositives {
+    pub alerts: u32,
+    pub reported: u32,
+}
+
+impl FalsePositives {
+    // The share of the alerts reported as wrong, if there were any alerts
+    pub fn rate(&self) -> Option<f32> {
+        (self.alerts > 0).then(|| self.reported as f32 / self.alerts as f32)
+    }
+
+    pub fn add(&mut self, other: FalsePositives) {
+        self.alerts += other.alerts;
+        self.reported += other.reported;
+    }
+}
+
+// The false positives of each week (by its Monday), from `neckcheck report-fp`
+pub fn weekly_false_positives(records: &[StatsRecord]) -> BTreeMap<NaiveDate, FalsePositives> {
+    let mut weeks: BTreeMap<NaiveDate, FalsePositives> = BTreeMap::new();
+    for record in records {
+        let reported = match &record.entry {
+            StatsEntry::Event { event } if is_posture_alert(*event) => false,
+            StatsEntry::FalsePositive { .. } => true,
+            _ => continue,
+        };
+        let date = record.time.date_naive();
+        let monday = date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64);
+        let week = weeks.entry(monday).or_default();
+        if reported {
+            week.reported += 1;
+        } else {
+            week.alerts += 1;
+        }
+    }
+    weeks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::AlertKind;
+    use crate::posture::Proximity;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn false_positives_are_counted_per_week() {
+        let record = |day: u32, entry: StatsEntry| StatsRecord {
+            // Thursday 2 May 2024 onwards
+            time: Local.with_ymd_and_hms(2024, 5, day, 10, 0, 0).unwrap(),
+            host: None,
+            profile: None,
+            session: None,
+            others_present: false,
+            entry,
+        };
+        let alert = |event| StatsEntry::Event { event };
+        let reported = StatsEntry::FalsePositive {
+            alert: AlertKind::TooClose,
+            current: Some(Proximity::Distance(44.0)),
+            smoothed: Some(Proximity::Distance(44.5)),
+            limit: Some(Proximity::Distance(45.0)),
+            frame: None,
+        };
+        let records = [
+            record(2, alert(AlertKind::TooClose)),
+            record(2, reported.clone()),
+            record(3, alert(AlertKind::NeckAngle)),
+            record(3, alert(AlertKind::StandUp)),
+            record(6, alert(AlertKind::TooClose)),
+            record(7, alert(AlertKind::TooClose)),
+        ];
+        let weeks = weekly_false_positives(&records);
+        let first = NaiveDate::from_ymd_opt(2024, 4, 29).unwrap();
+        let second = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        assert_eq!(
+            weeks[&first],
+            FalsePositives {
+                alerts: 2,
+                reported: 1
+            }
+        );
+        assert_eq!(weeks[&first].rate(), Some(0.5));
+        assert_eq!(weeks[&second].rate(), Some(0.0));
+        assert_eq!(FalsePositives::default().rate(), None);
+    }
+}
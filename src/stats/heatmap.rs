@@ -0,0 +1,99 @@
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike, Weekday};
+
+use super::Segment;
+
+// Minutes of bad posture in each hour of each day of the week, summed over every week in the log
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    // indexed by the days from Monday, then the hour
+    minutes: [[f64; 24]; 7],
+}
+
+impl Heatmap {
+    pub fn minutes(&self, weekday: Weekday, hour: u32) -> f64 {
+        self.minutes[weekday.num_days_from_monday() as usize][hour as usize]
+    }
+
+    pub fn max(&self) -> f64 {
+        self.minutes.iter().flatten().copied().fold(0.0, f64::max)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max() == 0.0
+    }
+
+    // The `count` hours of the week with the most bad posture, worst first
+    pub fn worst(&self, count: usize) -> Vec<(Weekday, u32)> {
+        let mut hours: Vec<(Weekday, u32, f64)> = super::WEEKDAYS
+            .iter()
+            .flat_map(|weekday| (0..24).map(|hour| (*weekday, hour, self.minutes(*weekday, hour))))
+            .filter(|(_, _, minutes)| *minutes > 0.0)
+            .collect();
+        hours.sort_by(|a, b| b.2.total_cmp(&a.2));
+        hours
+            .into_iter()
+            .take(count)
+            .map(|(weekday, hour, _)| (weekday, hour))
+            .collect()
+    }
+}
+
+// Spreads each stretch of bad posture over the hours it covers. Local wall clock times are used so
+// that an hour is the same hour on every day, DST or not.
+pub fn heatmap(bad_posture: &[Segment]) -> Heatmap {
+    let mut minutes = [[0.0; 24]; 7];
+    for segment in bad_posture {
+        let end = segment.end.naive_local();
+        let mut time = segment.start.naive_local();
+        while time < end {
+            let next_hour = hour_start(time) + Duration::hours(1);
+            let until = next_hour.min(end);
+            minutes[time.weekday().num_days_from_monday() as usize][time.hour() as usize] +=
+                (until - time).num_seconds() as f64 / 60.0;
+            time = until;
+        }
+    }
+    Heatmap { minutes }
+}
+
+fn hour_start(time: NaiveDateTime) -> NaiveDateTime {
+    time.date().and_hms_opt(time.hour(), 0, 0).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Local, TimeZone};
+
+    use super::*;
+
+    fn at(minutes: i64) -> DateTime<Local> {
+        // a Monday
+        Local.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn bad_posture_is_split_over_the_hours_it_covers() {
+        let bad = [
+            Segment {
+                start: at(90),
+                end: at(135),
+                seated: true,
+            },
+            Segment {
+                start: at(24 * 60 + 10),
+                end: at(24 * 60 + 20),
+                seated: true,
+            },
+        ];
+        let heatmap = heatmap(&bad);
+        assert_eq!(heatmap.minutes(Weekday::Mon, 10), 30.0);
+        assert_eq!(heatmap.minutes(Weekday::Mon, 11), 15.0);
+        assert_eq!(heatmap.minutes(Weekday::Tue, 9), 10.0);
+        assert_eq!(heatmap.minutes(Weekday::Mon, 9), 0.0);
+        assert_eq!(heatmap.max(), 30.0);
+        assert_eq!(
+            heatmap.worst(5),
+            vec![(Weekday::Mon, 10), (Weekday::Mon, 11), (Weekday::Tue, 9)]
+        );
+    }
+}
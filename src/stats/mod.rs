@@ -0,0 +1,768 @@
+mod daily;
+mod false_positives;
+mod heatmap;
+mod segments;
+
+pub use daily::daily_summary;
+pub use false_positives::{weekly_false_positives, FalsePositives};
+pub use heatmap::{heatmap, Heatmap};
+pub use segments::{bad_posture, merge_short_breaks, segments, Segment};
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Timelike, Weekday};
+use console::{style, Color};
+use serde::{Deserialize, Serialize};
+
+use crate::alert::AlertKind;
+use crate::posture::Proximity;
+
+// One line of the stats log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StatsRecord {
+    pub time: DateTime<Local>,
+    // the machine it was recorded on; older records don't say, and come from this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    // what the user said the session was, e.g. "standing-desk"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    // the same for every record of one run of neckcheck
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    // more than one person was in view, so the posture may not have been the user's
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub others_present: bool,
+    #[serde(flatten)]
+    pub entry: StatsEntry,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StatsEntry {
+    // the user's reaction to an alert, and how far the limit has drifted from the calibration
+    // since (see the adaptive config)
+    AlertFeedback {
+        false_positive: bool,
+        drift: f32,
+    },
+    // the user reported the latest posture alert as wrong, with the measurements it fired on and
+    // the --debug-dump frame behind it, if one was saved
+    FalsePositive {
+        alert: AlertKind,
+        current: Option<Proximity>,
+        smoothed: Option<Proximity>,
+        limit: Option<Proximity>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        frame: Option<PathBuf>,
+    },
+    // blinks per minute over the last blink rate window
+    BlinkRate {
+        per_minute: f32,
+    },
+    // neckcheck started or stopped monitoring
+    SessionStarted,
+    SessionEnded,
+    // still monitoring, written every few minutes so that a session that crashed can be told
+    // apart from one that was running all along
+    Alive,
+    // something that happened, named like the alert for it
+    Event {
+        event: AlertKind,
+    },
+    // the camera was on for this long since the previous such entry
+    CameraOn {
+        seconds: f32,
+    },
+    // the wall clock jumped by this many seconds beyond the time that passed: set by hand, or
+    // across a suspend. Times before and after it don't add up.
+    ClockChanged {
+        seconds: i64,
+    },
+    // an exercise of the physiotherapy program was prompted, and the user said they did it
+    ExercisePrompted {
+        exercise: String,
+    },
+    ExerciseDone {
+        exercise: String,
+    },
+}
+
+// An append-only log of JSON lines, so that a crash loses at most the line being written and the
+// file can be read by anything. Every line is synced to disk before `record` returns, and the log is
+// only ever rewritten whole, by compaction, through a temporary file that replaces it atomically.
+pub struct StatsLog {
+    path: PathBuf,
+    // what new records are tagged with
+    profile: Option<String>,
+    session: Option<String>,
+    // which records are read
+    filter: StatsFilter,
+}
+
+// Narrows the records read down to those of one machine or profile, e.g. for a report of only the
+// standing desk sessions
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct StatsFilter {
+    /// Only the records of this machine
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// Only the records of sessions run with this --profile
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Leave out what happened while other people were in view
+    #[arg(long)]
+    pub alone: bool,
+}
+
+impl StatsFilter {
+    fn matches(&self, record: &StatsRecord) -> bool {
+        let host = record.host.as_deref().unwrap_or(this_host());
+        self.host.as_ref().is_none_or(|wanted| wanted == host)
+            && self
+                .profile
+                .as_ref()
+                .is_none_or(|wanted| record.profile.as_ref() == Some(wanted))
+            && !(self.alone && record.others_present)
+    }
+}
+
+impl StatsLog {
+    pub fn new(path: PathBuf) -> StatsLog {
+        StatsLog {
+            path,
+            profile: None,
+            session: None,
+            filter: StatsFilter::default(),
+        }
+    }
+
+    // Tags the records of this run of neckcheck with a new session ID, and the profile if any
+    pub fn with_session(self, profile: Option<String>) -> StatsLog {
+        let session = format!(
+            "{}-{}",
+            Local::now().format("%Y%m%dT%H%M%S"),
+            std::process::id()
+        );
+        StatsLog {
+            profile,
+            session: Some(session),
+            ..self
+        }
+    }
+
+    // Only reads the records that match
+    pub fn with_filter(self, filter: StatsFilter) -> StatsLog {
+        StatsLog { filter, ..self }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("neckcheck").join("stats.jsonl"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Stats are nice to have, so failing to record them is only reported
+    pub fn record(&self, entry: StatsEntry) {
+        self.record_seen(entry, false);
+    }
+
+    // Records something seen by the camera, marked if other people were in view at the time
+    pub fn record_seen(&self, entry: StatsEntry, others_present: bool) {
+        if let Err(e) = self.append(&StatsRecord {
+            time: Local::now(),
+            host: Some(this_host().to_string()),
+            profile: self.profile.clone(),
+            session: self.session.clone(),
+            others_present,
+            entry,
+        }) {
+            println!("Failed to record stats in {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn append(&self, record: &StatsRecord) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        // a line torn by a crash is ended, so that it doesn't take this one down with it
+        if file.metadata()?.len() > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                line.insert(0, '\n');
+            }
+        }
+        file.write_all(line.as_bytes())?;
+        file.sync_data()
+    }
+
+    // Rewrites the log without torn lines and with only the last of every run of `Alive` records,
+    // returning how many lines were dropped. Lines from a newer version are kept.
+    pub fn compact(&self) -> std::io::Result<usize> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let lines: Vec<&str> = contents.lines().collect();
+        let is_alive = |line: &str| {
+            serde_json::from_str::<StatsRecord>(line)
+                .is_ok_and(|record| record.entry == StatsEntry::Alive)
+        };
+        let kept: Vec<&str> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| serde_json::from_str::<serde_json::Value>(line).is_ok())
+            .filter(|(i, line)| {
+                !(is_alive(line) && lines.get(i + 1).is_some_and(|next| is_alive(next)))
+            })
+            .map(|(_, line)| *line)
+            .collect();
+        let dropped = lines.len() - kept.len();
+        if dropped == 0 {
+            return Ok(0);
+        }
+        self.replace(&kept)?;
+        Ok(dropped)
+    }
+
+    // Adds the records of another machine's log, e.g. a copy of the laptop's stats.jsonl, leaving
+    // out those already here. Records that don't say which machine they are from are taken to be
+    // from `host` if imported, and from this machine if already here. Returns how many records were
+    // added and how many were already here.
+    pub fn merge(&self, other: &Path, host: &str) -> std::io::Result<(usize, usize)> {
+        let here = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let imported = fs::read_to_string(other)?;
+        let mut records = tagged(&here, this_host());
+        let mut seen: HashSet<(String, String)> = records.iter().map(merge_key).collect();
+        let (mut added, mut duplicates) = (0, 0);
+        for record in tagged(&imported, host) {
+            if seen.insert(merge_key(&record)) {
+                records.push(record);
+                added += 1;
+            } else {
+                duplicates += 1;
+            }
+        }
+        if added > 0 {
+            // a stable sort keeps records of the same instant in the order they were written
+            records.sort_by_key(|record| {
+                record["time"]
+                    .as_str()
+                    .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            });
+            let lines: Vec<String> = records.iter().map(|record| record.to_string()).collect();
+            self.replace(&lines)?;
+        }
+        Ok((added, duplicates))
+    }
+
+    // Swaps the log for `lines` in a single step
+    fn replace<T: AsRef<str>>(&self, lines: &[T]) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = self.path.with_extension("jsonl.tmp");
+        let mut file = fs::File::create(&temporary)?;
+        for line in lines {
+            writeln!(file, "{}", line.as_ref())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        // the rename itself only survives a power cut once the directory is synced
+        #[cfg(unix)]
+        if let Some(dir) = self.path.parent() {
+            fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    // Every record that can still be read; a missing log has none
+    pub fn read(&self) -> std::io::Result<Vec<StatsRecord>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            // lines from a newer version (or a torn write) are skipped
+            if let Ok(record) = serde_json::from_str(&line?) {
+                if self.filter.matches(&record) {
+                    records.push(record);
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    // The drift learned so far, to carry on from
+    pub fn last_drift(&self) -> Option<f32> {
+        self.read()
+            .ok()?
+            .iter()
+            .rev()
+            .find_map(|record| match record.entry {
+                StatsEntry::AlertFeedback { drift, .. } => Some(drift),
+                _ => None,
+            })
+    }
+}
+
+// The name of this machine, to tell apart the records of several in a merged log
+pub fn this_host() -> &'static str {
+    static HOST: OnceLock<String> = OnceLock::new();
+    HOST.get_or_init(|| {
+        hostname::get()
+            .ok()
+            .and_then(|name| name.into_string().ok())
+            .unwrap_or_else(|| "localhost".to_string())
+    })
+}
+
+// The records of a log as JSON, so that those from a newer version survive a merge, each saying
+// which machine it is from
+fn tagged(contents: &str, host: &str) -> Vec<serde_json::Value> {
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|mut record| {
+            let fields = record.as_object_mut()?;
+            fields
+                .entry("host")
+                .or_insert_with(|| serde_json::Value::from(host));
+            Some(record)
+        })
+        .collect()
+}
+
+// A record is the same record if it was written at the same instant on the same machine
+fn merge_key(record: &serde_json::Value) -> (String, String) {
+    let field = |name: &str| record[name].as_str().unwrap_or_default().to_string();
+    (field("time"), field("host"))
+}
+
+// The records of each machine, in order, for what only makes sense per machine, like sessions
+pub fn per_host(records: &[StatsRecord]) -> Vec<Vec<&StatsRecord>> {
+    let mut hosts: Vec<(&str, Vec<&StatsRecord>)> = Vec::new();
+    for record in records {
+        let host = record.host.as_deref().unwrap_or(this_host());
+        match hosts.iter_mut().find(|(name, _)| *name == host) {
+            Some((_, records)) => records.push(record),
+            None => hosts.push((host, vec![record])),
+        }
+    }
+    hosts.into_iter().map(|(_, records)| records).collect()
+}
+
+// Prints a summary of the stats log for `neckcheck stats`. Times away shorter than `min_break`
+// don't count as getting up.
+pub fn print_summary(log: &StatsLog, min_break: Duration) {
+    let records = match log.read() {
+        Ok(records) => records,
+        Err(e) => {
+            println!("Failed to read {}: {}", log.path().display(), e);
+            return;
+        }
+    };
+    if records.is_empty() {
+        println!("No stats recorded in {} yet", log.path().display());
+        return;
+    }
+    let (mut false_positives, mut true_positives) = (0, 0);
+    let mut drift = 0.0;
+    let mut blink_rates = Vec::new();
+    for record in &records {
+        match record.entry {
+            StatsEntry::AlertFeedback {
+                false_positive,
+                drift: current,
+            } => {
+                if false_positive {
+                    false_positives += 1;
+                } else {
+                    true_positives += 1;
+                }
+                drift = current;
+            }
+            StatsEntry::BlinkRate { per_minute } => blink_rates.push(per_minute),
+            _ => {}
+        }
+    }
+    println!("Stats since {}", records[0].time.format("%Y-%m-%d %H:%M"));
+    println!(
+        "Alerts dismissed straight away (likely false): {}",
+        false_positives
+    );
+    println!("Alerts followed by a correction: {}", true_positives);
+    print_false_positives(&weekly_false_positives(&records));
+    println!(
+        "Learned threshold drift: {:+.1}% ({})",
+        drift * 100.0,
+        if drift > 0.0 {
+            "more lenient than calibrated"
+        } else if drift < 0.0 {
+            "stricter than calibrated"
+        } else {
+            "as calibrated"
+        }
+    );
+    if !blink_rates.is_empty() {
+        let mean = blink_rates.iter().sum::<f32>() / blink_rates.len() as f32;
+        let lowest = blink_rates.iter().copied().fold(f32::INFINITY, f32::min);
+        println!(
+            "Blink rate: {:.1} per minute on average, {:.1} at the lowest",
+            mean, lowest
+        );
+    }
+    print_sitting(&merge_short_breaks(&segments(&records), min_break));
+    print_camera_time(&camera_time(&records));
+    print_heatmap(&heatmap(&bad_posture(&records)));
+}
+
+// The posture alerts reported with `neckcheck report-fp`, overall and by week so that changes to the
+// detector or the limit show
+fn print_false_positives(weeks: &BTreeMap<NaiveDate, FalsePositives>) {
+    let mut overall = FalsePositives::default();
+    for week in weeks.values() {
+        overall.add(*week);
+    }
+    let percent =
+        |rate: Option<f32>| rate.map_or("-".to_string(), |rate| format!("{:.1}%", rate * 100.0));
+    println!(
+        "Alerts reported as false positives: {} of {} ({})",
+        overall.reported,
+        overall.alerts,
+        percent(overall.rate())
+    );
+    if overall.reported == 0 || weeks.len() < 2 {
+        return;
+    }
+    for (monday, week) in weeks {
+        println!(
+            "  week of {}: {} of {} ({})",
+            monday.format("%Y-%m-%d"),
+            week.reported,
+            week.alerts,
+            percent(week.rate())
+        );
+    }
+}
+
+// How long the camera was on each day, oldest first
+pub fn camera_time(records: &[StatsRecord]) -> Vec<(NaiveDate, Duration)> {
+    let mut days: Vec<(NaiveDate, Duration)> = Vec::new();
+    for record in records {
+        let StatsEntry::CameraOn { seconds } = record.entry else {
+            continue;
+        };
+        let on = Duration::milliseconds((seconds * 1000.0) as i64);
+        let date = record.time.date_naive();
+        // a day may come up again after the clock was set back
+        match days.iter_mut().find(|(day, _)| *day == date) {
+            Some((_, total)) => *total += on,
+            None => days.push((date, on)),
+        }
+    }
+    days.sort_by_key(|(day, _)| *day);
+    days
+}
+
+fn print_camera_time(days: &[(NaiveDate, Duration)]) {
+    let days = &days[days.len().saturating_sub(DAYS_SHOWN)..];
+    if days.is_empty() {
+        return;
+    }
+    println!();
+    println!("Camera on:");
+    for (day, on) in days {
+        println!("{} {}", day.format("%a %m-%d"), format_duration(*on));
+    }
+}
+
+// Each half hour of the day is drawn as seated, away or not monitored
+const SLOTS_PER_DAY: u32 = 48;
+const DAYS_SHOWN: usize = 7;
+
+// Shows the seated and away stretches of the last few days, one line per day
+fn print_sitting(segments: &[Segment]) {
+    let mut days: Vec<NaiveDate> = segments
+        .iter()
+        .map(|segment| segment.start.date_naive())
+        .collect();
+    days.dedup();
+    let days = &days[days.len().saturating_sub(DAYS_SHOWN)..];
+    if days.is_empty() {
+        return;
+    }
+    println!();
+    println!("Sitting (█ seated, ░ away, · not monitored; one block per half hour from midnight):");
+    for day in days {
+        let on_day: Vec<&Segment> = segments
+            .iter()
+            .filter(|segment| segment.start.date_naive() == *day)
+            .collect();
+        let line: String = (0..SLOTS_PER_DAY)
+            .map(|slot| {
+                let minute = slot * 30;
+                let covering = on_day.iter().find(|segment| {
+                    let start = segment.start.hour() * 60 + segment.start.minute();
+                    let end = if segment.end.date_naive() == *day {
+                        segment.end.hour() * 60 + segment.end.minute()
+                    } else {
+                        24 * 60
+                    };
+                    start < minute + 30 && end > minute
+                });
+                match covering {
+                    Some(segment) if segment.seated => '█',
+                    Some(_) => '░',
+                    None => '·',
+                }
+            })
+            .collect();
+        let longest = on_day
+            .iter()
+            .filter(|segment| segment.seated)
+            .map(|segment| segment.duration())
+            .max()
+            .unwrap_or_else(Duration::zero);
+        let breaks = on_day.iter().filter(|segment| !segment.seated).count();
+        println!(
+            "{} {} longest seated {}, {} breaks",
+            day.format("%a %m-%d"),
+            line,
+            format_duration(longest),
+            breaks
+        );
+    }
+}
+
+pub const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+// Shows when bad posture tends to happen, one line per day of the week and one block per hour,
+// shaded from none to the worst hour
+fn print_heatmap(heatmap: &Heatmap) {
+    if heatmap.is_empty() {
+        return;
+    }
+    let colors = [Color::Green, Color::Yellow, Color::Red, Color::Magenta];
+    println!();
+    println!(
+        "Bad posture by hour (· none, {} {} {} {} least to most; one block per hour from midnight):",
+        style('█').fg(colors[0]),
+        style('█').fg(colors[1]),
+        style('█').fg(colors[2]),
+        style('█').fg(colors[3])
+    );
+    let max = heatmap.max();
+    for weekday in WEEKDAYS {
+        let line: String = (0..24)
+            .map(|hour| {
+                let minutes = heatmap.minutes(weekday, hour);
+                if minutes == 0.0 {
+                    return "·".to_string();
+                }
+                let level =
+                    ((minutes / max * colors.len() as f64).ceil() as usize).clamp(1, colors.len());
+                style('█').fg(colors[level - 1]).to_string()
+            })
+            .collect();
+        println!("{} {}", weekday, line);
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    format!("{}h{:02}m", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn camera_time_adds_up_per_day() {
+        let record = |day: u32, hour: u32, seconds: f32| StatsRecord {
+            time: Local.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap(),
+            host: None,
+            profile: None,
+            session: None,
+            others_present: false,
+            entry: StatsEntry::CameraOn { seconds },
+        };
+        let records = vec![
+            record(4, 9, 300.0),
+            StatsRecord {
+                time: Local.with_ymd_and_hms(2024, 3, 4, 9, 1, 0).unwrap(),
+                host: None,
+                profile: None,
+                session: None,
+                others_present: false,
+                entry: StatsEntry::Alive,
+            },
+            record(4, 10, 60.0),
+            record(5, 9, 120.0),
+        ];
+        assert_eq!(
+            camera_time(&records),
+            vec![
+                (
+                    NaiveDate::from_ymd_opt(2024, 3, 4).unwrap(),
+                    Duration::minutes(6)
+                ),
+                (
+                    NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(),
+                    Duration::minutes(2)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_torn_line_costs_only_itself_and_compaction_drops_it() {
+        let dir = std::env::temp_dir().join(format!("neckcheck-stats-{}", std::process::id()));
+        let log = StatsLog::new(dir.join("stats.jsonl"));
+        log.record(StatsEntry::SessionStarted);
+        log.record(StatsEntry::Alive);
+        log.record(StatsEntry::Alive);
+        // killed halfway through a line, with a line from a newer version before it
+        let mut file = OpenOptions::new().append(true).open(log.path()).unwrap();
+        write!(
+            file,
+            "{{\"time\":\"2024-03-04T09:00:00+00:00\",\"kind\":\"new\"}}\n{{\"time\":"
+        )
+        .unwrap();
+        log.record(StatsEntry::Alive);
+        log.record(StatsEntry::SessionEnded);
+        assert_eq!(log.read().unwrap().len(), 5);
+
+        // the torn line and the first of the two Alives in a row
+        assert_eq!(log.compact().unwrap(), 2);
+        let records = log.read().unwrap();
+        let entries: Vec<&StatsEntry> = records.iter().map(|record| &record.entry).collect();
+        assert_eq!(
+            entries,
+            vec![
+                &StatsEntry::SessionStarted,
+                &StatsEntry::Alive,
+                &StatsEntry::Alive,
+                &StatsEntry::SessionEnded
+            ]
+        );
+        assert!(fs::read_to_string(log.path()).unwrap().contains("\"new\""));
+        assert_eq!(log.compact().unwrap(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn merging_adds_only_what_is_new() {
+        let dir = std::env::temp_dir().join(format!("neckcheck-merge-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let log = StatsLog::new(dir.join("stats.jsonl"));
+        let line = |minute: u32, kind: &str| {
+            format!(
+                "{{\"time\":\"2024-03-04T09:{:02}:00+00:00\",\"kind\":\"{}\"}}\n",
+                minute, kind
+            )
+        };
+        fs::write(
+            log.path(),
+            line(0, "session_started") + &line(30, "session_ended"),
+        )
+        .unwrap();
+        let laptop = dir.join("laptop.jsonl");
+        fs::write(
+            &laptop,
+            line(10, "session_started") + &line(20, "session_ended"),
+        )
+        .unwrap();
+
+        assert_eq!(log.merge(&laptop, "laptop").unwrap(), (2, 0));
+        // merging the same file again changes nothing
+        assert_eq!(log.merge(&laptop, "laptop").unwrap(), (0, 2));
+        let records = log.read().unwrap();
+        let hosts: Vec<&str> = records
+            .iter()
+            .map(|record| record.host.as_deref().unwrap())
+            .collect();
+        assert_eq!(hosts, vec![this_host(), "laptop", "laptop", this_host()]);
+        // the laptop's session doesn't cut short the one on this machine
+        assert_eq!(segments(&records).len(), 2);
+        assert_eq!(segments(&records)[0].duration(), Duration::minutes(30));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn records_can_be_narrowed_down_to_a_machine_profile_or_being_alone() {
+        let record = |host: Option<&str>, profile: Option<&str>| StatsRecord {
+            time: Local.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap(),
+            host: host.map(str::to_string),
+            profile: profile.map(str::to_string),
+            session: None,
+            others_present: false,
+            entry: StatsEntry::Alive,
+        };
+        let standing = StatsFilter {
+            profile: Some("standing-desk".to_string()),
+            ..StatsFilter::default()
+        };
+        assert!(standing.matches(&record(Some("laptop"), Some("standing-desk"))));
+        assert!(!standing.matches(&record(Some("laptop"), None)));
+        let laptop = StatsFilter {
+            host: Some("laptop".to_string()),
+            ..StatsFilter::default()
+        };
+        assert!(laptop.matches(&record(Some("laptop"), None)));
+        assert!(!laptop.matches(&record(Some("desktop"), None)));
+        // records from before hosts were noted are this machine's
+        let here = StatsFilter {
+            host: Some(this_host().to_string()),
+            ..StatsFilter::default()
+        };
+        assert!(here.matches(&record(None, None)));
+        // and what happened with others in view can be left out
+        let alone = StatsFilter {
+            alone: true,
+            ..StatsFilter::default()
+        };
+        let crowded = StatsRecord {
+            others_present: true,
+            ..record(None, None)
+        };
+        assert!(alone.matches(&record(None, None)));
+        assert!(!alone.matches(&crowded));
+        assert!(StatsFilter::default().matches(&crowded));
+    }
+}
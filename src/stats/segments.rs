@@ -0,0 +1,306 @@
+use chrono::{DateTime, Duration, Local};
+
+use super::{per_host, StatsEntry, StatsRecord};
+use crate::alert::AlertKind;
+
+// A stretch of time spent either at the desk or away from it while neckcheck was running
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub seated: bool,
+}
+
+impl Segment {
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+// The seated and away stretches in the log, of every machine. A session that ended without saying
+// so (a crash, a power cut) is taken to have ended at its last record.
+pub fn segments(records: &[StatsRecord]) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = per_host(records)
+        .iter()
+        .flat_map(|records| host_segments(records))
+        .collect();
+    segments.sort_by_key(|segment| segment.start);
+    segments
+}
+
+fn host_segments(records: &[&StatsRecord]) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    // the stretch in progress: when it started and whether it is seated
+    let mut current: Option<(DateTime<Local>, bool)> = None;
+    let mut last_time = None;
+    let mut close = |current: &mut Option<(DateTime<Local>, bool)>, end| {
+        if let Some((start, seated)) = current.take() {
+            if end > start {
+                segments.push(Segment { start, end, seated });
+            }
+        }
+    };
+    for record in records {
+        match &record.entry {
+            StatsEntry::SessionStarted => {
+                if let Some(last) = last_time {
+                    close(&mut current, last);
+                }
+                current = Some((record.time, true));
+            }
+            StatsEntry::SessionEnded => close(&mut current, record.time),
+            // the stretch goes on, but not across the jump
+            StatsEntry::ClockChanged { seconds } => {
+                if let Some((_, seated)) = current {
+                    close(&mut current, record.time - Duration::seconds(*seconds));
+                    current = Some((record.time, seated));
+                }
+            }
+            StatsEntry::Event { event } => {
+                let seated = match event {
+                    AlertKind::Away => false,
+                    AlertKind::Returned => true,
+                    _ => {
+                        last_time = Some(record.time);
+                        continue;
+                    }
+                };
+                if current.is_some_and(|(_, current)| current != seated) {
+                    close(&mut current, record.time);
+                    current = Some((record.time, seated));
+                }
+            }
+            // marked done from the command line, perhaps with neckcheck not running
+            StatsEntry::ExerciseDone { .. } => continue,
+            _ => {}
+        }
+        last_time = Some(record.time);
+    }
+    if let Some(last) = last_time {
+        close(&mut current, last);
+    }
+    segments
+}
+
+// Treats times away shorter than `min_break` as part of the surrounding seated stretch: getting a
+// coffee from the desk drawer is not standing up
+pub fn merge_short_breaks(segments: &[Segment], min_break: Duration) -> Vec<Segment> {
+    let mut merged: Vec<Segment> = Vec::new();
+    for segment in segments {
+        let seated = segment.seated || segment.duration() < min_break;
+        match merged.last_mut() {
+            Some(last) if last.seated == seated && last.end == segment.start => {
+                last.end = segment.end;
+            }
+            _ => merged.push(Segment { seated, ..*segment }),
+        }
+    }
+    merged
+}
+
+// The stretches spent too close to the screen: from each posture alert until the user recovered,
+// left or neckcheck stopped
+pub fn bad_posture(records: &[StatsRecord]) -> Vec<Segment> {
+    let mut intervals: Vec<Segment> = per_host(records)
+        .iter()
+        .flat_map(|records| host_bad_posture(records))
+        .collect();
+    intervals.sort_by_key(|interval| interval.start);
+    intervals
+}
+
+fn host_bad_posture(records: &[&StatsRecord]) -> Vec<Segment> {
+    let mut intervals = Vec::new();
+    let mut since: Option<DateTime<Local>> = None;
+    let mut last_time = None;
+    for record in records {
+        let ends = match &record.entry {
+            StatsEntry::Event {
+                event: AlertKind::TooClose,
+            } => {
+                since.get_or_insert(record.time);
+                false
+            }
+            StatsEntry::Event {
+                event: AlertKind::Recovered | AlertKind::Away,
+            }
+            | StatsEntry::SessionEnded => true,
+            StatsEntry::ClockChanged { seconds } => {
+                if let Some(start) = since {
+                    intervals.push(Segment {
+                        start,
+                        end: record.time - Duration::seconds(*seconds),
+                        seated: true,
+                    });
+                    since = Some(record.time);
+                }
+                false
+            }
+            // a crash ends the episode at the last thing recorded before it
+            StatsEntry::SessionStarted => {
+                if let (Some(start), Some(end)) = (since.take(), last_time) {
+                    intervals.push(Segment {
+                        start,
+                        end,
+                        seated: true,
+                    });
+                }
+                false
+            }
+            _ => false,
+        };
+        if ends {
+            if let Some(start) = since.take() {
+                intervals.push(Segment {
+                    start,
+                    end: record.time,
+                    seated: true,
+                });
+            }
+        }
+        last_time = Some(record.time);
+    }
+    if let (Some(start), Some(end)) = (since, last_time) {
+        intervals.push(Segment {
+            start,
+            end,
+            seated: true,
+        });
+    }
+    intervals.retain(|interval| interval.end > interval.start);
+    intervals
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(minutes: i64) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn record(minutes: i64, entry: StatsEntry) -> StatsRecord {
+        StatsRecord {
+            time: at(minutes),
+            host: None,
+            profile: None,
+            session: None,
+            others_present: false,
+            entry,
+        }
+    }
+
+    fn event(minutes: i64, event: AlertKind) -> StatsRecord {
+        record(minutes, StatsEntry::Event { event })
+    }
+
+    #[test]
+    fn away_and_return_split_a_session() {
+        let records = vec![
+            record(0, StatsEntry::SessionStarted),
+            event(50, AlertKind::Away),
+            event(60, AlertKind::Returned),
+            record(90, StatsEntry::SessionEnded),
+        ];
+        assert_eq!(
+            segments(&records),
+            vec![
+                Segment {
+                    start: at(0),
+                    end: at(50),
+                    seated: true
+                },
+                Segment {
+                    start: at(50),
+                    end: at(60),
+                    seated: false
+                },
+                Segment {
+                    start: at(60),
+                    end: at(90),
+                    seated: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn crashed_session_ends_at_its_last_record() {
+        let records = vec![
+            record(0, StatsEntry::SessionStarted),
+            record(30, StatsEntry::Alive),
+            record(120, StatsEntry::SessionStarted),
+            record(150, StatsEntry::SessionEnded),
+        ];
+        let segments = segments(&records);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].end, at(30));
+        assert_eq!(segments[1].start, at(120));
+    }
+
+    #[test]
+    fn clock_changes_are_left_out() {
+        let records = vec![
+            record(0, StatsEntry::SessionStarted),
+            event(10, AlertKind::TooClose),
+            // asleep for an hour from minute 20
+            record(80, StatsEntry::ClockChanged { seconds: 3600 }),
+            event(85, AlertKind::Recovered),
+            // set back by an hour
+            record(30, StatsEntry::ClockChanged { seconds: -3600 }),
+            record(40, StatsEntry::SessionEnded),
+        ];
+        let seated: Vec<_> = segments(&records)
+            .iter()
+            .map(|segment| segment.duration())
+            .collect();
+        assert_eq!(
+            seated,
+            vec![
+                Duration::minutes(20),
+                Duration::minutes(10),
+                Duration::minutes(10)
+            ]
+        );
+        let bad: Vec<_> = bad_posture(&records)
+            .iter()
+            .map(|segment| segment.duration())
+            .collect();
+        assert_eq!(bad, vec![Duration::minutes(10), Duration::minutes(5)]);
+    }
+
+    #[test]
+    fn short_breaks_are_merged() {
+        let records = vec![
+            record(0, StatsEntry::SessionStarted),
+            event(20, AlertKind::Away),
+            event(21, AlertKind::Returned),
+            event(40, AlertKind::Away),
+            event(50, AlertKind::Returned),
+            record(60, StatsEntry::SessionEnded),
+        ];
+        let merged = merge_short_breaks(&segments(&records), Duration::minutes(5));
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].duration(), Duration::minutes(40));
+        assert!(!merged[1].seated);
+    }
+
+    #[test]
+    fn bad_posture_lasts_until_recovery() {
+        let records = vec![
+            record(0, StatsEntry::SessionStarted),
+            event(10, AlertKind::TooClose),
+            event(10, AlertKind::Reminder),
+            event(15, AlertKind::Recovered),
+            event(20, AlertKind::TooClose),
+            record(25, StatsEntry::Alive),
+            record(30, StatsEntry::SessionStarted),
+        ];
+        let bad = bad_posture(&records);
+        assert_eq!(bad.len(), 2);
+        assert_eq!(bad[0].duration(), Duration::minutes(5));
+        assert_eq!(bad[1].end, at(25));
+    }
+}
@@ -0,0 +1,393 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::alert::{alert_kind, AlertKind, BadPosture};
+use crate::events::{Feedback, MemoryUsage, Metrics, PostureEvent};
+use crate::ipc;
+use crate::posture::Proximity;
+use crate::stats::format_duration;
+
+// How often a snooze is checked for having run out
+const SNOOZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// How long `neckcheck waybar` waits before connecting again
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+// What the running neckcheck currently sees, at a glance (e.g. for a Stream Deck key)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PostureState {
+    #[default]
+    Good,
+    Bad,
+    Away,
+    Snoozed,
+    Paused,
+    // someone other than the enrolled user is at the computer
+    Guest,
+    CameraLost,
+}
+
+// The state and the numbers behind it, for status displays
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Status {
+    pub state: PostureState,
+    // when the state last changed
+    pub since: Option<DateTime<Local>>,
+    // from the latest check
+    pub metrics: Option<Metrics>,
+    // posture alerts since midnight
+    pub alerts_today: u32,
+    #[serde(default)]
+    pub camera: CameraHealth,
+    // from the latest memory check
+    #[serde(default)]
+    pub memory: Option<MemoryUsage>,
+    // when the latest check finished
+    #[serde(default)]
+    pub last_check: Option<DateTime<Local>>,
+    // why monitoring is reduced to save power, if it is
+    #[serde(default)]
+    pub power_saving: Option<String>,
+}
+
+// Whether frames are coming in, and if not, why
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CameraHealth {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    // whether the camera is switched on right now, i.e. neckcheck is looking
+    #[serde(default)]
+    pub active: bool,
+}
+
+impl Default for CameraHealth {
+    fn default() -> Self {
+        CameraHealth {
+            ok: true,
+            error: None,
+            active: false,
+        }
+    }
+}
+
+// Follows the status from the events
+#[derive(Debug, Default)]
+pub struct StatusTracker {
+    bad: BadPosture,
+    away: bool,
+    camera_lost: bool,
+    paused: bool,
+    guest: bool,
+    snoozed_until: Option<Instant>,
+    status: Status,
+    alerts_date: Option<NaiveDate>,
+}
+
+impl StatusTracker {
+    pub fn new() -> StatusTracker {
+        StatusTracker::default()
+    }
+
+    pub fn update(&mut self, event: &PostureEvent, now: Instant, local: DateTime<Local>) {
+        if let Some(kind) = alert_kind(event) {
+            // a new day starts the count over
+            if self.alerts_date != Some(local.date_naive()) {
+                self.alerts_date = Some(local.date_naive());
+                self.status.alerts_today = 0;
+            }
+            if matches!(
+                kind,
+                AlertKind::TooClose | AlertKind::NeckAngle | AlertKind::Slouch | AlertKind::Sinking
+            ) {
+                self.status.alerts_today += 1;
+            }
+            self.bad.update(kind);
+        }
+        match event {
+            PostureEvent::Away => self.away = true,
+            PostureEvent::Returned => self.away = false,
+            PostureEvent::CameraLost(reason) | PostureEvent::MonitoringFailed(reason) => {
+                self.camera_lost = true;
+                self.status.camera = CameraHealth {
+                    ok: false,
+                    error: Some(reason.clone()),
+                    active: self.status.camera.active,
+                };
+            }
+            PostureEvent::CameraRecovered => {
+                self.camera_lost = false;
+                self.status.camera = CameraHealth {
+                    active: self.status.camera.active,
+                    ..CameraHealth::default()
+                };
+            }
+            PostureEvent::CameraOpened => self.status.camera.active = true,
+            PostureEvent::CameraClosed => self.status.camera.active = false,
+            PostureEvent::Paused => self.paused = true,
+            PostureEvent::Resumed => self.paused = false,
+            PostureEvent::GuestArrived => self.guest = true,
+            PostureEvent::GuestLeft => self.guest = false,
+            PostureEvent::Feedback(Feedback::Snooze(duration)) => {
+                self.snoozed_until = Some(now + *duration)
+            }
+            PostureEvent::Metrics(metrics) => {
+                self.status.metrics = Some(*metrics);
+                self.status.last_check = Some(local);
+            }
+            PostureEvent::Memory(usage) => self.status.memory = Some(*usage),
+            PostureEvent::PowerSaving(reason) => self.status.power_saving = reason.clone(),
+            _ => {}
+        }
+    }
+
+    // The state that matters most
+    pub fn state(&self, now: Instant) -> PostureState {
+        if self.paused {
+            PostureState::Paused
+        } else if self.guest {
+            PostureState::Guest
+        } else if self.camera_lost {
+            PostureState::CameraLost
+        } else if self.away {
+            PostureState::Away
+        } else if self.snoozed_until.is_some_and(|until| now < until) {
+            PostureState::Snoozed
+        } else if self.bad.is_bad() {
+            PostureState::Bad
+        } else {
+            PostureState::Good
+        }
+    }
+
+    pub fn status(&mut self, now: Instant, local: DateTime<Local>) -> &Status {
+        let state = self.state(now);
+        if state != self.status.state || self.status.since.is_none() {
+            self.status.state = state;
+            self.status.since = Some(local);
+        }
+        &self.status
+    }
+}
+
+// Keeps `status` up to date with the events until shutdown
+pub async fn run(
+    mut events: broadcast::Receiver<PostureEvent>,
+    status: watch::Sender<Status>,
+    shutdown: CancellationToken,
+) {
+    let mut tracker = StatusTracker::new();
+    let mut snooze_check = tokio::time::interval(SNOOZE_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            event = events.recv() => match event {
+                Ok(event) => tracker.update(&event, Instant::now(), Local::now()),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            },
+            _ = snooze_check.tick() => {}
+        }
+        let current = tracker.status(Instant::now(), Local::now());
+        status.send_if_modified(|status| {
+            let changed = status != current;
+            status.clone_from(current);
+            changed
+        });
+    }
+}
+
+fn describe(proximity: Proximity) -> String {
+    match proximity {
+        Proximity::FaceSize { width, height } => format!("face {}x{}", width, height),
+        Proximity::Distance(distance) => format!("{:.0} cm", distance),
+    }
+}
+
+// A few lines about the status for a tooltip
+pub fn tooltip(status: &Status, now: DateTime<Local>) -> String {
+    let mut lines = Vec::new();
+    let state = serde_json::to_value(status.state).unwrap_or_default();
+    let state = state.as_str().unwrap_or_default().replace('_', " ");
+    match status.since {
+        Some(since) => lines.push(format!("{} for {}", state, format_duration(now - since))),
+        None => lines.push(state),
+    }
+    if let Some(metrics) = &status.metrics {
+        if let Some(current) = metrics.current {
+            lines.push(format!("Now: {}", describe(current)));
+        }
+        if let Some(smoothed) = metrics.smoothed {
+            lines.push(format!(
+                "Smoothed: {} (limit {})",
+                describe(smoothed),
+                describe(metrics.limit)
+            ));
+        }
+        if metrics.detection_scale < 1.0 {
+            lines.push(format!(
+                "Slow checks ({} ms): detecting faces at {:.0}% resolution",
+                metrics.latency_ms,
+                metrics.detection_scale * 100.0
+            ));
+        }
+    }
+    if let Some(reason) = &status.power_saving {
+        lines.push(format!("Reduced monitoring (power saving: {})", reason));
+    }
+    lines.push(format!("Posture alerts today: {}", status.alerts_today));
+    if status.camera.active {
+        lines.push("Camera on".to_string());
+    }
+    lines.join("\n")
+}
+
+// A line for a waybar custom module with `"return-type": "json"`
+pub fn waybar(status: &Status, now: DateTime<Local>) -> Value {
+    let class = serde_json::to_value(status.state).unwrap_or_default();
+    // styled with e.g. `#custom-neckcheck.camera-on`
+    let mut classes = vec![class.clone()];
+    if status.camera.active {
+        classes.push(json!("camera-on"));
+    }
+    json!({
+        "text": format!("neckcheck: {}", class.as_str().unwrap_or_default().replace('_', " ")),
+        "tooltip": tooltip(status, now),
+        "class": classes,
+        "alt": class,
+    })
+}
+
+// Prints the status of the running neckcheck, as JSON or for people
+pub fn print_snapshot(snapshot: &ipc::Snapshot, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(snapshot).unwrap());
+        return;
+    }
+    println!("{}", tooltip(&snapshot.status, Local::now()));
+    if let Some(error) = &snapshot.status.camera.error {
+        println!("Camera: {}", error);
+    }
+    if let Some(memory) = &snapshot.status.memory {
+        println!(
+            "Memory: {:.0} MB ({:+.0} MB since settling)",
+            memory.resident_mb, memory.growth_mb
+        );
+    }
+    if let Some(metrics) = &snapshot.status.metrics {
+        println!("Allocations in the latest check: {}", metrics.allocations);
+    }
+    println!(
+        "Running for {}",
+        format_duration(chrono::Duration::seconds(snapshot.uptime_seconds as i64))
+    );
+    if let Some(config) = &snapshot.config {
+        println!("Config: {}", config.display());
+    }
+}
+
+// Prints a waybar line for every status update of the running neckcheck, forever, and says so
+// while neckcheck isn't running
+pub fn run_waybar(address: SocketAddr) {
+    loop {
+        let result = ipc::subscribe(address, |status| {
+            println!("{}", waybar(&status, Local::now()));
+        });
+        let tooltip = match result {
+            Ok(()) => "neckcheck stopped".to_string(),
+            Err(e) => e,
+        };
+        println!(
+            "{}",
+            json!({"text": "neckcheck: off", "tooltip": tooltip, "class": "off", "alt": "off"})
+        );
+        std::thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn the_state_follows_the_events() {
+        let start = Instant::now();
+        let local = Local.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        let mut tracker = StatusTracker::new();
+        assert_eq!(tracker.state(start), PostureState::Good);
+        tracker.update(&PostureEvent::TooClose, start, local);
+        assert_eq!(tracker.state(start), PostureState::Bad);
+        tracker.update(
+            &PostureEvent::Feedback(Feedback::Snooze(Duration::from_secs(60))),
+            start,
+            local,
+        );
+        assert_eq!(tracker.state(start), PostureState::Snoozed);
+        let later = start + Duration::from_secs(61);
+        assert_eq!(tracker.state(later), PostureState::Bad);
+        tracker.update(&PostureEvent::Paused, later, local);
+        assert_eq!(tracker.state(later), PostureState::Paused);
+        tracker.update(&PostureEvent::Resumed, later, local);
+        tracker.update(&PostureEvent::Away, later, local);
+        assert_eq!(tracker.state(later), PostureState::Away);
+        tracker.update(&PostureEvent::Returned, later, local);
+        assert_eq!(tracker.state(later), PostureState::Good);
+        tracker.update(
+            &PostureEvent::CameraLost("unplugged".to_string()),
+            later,
+            local,
+        );
+        assert_eq!(tracker.state(later), PostureState::CameraLost);
+        assert_eq!(tracker.status.camera.error.as_deref(), Some("unplugged"));
+        tracker.update(&PostureEvent::CameraRecovered, later, local);
+        assert!(tracker.status.camera.ok);
+    }
+
+    #[test]
+    fn the_status_counts_todays_alerts_and_keeps_the_metrics() {
+        let now = Instant::now();
+        let morning = Local.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        let mut tracker = StatusTracker::new();
+        tracker.update(&PostureEvent::TooClose, now, morning);
+        tracker.update(&PostureEvent::Recovered, now, morning);
+        tracker.update(&PostureEvent::Slouching, now, morning);
+        let metrics = Metrics {
+            current: Some(Proximity::Distance(45.0)),
+            smoothed: Some(Proximity::Distance(47.0)),
+            limit: Proximity::Distance(50.0),
+            latency_ms: 400,
+            decision_latency_ms: 450,
+            detection_scale: 0.5,
+            allocations: 120,
+            severity: None,
+        };
+        tracker.update(&PostureEvent::Metrics(metrics), now, morning);
+        let status = tracker.status(now, morning).clone();
+        assert_eq!(status.state, PostureState::Bad);
+        assert_eq!(status.since, Some(morning));
+        assert_eq!(status.alerts_today, 2);
+        assert_eq!(status.metrics, Some(metrics));
+        assert_eq!(
+            tooltip(&status, morning + chrono::Duration::minutes(5)),
+            "bad for 0h05m\nNow: 45 cm\nSmoothed: 47 cm (limit 50 cm)\n\
+             Slow checks (400 ms): detecting faces at 50% resolution\nPosture alerts today: 2"
+        );
+
+        tracker.update(
+            &PostureEvent::TooClose,
+            now,
+            morning + chrono::Duration::days(1),
+        );
+        assert_eq!(tracker.status.alerts_today, 1);
+    }
+}
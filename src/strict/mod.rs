@@ -0,0 +1,61 @@
+// The strict profile, meant for children's computers: posture alerts can't be snoozed or dismissed
+// and monitoring can't be paused, except by someone who knows the passcode
+
+use sha2::{Digest, Sha256};
+
+use crate::config::StrictConfig;
+
+// what refusing comes back as, so that `neckcheck pause` and the like know to ask for the passcode
+pub const LOCKED: &str = "Not allowed by the strict profile without the passcode";
+
+#[derive(Debug, Clone)]
+pub struct Strict {
+    // the SHA-256 of the passcode, in lowercase hex
+    passcode_sha256: Option<String>,
+}
+
+impl Strict {
+    pub fn new(config: &StrictConfig) -> Option<Strict> {
+        config.enabled.then(|| Strict {
+            passcode_sha256: config
+                .passcode_sha256
+                .as_ref()
+                .map(|hash| hash.trim().to_lowercase()),
+        })
+    }
+
+    // Whether `passcode` is the one whose hash is in the config. Without one in the config, none
+    // is.
+    pub fn unlocks(&self, passcode: &str) -> bool {
+        let hash = format!("{:x}", Sha256::digest(passcode.as_bytes()));
+        self.passcode_sha256.as_ref() == Some(&hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_passcode_unlocks() {
+        let config = StrictConfig {
+            enabled: true,
+            // echo -n 1234 | sha256sum
+            passcode_sha256: Some(
+                "03AC674216F3E15C761EE1A5E255F067953623C8B388B4459E13F978D7C846F4".to_string(),
+            ),
+            ..StrictConfig::default()
+        };
+        let strict = Strict::new(&config).unwrap();
+        assert!(strict.unlocks("1234"));
+        assert!(!strict.unlocks("12345"));
+        assert!(!strict.unlocks(""));
+
+        let without_passcode = StrictConfig {
+            passcode_sha256: None,
+            ..config
+        };
+        assert!(!Strict::new(&without_passcode).unwrap().unlocks(""));
+        assert!(Strict::new(&StrictConfig::default()).is_none());
+    }
+}
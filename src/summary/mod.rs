@@ -0,0 +1,97 @@
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+use tokio_util::sync::CancellationToken;
+
+use crate::clock::ClockWatch;
+use crate::config::SummaryConfig;
+use crate::events::{EventBus, PostureEvent};
+use crate::stats::{daily_summary, StatsLog};
+
+// the wall clock is checked this often rather than sleeping until the summary is due, so that
+// suspend and clock changes can't make it late by hours
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(60);
+
+// Publishes a summary of the day from the stats every day at the configured time, unless nothing
+// was recorded that day
+pub async fn run(
+    config: SummaryConfig,
+    stats: StatsLog,
+    min_break: Duration,
+    events: EventBus,
+    shutdown: CancellationToken,
+) {
+    let mut due = next_due(config.at, Local::now());
+    let mut clock = ClockWatch::new();
+    // so that a clock set back doesn't get the same day summarized twice
+    let mut summarized = None;
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let now = Local::now();
+        // after the clock was set or the time zone changed, the time on the clock it's due at may
+        // be another instant; one that was missed is still made up for below
+        if clock.check(Instant::now(), now).is_some() && now < due {
+            due = next_due(config.at, now);
+        }
+        if now < due {
+            continue;
+        }
+        due = next_due(config.at, now);
+        if summarized.replace(now.date_naive()) == Some(now.date_naive()) {
+            continue;
+        }
+        let records = match stats.read() {
+            Ok(records) => records,
+            Err(e) => {
+                println!("Failed to read {}: {}", stats.path().display(), e);
+                continue;
+            }
+        };
+        let summary = daily_summary(&records, now.date_naive(), min_break);
+        if summary.at_desk > Duration::zero() {
+            events.publish(PostureEvent::Summary(summary.message()));
+        }
+    }
+}
+
+// The first time after `now` that the clock shows `at`
+pub fn next_due(at: NaiveTime, now: DateTime<Local>) -> DateTime<Local> {
+    let mut date = now.date_naive();
+    loop {
+        // a time skipped by a DST change happens an hour later that day
+        let local = date.and_time(at);
+        let candidate = Local.from_local_datetime(&local).earliest().or_else(|| {
+            Local
+                .from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+        });
+        if let Some(candidate) = candidate.filter(|candidate| *candidate > now) {
+            return candidate;
+        }
+        date = date.succ_opt().expect("date out of range");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn due_later_today_or_tomorrow() {
+        let at = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        let morning = Local.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        let evening = Local.with_ymd_and_hms(2024, 3, 4, 18, 0, 0).unwrap();
+        assert_eq!(
+            next_due(at, morning),
+            Local.with_ymd_and_hms(2024, 3, 4, 18, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_due(at, evening),
+            Local.with_ymd_and_hms(2024, 3, 5, 18, 0, 0).unwrap()
+        );
+    }
+}
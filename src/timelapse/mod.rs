@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveDate};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{imageops, Delay, DynamicImage, Frame, RgbImage};
+use imageproc::rect::Rect;
+
+use crate::output::{annotate, OverlayStyle};
+use crate::privacy::ImageAccess;
+use crate::snapshot::blur_faces;
+
+const RECORD_INTERVAL: Duration = Duration::from_secs(60);
+// recorded frames are shrunk to this width; enough to see posture, too small to be a photo album
+const RECORD_WIDTH: u32 = 160;
+const FRAME_DELAY_MS: u32 = 150;
+
+// Keeps one small, face-blurred, annotated frame per minute in a directory per day, and turns
+// each finished day into a timelapse.
+pub struct TimelapseRecorder {
+    dir: PathBuf,
+    last_recorded: Option<Instant>,
+    day: NaiveDate,
+}
+
+impl TimelapseRecorder {
+    pub fn new(dir: PathBuf, _access: ImageAccess) -> TimelapseRecorder {
+        TimelapseRecorder {
+            dir,
+            last_recorded: None,
+            day: Local::now().date_naive(),
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        frame: &RgbImage,
+        faces: &[Rect],
+        threshold: Option<(u32, u32)>,
+        ok: bool,
+        style: &OverlayStyle,
+    ) {
+        if self
+            .last_recorded
+            .is_some_and(|last| last.elapsed() < RECORD_INTERVAL)
+        {
+            return;
+        }
+        self.last_recorded = Some(Instant::now());
+
+        let now = Local::now();
+        if now.date_naive() != self.day {
+            let finished = self.day;
+            self.day = now.date_naive();
+            let out = self.dir.join(format!("{}.gif", finished));
+            if let Err(e) = assemble(&self.dir, finished, &out) {
+                println!("Failed to assemble timelapse for {}: {}", finished, e);
+            }
+        }
+
+        let mut frame = blur_faces(frame, faces);
+        annotate(&mut frame, faces, threshold, ok, style);
+        let height = (frame.height() * RECORD_WIDTH / frame.width().max(1)).max(1);
+        let frame = imageops::thumbnail(&frame, RECORD_WIDTH, height);
+
+        let day_dir = day_dir(&self.dir, self.day);
+        let path = day_dir.join(format!("{}.png", now.format("%H%M")));
+        let result = fs::create_dir_all(&day_dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| frame.save(&path).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            println!("Failed to record timelapse frame {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn day_dir(dir: &Path, day: NaiveDate) -> PathBuf {
+    dir.join(day.to_string())
+}
+
+// Assembles the frames recorded on `day` into a timelapse at `out`. A `.gif` is encoded directly,
+// any other extension (e.g. `.mp4`) is handed to ffmpeg.
+pub fn assemble(dir: &Path, day: NaiveDate, out: &Path) -> Result<(), String> {
+    let day_dir = day_dir(dir, day);
+    let mut paths: Vec<PathBuf> = fs::read_dir(&day_dir)
+        .map_err(|e| format!("{}: {}", day_dir.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .collect();
+    if paths.is_empty() {
+        return Err(format!("No frames recorded in {}", day_dir.display()));
+    }
+    // file names are HHMM so this is chronological
+    paths.sort();
+
+    if out.extension().is_some_and(|ext| ext == "gif") {
+        encode_gif(&paths, out)?;
+    } else {
+        encode_ffmpeg(&day_dir, out)?;
+    }
+    println!("Saved {} frame timelapse to {}", paths.len(), out.display());
+    Ok(())
+}
+
+fn encode_gif(paths: &[PathBuf], out: &Path) -> Result<(), String> {
+    let file = fs::File::create(out).map_err(|e| e.to_string())?;
+    let mut encoder = GifEncoder::new_with_speed(file, 10);
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .map_err(|e| e.to_string())?;
+    let delay = Delay::from_numer_denom_ms(FRAME_DELAY_MS, 1);
+    for path in paths {
+        let image: DynamicImage = image::open(path).map_err(|e| e.to_string())?;
+        encoder
+            .encode_frame(Frame::from_parts(image.to_rgba8(), 0, 0, delay))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn encode_ffmpeg(day_dir: &Path, out: &Path) -> Result<(), String> {
+    let pattern = day_dir.join("*.png");
+    let status = Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-y"])
+        .args(["-framerate", &(1000 / FRAME_DELAY_MS).to_string()])
+        .args(["-pattern_type", "glob", "-i"])
+        .arg(&pattern)
+        // scale to even dimensions, which most video codecs require
+        .args([
+            "-vf",
+            "scale=trunc(iw/2)*2:trunc(ih/2)*2",
+            "-pix_fmt",
+            "yuv420p",
+        ])
+        .arg(out)
+        .status()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    if !status.success() {
+        return Err(format!("ffmpeg exited with {}", status));
+    }
+    Ok(())
+}
@@ -3,7 +3,12 @@ use std::time::Duration;
 use rodio::source::SineWave;
 use rodio::{OutputStream, Sink, Source};
 
-pub fn play_tone(duration: f64) {
+// Whether there is a sound device to play tones on
+pub fn audio_available() -> bool {
+    OutputStream::try_default().is_ok()
+}
+
+pub fn play_tone(duration: f64, volume: f32) {
     // _stream must live as long as the sink
     let (_stream, stream_handle) = OutputStream::try_default().unwrap();
     let sink = Sink::try_new(&stream_handle).unwrap();
@@ -11,7 +16,7 @@ pub fn play_tone(duration: f64) {
     // Add a dummy source of the sake of the example.
     let source = SineWave::new(440.0)
         .take_duration(Duration::from_secs_f64(duration))
-        .amplify(1.0);
+        .amplify(volume);
 
     sink.append(source);
 
@@ -0,0 +1,165 @@
+use std::fs;
+use std::io::Read;
+use std::time::Duration;
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+// where releases are published, each with a binary per target and a SHA256SUMS file
+const REPOSITORY: &str = "harryjjacobs/neckcheck";
+const CHECKSUMS: &str = "SHA256SUMS";
+// far more than a neckcheck binary, so that a bad response can't fill the disk
+const MAX_DOWNLOAD_SIZE: u64 = 256 * 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+// The binary released for this platform, e.g. neckcheck-x86_64-unknown-linux-gnu
+fn asset_name() -> String {
+    let name = format!("neckcheck-{}", env!("NECKCHECK_TARGET"));
+    if cfg!(windows) {
+        name + ".exe"
+    } else {
+        name
+    }
+}
+
+// "v1.2.3" or "1.2.3" as numbers to compare; pre-releases aren't offered
+fn version(text: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = text.trim_start_matches('v').split('.');
+    let mut next = || parts.next()?.parse().ok();
+    let version = (next()?, next()?, next()?);
+    parts.next().is_none().then_some(version)
+}
+
+// The expected SHA-256 of `name`, from sha256sum's output
+fn checksum_for(sums: &str, name: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (hash, file) = line.split_once(char::is_whitespace)?;
+        // sha256sum marks files read in binary mode with a '*'
+        (file.trim().trim_start_matches('*') == name).then(|| hash.to_lowercase())
+    })
+}
+
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    let response = ureq::get(url)
+        .timeout(TIMEOUT)
+        .call()
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_SIZE)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    Ok(bytes)
+}
+
+// Looks for a newer release on GitHub and, unless only checking, replaces this binary with it once
+// its checksum matches the one published with it
+pub fn run(check: bool) -> Result<(), String> {
+    let current = env!("CARGO_PKG_VERSION");
+    let release: Release = ureq::get(&format!(
+        "https://api.github.com/repos/{}/releases/latest",
+        REPOSITORY
+    ))
+    .set("Accept", "application/vnd.github+json")
+    .set("User-Agent", &format!("neckcheck/{}", current))
+    .timeout(TIMEOUT)
+    .call()
+    .map_err(|e| format!("Failed to look for releases: {}", e))?
+    .into_json()
+    .map_err(|e| format!("Failed to read the latest release: {}", e))?;
+    let latest = release.tag_name.trim_start_matches('v');
+    match (version(current), version(latest)) {
+        (Some(current), Some(latest)) if latest > current => {}
+        (_, Some(_)) => {
+            println!("neckcheck {} is up to date", current);
+            return Ok(());
+        }
+        (_, None) => return Err(format!("Unexpected release tag {}", release.tag_name)),
+    }
+    if check {
+        println!(
+            "neckcheck {} is out; run `neckcheck self-update` to install it",
+            latest
+        );
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let url = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .map(|asset| asset.browser_download_url.clone())
+            .ok_or_else(|| format!("Release {} has no {}", release.tag_name, name))
+    };
+    let sums = String::from_utf8(download(&url(CHECKSUMS)?)?)
+        .map_err(|_| format!("{} isn't text", CHECKSUMS))?;
+    let expected =
+        checksum_for(&sums, &name).ok_or_else(|| format!("{} doesn't list {}", CHECKSUMS, name))?;
+    println!("Downloading neckcheck {}", latest);
+    let binary = download(&url(&name)?)?;
+    let actual = format!("{:x}", Sha256::digest(&binary));
+    if actual != expected {
+        return Err(format!(
+            "The download of {} doesn't match its checksum, so it was not installed",
+            name
+        ));
+    }
+
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    // next to the binary, so that it's on the same filesystem
+    let temporary = exe.with_file_name(format!(".{}.new", name));
+    fs::write(&temporary, &binary)
+        .map_err(|e| format!("Failed to write {}: {}", temporary.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&temporary, fs::Permissions::from_mode(0o755))
+            .map_err(|e| e.to_string())?;
+    }
+    let replaced = self_replace::self_replace(&temporary)
+        .map_err(|e| format!("Failed to replace {}: {}", exe.display(), e));
+    let _ = fs::remove_file(&temporary);
+    replaced?;
+    println!("Updated neckcheck {} to {}", current, latest);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releases_are_compared_and_checked() {
+        assert_eq!(version("v1.2.10"), Some((1, 2, 10)));
+        assert!(version("1.10.0") > version("1.9.3"));
+        assert_eq!(version("v2.0.0-rc.1"), None);
+        assert_eq!(version("2.0"), None);
+
+        let sums = "0a1b  neckcheck-x86_64-unknown-linux-gnu\n\
+                    FF00 *neckcheck-x86_64-pc-windows-msvc.exe\n";
+        assert_eq!(
+            checksum_for(sums, "neckcheck-x86_64-unknown-linux-gnu").as_deref(),
+            Some("0a1b")
+        );
+        assert_eq!(
+            checksum_for(sums, "neckcheck-x86_64-pc-windows-msvc.exe").as_deref(),
+            Some("ff00")
+        );
+        assert_eq!(checksum_for(sums, "neckcheck-aarch64-apple-darwin"), None);
+    }
+}
@@ -0,0 +1,894 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{
+    channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError,
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::adaptive::{ThresholdLearner, Verdict};
+use crate::alert::{alert_kind, is_posture_alert, AlertKind};
+use crate::body::create_body_pose_detector;
+use crate::cli::Args;
+use crate::clock::ClockWatch;
+use crate::config::Config;
+use crate::dataset::DatasetRecorder;
+use crate::detector::{create_detector, DetectorConfig};
+use crate::dump::DebugDump;
+use crate::events::{EventBus, Feedback, PostureEvent};
+use crate::i18n::tr;
+use crate::landmarks::create_landmark_detector;
+use crate::neckcheck::{CalibrationStore, NeckCheck, NeckCheckCalibration};
+use crate::observe::Observer;
+use crate::output::{OverlayStyle, VirtualCamera};
+use crate::posture::{Condition, DutyCycle, JumpFilter};
+use crate::privacy::PrivacyMode;
+use crate::proximity::{
+    CameraProximity, CameraSelection, FaceTracker, FusedProximity, LatencyWatchdog, PhoneSensor,
+    ProximitySource, SerialSensor,
+};
+use crate::recognition::{create_face_recognizer, EnrolledFaces, Recognition};
+use crate::script;
+use crate::snapshot::SnapshotRecorder;
+use crate::source::{CameraActivity, FrameSource, NetworkSource, WebCam, WebCamMode};
+use crate::stats::{StatsEntry, StatsLog};
+use crate::strict::{Strict, LOCKED};
+use crate::timelapse::TimelapseRecorder;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// consecutive crashes without a single successful check before the user is told
+const FAILURES_BEFORE_NOTIFYING: u32 = 3;
+// how often the stats are told that monitoring is still going
+const ALIVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+pub enum WorkerCommand {
+    Calibrate,
+    // calibrate from the user's normal posture over this long, at this percentile
+    Observe(Duration, f32),
+    Check,
+    // rebuild only the detector, replying with whether that worked
+    SetDetector(DetectorConfig, Sender<Result<(), String>>),
+    // the user snoozed or dismissed an alert
+    Feedback(Feedback),
+    // the user says the latest posture alert was wrong; replies with whether it was recorded
+    ReportFalsePositive(Sender<Result<(), String>>),
+    // stop or start checking again
+    Pause(bool),
+    // detect faces at no more than this fraction of the resolution, e.g. to save power
+    LimitDetectionScale(f32),
+}
+
+// Handle to the thread that owns the camera and the detector. Neither of them is Send, so they are
+// created, used and dropped on that one thread, and everything else talks to it through a command
+// channel and hears back through the event bus.
+pub struct Worker {
+    commands: SyncSender<WorkerCommand>,
+    thread: JoinHandle<()>,
+    strict: Option<Strict>,
+}
+
+impl Worker {
+    pub fn spawn(args: Args, config: &Config, events: EventBus) -> Worker {
+        // a single pending command at most: if a check takes longer than the check interval,
+        // further checks are skipped rather than queueing up behind it
+        let (commands, receiver) = sync_channel(1);
+        let stats = config
+            .stats
+            .path
+            .clone()
+            .or_else(StatsLog::default_path)
+            .map(|path| {
+                let profile = args
+                    .profile
+                    .clone()
+                    .or_else(|| config.stats.profile.clone());
+                StatsLog::new(path).with_session(profile)
+            });
+        // the strict profile's limit isn't loosened by dismissing alerts
+        let learner = (config.adaptive.enabled && !config.strict.enabled).then(|| {
+            // carry on from what was learned in earlier runs
+            let drift = stats.as_ref().and_then(StatsLog::last_drift);
+            ThresholdLearner::new(config.adaptive.clone(), drift.unwrap_or(0.0))
+        });
+        let saved_calibrations = CalibrationStore::default_path().map(CalibrationStore::new);
+        let state = PipelineState {
+            calibrations: saved_calibrations
+                .as_ref()
+                .map(CalibrationStore::load)
+                .unwrap_or_default(),
+            saved_calibrations,
+            detector: config.detector.clone(),
+            learner,
+            stats,
+            config: config.clone(),
+            ..PipelineState::default()
+        };
+        let thread = thread::Builder::new()
+            .name("neckcheck-pipeline".to_string())
+            .spawn(move || {
+                if args.no_camera {
+                    without_camera(receiver, events)
+                } else {
+                    supervise(args, state, receiver, events)
+                }
+            })
+            .expect("Failed to spawn the pipeline thread");
+        Worker {
+            commands,
+            thread,
+            strict: Strict::new(&config.strict),
+        }
+    }
+
+    pub fn calibrate(&self) {
+        let _ = self.commands.send(WorkerCommand::Calibrate);
+    }
+
+    // Calibrates without prompting, from what the checks see over the given period
+    pub fn observe(&self, duration: Duration, percentile: f32) {
+        let _ = self
+            .commands
+            .send(WorkerCommand::Observe(duration, percentile));
+    }
+
+    // Asks for a check unless one is already waiting. Returns false once the worker has stopped.
+    pub fn request_check(&self) -> bool {
+        !matches!(
+            self.commands.try_send(WorkerCommand::Check),
+            Err(TrySendError::Disconnected(_))
+        )
+    }
+
+    pub fn handle(&self) -> WorkerHandle {
+        WorkerHandle {
+            commands: self.commands.clone(),
+            strict: self.strict.clone(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    // Stops the worker after its current command, waiting at most `timeout` for it to do so.
+    // Returns false if it is still busy, e.g. blocked in a calibration prompt.
+    pub fn shutdown(self, timeout: Duration) -> bool {
+        drop(self.commands);
+        let deadline = Instant::now() + timeout;
+        while !self.thread.is_finished() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        let _ = self.thread.join();
+        true
+    }
+}
+
+// Lets other tasks (IPC, config reload) send commands to the worker. Its methods block, so call
+// them from a blocking task.
+#[derive(Clone)]
+pub struct WorkerHandle {
+    commands: SyncSender<WorkerCommand>,
+    // set while the strict profile keeps this handle from silencing monitoring
+    strict: Option<Strict>,
+}
+
+impl WorkerHandle {
+    // A handle that the strict profile doesn't hold back, given its passcode
+    pub fn unlock(&self, passcode: Option<&str>) -> Result<WorkerHandle, String> {
+        match (&self.strict, passcode) {
+            (None, _) => Ok(self.clone()),
+            (Some(strict), Some(passcode)) if strict.unlocks(passcode) => Ok(WorkerHandle {
+                commands: self.commands.clone(),
+                strict: None,
+            }),
+            (Some(_), Some(_)) => Err("Wrong passcode".to_string()),
+            (Some(_), None) => Err(LOCKED.to_string()),
+        }
+    }
+
+    // Switches the face detector without touching the camera
+    pub fn set_detector(&self, detector: DetectorConfig) -> Result<(), String> {
+        let (reply, result) = channel();
+        self.commands
+            .send(WorkerCommand::SetDetector(detector, reply))
+            .map_err(|_| "The pipeline has stopped".to_string())?;
+        result
+            .recv()
+            .unwrap_or_else(|_| Err("The pipeline restarted before switching".to_string()))
+    }
+
+    pub fn feedback(&self, feedback: Feedback) -> Result<(), String> {
+        if self.strict.is_some() {
+            return Err(LOCKED.to_string());
+        }
+        self.commands
+            .send(WorkerCommand::Feedback(feedback))
+            .map_err(|_| "The pipeline has stopped".to_string())
+    }
+
+    // Records the latest posture alert as a false positive in the stats
+    pub fn report_false_positive(&self) -> Result<(), String> {
+        let (reply, result) = channel();
+        self.commands
+            .send(WorkerCommand::ReportFalsePositive(reply))
+            .map_err(|_| "The pipeline has stopped".to_string())?;
+        result
+            .recv()
+            .unwrap_or_else(|_| Err("The pipeline restarted before recording it".to_string()))
+    }
+
+    pub fn pause(&self, paused: bool) -> Result<(), String> {
+        if paused && self.strict.is_some() {
+            return Err(LOCKED.to_string());
+        }
+        self.commands
+            .send(WorkerCommand::Pause(paused))
+            .map_err(|_| "The pipeline has stopped".to_string())
+    }
+
+    pub fn limit_detection_scale(&self, max: f32) -> Result<(), String> {
+        self.commands
+            .send(WorkerCommand::LimitDetectionScale(max))
+            .map_err(|_| "The pipeline has stopped".to_string())
+    }
+
+    // Calibrates without prompting, from what the checks see over the given period
+    #[cfg(feature = "grpc")]
+    pub fn observe(&self, duration: Duration, percentile: f32) -> Result<(), String> {
+        self.commands
+            .send(WorkerCommand::Observe(duration, percentile))
+            .map_err(|_| "The pipeline has stopped".to_string())
+    }
+}
+
+// The frames of the camera with the given index, unless a network or depth camera is in use
+fn camera_source(args: &Args, index: u32) -> Box<dyn FrameSource> {
+    match &args.url {
+        Some(url) => Box::new(NetworkSource::new(url)),
+        None if args.realsense => realsense(),
+        None => {
+            let mode = match args.discrete {
+                Some(_) => WebCamMode::Discrete { burst: args.burst },
+                None => WebCamMode::Continuous,
+            };
+            Box::new(WebCam::new(index, mode))
+        }
+    }
+}
+
+// A camera with a detector of its own
+fn build_camera(
+    source: Box<dyn FrameSource>,
+    detector: &DetectorConfig,
+    config: &Config,
+    privacy: PrivacyMode,
+) -> CameraProximity {
+    let detector = match create_detector(detector) {
+        Ok(detector) => detector,
+        Err(e) => panic!("{}", e),
+    };
+    let mut camera = CameraProximity::new(source, detector, privacy);
+    if config.latency.enabled {
+        camera.set_latency_watchdog(LatencyWatchdog::new(&config.latency));
+    }
+    if config.skip_frames.detect_every > 1 {
+        camera.set_face_tracker(FaceTracker::new(&config.skip_frames));
+    }
+    if config.household.enabled {
+        camera.pick_main_face();
+    }
+    let rule = &config.decision.rule;
+    let neck_angle = config.neck_angle.enabled || rule.uses(Condition::TiltDown);
+    if neck_angle || config.drowsiness.enabled || config.blink_rate.enabled {
+        match create_landmark_detector(&config.landmarks) {
+            Ok(landmarks) => camera.set_landmark_detector(landmarks),
+            Err(e) => println!("Facial landmarks are unavailable: {}", e),
+        }
+    }
+    if config.slouch.enabled || rule.uses(Condition::Slouching) {
+        match create_body_pose_detector(&config.body_pose) {
+            Ok(body) => camera.set_body_pose_detector(body),
+            Err(e) => println!("Slouching is not monitored: {}", e),
+        }
+    }
+    if config.guest.enabled {
+        match build_recognition(config, privacy) {
+            Ok(recognition) => camera.set_recognition(recognition),
+            Err(e) => println!("Guests are not told apart: {}", e),
+        }
+    }
+    camera
+}
+
+fn build_recognition(config: &Config, privacy: PrivacyMode) -> Result<Recognition, String> {
+    let recognizer =
+        create_face_recognizer(&config.face_recognition, privacy).map_err(|e| e.to_string())?;
+    let enrolled = EnrolledFaces::default_path()
+        .ok_or("There is no data directory to keep the enrolled face in")?;
+    let enrolled = EnrolledFaces::new(enrolled)
+        .load()
+        .map_err(|e| format!("{}; run `neckcheck enroll` first", e))?;
+    Ok(Recognition::new(
+        recognizer,
+        enrolled,
+        config.guest.threshold,
+    ))
+}
+
+fn build_neckcheck(args: &Args, detector: &DetectorConfig, config: &Config) -> NeckCheck {
+    let privacy = PrivacyMode::from_strict(args.privacy_strict);
+    let source: Box<dyn ProximitySource> = match (args.phone, &args.serial) {
+        (Some(address), _) => Box::new(PhoneSensor::new(address)),
+        (_, Some(port)) => Box::new(SerialSensor::new(port, args.serial_baud)),
+        _ if args.camera.len() > 1 && args.url.is_none() && !args.realsense => {
+            let cameras = args
+                .camera
+                .iter()
+                .map(|&index| {
+                    let camera =
+                        build_camera(camera_source(args, index), detector, config, privacy);
+                    (
+                        index.to_string(),
+                        Box::new(camera) as Box<dyn ProximitySource>,
+                    )
+                })
+                .collect();
+            let selection = if args.camera_failover {
+                CameraSelection::Failover
+            } else {
+                CameraSelection::Best
+            };
+            Box::new(FusedProximity::new(cameras, selection))
+        }
+        _ => {
+            let index = args.camera.first().copied().unwrap_or_default();
+            Box::new(build_camera(
+                camera_source(args, index),
+                detector,
+                config,
+                privacy,
+            ))
+        }
+    };
+    let mut neckcheck = NeckCheck::new(source, privacy);
+    neckcheck.set_grace_period(Duration::from_secs_f64(args.grace_period));
+    neckcheck.set_schedule(config.schedule.clone());
+    if config.background_faces.enabled {
+        neckcheck.set_min_face_size(config.background_faces.min_size);
+    }
+    if config.outliers.enabled {
+        neckcheck.set_outliers(JumpFilter::new(&config.outliers));
+    }
+    if config.duty_cycle.enabled {
+        let window = Duration::from_secs_f64(config.duty_cycle.window);
+        neckcheck.set_duty_cycle(DutyCycle {
+            too_close_for: Duration::from_secs_f64(config.duty_cycle.too_close_for).min(window),
+            window,
+        });
+    }
+    neckcheck.set_overlay_style(OverlayStyle::new(&config.accessibility));
+    let rule = &config.decision.rule;
+    neckcheck.set_rule(rule.clone());
+    if let Some(path) = &config.decision.script {
+        match script::load(path) {
+            Ok(script) => neckcheck.set_script(script),
+            Err(e) => println!("{}", e),
+        }
+    }
+    if config.neck_angle.enabled || rule.uses(Condition::TiltDown) {
+        neckcheck.set_neck_angle(config.neck_angle.clone());
+    }
+    if config.drowsiness.enabled {
+        neckcheck.set_drowsiness(config.drowsiness.clone());
+    }
+    if config.blink_rate.enabled {
+        neckcheck.set_blink_rate(config.blink_rate.clone());
+    }
+    if config.slouch.enabled || rule.uses(Condition::Slouching) {
+        neckcheck.set_slouch(config.slouch.clone());
+    }
+    if config.face_drift.enabled || rule.uses(Condition::LowPosition) {
+        neckcheck.set_face_drift(config.face_drift.clone());
+    }
+    if let Some(device) = &args.virtual_camera {
+        match privacy.image_access("the virtual camera") {
+            Ok(access) => neckcheck.set_virtual_camera(VirtualCamera::new(device, access)),
+            Err(e) => println!("{}", e),
+        }
+    }
+    if let Some(dir) = &args.snapshot_dir {
+        match privacy.image_access("snapshots") {
+            Ok(access) => neckcheck.set_snapshots(SnapshotRecorder::new(
+                dir.clone(),
+                args.snapshot_privacy,
+                args.snapshot_retention_days,
+                access,
+            )),
+            Err(e) => println!("{}", e),
+        }
+    }
+    if let Some(dir) = &args.timelapse_dir {
+        match privacy.image_access("the timelapse recorder") {
+            Ok(access) => neckcheck.set_timelapse(TimelapseRecorder::new(dir.clone(), access)),
+            Err(e) => println!("{}", e),
+        }
+    }
+    if let Some(dir) = &args.debug_dump {
+        match privacy.image_access("debug dumps") {
+            Ok(access) => neckcheck.set_debug_dump(DebugDump::new(dir.clone(), access)),
+            Err(e) => println!("{}", e),
+        }
+    }
+    let dataset_dir = config
+        .dataset
+        .dir
+        .clone()
+        .or_else(DatasetRecorder::default_dir);
+    if let (true, Some(dir)) = (config.dataset.enabled, dataset_dir) {
+        match privacy.image_access("the training dataset") {
+            Ok(access) => neckcheck.set_dataset(DatasetRecorder::new(dir, &config.dataset, access)),
+            Err(e) => println!("{}", e),
+        }
+    }
+    neckcheck
+}
+
+#[cfg(feature = "realsense")]
+fn realsense() -> Box<dyn FrameSource> {
+    Box::new(crate::source::RealSense::new())
+}
+
+#[cfg(not(feature = "realsense"))]
+fn realsense() -> Box<dyn FrameSource> {
+    panic!("RealSense support is not available; rebuild with the `realsense` feature")
+}
+
+// What survives a restart of the pipeline
+#[derive(Default)]
+struct PipelineState {
+    // of each camera, the one in use last
+    calibrations: Vec<NeckCheckCalibration>,
+    // whether one of them is in use, rather than only saved from an earlier run
+    calibrated: bool,
+    // asked to calibrate, but the pipeline crashed (e.g. the camera was busy) before it was done
+    calibration_pending: bool,
+    // where they are kept between runs
+    saved_calibrations: Option<CalibrationStore>,
+    // observe mode, until it has come up with a calibration
+    observer: Option<Observer>,
+    // the detector in use, which may have been switched since launch
+    detector: DetectorConfig,
+    camera_lost: bool,
+    learner: Option<ThresholdLearner>,
+    stats: Option<StatsLog>,
+    last_alive: Option<Instant>,
+    // the config at launch; the detector above is the one that can change
+    config: Config,
+    // whether the pipeline managed a successful check since it was last (re)started
+    healthy: bool,
+    // paused by the user, so checks are skipped
+    paused: bool,
+    // when a guest at the computer was last checked on, to check less often while they are there
+    guest_checked: Option<Instant>,
+    // since when the camera has been on, if it is
+    camera_on_since: Option<Instant>,
+    // how long the camera was on since this was last written to the stats
+    camera_on_for: Duration,
+    // the cap on the detection resolution, if there is one
+    max_detection_scale: Option<f32>,
+    // so that the stats can leave out jumps of the wall clock
+    clock: ClockWatch,
+    // the latest posture alert, until it is reported as a false positive
+    last_alert: Option<StatsEntry>,
+}
+
+// Runs the pipeline, rebuilding it whenever it panics (e.g. the camera vanished mid-capture or
+// can't be opened at all) so that monitoring never silently stops. Restarts back off
+// exponentially, and the user is told once it looks like the camera isn't coming back.
+fn supervise(
+    args: Args,
+    mut state: PipelineState,
+    commands: Receiver<WorkerCommand>,
+    events: EventBus,
+) {
+    if let Some(stats) = &state.stats {
+        match stats.compact() {
+            Ok(0) => {}
+            Ok(dropped) => println!("Compacted the stats log, dropping {} lines", dropped),
+            Err(e) => println!("Failed to compact {}: {}", stats.path().display(), e),
+        }
+        stats.record(StatsEntry::SessionStarted);
+    }
+    let mut backoff = INITIAL_BACKOFF;
+    let mut failures = 0;
+    loop {
+        state.healthy = false;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            run(&args, &commands, &events, &mut state)
+        }));
+        // the camera went with the pipeline
+        camera_closed(&mut state, &events, Instant::now());
+        record_camera_time(&mut state);
+        let payload = match result {
+            Ok(()) => {
+                if let Some(stats) = &state.stats {
+                    stats.record(StatsEntry::SessionEnded);
+                }
+                return;
+            }
+            Err(payload) => payload,
+        };
+        let reason = panic_message(payload.as_ref());
+        if state.healthy {
+            backoff = INITIAL_BACKOFF;
+            failures = 0;
+        }
+        failures += 1;
+        println!(
+            "The pipeline crashed ({}), restarting in {:.0?}",
+            reason, backoff
+        );
+        if !state.camera_lost {
+            state.camera_lost = true;
+            events.publish(PostureEvent::CameraLost(reason.clone()));
+        }
+        if failures == FAILURES_BEFORE_NOTIFYING {
+            events.publish(PostureEvent::MonitoringFailed(reason));
+        }
+        // wait out the backoff, but stop straight away if we are being shut down. Commands that
+        // arrive in the meantime are dropped, except for calibrating, which is done on restart; a
+        // calibration already in use is restored then.
+        let deadline = Instant::now() + backoff;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            match commands.recv_timeout(deadline - now) {
+                Err(RecvTimeoutError::Disconnected) => return,
+                Ok(WorkerCommand::Calibrate) => state.calibration_pending = true,
+                _ => {}
+            }
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+// With --no-camera there is nothing to check, but pausing and reacting to alerts still reach the
+// alert manager and the reminders through here
+fn without_camera(commands: Receiver<WorkerCommand>, events: EventBus) {
+    let mut paused = false;
+    while let Ok(command) = commands.recv() {
+        match command {
+            WorkerCommand::Feedback(feedback) => events.publish(PostureEvent::Feedback(feedback)),
+            WorkerCommand::Pause(pause) if pause != paused => {
+                paused = pause;
+                events.publish(if paused {
+                    PostureEvent::Paused
+                } else {
+                    PostureEvent::Resumed
+                });
+            }
+            WorkerCommand::SetDetector(_, reply) | WorkerCommand::ReportFalsePositive(reply) => {
+                let _ = reply.send(Err("No camera is in use".to_string()));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    if let Some(message) = payload.downcast_ref::<String>() {
+        return message.clone();
+    }
+    "unknown panic".to_string()
+}
+
+fn run(
+    args: &Args,
+    commands: &Receiver<WorkerCommand>,
+    events: &EventBus,
+    state: &mut PipelineState,
+) {
+    let mut neckcheck = build_neckcheck(args, &state.detector, &state.config);
+    if let Some(learner) = &state.learner {
+        neckcheck.set_limit_scale(learner.scale());
+    }
+    if state.config.strict.enabled {
+        neckcheck.set_limit_scale(state.config.strict.limit_scale);
+    }
+    neckcheck.set_saved_calibrations(state.calibrations.clone());
+    if let Some(max) = state.max_detection_scale {
+        neckcheck.limit_detection_scale(max);
+    }
+    if state.calibrated {
+        // the camera was fine with it before the restart
+        neckcheck.restore_calibration(true);
+    }
+    let pending =
+        std::mem::take(&mut state.calibration_pending).then_some(WorkerCommand::Calibrate);
+    for command in pending.into_iter().chain(commands.iter()) {
+        match command {
+            WorkerCommand::Calibrate => {
+                state.calibration_pending = true;
+                if args.recalibrate || !neckcheck.restore_calibration(args.ignore_camera_mismatch) {
+                    neckcheck.calibrate();
+                    events.publish(PostureEvent::Calibrated);
+                }
+                calibrated(&neckcheck, state);
+            }
+            WorkerCommand::Observe(duration, percentile) => {
+                println!(
+                    "{}",
+                    tr!(
+                        "observe-start",
+                        minutes = (duration.as_secs_f64() / 60.0).round()
+                    )
+                );
+                state.observer = Some(Observer::new(duration, percentile, Instant::now()));
+            }
+            WorkerCommand::Check => {
+                if state.paused || waiting_for_guest(&neckcheck, state) {
+                    continue;
+                }
+                if neckcheck.is_calibrated() {
+                    check(&mut neckcheck, events, state);
+                } else {
+                    observe(&mut neckcheck, state);
+                }
+            }
+            WorkerCommand::SetDetector(detector, reply) => {
+                let result = switch_detector(&mut neckcheck, &detector);
+                if result.is_ok() {
+                    state.detector = detector;
+                }
+                let _ = reply.send(result);
+            }
+            WorkerCommand::Feedback(feedback) => {
+                events.publish(PostureEvent::Feedback(feedback.clone()));
+                let verdict = state
+                    .learner
+                    .as_mut()
+                    .and_then(|learner| learner.feedback(&feedback, Instant::now()));
+                learn(&mut neckcheck, state, verdict);
+            }
+            WorkerCommand::ReportFalsePositive(reply) => {
+                let _ = reply.send(report_false_positive(state));
+            }
+            WorkerCommand::Pause(paused) if paused != state.paused => {
+                state.paused = paused;
+                if paused {
+                    events.publish(PostureEvent::Paused);
+                } else {
+                    // start over, so that sinks hear about the posture as it is now
+                    neckcheck.reset();
+                    events.publish(PostureEvent::Resumed);
+                }
+            }
+            WorkerCommand::Pause(_) => {}
+            WorkerCommand::LimitDetectionScale(max) => {
+                state.max_detection_scale = (max < 1.0).then_some(max);
+                neckcheck.limit_detection_scale(max);
+            }
+        }
+        for activity in neckcheck.take_camera_activity() {
+            match activity {
+                CameraActivity::Opened(at) => {
+                    if state.camera_on_since.is_none() {
+                        state.camera_on_since = Some(at);
+                        events.publish(PostureEvent::CameraOpened);
+                    }
+                }
+                CameraActivity::Closed(at) => camera_closed(state, events, at),
+            }
+        }
+    }
+}
+
+// Whether to skip a check because a guest is at the computer and was checked on recently
+fn waiting_for_guest(neckcheck: &NeckCheck, state: &mut PipelineState) -> bool {
+    if !neckcheck.is_guest() {
+        state.guest_checked = None;
+        return false;
+    }
+    let interval = Duration::from_secs_f64(state.config.guest.check_interval);
+    if state
+        .guest_checked
+        .is_some_and(|checked| checked.elapsed() < interval)
+    {
+        return true;
+    }
+    state.guest_checked = Some(Instant::now());
+    false
+}
+
+fn camera_closed(state: &mut PipelineState, events: &EventBus, at: Instant) {
+    if let Some(since) = state.camera_on_since.take() {
+        state.camera_on_for += at.saturating_duration_since(since);
+        events.publish(PostureEvent::CameraClosed);
+    }
+}
+
+// Writes how long the camera has been on to the stats. It is added up rather than written every
+// time the camera closes, which with --discrete is every check.
+fn record_camera_time(state: &mut PipelineState) {
+    if let Some(since) = &mut state.camera_on_since {
+        let now = Instant::now();
+        state.camera_on_for += now - *since;
+        *since = now;
+    }
+    let seconds = std::mem::take(&mut state.camera_on_for).as_secs_f32();
+    if let (Some(stats), true) = (&state.stats, seconds > 0.0) {
+        stats.record(StatsEntry::CameraOn { seconds });
+    }
+}
+
+fn switch_detector(neckcheck: &mut NeckCheck, config: &DetectorConfig) -> Result<(), String> {
+    let detector = create_detector(config).map_err(|e| e.to_string())?;
+    let name = detector.name();
+    neckcheck.set_detector(detector)?;
+    // each backend draws its boxes a little differently
+    println!(
+        "Switched to the {} detector; recalibrate if alerts seem off",
+        name
+    );
+    Ok(())
+}
+
+fn observe(neckcheck: &mut NeckCheck, state: &mut PipelineState) {
+    let observer = match &mut state.observer {
+        Some(observer) => observer,
+        None => return,
+    };
+    match neckcheck.observe() {
+        Ok(metric) => observer.record(metric),
+        // a camera hiccup only costs a measurement
+        Err(e) => println!("{}", e),
+    }
+    if let Some(limit) = observer.finish(Instant::now()) {
+        state.observer = None;
+        neckcheck.calibrate_to(limit);
+        calibrated(neckcheck, state);
+    }
+}
+
+// Keeps the calibrations for restarts and later runs
+fn calibrated(neckcheck: &NeckCheck, state: &mut PipelineState) {
+    state.calibrations = neckcheck.calibrations();
+    state.calibrated = true;
+    state.calibration_pending = false;
+    if let Some(saved) = &state.saved_calibrations {
+        saved.save(&state.calibrations);
+    }
+}
+
+// Applies what the learner made of the user's reaction to an alert
+fn learn(neckcheck: &mut NeckCheck, state: &PipelineState, verdict: Option<Verdict>) {
+    let (verdict, learner) = match (verdict, &state.learner) {
+        (Some(verdict), Some(learner)) => (verdict, learner),
+        _ => return,
+    };
+    neckcheck.set_limit_scale(learner.scale());
+    if let Some(stats) = &state.stats {
+        stats.record(StatsEntry::AlertFeedback {
+            false_positive: verdict == Verdict::FalsePositive,
+            drift: learner.drift(),
+        });
+    }
+}
+
+// Keeps what the latest posture alert fired on, for the user to report it as wrong
+fn remember_alert(neckcheck: &NeckCheck, state: &mut PipelineState, events: &[PostureEvent]) {
+    let Some(alert) = events
+        .iter()
+        .rev()
+        .filter_map(alert_kind)
+        .find(|kind| is_posture_alert(*kind))
+    else {
+        return;
+    };
+    let metrics = neckcheck.metrics();
+    state.last_alert = Some(StatsEntry::FalsePositive {
+        alert,
+        current: metrics.as_ref().and_then(|metrics| metrics.current),
+        smoothed: metrics.as_ref().and_then(|metrics| metrics.smoothed),
+        limit: metrics.map(|metrics| metrics.limit),
+        // only changes of the posture itself are dumped
+        frame: (alert == AlertKind::TooClose)
+            .then(|| neckcheck.last_debug_frame())
+            .flatten(),
+    });
+}
+
+fn report_false_positive(state: &mut PipelineState) -> Result<(), String> {
+    let stats = state
+        .stats
+        .as_ref()
+        .ok_or("There is no stats log to record it in")?;
+    let alert = state
+        .last_alert
+        .take()
+        .ok_or("There has been no posture alert to report since neckcheck started")?;
+    stats.record(alert);
+    println!("Recorded the latest posture alert as a false positive");
+    Ok(())
+}
+
+// Records the events of a check, marked if someone else was in view so that they can be told apart
+fn record_stats(stats: &StatsLog, events: &[PostureEvent], others_present: bool) {
+    for event in events {
+        if let PostureEvent::BlinkRate { blinks, seconds } = event {
+            stats.record_seen(
+                StatsEntry::BlinkRate {
+                    per_minute: *blinks as f32 * 60.0 / (*seconds).max(1) as f32,
+                },
+                others_present,
+            );
+        } else if let Some(event) = alert_kind(event) {
+            stats.record_seen(StatsEntry::Event { event }, others_present);
+        }
+    }
+}
+
+fn check(neckcheck: &mut NeckCheck, events: &EventBus, state: &mut PipelineState) {
+    match neckcheck.check() {
+        Ok(posture_events) => {
+            state.healthy = true;
+            if state.camera_lost {
+                state.camera_lost = false;
+                events.publish(PostureEvent::CameraRecovered);
+            }
+            let verdict = state
+                .learner
+                .as_mut()
+                .and_then(|learner| learner.observe(&posture_events, Instant::now()));
+            learn(neckcheck, state, verdict);
+            remember_alert(neckcheck, state, &posture_events);
+            let change = state.clock.check(Instant::now(), chrono::Local::now());
+            if let Some(stats) = &state.stats {
+                if let Some(change) = change.filter(|change| !change.jump.is_zero()) {
+                    stats.record(StatsEntry::ClockChanged {
+                        seconds: change.jump.num_seconds(),
+                    });
+                }
+                record_stats(stats, &posture_events, neckcheck.others_present());
+            }
+            // a guest's time at the computer isn't the user's
+            if state.stats.is_some()
+                && !neckcheck.is_guest()
+                && state
+                    .last_alive
+                    .is_none_or(|last| last.elapsed() >= ALIVE_INTERVAL)
+            {
+                state.last_alive = Some(Instant::now());
+                if let Some(stats) = &state.stats {
+                    stats.record(StatsEntry::Alive);
+                }
+                record_camera_time(state);
+            }
+            // the metrics first, so that the alert manager knows how late the events are
+            if let Some(metrics) = neckcheck.metrics() {
+                events.publish(PostureEvent::Metrics(metrics));
+            }
+            for event in posture_events {
+                events.publish(event);
+            }
+        }
+        Err(e) => {
+            if !state.camera_lost {
+                state.camera_lost = true;
+                events.publish(PostureEvent::CameraLost(e.to_string()));
+            }
+            // start over once the camera is back, so that sinks hear about the
+            // posture at that point even if it is the same as before
+            neckcheck.reset();
+        }
+    }
+}